@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::context::Context;
+use crate::segment::Segment;
+
+/// The output of the `async_modules` rendered for a single prompt, `None` for modules
+/// which weren't shown
+#[derive(Default, Serialize, Deserialize)]
+struct AsyncState {
+    id: u64,
+    modules: BTreeMap<String, Option<Vec<Segment>>>,
+}
+
+/// The modules rendered in the background for the prompt with the id given by
+/// `--async-id`.
+///
+/// The init scripts of shells which can redraw their prompt pass the id of each new
+/// prompt to `starship prompt` and to `starship render-async`, which runs in the
+/// background. The prompt leaves out the async modules until their output is saved by
+/// `render-async`, and the shell then redraws it.
+pub struct AsyncResults {
+    path: PathBuf,
+    id: u64,
+    modules: Vec<String>,
+    state: AsyncState,
+}
+
+impl AsyncResults {
+    /// Loads the results for the current prompt. Returns `None` if no async id was given
+    /// or there are no `async_modules`.
+    pub fn new(context: &Context) -> Option<Self> {
+        let id = context.properties.get("async_id")?.parse().ok()?;
+        let modules = context.config.get_root_config().async_modules;
+        if modules.is_empty() {
+            return None;
+        }
+
+        let session = context
+            .get_env("STARSHIP_SESSION_KEY")
+            .filter(|session| !session.is_empty())?;
+        let path = context
+            .get_cache_dir()?
+            .join("async")
+            .join(format!("{}.json", session));
+        Some(Self::load(
+            path,
+            id,
+            modules.iter().map(|module| module.to_string()).collect(),
+        ))
+    }
+
+    fn load(path: PathBuf, id: u64, modules: Vec<String>) -> Self {
+        let state = read_state(&path);
+        Self {
+            path,
+            id,
+            modules,
+            state,
+        }
+    }
+
+    /// The modules rendered in the background
+    pub fn modules(&self) -> &[String] {
+        &self.modules
+    }
+
+    /// Whether `module` is rendered in the background
+    pub fn is_async(&self, module: &str) -> bool {
+        self.modules.iter().any(|name| name == module)
+    }
+
+    /// The output of `module` for the current prompt, `None` if it is still being
+    /// rendered or isn't shown
+    pub fn get(&self, module: &str) -> Option<Vec<Segment>> {
+        if self.state.id != self.id {
+            return None;
+        }
+        self.state.modules.get(module).cloned().flatten()
+    }
+
+    /// Saves the output of the async modules for the current prompt, unless the results
+    /// of a later prompt were already saved
+    pub fn save(&self, modules: BTreeMap<String, Option<Vec<Segment>>>) {
+        if read_state(&self.path).id > self.id {
+            return;
+        }
+
+        let state = AsyncState {
+            id: self.id,
+            modules,
+        };
+        let result = serde_json::to_string(&state)
+            .map_err(io::Error::from)
+            .and_then(|content| {
+                if let Some(parent) = self.path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&self.path, content)
+            });
+        if let Err(error) = result {
+            log::warn!("Unable to save async modules to {:?}: {}", self.path, error);
+        }
+    }
+}
+
+fn read_state(path: &Path) -> AsyncState {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(path: &Path, id: u64) -> AsyncResults {
+        AsyncResults::load(path.to_path_buf(), id, vec!["git_status".to_string()])
+    }
+
+    #[test]
+    fn uses_results_of_the_same_prompt() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("session.json");
+        let output = |value: &str| {
+            let mut modules = BTreeMap::new();
+            modules.insert(
+                "git_status".to_string(),
+                Some(vec![Segment::new(None, value)]),
+            );
+            modules
+        };
+
+        assert!(results(&path, 1).get("git_status").is_none());
+        results(&path, 2).save(output("[!]"));
+        // Results of an earlier prompt finishing late are dropped
+        results(&path, 1).save(output("[+]"));
+
+        let segments = results(&path, 2).get("git_status").unwrap();
+        assert_eq!("[!]", segments[0].value);
+        assert!(results(&path, 1).get("git_status").is_none());
+        assert!(results(&path, 3).get("git_status").is_none());
+        dir.close()
+    }
+}
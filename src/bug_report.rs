@@ -1,6 +1,10 @@
+use crate::config::StarshipConfig;
+use crate::module::{Module, ALL_MODULES};
+use crate::print::format_duration;
 use crate::shadow;
 use crate::utils::exec_cmd;
 
+use std::env;
 use std::fs;
 use std::path::PathBuf;
 use std::time::Duration;
@@ -18,6 +22,7 @@ pub fn create() {
         shell_info: get_shell_info(),
         terminal_info: get_terminal_info(),
         starship_config: get_starship_config(),
+        timings: get_timings(),
     };
 
     let link = make_github_issue_link(environment);
@@ -55,6 +60,12 @@ const UNKNOWN_SHELL: &str = "<unknown shell>";
 const UNKNOWN_TERMINAL: &str = "<unknown terminal>";
 const UNKNOWN_VERSION: &str = "<unknown version>";
 const UNKNOWN_CONFIG: &str = "<unknown config>";
+const UNKNOWN_LOCALE: &str = "<unknown locale>";
+const NO_TIMINGS: &str = "<run `starship timings` to include timings>";
+/// Shown instead of values which may be secrets
+const REDACTED: &str = "<redacted>";
+/// The file the last `starship timings` run is saved to, in the cache dir
+const TIMINGS_FILE: &str = "last_timings.txt";
 const GITHUB_CHAR_LIMIT: usize = 8100; // Magic number accepted by Github
 
 struct Environment {
@@ -63,6 +74,7 @@ struct Environment {
     shell_info: ShellInfo,
     terminal_info: TerminalInfo,
     starship_config: String,
+    timings: String,
 }
 
 fn get_pkg_branch_tag() -> &'static str {
@@ -90,6 +102,8 @@ fn make_github_issue_link(environment: Environment) -> String {
 - {shell_name} version: {shell_version}
 - Operating system: {os_name} {os_version}
 - Terminal emulator: {terminal_name} {terminal_version}
+- Terminal type: {terminal_type}
+- Locale: {locale}
 - Git Commit Hash: {git_commit_hash}
 - Branch/Tag: {pkg_branch_tag}
 - Rust Version: {rust_version}
@@ -105,16 +119,25 @@ fn make_github_issue_link(environment: Environment) -> String {
 
 ```toml
 {starship_config}
+```
+
+#### Module Timings
+
+```
+{timings}
 ```",
         starship_version = shadow::PKG_VERSION,
         shell_name = environment.shell_info.name,
         shell_version = environment.shell_info.version,
         terminal_name = environment.terminal_info.name,
         terminal_version = environment.terminal_info.version,
+        terminal_type = environment.terminal_info.term,
+        locale = environment.terminal_info.locale,
         os_name = environment.os_type,
         os_version = environment.os_version,
         shell_config = environment.shell_info.config,
         starship_config = environment.starship_config,
+        timings = environment.timings,
         git_commit_hash =  shadow::SHORT_COMMIT,
         pkg_branch_tag =  get_pkg_branch_tag(),
         rust_version =  shadow::RUST_VERSION,
@@ -173,6 +196,9 @@ fn get_shell_info() -> ShellInfo {
 struct TerminalInfo {
     name: String,
     version: String,
+    /// `TERM`, and `COLORTERM` if set
+    term: String,
+    locale: String,
 }
 
 fn get_terminal_info() -> TerminalInfo {
@@ -184,9 +210,22 @@ fn get_terminal_info() -> TerminalInfo {
         .or_else(|_| std::env::var("LC_TERMINAL_VERSION"))
         .unwrap_or_else(|_| UNKNOWN_VERSION.to_string());
 
+    let term = std::env::var("TERM").unwrap_or_else(|_| UNKNOWN_TERMINAL.to_string());
+    let term = match std::env::var("COLORTERM") {
+        Ok(colorterm) => format!("{} (COLORTERM={})", term, colorterm),
+        Err(_) => term,
+    };
+
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|key| std::env::var(key).ok().filter(|locale| !locale.is_empty()))
+        .unwrap_or_else(|| UNKNOWN_LOCALE.to_string());
+
     TerminalInfo {
         name: terminal,
         version,
+        term,
+        locale,
     }
 }
 
@@ -212,18 +251,151 @@ fn get_config_path(shell: &str) -> Option<PathBuf> {
     })
 }
 
+/// The config starship uses, with values which may be secrets redacted. `${env:VAR}` is
+/// left as written, so the values of environment variables don't leak.
 fn get_starship_config() -> String {
-    std::env::var("STARSHIP_CONFIG")
+    match StarshipConfig::effective_config() {
+        Some(mut config) => {
+            redact_config(&mut config);
+            toml::to_string_pretty(&config).unwrap_or_else(|_| UNKNOWN_CONFIG.to_string())
+        }
+        None => UNKNOWN_CONFIG.to_string(),
+    }
+}
+
+/// Redacts the default value of `env_var`, the commands of custom modules, the options
+/// passed to plugins and any option named like a secret, e.g. `weather.api_key`, in the
+/// config and in each of its profiles
+fn redact_config(config: &mut toml::Value) {
+    redact_secrets(config);
+
+    let profiles = config
+        .get_mut("profiles")
+        .and_then(toml::Value::as_table_mut);
+    for profile in profiles
+        .into_iter()
+        .flat_map(|profiles| profiles.iter_mut().map(|(_, profile)| profile))
+    {
+        redact_config(profile);
+    }
+
+    if let Some(default) = config
+        .get_mut("env_var")
+        .and_then(|env_var| env_var.get_mut("default"))
+    {
+        *default = REDACTED.into();
+    }
+    let custom_modules = config.get_mut("custom").and_then(toml::Value::as_table_mut);
+    for module in custom_modules.into_iter().flat_map(|modules| {
+        modules
+            .iter_mut()
+            .filter_map(|(_, module)| module.as_table_mut())
+    }) {
+        for name in ["command", "when"] {
+            if let Some(command) = module.get_mut(name).filter(|command| command.is_str()) {
+                *command = REDACTED.into();
+            }
+        }
+    }
+    let plugins = config
+        .get_mut("plugins")
+        .and_then(toml::Value::as_table_mut);
+    for plugin in plugins
+        .into_iter()
+        .flat_map(|plugins| plugins.iter_mut().map(|(_, plugin)| plugin))
+    {
+        let options = plugin
+            .get_mut("options")
+            .and_then(toml::Value::as_table_mut);
+        for option in options
+            .into_iter()
+            .flat_map(|options| options.iter_mut().map(|(_, option)| option))
+        {
+            *option = REDACTED.into();
+        }
+    }
+}
+
+/// Redacts the options named like a secret, i.e. ending in `key`, `token` or `secret`, in
+/// all tables of the config
+fn redact_secrets(value: &mut toml::Value) {
+    let table = match value.as_table_mut() {
+        Some(table) => table,
+        None => return,
+    };
+    for (name, value) in table.iter_mut() {
+        let name = name.to_lowercase();
+        if value.is_table() {
+            redact_secrets(value);
+        } else if ["key", "token", "secret"]
+            .iter()
+            .any(|suffix| name.ends_with(suffix))
+        {
+            *value = REDACTED.into();
+        }
+    }
+}
+
+fn timings_path() -> Option<PathBuf> {
+    env::var_os("STARSHIP_CACHE")
         .map(PathBuf::from)
-        .ok()
-        .or_else(|| {
-            dirs_next::home_dir().map(|mut home_dir| {
-                home_dir.push(".config/starship.toml");
-                home_dir
-            })
+        .or_else(|| Some(dirs_next::home_dir()?.join(".cache/starship")))
+        .map(|dir| dir.join(TIMINGS_FILE))
+}
+
+/// Saves the timings of modules to include in bug reports. The output of `env_var`, custom
+/// modules and plugins is redacted, since it may show secrets.
+pub fn save_timings(modules: &[Module]) {
+    let path = match timings_path() {
+        Some(path) => path,
+        None => return,
+    };
+    let timings = format_timings(modules);
+    let saved = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&path, timings));
+    if let Err(error) = saved {
+        log::debug!("Unable to save the timings to {:?}: {}", path, error);
+    }
+}
+
+fn format_timings(modules: &[Module]) -> String {
+    let modules: Vec<_> = modules
+        .iter()
+        .filter(|module| !module.is_empty() || module.duration.as_millis() > 0)
+        .collect();
+    let name_width = modules
+        .iter()
+        .map(|module| module.get_name().len())
+        .max()
+        .unwrap_or(0);
+    modules
+        .iter()
+        .map(|module| {
+            let name = module.get_name();
+            let output = if name == "env_var" || !ALL_MODULES.contains(&name.as_str()) {
+                REDACTED.to_string()
+            } else {
+                format!("{:?}", module.get_segments().concat())
+            };
+            format!(
+                "{:<width$}  -  {:>5}  -  {}\n",
+                name,
+                format_duration(&module.duration),
+                output,
+                width = name_width
+            )
         })
-        .and_then(|config_path| fs::read_to_string(config_path).ok())
-        .unwrap_or_else(|| UNKNOWN_CONFIG.to_string())
+        .collect()
+}
+
+fn get_timings() -> String {
+    timings_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|timings| timings.trim_end().to_string())
+        .filter(|timings| !timings.is_empty())
+        .unwrap_or_else(|| NO_TIMINGS.to_string())
 }
 
 #[cfg(test)]
@@ -244,8 +416,11 @@ mod tests {
             terminal_info: TerminalInfo {
                 name: "test_terminal".to_string(),
                 version: "5.6.7".to_string(),
+                term: "xterm-256color".to_string(),
+                locale: "de_DE.UTF-8".to_string(),
             },
             starship_config: "No Starship config".to_string(),
+            timings: "character  -  <1ms  -  \"❯\"".to_string(),
         };
 
         let link = make_github_issue_link(environment);
@@ -257,6 +432,72 @@ mod tests {
         assert!(link.contains("2.3.4"));
         assert!(link.contains("No+config"));
         assert!(link.contains("No+Starship+config"));
+        assert!(link.contains("xterm-256color"));
+        assert!(link.contains("de_DE.UTF-8"));
+        assert!(link.contains("character"));
+    }
+
+    #[test]
+    fn test_redact_config() {
+        let mut config = toml::toml! {
+            [env_var]
+            variable = "API_TOKEN"
+            default = "hunter2"
+            [plugins.vault.options]
+            token = "s.secret"
+            [profiles.work.env_var]
+            default = "hunter3"
+            [weather]
+            location = "Berlin"
+            api_key = "0123456789abcdef"
+            [custom.deploy]
+            command = "deploy --token hunter5 status"
+            when = "test -n \"$DEPLOY_TOKEN\""
+            github_token = "ghp_secret"
+            client_secret = "hunter4"
+        };
+        redact_config(&mut config);
+        assert_eq!(
+            toml::toml! {
+                [env_var]
+                variable = "API_TOKEN"
+                default = "<redacted>"
+                [plugins.vault.options]
+                token = "<redacted>"
+                [profiles.work.env_var]
+                default = "<redacted>"
+                [weather]
+                location = "Berlin"
+                api_key = "<redacted>"
+                [custom.deploy]
+                command = "<redacted>"
+                when = "<redacted>"
+                github_token = "<redacted>"
+                client_secret = "<redacted>"
+            },
+            config
+        );
+    }
+
+    #[test]
+    fn test_format_timings() {
+        let mut directory = Module::new("directory", "", None);
+        directory.set_segments(vec![crate::segment::Segment::new(None, "~/src")]);
+        directory.duration = Duration::from_millis(3);
+        let mut env_var = Module::new("env_var", "", None);
+        env_var.set_segments(vec![crate::segment::Segment::new(None, "hunter2")]);
+        let mut custom = Module::new("deploy", "", None);
+        custom.set_segments(vec![crate::segment::Segment::new(None, "prod")]);
+        let empty = Module::new("aws", "", None);
+
+        assert_eq!(
+            concat!(
+                "directory  -    3ms  -  \"~/src\"\n",
+                "env_var    -   <1ms  -  <redacted>\n",
+                "deploy     -   <1ms  -  <redacted>\n",
+            ),
+            format_timings(&[directory, env_var, custom, empty])
+        );
     }
 
     #[test]
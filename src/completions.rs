@@ -0,0 +1,171 @@
+use clap::Shell;
+use toml::Value;
+
+use crate::config::StarshipConfig;
+use crate::configs::FullConfig;
+use crate::module::ALL_MODULES;
+use crate::presets::PRESETS;
+
+/// The arguments completed with `starship complete <kind>`, as `(kind, subcommands, help
+/// of the argument)`
+const DYNAMIC_ARGS: &[(&str, &[&str], &str)] = &[
+    (
+        "modules",
+        &["module"],
+        "The name of the module to be printed",
+    ),
+    (
+        "modules",
+        &["toggle"],
+        "The name of the module to be toggled",
+    ),
+    (
+        "config-keys",
+        &["config", "configure"],
+        "Configuration key to edit",
+    ),
+    ("presets", &["preset"], "The preset to print or install"),
+];
+
+pub const KINDS: &[&str] = &["modules", "config-keys", "presets"];
+
+/// The values an argument of the `kind` can have, e.g. the names of all modules
+pub fn candidates(kind: &str) -> Vec<String> {
+    match kind {
+        "modules" => {
+            let config = StarshipConfig::initialize();
+            let mut modules: Vec<String> =
+                ALL_MODULES.iter().map(|&name| name.to_owned()).collect();
+            for table in &["custom", "plugins"] {
+                let names = config
+                    .get_config(&[table])
+                    .and_then(Value::as_table)
+                    .into_iter()
+                    .flat_map(|modules| modules.keys());
+                modules.extend(names.map(|name| format!("{}.{}", table, name)));
+            }
+            modules
+        }
+        "config-keys" => {
+            let mut keys = Vec::new();
+            if let Ok(Value::Table(config)) = Value::try_from(FullConfig::default()) {
+                add_keys(&mut keys, "", &config);
+            }
+            keys.sort();
+            keys
+        }
+        "presets" => PRESETS
+            .iter()
+            .map(|preset| preset.name.to_owned())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Adds the dotted keys of all options in `table`, like `git_status.ahead`
+fn add_keys(keys: &mut Vec<String>, prefix: &str, table: &toml::value::Table) {
+    for (key, value) in table {
+        let key = format!("{}{}", prefix, key);
+        match value {
+            Value::Table(table) => add_keys(keys, &format!("{}.", key), table),
+            _ => keys.push(key),
+        }
+    }
+}
+
+/// Extends the completions generated by clap, which only know the static flags, to
+/// complete module names, config keys and presets with `starship complete`
+pub fn add_dynamic_completions(shell: Shell, script: String) -> String {
+    match shell {
+        Shell::Bash => {
+            let mut cases = String::new();
+            for (kind, subcommands, _) in DYNAMIC_ARGS {
+                cases.push_str(&format!(
+                    "            {}) kind={} ;;\n",
+                    subcommands.join("|"),
+                    kind
+                ));
+            }
+            replace_matched(
+                &script,
+                "complete -F _starship -o bashdefault -o default starship",
+                &format!(
+                    r#"_starship_dynamic() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" kind
+    if [[ ${{COMP_CWORD}} -eq 2 && ${{cur}} != -* ]]; then
+        case "${{COMP_WORDS[1]}}" in
+{}        esac
+    fi
+    if [[ -n ${{kind}} ]]; then
+        COMPREPLY=( $(compgen -W "$(starship complete ${{kind}})" -- "${{cur}}") )
+        return 0
+    fi
+    _starship "$@"
+}}
+
+complete -F _starship_dynamic -o bashdefault -o default starship"#,
+                    cases
+                ),
+            )
+        }
+        Shell::Zsh => DYNAMIC_ARGS.iter().fold(script, |script, (kind, _, help)| {
+            replace_matched(
+                &script,
+                &format!("name -- {}:_files'", help),
+                &format!(
+                    "name -- {}:{{compadd -- $(starship complete {})}}'",
+                    help, kind
+                ),
+            )
+        }),
+        Shell::Fish => {
+            let mut script = script;
+            for (kind, subcommands, _) in DYNAMIC_ARGS {
+                script.push_str(&format!(
+                    "complete -c starship -n \"__fish_seen_subcommand_from {}\" -f -a \"(starship complete {})\"\n",
+                    subcommands.join(" "),
+                    kind
+                ));
+            }
+            script
+        }
+        _ => script,
+    }
+}
+
+/// Replaces `from` in a script generated by clap. If clap changed how it generates
+/// completions and the script doesn't contain it, the script is returned unchanged with a
+/// warning, so the dynamic completions don't go missing silently.
+fn replace_matched(script: &str, from: &str, to: &str) -> String {
+    if !script.contains(from) {
+        log::warn!(
+            "The completions generated by clap don't contain {:?}, completing without it",
+            from
+        );
+        return script.to_string();
+    }
+    script.replace(from, to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completes_config_keys_and_presets() {
+        let keys = candidates("config-keys");
+        assert!(keys.contains(&"format".to_string()));
+        assert!(keys.contains(&"git_status.ahead".to_string()));
+        assert!(keys.contains(&"metrics.statsd_address".to_string()));
+        assert!(!keys.contains(&"git_status".to_string()));
+
+        assert!(candidates("presets").contains(&"pure-style".to_string()));
+        assert!(candidates("unknown").is_empty());
+    }
+
+    #[test]
+    fn keeps_scripts_without_the_replaced_text() {
+        assert_eq!("a c", replace_matched("a b", "b", "c"));
+        assert_eq!("a b", replace_matched("a b", "d", "c"));
+    }
+}
@@ -1,13 +1,18 @@
-use crate::configs::StarshipRootConfig;
+use crate::configs::{FullConfig, StarshipRootConfig};
+use crate::symbol_sets;
 use crate::utils;
 use ansi_term::{Color, Style};
 use indexmap::IndexMap;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use std::clone::Clone;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::io::ErrorKind;
 use std::marker::Sized;
+use std::path::{Path, PathBuf};
 
 use std::env;
 use toml::Value;
@@ -54,6 +59,136 @@ where
             let _ = std::mem::replace(self, value);
         }
     }
+
+    /// Describes the values this config accepts, for validation messages.
+    fn expected() -> &'static str {
+        "a valid value"
+    }
+
+    /// Adds the problems of a toml value for this config to `problems`, e.g. unknown keys
+    /// or values of the wrong type. `path` is the dotted path of the value in the config.
+    fn validate_config(config: &'a Value, path: &str, problems: &mut Vec<ConfigProblem>) {
+        if Self::from_config(config).is_none() {
+            problems.push(ConfigProblem::invalid_value(
+                path,
+                format!(
+                    "expected {}, found {}",
+                    Self::expected(),
+                    describe_value(config)
+                ),
+            ));
+        }
+    }
+}
+
+/// Options every module accepts besides the ones of its config, unless it has its own.
+/// They are read when rendering: the limits of commands and directory scans, when to hide
+/// the module or how wide it is, the background of its segments and how it is displayed.
+pub const COMMON_MODULE_OPTIONS: &[&str] = &[
+    "command_timeout",
+    "scan_timeout",
+    "max_depth",
+    "max_files",
+    "min_terminal_width",
+    "min_width",
+    "max_width",
+    "background",
+    "display",
+];
+
+/// Adds the problems of the value of one of `COMMON_MODULE_OPTIONS` to `problems`
+pub fn validate_common_option(
+    key: &str,
+    value: &Value,
+    path: &str,
+    problems: &mut Vec<ConfigProblem>,
+) {
+    match key {
+        "background" => <&str as ModuleConfig>::validate_config(value, path, problems),
+        "display" => {
+            if !matches!(value.as_str(), Some("full" | "symbol" | "hidden")) {
+                problems.push(ConfigProblem::invalid_value(
+                    path,
+                    format!(
+                        "expected \"full\", \"symbol\" or \"hidden\", found {}",
+                        describe_value(value)
+                    ),
+                ));
+            }
+        }
+        _ => <u64 as ModuleConfig>::validate_config(value, path, problems),
+    }
+}
+
+/// Style strings are only parsed when rendering, so the options holding one, which are
+/// named `style` or end with `_style`, are checked when validating
+pub fn validate_style_option(
+    key: &str,
+    value: &Value,
+    path: &str,
+    problems: &mut Vec<ConfigProblem>,
+) {
+    if key != "style" && !key.ends_with("_style") {
+        return;
+    }
+    if let Some(style) = value.as_str() {
+        if !style.contains("${env:") && parse_style_string(style).is_none() {
+            problems.push(ConfigProblem::invalid_value(
+                path,
+                format!("invalid style string \"{}\"", style),
+            ));
+        }
+    }
+}
+
+/// A problem of the config, found by validating it against the configs of all modules
+#[derive(Debug, PartialEq)]
+pub struct ConfigProblem {
+    /// The dotted path of the key with the problem, e.g. `directory.style`
+    pub path: String,
+    pub kind: ConfigProblemKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigProblemKind {
+    /// A key that no module uses
+    UnknownKey,
+    /// A value of the wrong type, or an invalid style string
+    InvalidValue,
+}
+
+impl ConfigProblem {
+    pub fn unknown_key<T: Into<String>>(path: &str, message: T) -> Self {
+        Self {
+            path: path.to_owned(),
+            kind: ConfigProblemKind::UnknownKey,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_value<T: Into<String>>(path: &str, message: T) -> Self {
+        Self {
+            path: path.to_owned(),
+            kind: ConfigProblemKind::InvalidValue,
+            message: message.into(),
+        }
+    }
+}
+
+/// Describes a value found in the config, for validation messages
+pub fn describe_value(value: &Value) -> String {
+    match value {
+        Value::Table(_) => "a table".to_string(),
+        Value::Array(_) => "an array".to_string(),
+        value => value.to_string(),
+    }
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{}`: {}", self.path, self.message)
+    }
 }
 
 // TODO: Add logging to default implementations
@@ -61,24 +196,40 @@ impl<'a> ModuleConfig<'a> for &'a str {
     fn from_config(config: &'a Value) -> Option<Self> {
         config.as_str()
     }
+
+    fn expected() -> &'static str {
+        "a string"
+    }
 }
 
 impl<'a> ModuleConfig<'a> for Style {
     fn from_config(config: &Value) -> Option<Self> {
         parse_style_string(config.as_str()?)
     }
+
+    fn expected() -> &'static str {
+        "a style string"
+    }
 }
 
 impl<'a> ModuleConfig<'a> for bool {
     fn from_config(config: &Value) -> Option<Self> {
         config.as_bool()
     }
+
+    fn expected() -> &'static str {
+        "a boolean"
+    }
 }
 
 impl<'a> ModuleConfig<'a> for i64 {
     fn from_config(config: &Value) -> Option<Self> {
         config.as_integer()
     }
+
+    fn expected() -> &'static str {
+        "an integer"
+    }
 }
 
 impl<'a> ModuleConfig<'a> for u64 {
@@ -96,12 +247,20 @@ impl<'a> ModuleConfig<'a> for u64 {
             _ => None,
         }
     }
+
+    fn expected() -> &'static str {
+        "a positive integer"
+    }
 }
 
 impl<'a> ModuleConfig<'a> for f64 {
     fn from_config(config: &Value) -> Option<Self> {
         config.as_float()
     }
+
+    fn expected() -> &'static str {
+        "a float"
+    }
 }
 
 impl<'a> ModuleConfig<'a> for usize {
@@ -118,6 +277,10 @@ impl<'a> ModuleConfig<'a> for usize {
             _ => None,
         }
     }
+
+    fn expected() -> &'static str {
+        "a positive integer"
+    }
 }
 
 impl<'a, T> ModuleConfig<'a> for Vec<T>
@@ -131,6 +294,26 @@ where
             .map(|value| T::from_config(value))
             .collect()
     }
+
+    fn expected() -> &'static str {
+        "an array"
+    }
+
+    fn validate_config(config: &'a Value, path: &str, problems: &mut Vec<ConfigProblem>) {
+        match config.as_array() {
+            Some(array) => array
+                .iter()
+                .for_each(|value| T::validate_config(value, path, problems)),
+            None => problems.push(ConfigProblem::invalid_value(
+                path,
+                format!(
+                    "expected {}, found {}",
+                    Self::expected(),
+                    describe_value(config)
+                ),
+            )),
+        }
+    }
 }
 
 impl<'a, T, S: ::std::hash::BuildHasher + Default> ModuleConfig<'a> for HashMap<String, T, S>
@@ -147,6 +330,14 @@ where
 
         Some(hm)
     }
+
+    fn expected() -> &'static str {
+        "a table"
+    }
+
+    fn validate_config(config: &'a Value, path: &str, problems: &mut Vec<ConfigProblem>) {
+        validate_table::<T>(config, path, problems)
+    }
 }
 
 impl<'a, T, S: ::std::hash::BuildHasher + Default> ModuleConfig<'a> for IndexMap<String, T, S>
@@ -163,6 +354,31 @@ where
 
         Some(im)
     }
+
+    fn expected() -> &'static str {
+        "a table"
+    }
+
+    fn validate_config(config: &'a Value, path: &str, problems: &mut Vec<ConfigProblem>) {
+        validate_table::<T>(config, path, problems)
+    }
+}
+
+/// Validates each value of a table whose keys can be anything
+fn validate_table<'a, T: ModuleConfig<'a>>(
+    config: &'a Value,
+    path: &str,
+    problems: &mut Vec<ConfigProblem>,
+) {
+    match config.as_table() {
+        Some(table) => table.iter().for_each(|(key, value)| {
+            T::validate_config(value, &format!("{}.{}", path, key), problems)
+        }),
+        None => problems.push(ConfigProblem::invalid_value(
+            path,
+            format!("expected a table, found {}", describe_value(config)),
+        )),
+    }
 }
 
 impl<'a, T> ModuleConfig<'a> for Option<T>
@@ -172,6 +388,21 @@ where
     fn from_config(config: &'a Value) -> Option<Self> {
         Some(T::from_config(config))
     }
+
+    fn expected() -> &'static str {
+        T::expected()
+    }
+
+    fn validate_config(config: &'a Value, path: &str, problems: &mut Vec<ConfigProblem>) {
+        T::validate_config(config, path, problems)
+    }
+}
+
+/// Any value, for options which are passed on as they are, e.g. to plugins
+impl<'a> ModuleConfig<'a> for Value {
+    fn from_config(config: &'a Value) -> Option<Self> {
+        Some(config.clone())
+    }
 }
 
 /// A wrapper around `Vec<T>` that implements `ModuleConfig`, and either
@@ -196,6 +427,19 @@ where
 
         Some(VecOr(vec))
     }
+
+    fn expected() -> &'static str {
+        "a value or an array of values"
+    }
+
+    fn validate_config(config: &'a Value, path: &str, problems: &mut Vec<ConfigProblem>) {
+        match config.as_array() {
+            Some(array) if T::from_config(config).is_none() => array
+                .iter()
+                .for_each(|value| T::validate_config(value, path, problems)),
+            _ => T::validate_config(config, path, problems),
+        }
+    }
 }
 
 /// Root config of starship.
@@ -206,33 +450,66 @@ pub struct StarshipConfig {
 impl StarshipConfig {
     /// Initialize the Config struct
     pub fn initialize() -> Self {
-        if let Some(file_data) = Self::config_from_file() {
-            StarshipConfig {
-                config: Some(file_data),
-            }
-        } else {
-            StarshipConfig {
-                config: Some(Value::Table(toml::value::Table::new())),
+        Self::initialize_with_env(&|key| env::var(key).ok())
+    }
+
+    /// Initialize the Config struct for a shell with the given environment variables
+    pub(crate) fn initialize_with_env(get_env: &dyn Fn(&str) -> Option<String>) -> Self {
+        let file_data = match Self::find_config_file(get_env) {
+            Some(file_data) => {
+                warn_invalid_values_once(&file_data, get_env);
+                file_data
             }
+            None => Value::Table(toml::value::Table::new()),
+        };
+        StarshipConfig {
+            config: Some(Self::resolve(file_data, get_env)),
         }
     }
 
-    /// Create a config from a starship configuration file
-    fn config_from_file() -> Option<Value> {
-        let file_path = if let Ok(path) = env::var("STARSHIP_CONFIG") {
+    /// Read the starship configuration file as it is written, without applying profiles,
+    /// environment variables and symbol sets. Use this to edit the file.
+    pub fn config_from_file() -> Option<Value> {
+        Self::find_config_file(&|key| env::var(key).ok())
+    }
+
+    fn find_config_file(get_env: &dyn Fn(&str) -> Option<String>) -> Option<Value> {
+        let file_path = if let Some(path) = get_env("STARSHIP_CONFIG") {
             // Use $STARSHIP_CONFIG as the config path if available
             log::debug!("STARSHIP_CONFIG is set: {}", &path);
             path
         } else {
             // Default to using ~/.config/starship.toml
             log::debug!("STARSHIP_CONFIG is not set");
-            let config_path = dirs_next::home_dir()?.join(".config/starship.toml");
+            let config_path = home_dir_with_env(get_env)?.join(".config/starship.toml");
             let config_path_str = config_path.to_str()?.to_owned();
             log::debug!("Using default config path: {}", config_path_str);
             config_path_str
         };
 
-        let toml_content = match utils::read_file(&file_path) {
+        Self::read_config_file(&file_path)
+    }
+
+    /// The config starship uses, with the active profile, the toggles of the shell session and
+    /// the symbol set applied. `${env:VAR}` is left as written, so that the values of
+    /// environment variables aren't included, e.g. in bug reports.
+    pub fn effective_config() -> Option<Value> {
+        let get_env = |key: &str| env::var(key).ok();
+        let config = Self::find_config_file(&get_env)?;
+        Some(Self::resolve_with(config, &get_env, false))
+    }
+
+    /// Create a config from the given file, e.g. to compare it with the active config
+    pub fn from_file(file_path: &str) -> Option<Self> {
+        Some(StarshipConfig {
+            config: Some(Self::resolve(Self::read_config_file(file_path)?, &|key| {
+                env::var(key).ok()
+            })),
+        })
+    }
+
+    fn read_config_file(file_path: &str) -> Option<Value> {
+        let toml_content = match utils::read_file(file_path) {
             Ok(content) => {
                 log::trace!("Config file content: \"\n{}\"", &content);
                 Some(content)
@@ -261,6 +538,31 @@ impl StarshipConfig {
         }
     }
 
+    /// Applies the active profile, the toggles of the shell session, environment variables
+    /// and the symbol set to a config file
+    fn resolve(config: Value, get_env: &dyn Fn(&str) -> Option<String>) -> Value {
+        Self::resolve_with(config, get_env, true)
+    }
+
+    fn resolve_with(
+        mut config: Value,
+        get_env: &dyn Fn(&str) -> Option<String>,
+        interpolate: bool,
+    ) -> Value {
+        apply_profile(&mut config, get_env("STARSHIP_PROFILE").as_deref());
+        let toggles = session_toggles_path_with_env(get_env);
+        if let Some(toggles) = toggles.and_then(|path| read_session_toggles(&path)) {
+            merge_config(&mut config, toggles);
+        }
+        join_format_lines(&mut config);
+        if interpolate {
+            interpolate_env(&mut config, get_env);
+        }
+        apply_random_accent(&mut config, get_env);
+        symbol_sets::apply(&mut config, get_env);
+        config
+    }
+
     /// Get the subset of the table for a module by its name
     pub fn get_module_config(&self, module_name: &str) -> Option<&Value> {
         let module_config = self.get_config(&[module_name]);
@@ -343,6 +645,16 @@ impl StarshipConfig {
         self.get_config(&["custom"])?.as_table()
     }
 
+    /// Get the subset of the table for a plugin by its name
+    pub fn get_plugin_config(&self, plugin_name: &str) -> Option<&Value> {
+        self.get_config(&["plugins", plugin_name])
+    }
+
+    /// Get the table of all the registered plugins, if any
+    pub fn get_plugins(&self) -> Option<&toml::value::Table> {
+        self.get_config(&["plugins"])?.as_table()
+    }
+
     pub fn get_root_config(&self) -> StarshipRootConfig {
         if let Some(root_config) = &self.config {
             StarshipRootConfig::load(root_config)
@@ -352,19 +664,294 @@ impl StarshipConfig {
     }
 }
 
+/// Logs the invalid values of the config file, like misspelled styles, which are otherwise
+/// ignored when rendering. Unknown keys are already reported when modules load their config.
+pub fn warn_invalid_values() {
+    if let Some(config) = StarshipConfig::config_from_file() {
+        log_invalid_values(&config);
+    }
+}
+
+fn log_invalid_values(config: &Value) {
+    validate(config)
+        .iter()
+        .filter(|problem| problem.kind == ConfigProblemKind::InvalidValue)
+        .for_each(|problem| log::warn!("{}", problem));
+}
+
+/// Like `warn_invalid_values` when loading the config, but only once after the config changed,
+/// since validating is too slow for every prompt. A hash of the validated config is kept in
+/// the cache directory.
+fn warn_invalid_values_once(config: &Value, get_env: &dyn Fn(&str) -> Option<String>) {
+    let stamp_path = match get_env("STARSHIP_CACHE")
+        .map(PathBuf::from)
+        .or_else(|| Some(home_dir_with_env(get_env)?.join(".cache/starship")))
+    {
+        Some(dir) => dir.join("config_validated"),
+        None => return,
+    };
+    let mut hasher = DefaultHasher::new();
+    config.to_string().hash(&mut hasher);
+    let hash = format!("{:016x}", hasher.finish());
+    if utils::read_file(&stamp_path).is_ok_and(|stamp| stamp.trim() == hash) {
+        return;
+    }
+
+    log_invalid_values(config);
+    if let Some(dir) = stamp_path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    if let Err(error) = std::fs::write(&stamp_path, hash) {
+        log::debug!("Unable to write {:?}: {}", stamp_path, error);
+    }
+}
+
+/// Validates a config file as it is written against the configs of all modules,
+/// including each of its profiles
+pub fn validate(config: &Value) -> Vec<ConfigProblem> {
+    let mut config = config.clone();
+    let mut problems = Vec::new();
+
+    let profiles = config
+        .as_table_mut()
+        .and_then(|table| table.remove("profiles"));
+    symbol_sets::remove_symbols(&mut config, "", &mut problems);
+    FullConfig::validate_config(&config, "", &mut problems);
+
+    match profiles {
+        Some(Value::Table(profiles)) => {
+            for (name, mut profile) in profiles {
+                let path = format!("profiles.{}", name);
+                symbol_sets::remove_symbols(&mut profile, &path, &mut problems);
+                FullConfig::validate_config(&profile, &path, &mut problems);
+            }
+        }
+        Some(profiles) => problems.push(ConfigProblem::invalid_value(
+            "profiles",
+            format!("expected a table, found {}", describe_value(&profiles)),
+        )),
+        None => (),
+    }
+
+    problems
+}
+
+/// Merges the `[profiles.<profile>]` table of the config into the rest of the config.
+/// The `profiles` table is removed, whether a profile is used or not.
+fn apply_profile(config: &mut Value, profile: Option<&str>) {
+    let profile = profile.filter(|profile| !profile.is_empty());
+    let profiles = config
+        .as_table_mut()
+        .and_then(|table| table.remove("profiles"));
+    let (profile, mut profiles) = match (profile, profiles) {
+        (Some(profile), Some(profiles)) => (profile, profiles),
+        (Some(profile), None) => {
+            log::warn!(
+                "Profile \"{}\" not found, the config has no profiles",
+                profile
+            );
+            return;
+        }
+        (None, _) => return,
+    };
+
+    match profiles
+        .as_table_mut()
+        .and_then(|profiles| profiles.remove(profile))
+    {
+        Some(overrides) => {
+            log::debug!("Using profile \"{}\"", profile);
+            merge_config(config, overrides);
+        }
+        None => log::warn!("Profile \"{}\" not found in the config", profile),
+    }
+}
+
+/// Merges `overrides` into `config`, replacing the values of keys set in both
+fn merge_config(config: &mut Value, overrides: Value) {
+    match (config, overrides) {
+        (Value::Table(config), Value::Table(overrides)) => {
+            for (key, value) in overrides {
+                match config.get_mut(&key) {
+                    Some(config_value) => merge_config(config_value, value),
+                    None => {
+                        config.insert(key, value);
+                    }
+                }
+            }
+        }
+        (config, overrides) => *config = overrides,
+    }
+}
+
+/// The file with the options toggled for the current shell session only,
+/// see `starship toggle --session`
+pub fn session_toggles_path() -> Option<PathBuf> {
+    session_toggles_path_with_env(&|key| env::var(key).ok())
+}
+
+fn session_toggles_path_with_env(get_env: &dyn Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    let session_key = get_env("STARSHIP_SESSION_KEY").filter(|key| !key.is_empty())?;
+    let cache_dir = get_env("STARSHIP_CACHE")
+        .map(PathBuf::from)
+        .or_else(|| Some(home_dir_with_env(get_env)?.join(".cache/starship")))?;
+    Some(cache_dir.join(format!("toggles_{}.toml", session_key)))
+}
+
+/// The home directory of the shell whose environment variables `get_env` reads, which may
+/// not be the one of this process, e.g. for prompts rendered by the daemon
+fn home_dir_with_env(get_env: &dyn Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    // Like `dirs_next`, which only reads `HOME` on unix
+    if cfg!(unix) {
+        if let Some(home) = get_env("HOME").filter(|home| !home.is_empty()) {
+            return Some(PathBuf::from(home));
+        }
+    }
+    dirs_next::home_dir()
+}
+
+/// Reads the options toggled for a shell session, if any
+pub fn read_session_toggles(path: &Path) -> Option<Value> {
+    let content = utils::read_file(path).ok()?;
+    match toml::from_str(&content) {
+        Ok(toggles) => Some(toggles),
+        Err(error) => {
+            log::warn!("Unable to parse the session toggles {:?}: {}", path, error);
+            None
+        }
+    }
+}
+
+/// Joins format strings written as an array of lines, e.g. `format = ["$directory", "$character"]`,
+/// into a single string with a newline between the lines. A line can also be a table with
+/// an `align` hint of `left`, `center` or `right`, which fills the rest of the line with spaces.
+fn join_format_lines(value: &mut Value) {
+    fn aligned_line(line: &Value) -> Option<String> {
+        let (format, align) = match line {
+            Value::String(format) => (format.as_str(), "left"),
+            Value::Table(table) => (
+                table.get("format")?.as_str()?,
+                table.get("align").and_then(Value::as_str).unwrap_or("left"),
+            ),
+            _ => return None,
+        };
+        // `$align` is a fill of spaces, see `print::render_root_module` and `StringFormatter::parse`
+        Some(match align {
+            "left" => format.to_string(),
+            "center" => format!("${{align}}{}${{align}}", format),
+            "right" => format!("${{align}}{}", format),
+            _ => {
+                log::warn!(
+                    "Unknown alignment {:?} of the format line {:?}",
+                    align,
+                    format
+                );
+                format.to_string()
+            }
+        })
+    }
+
+    if let Value::Table(table) = value {
+        for (key, value) in table.iter_mut() {
+            match value {
+                Value::Array(lines) if key == "format" || key.ends_with("_format") => {
+                    match lines.iter().map(aligned_line).collect::<Option<Vec<_>>>() {
+                        Some(lines) => *value = Value::String(lines.join("\n")),
+                        None => log::warn!("Invalid lines of the format option `{}`", key),
+                    }
+                }
+                value => join_format_lines(value),
+            }
+        }
+    }
+}
+
+/// Replaces `${env:VAR}` in all strings of the config with the value of the environment
+/// variable `VAR`, or with nothing if it isn't set
+fn interpolate_env(value: &mut Value, get_env: &dyn Fn(&str) -> Option<String>) {
+    match value {
+        Value::String(string) if string.contains("${env:") => {
+            let mut result = String::with_capacity(string.len());
+            let mut rest = string.as_str();
+            while let Some(start) = rest.find("${env:") {
+                let name_start = start + "${env:".len();
+                let end = match rest[name_start..].find('}') {
+                    Some(end) => name_start + end,
+                    None => break,
+                };
+                let name = &rest[name_start..end];
+                result.push_str(&rest[..start]);
+                match get_env(name) {
+                    Some(value) => result.push_str(&value),
+                    None => log::debug!("Environment variable {} in config is not set", name),
+                }
+                rest = &rest[end + 1..];
+            }
+            result.push_str(rest);
+            *string = result;
+        }
+        Value::Array(array) => array
+            .iter_mut()
+            .for_each(|value| interpolate_env(value, get_env)),
+        Value::Table(table) => table
+            .iter_mut()
+            .for_each(|(_, value)| interpolate_env(value, get_env)),
+        _ => (),
+    }
+}
+
+/// Replaces the `random_accent` color in the strings of a config with the color picked for
+/// the shell session, from the `STARSHIP_SESSION_KEY` of the shell
+fn apply_random_accent(value: &mut Value, get_env: &dyn Fn(&str) -> Option<String>) {
+    match value {
+        Value::String(string) if string.contains("random_accent") => {
+            let session_key = get_env("STARSHIP_SESSION_KEY").unwrap_or_default();
+            let color = match random_accent(&session_key) {
+                Color::Fixed(color) => color.to_string(),
+                _ => return,
+            };
+            let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+            let mut result = String::with_capacity(string.len());
+            let mut rest = string.as_str();
+            while let Some(start) = rest.find("random_accent") {
+                let end = start + "random_accent".len();
+                let is_word = !rest[..start].ends_with(is_name_char)
+                    && !rest[end..].starts_with(is_name_char);
+                result.push_str(&rest[..start]);
+                result.push_str(if is_word { &color } else { &rest[start..end] });
+                rest = &rest[end..];
+            }
+            result.push_str(rest);
+            *string = result;
+        }
+        Value::Array(array) => array
+            .iter_mut()
+            .for_each(|value| apply_random_accent(value, get_env)),
+        Value::Table(table) => table
+            .iter_mut()
+            .for_each(|(_, value)| apply_random_accent(value, get_env)),
+        _ => (),
+    }
+}
+
 /** Parse a style string which represents an ansi style. Valid tokens in the style
  string include the following:
  - 'fg:<color>'    (specifies that the color read should be a foreground color)
  - 'bg:<color>'    (specifies that the color read should be a background color)
  - 'underline'
+ - 'double-underline'
+ - 'underline:<color>'  (an underline with its own color, see parse_underline_string)
  - 'bold'
  - 'italic'
+ - 'strikethrough'
+ - 'blink'
  - 'inverted'
  - '<color>'       (see the parse_color_string doc for valid color strings)
+ - 'gradient(<color>,<color>)'  (the first color, see parse_gradient_string)
 */
 pub fn parse_style_string(style_string: &str) -> Option<ansi_term::Style> {
-    style_string
-        .split_whitespace()
+    style_tokens(style_string)
+        .iter()
         .fold(Some(ansi_term::Style::new()), |maybe_style, token| {
             maybe_style.and_then(|style| {
                 let token = token.to_lowercase();
@@ -380,11 +967,17 @@ pub fn parse_style_string(style_string: &str) -> Option<ansi_term::Style> {
                 };
 
                 match token.as_str() {
-                    "underline" => Some(style.underline()),
+                    "underline" | "double-underline" => Some(style.underline()),
                     "bold" => Some(style.bold()),
                     "italic" => Some(style.italic()),
+                    "strikethrough" => Some(style.strikethrough()),
+                    "blink" => Some(style.blink()),
                     "dimmed" => Some(style.dimmed()),
                     "inverted" => Some(style.reverse()),
+                    underline if underline.starts_with("underline:") => {
+                        parse_color_string(&underline["underline:".len()..])
+                            .map(|_| style.underline())
+                    }
                     // When the string is supposed to be a color:
                     // Decide if we yield none, reset background or set color.
                     color_string => {
@@ -392,7 +985,10 @@ pub fn parse_style_string(style_string: &str) -> Option<ansi_term::Style> {
                             None // fg:none yields no style.
                         } else {
                             // Either bg or valid color or both.
-                            let parsed = parse_color_string(color_string);
+                            let parsed = match parse_gradient_colors(color_string) {
+                                Some((start, _)) => Some(start),
+                                None => parse_color_string(color_string),
+                            };
                             // bg + invalid color = reset the background to default.
                             if !col_fg && parsed.is_none() {
                                 let mut new_style = style;
@@ -415,13 +1011,129 @@ pub fn parse_style_string(style_string: &str) -> Option<ansi_term::Style> {
         })
 }
 
+/// The RGB colors a gradient starts and ends with
+pub type GradientColors = ((u8, u8, u8), (u8, u8, u8));
+
+/// A color gradient across the characters of a text group
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Gradient {
+    pub foreground: Option<GradientColors>,
+    pub background: Option<GradientColors>,
+}
+
+/** Parse the gradients of a style string, e.g. `fg:gradient(#ff0000,#0000ff)`.
+ Returns None if the style has no gradient. Gradients can only be made of RGB colors,
+ other colors are styled with the first color only (see parse_style_string).
+*/
+pub fn parse_gradient_string(style_string: &str) -> Option<Gradient> {
+    fn rgb(color: Color) -> Option<(u8, u8, u8)> {
+        match color {
+            Color::RGB(r, g, b) => Some((r, g, b)),
+            _ => None,
+        }
+    }
+
+    let gradient =
+        style_tokens(style_string)
+            .iter()
+            .fold(Gradient::default(), |mut gradient, token| {
+                let token = token.to_lowercase();
+                let (token, col_fg) = match token.strip_prefix("bg:") {
+                    Some(token) => (token, false),
+                    None => (token.trim_start_matches("fg:"), true),
+                };
+                let colors = parse_gradient_colors(token)
+                    .and_then(|(start, end)| Some((rgb(start)?, rgb(end)?)));
+                if colors.is_some() {
+                    if col_fg {
+                        gradient.foreground = colors;
+                    } else {
+                        gradient.background = colors;
+                    }
+                }
+                gradient
+            });
+
+    if gradient == Gradient::default() {
+        None
+    } else {
+        Some(gradient)
+    }
+}
+
+/// An underline which is doubled or colored apart from the text. Terminals which don't
+/// support these show a plain underline instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Underline {
+    pub double: bool,
+    pub color: Option<Color>,
+}
+
+/** Parse the underline of a style string, from the `double-underline` and
+ `underline:<color>` tokens. Returns None if the style has neither, a plain
+ underline is part of the style itself (see parse_style_string).
+*/
+pub fn parse_underline_string(style_string: &str) -> Option<Underline> {
+    let underline =
+        style_tokens(style_string)
+            .iter()
+            .fold(Underline::default(), |mut underline, token| {
+                let token = token.to_lowercase();
+                if token == "double-underline" {
+                    underline.double = true;
+                } else if let Some(color) = token.strip_prefix("underline:") {
+                    underline.color = parse_color_string(color).or(underline.color);
+                }
+                underline
+            });
+
+    if underline == Underline::default() {
+        None
+    } else {
+        Some(underline)
+    }
+}
+
+/// Parses the colors of `gradient(<color>,<color>)`
+fn parse_gradient_colors(color_string: &str) -> Option<(Color, Color)> {
+    let colors = color_string
+        .strip_prefix("gradient(")?
+        .strip_suffix(')')?
+        .split(',')
+        .map(parse_color_string)
+        .collect::<Option<Vec<_>>>()?;
+    match colors[..] {
+        [start, end] => Some((start, end)),
+        _ => None,
+    }
+}
+
+/// Splits a style string into its tokens, ignoring whitespace inside parentheses so that
+/// e.g. `gradient(#ff0000, #0000ff)` stays a single token
+fn style_tokens(style_string: &str) -> Vec<String> {
+    let mut depth = 0_usize;
+    let joined: String = style_string
+        .chars()
+        .filter(|c| {
+            match c {
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                _ => (),
+            }
+            depth == 0 || !c.is_whitespace()
+        })
+        .collect();
+    joined.split_whitespace().map(String::from).collect()
+}
+
 /** Parse a string that represents a color setting, returning None if this fails
- There are three valid color formats:
-  - #RRGGBB      (a hash followed by an RGB hex)
-  - u8           (a number from 0-255, representing an ANSI color)
-  - colstring    (one of the 16 predefined color strings)
+ There are four valid color formats:
+  - #RRGGBB        (a hash followed by an RGB hex)
+  - u8             (a number from 0-255, representing an ANSI color)
+  - colstring      (one of the 16 predefined color strings)
+  - random_accent  (a color picked once per shell session, see `apply_random_accent`)
 */
-fn parse_color_string(color_string: &str) -> Option<ansi_term::Color> {
+pub(crate) fn parse_color_string(color_string: &str) -> Option<ansi_term::Color> {
     // Parse RGB hex values
     log::trace!("Parsing color_string: {}", color_string);
     if color_string.starts_with('#') {
@@ -465,6 +1177,7 @@ fn parse_color_string(color_string: &str) -> Option<ansi_term::Color> {
         "bright-purple" => Some(Color::Fixed(13)),
         "bright-cyan" => Some(Color::Fixed(14)),
         "bright-white" => Some(Color::Fixed(15)),
+        "random_accent" => Some(random_accent("")),
         _ => None,
     };
 
@@ -476,6 +1189,20 @@ fn parse_color_string(color_string: &str) -> Option<ansi_term::Color> {
     predefined_color
 }
 
+/// Colors `random_accent` is picked from, chosen to be readable on dark and light backgrounds
+const ACCENT_COLORS: &[u8] = &[9, 10, 11, 12, 13, 14, 39, 69, 135, 172, 208, 213];
+
+/// Picks an accent color from the session key, so every shell session keeps its own color
+fn random_accent(session_key: &str) -> Color {
+    // FNV-1a, which unlike the std hasher is stable across Rust versions
+    let hash = session_key
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    Color::Fixed(ACCENT_COLORS[(hash % ACCENT_COLORS.len() as u64) as usize])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -664,6 +1391,273 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_random_accent() {
+        assert_eq!(
+            random_accent("1234567890abcdef"),
+            random_accent("1234567890abcdef")
+        );
+        assert!(matches!(random_accent(""), Color::Fixed(n) if ACCENT_COLORS.contains(&n)));
+
+        let colors = ["a", "b", "c", "d", "e", "f"]
+            .iter()
+            .map(|key| random_accent(key))
+            .collect::<Vec<_>>();
+        assert!(colors.iter().any(|color| *color != colors[0]));
+
+        let config = Value::from("bold fg:random_accent");
+        assert!(<Style>::from_config(&config).unwrap().foreground.is_some());
+    }
+
+    #[test]
+    fn random_accent_uses_the_session_key_of_the_shell() {
+        let get_env = |key: &str| match key {
+            "STARSHIP_SESSION_KEY" => Some("1234567890abcdef".to_string()),
+            _ => None,
+        };
+        let color = match random_accent("1234567890abcdef") {
+            Color::Fixed(color) => color,
+            color => panic!("{:?} is not an accent color", color),
+        };
+        let mut config = toml::toml! {
+            format = "[$all](bold random_accent) random_accents"
+            [git_branch]
+            style = "fg:random_accent"
+        };
+        apply_random_accent(&mut config, &get_env);
+        assert_eq!(
+            Some(format!("[$all](bold {}) random_accents", color).as_str()),
+            config.get("format").and_then(Value::as_str)
+        );
+        assert_eq!(
+            Some(format!("fg:{}", color).as_str()),
+            config["git_branch"].get("style").and_then(Value::as_str)
+        );
+    }
+
+    #[test]
+    fn invalid_values_are_validated_once_for_each_config() -> std::io::Result<()> {
+        let cache_dir = tempfile::tempdir()?;
+        let get_env = |key: &str| match key {
+            "STARSHIP_CACHE" => Some(cache_dir.path().to_string_lossy().to_string()),
+            _ => None,
+        };
+        let stamp_path = cache_dir.path().join("config_validated");
+
+        warn_invalid_values_once(&toml::toml! { format = "$all" }, &get_env);
+        let stamp = std::fs::read_to_string(&stamp_path)?;
+        warn_invalid_values_once(&toml::toml! { format = "$all" }, &get_env);
+        assert_eq!(stamp, std::fs::read_to_string(&stamp_path)?);
+
+        warn_invalid_values_once(&toml::toml! { format = "$character" }, &get_env);
+        assert_ne!(stamp, std::fs::read_to_string(&stamp_path)?);
+        cache_dir.close()
+    }
+
+    #[test]
+    fn test_default_config_is_valid() {
+        let config = Value::try_from(FullConfig::default()).unwrap();
+        assert_eq!(Vec::<ConfigProblem>::new(), validate(&config));
+    }
+
+    #[test]
+    fn test_validate_module_command_timeout() {
+        let config = toml::toml! {
+            [kubernetes]
+            command_timeout = 2000
+            background = "238"
+            min_terminal_width = 100
+
+            [golang]
+            command_timeout = "slow"
+        };
+        let problems: Vec<String> = validate(&config).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            vec![
+                "`golang.command_timeout`: expected a positive integer, found \"slow\"".to_string()
+            ],
+            problems
+        );
+    }
+
+    #[test]
+    fn test_apply_profile() {
+        let config = toml::toml! {
+            format = "$all"
+            add_newline = true
+            [directory]
+            truncation_length = 3
+            style = "cyan"
+            [profiles.streaming]
+            format = "$directory$character"
+            [profiles.streaming.directory]
+            style = "bold"
+        };
+
+        let mut streaming = config.clone();
+        apply_profile(&mut streaming, Some("streaming"));
+        assert_eq!(
+            toml::toml! {
+                format = "$directory$character"
+                add_newline = true
+                [directory]
+                truncation_length = 3
+                style = "bold"
+            },
+            streaming
+        );
+
+        let mut default = config.clone();
+        apply_profile(&mut default, None);
+        let mut unknown = config;
+        apply_profile(&mut unknown, Some("work"));
+        let expected = toml::toml! {
+            format = "$all"
+            add_newline = true
+            [directory]
+            truncation_length = 3
+            style = "cyan"
+        };
+        assert_eq!(expected, default);
+        assert_eq!(expected, unknown);
+    }
+
+    #[test]
+    fn test_interpolate_env() {
+        let mut config = toml::toml! {
+            format = "${env:HOST_COLOR}$all${env:UNSET}"
+            [custom.host]
+            files = ["${env:HOST}-${env:HOST_COLOR}", "${env:HOST"]
+            disabled = false
+        };
+        let get_env = |key: &str| match key {
+            "HOST" => Some("box".to_string()),
+            "HOST_COLOR" => Some("red".to_string()),
+            _ => None,
+        };
+        interpolate_env(&mut config, &get_env);
+
+        assert_eq!(
+            toml::toml! {
+                format = "red$all"
+                [custom.host]
+                files = ["box-red", "${env:HOST"]
+                disabled = false
+            },
+            config
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn session_toggles_are_in_the_home_of_the_shell() {
+        let get_env = |key: &str| match key {
+            "HOME" => Some("/home/client".to_string()),
+            "STARSHIP_SESSION_KEY" => Some("1234".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            Some(PathBuf::from(
+                "/home/client/.cache/starship/toggles_1234.toml"
+            )),
+            session_toggles_path_with_env(&get_env)
+        );
+    }
+
+    #[test]
+    fn effective_config_keeps_env_references() {
+        let config = toml::toml! {
+            format = "${env:HOST_COLOR}$all"
+            [profiles.work]
+            format = "${env:HOST_COLOR}$directory"
+        };
+        let get_env = |key: &str| match key {
+            "STARSHIP_PROFILE" => Some("work".to_string()),
+            "HOST_COLOR" => Some("red".to_string()),
+            _ => None,
+        };
+
+        let config = StarshipConfig::resolve_with(config, &get_env, false);
+        assert_eq!(
+            Some("${env:HOST_COLOR}$directory"),
+            config.get("format").and_then(Value::as_str)
+        );
+        assert!(config.get("profiles").is_none());
+    }
+
+    #[test]
+    fn test_join_format_lines() {
+        let mut config = toml::toml! {
+            format = [
+                "$directory$git_branch",
+                { format = "$time", align = "right" },
+                { format = "$battery", align = "center" },
+                "$character",
+            ]
+            right_format = ["$status", 1]
+            [custom.host]
+            format = ["on", "$output"]
+        };
+        join_format_lines(&mut config);
+        assert_eq!(
+            toml::toml! {
+                format = "$directory$git_branch\n${align}$time\n${align}$battery${align}\n$character"
+                right_format = ["$status", 1]
+                [custom.host]
+                format = "on\n$output"
+            },
+            config
+        );
+    }
+
+    #[test]
+    fn test_gradient_style() {
+        let config = Value::from("bold fg:gradient(#ff0000, #0000ff)");
+        assert_eq!(
+            <Style>::from_config(&config).unwrap(),
+            Color::RGB(255, 0, 0).bold()
+        );
+
+        assert_eq!(
+            parse_gradient_string(
+                "bold fg:gradient(#ff0000, #0000ff) bg:gradient(#000000,#ffffff)"
+            ),
+            Some(Gradient {
+                foreground: Some(((255, 0, 0), (0, 0, 255))),
+                background: Some(((0, 0, 0), (255, 255, 255))),
+            })
+        );
+        assert_eq!(parse_gradient_string("fg:gradient(red,blue)"), None);
+        assert_eq!(parse_gradient_string("fg:red"), None);
+    }
+
+    #[test]
+    fn test_extended_attributes() {
+        let config = Value::from("italic strikethrough blink double-underline underline:#ff0000");
+        let style = <Style>::from_config(&config).unwrap();
+        assert!(style.is_italic);
+        assert!(style.is_strikethrough);
+        assert!(style.is_blink);
+        assert!(style.is_underline);
+
+        assert_eq!(
+            parse_underline_string("double-underline underline:#ff0000"),
+            Some(Underline {
+                double: true,
+                color: Some(Color::RGB(255, 0, 0)),
+            })
+        );
+        assert_eq!(
+            parse_underline_string("underline:blue"),
+            Some(Underline {
+                double: false,
+                color: Some(Color::Blue),
+            })
+        );
+        assert_eq!(parse_underline_string("bold underline"), None);
+        assert_eq!(parse_style_string("underline:nocolor"), None);
+    }
+
     #[test]
     fn test_from_vec() {
         let config: Value = Value::Array(vec![Value::from("S")]);
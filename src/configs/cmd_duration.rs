@@ -9,9 +9,15 @@ pub struct CmdDurationConfig<'a> {
     pub format: &'a str,
     pub style: &'a str,
     pub show_milliseconds: bool,
+    pub min_unit: &'a str,
+    pub rounding: &'a str,
+    pub format_duration: &'a str,
+    pub time_format: &'a str,
     pub disabled: bool,
+    pub notify: bool,
     pub show_notifications: bool,
     pub min_time_to_notify: i64,
+    pub notify_timeout: u64,
 }
 
 impl<'a> Default for CmdDurationConfig<'a> {
@@ -20,10 +26,16 @@ impl<'a> Default for CmdDurationConfig<'a> {
             min_time: 2_000,
             format: "took [$duration]($style) ",
             show_milliseconds: false,
+            min_unit: "",
+            rounding: "floor",
+            format_duration: "compact",
+            time_format: "%T",
             style: "yellow bold",
             disabled: false,
+            notify: false,
             show_notifications: false,
             min_time_to_notify: 45_000,
+            notify_timeout: 750,
         }
     }
 }
@@ -0,0 +1,33 @@
+use crate::config::ModuleConfig;
+
+use serde::Serialize;
+use starship_module_config_derive::ModuleConfig;
+
+#[derive(Clone, ModuleConfig, Serialize)]
+pub struct CpuTempConfig<'a> {
+    pub format: &'a str,
+    pub symbol: &'a str,
+    pub style: &'a str,
+    pub sensor: &'a str,
+    pub warning_threshold: i64,
+    pub warning_style: &'a str,
+    pub critical_threshold: i64,
+    pub critical_style: &'a str,
+    pub disabled: bool,
+}
+
+impl<'a> Default for CpuTempConfig<'a> {
+    fn default() -> Self {
+        CpuTempConfig {
+            format: "[$symbol$temperature]($style) ",
+            symbol: "🌡️ ",
+            style: "bold green",
+            sensor: "",
+            warning_threshold: 70,
+            warning_style: "bold yellow",
+            critical_threshold: 90,
+            critical_style: "bold red",
+            disabled: true,
+        }
+    }
+}
@@ -11,6 +11,13 @@ pub struct CustomConfig<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub when: Option<&'a str>,
     pub shell: VecOr<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shell_windows: Option<VecOr<&'a str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shell_unix: Option<VecOr<&'a str>>,
+    pub when_status: Vec<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parse: Option<&'a str>,
     pub description: &'a str,
     pub style: &'a str,
     pub disabled: bool,
@@ -27,6 +34,10 @@ impl<'a> Default for CustomConfig<'a> {
             command: "",
             when: None,
             shell: VecOr::default(),
+            shell_windows: None,
+            shell_unix: None,
+            when_status: Vec::default(),
+            parse: None,
             description: "<custom config>",
             style: "green bold",
             disabled: false,
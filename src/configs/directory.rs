@@ -8,15 +8,24 @@ use starship_module_config_derive::ModuleConfig;
 pub struct DirectoryConfig<'a> {
     pub truncation_length: i64,
     pub truncate_to_repo: bool,
+    pub truncate_to_width: f64,
     pub substitutions: IndexMap<String, &'a str>,
+    pub regex_substitutions: bool,
     pub fish_style_pwd_dir_length: i64,
     pub use_logical_path: bool,
     pub format: &'a str,
+    pub repo_root_format: &'a str,
     pub style: &'a str,
     pub disabled: bool,
     pub read_only: &'a str,
     pub read_only_style: &'a str,
+    pub read_only_detection: &'a str,
+    pub repo_root_style: Option<&'a str>,
+    pub path_in_repo_style: Option<&'a str>,
     pub truncation_symbol: &'a str,
+    pub truncation_symbol_style: Option<&'a str>,
+    pub separator_style: Option<&'a str>,
+    pub basename_style: Option<&'a str>,
     pub home_symbol: &'a str,
 }
 
@@ -25,15 +34,24 @@ impl<'a> Default for DirectoryConfig<'a> {
         DirectoryConfig {
             truncation_length: 3,
             truncate_to_repo: true,
+            truncate_to_width: 0.0,
             fish_style_pwd_dir_length: 0,
             use_logical_path: true,
             substitutions: IndexMap::new(),
+            regex_substitutions: false,
             format: "[$path]($style)[$read_only]($read_only_style) ",
+            repo_root_format: "[$repo_root]($repo_root_style)([/$path_in_repo]($path_in_repo_style))[$read_only]($read_only_style) ",
             style: "cyan bold",
             disabled: false,
             read_only: "🔒",
             read_only_style: "red",
+            read_only_detection: "access",
+            repo_root_style: None,
+            path_in_repo_style: None,
             truncation_symbol: "",
+            truncation_symbol_style: None,
+            separator_style: None,
+            basename_style: None,
             home_symbol: "~",
         }
     }
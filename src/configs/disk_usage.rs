@@ -0,0 +1,31 @@
+use crate::config::ModuleConfig;
+
+use serde::Serialize;
+use starship_module_config_derive::ModuleConfig;
+
+#[derive(Clone, ModuleConfig, Serialize)]
+pub struct DiskUsageConfig<'a> {
+    pub format: &'a str,
+    pub symbol: &'a str,
+    pub style: &'a str,
+    pub threshold: i64,
+    pub critical_threshold: i64,
+    pub critical_style: &'a str,
+    pub mounts: Vec<&'a str>,
+    pub disabled: bool,
+}
+
+impl<'a> Default for DiskUsageConfig<'a> {
+    fn default() -> Self {
+        DiskUsageConfig {
+            format: "[$symbol$free( on $path)]($style) ",
+            symbol: "💾 ",
+            style: "bold yellow",
+            threshold: 90,
+            critical_threshold: 95,
+            critical_style: "bold red",
+            mounts: vec![],
+            disabled: true,
+        }
+    }
+}
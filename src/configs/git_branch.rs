@@ -10,6 +10,8 @@ pub struct GitBranchConfig<'a> {
     pub style: &'a str,
     pub truncation_length: i64,
     pub truncation_symbol: &'a str,
+    pub sparse: &'a str,
+    pub shallow: &'a str,
     pub only_attached: bool,
     pub always_show_remote: bool,
     pub disabled: bool,
@@ -23,6 +25,8 @@ impl<'a> Default for GitBranchConfig<'a> {
             style: "bold purple",
             truncation_length: std::i64::MAX,
             truncation_symbol: "…",
+            sparse: "⊂",
+            shallow: "≈",
             only_attached: false,
             always_show_remote: false,
             disabled: false,
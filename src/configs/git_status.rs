@@ -17,13 +17,17 @@ pub struct GitStatusConfig<'a> {
     pub modified: &'a str,
     pub staged: &'a str,
     pub untracked: &'a str,
+    pub stale: &'a str,
+    pub sparse: &'a str,
+    pub shallow: &'a str,
+    pub async_refresh: bool,
     pub disabled: bool,
 }
 
 impl<'a> Default for GitStatusConfig<'a> {
     fn default() -> Self {
         GitStatusConfig {
-            format: "([\\[$all_status$ahead_behind\\]]($style) )",
+            format: "([\\[$all_status$ahead_behind$stale\\]]($style) )",
             style: "red bold",
             stashed: "\\$",
             ahead: "⇡",
@@ -35,6 +39,10 @@ impl<'a> Default for GitStatusConfig<'a> {
             modified: "!",
             staged: "+",
             untracked: "?",
+            stale: "…",
+            sparse: "⊂",
+            shallow: "≈",
+            async_refresh: false,
             disabled: false,
         }
     }
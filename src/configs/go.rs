@@ -10,6 +10,8 @@ pub struct GoConfig<'a> {
     pub symbol: &'a str,
     pub style: &'a str,
     pub disabled: bool,
+    pub use_go_mod: bool,
+    pub mismatch_style: &'a str,
     pub detect_extensions: Vec<&'a str>,
     pub detect_files: Vec<&'a str>,
     pub detect_folders: Vec<&'a str>,
@@ -23,6 +25,8 @@ impl<'a> Default for GoConfig<'a> {
             symbol: "🐹 ",
             style: "bold cyan",
             disabled: false,
+            use_go_mod: false,
+            mismatch_style: "bold red",
             detect_extensions: vec!["go"],
             detect_files: vec![
                 "go.mod",
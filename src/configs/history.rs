@@ -0,0 +1,23 @@
+use crate::config::ModuleConfig;
+
+use serde::Serialize;
+use starship_module_config_derive::ModuleConfig;
+
+#[derive(Clone, ModuleConfig, Serialize)]
+pub struct HistoryConfig<'a> {
+    pub format: &'a str,
+    pub symbol: &'a str,
+    pub style: &'a str,
+    pub disabled: bool,
+}
+
+impl<'a> Default for HistoryConfig<'a> {
+    fn default() -> Self {
+        HistoryConfig {
+            format: "[$symbol$number]($style) ",
+            symbol: "!",
+            style: "bold dimmed white",
+            disabled: true,
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use crate::config::ModuleConfig;
+
+use serde::Serialize;
+use starship_module_config_derive::ModuleConfig;
+
+#[derive(Clone, ModuleConfig, Serialize)]
+pub struct HistoryLogConfig<'a> {
+    pub path: &'a str,
+    pub max_size: u64,
+    pub max_files: usize,
+    pub disabled: bool,
+}
+
+impl<'a> Default for HistoryLogConfig<'a> {
+    fn default() -> Self {
+        HistoryLogConfig {
+            path: "",
+            max_size: 1_048_576,
+            max_files: 3,
+            disabled: true,
+        }
+    }
+}
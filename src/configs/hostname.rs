@@ -1,5 +1,6 @@
 use crate::config::ModuleConfig;
 
+use indexmap::IndexMap;
 use serde::Serialize;
 use starship_module_config_derive::ModuleConfig;
 
@@ -7,6 +8,8 @@ use starship_module_config_derive::ModuleConfig;
 pub struct HostnameConfig<'a> {
     pub ssh_only: bool,
     pub trim_at: &'a str,
+    pub aliases: IndexMap<String, &'a str>,
+    pub class_styles: IndexMap<String, &'a str>,
     pub format: &'a str,
     pub style: &'a str,
     pub disabled: bool,
@@ -17,6 +20,8 @@ impl<'a> Default for HostnameConfig<'a> {
         HostnameConfig {
             ssh_only: true,
             trim_at: ".",
+            aliases: IndexMap::new(),
+            class_styles: IndexMap::new(),
             format: "[$hostname]($style) in ",
             style: "green dimmed bold",
             disabled: false,
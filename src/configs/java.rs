@@ -9,6 +9,7 @@ pub struct JavaConfig<'a> {
     pub format: &'a str,
     pub version_format: &'a str,
     pub style: &'a str,
+    pub mismatch_style: &'a str,
     pub symbol: &'a str,
     pub detect_extensions: Vec<&'a str>,
     pub detect_files: Vec<&'a str>,
@@ -22,6 +23,7 @@ impl<'a> Default for JavaConfig<'a> {
             version_format: "v${raw}",
             disabled: false,
             style: "red dimmed",
+            mismatch_style: "bold red",
             symbol: "☕ ",
             detect_extensions: vec!["java", "class", "jar", "gradle", "clj", "cljc"],
             detect_files: vec![
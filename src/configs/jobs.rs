@@ -8,6 +8,8 @@ pub struct JobsConfig<'a> {
     pub threshold: i64,
     pub format: &'a str,
     pub symbol: &'a str,
+    pub running_symbol: &'a str,
+    pub stopped_symbol: &'a str,
     pub style: &'a str,
     pub disabled: bool,
 }
@@ -18,6 +20,8 @@ impl<'a> Default for JobsConfig<'a> {
             threshold: 1,
             format: "[$symbol$number]($style) ",
             symbol: "✦",
+            running_symbol: "▶",
+            stopped_symbol: "⏸",
             style: "bold blue",
             disabled: false,
         }
@@ -0,0 +1,23 @@
+use crate::config::ModuleConfig;
+
+use serde::Serialize;
+use starship_module_config_derive::ModuleConfig;
+
+#[derive(Clone, ModuleConfig, Serialize)]
+pub struct LocaleConfig<'a> {
+    pub format: &'a str,
+    pub symbol: &'a str,
+    pub style: &'a str,
+    pub disabled: bool,
+}
+
+impl<'a> Default for LocaleConfig<'a> {
+    fn default() -> Self {
+        LocaleConfig {
+            format: "[$symbol$locale]($style) ",
+            symbol: "🔤 ",
+            style: "bold red",
+            disabled: true,
+        }
+    }
+}
@@ -6,8 +6,13 @@ use starship_module_config_derive::ModuleConfig;
 #[derive(Clone, ModuleConfig, Serialize)]
 pub struct MemoryConfig<'a> {
     pub threshold: i64,
+    pub ram_threshold: Option<i64>,
+    pub swap_threshold: Option<i64>,
+    pub display: &'a str,
     pub format: &'a str,
     pub style: &'a str,
+    pub ram_style: Option<&'a str>,
+    pub swap_style: Option<&'a str>,
     pub symbol: &'a str,
     pub disabled: bool,
 }
@@ -16,8 +21,13 @@ impl<'a> Default for MemoryConfig<'a> {
     fn default() -> Self {
         MemoryConfig {
             threshold: 75,
+            ram_threshold: None,
+            swap_threshold: None,
+            display: "both",
             format: "via $symbol[$ram( | $swap)]($style) ",
             style: "white bold dimmed",
+            ram_style: None,
+            swap_style: None,
             symbol: "🐏 ",
             disabled: true,
         }
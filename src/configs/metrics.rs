@@ -0,0 +1,19 @@
+use crate::config::ModuleConfig;
+
+use serde::Serialize;
+use starship_module_config_derive::ModuleConfig;
+
+#[derive(Clone, ModuleConfig, Serialize)]
+pub struct MetricsConfig<'a> {
+    pub statsd_address: &'a str,
+    pub prefix: &'a str,
+}
+
+impl<'a> Default for MetricsConfig<'a> {
+    fn default() -> Self {
+        MetricsConfig {
+            statsd_address: "",
+            prefix: "starship",
+        }
+    }
+}
@@ -9,17 +9,21 @@ pub mod character;
 pub mod cmake;
 pub mod cmd_duration;
 pub mod conda;
+pub mod container;
+pub mod cpu_temp;
 pub mod crystal;
 pub mod custom;
 pub mod dart;
 pub mod deno;
 pub mod directory;
+pub mod disk_usage;
 pub mod docker_context;
 pub mod dotnet;
 pub mod elixir;
 pub mod elm;
 pub mod env_var;
 pub mod erlang;
+pub mod fill;
 pub mod gcloud;
 pub mod git_branch;
 pub mod git_commit;
@@ -28,22 +32,33 @@ pub mod git_status;
 pub mod go;
 pub mod helm;
 pub mod hg_branch;
+pub mod history;
+pub mod history_log;
 pub mod hostname;
 pub mod java;
 pub mod jobs;
 pub mod julia;
 pub mod kotlin;
 pub mod kubernetes;
+pub mod locale;
+pub mod localip;
 pub mod lua;
 pub mod memory_usage;
+pub mod metrics;
+pub mod module_cache;
+pub mod network_fs;
 pub mod nim;
 pub mod nix_shell;
 pub mod nodejs;
 pub mod ocaml;
 pub mod openstack;
+pub mod os;
 pub mod package;
 pub mod perl;
 pub mod php;
+pub mod plugin;
+pub mod proxy;
+pub mod public_ip;
 pub mod purescript;
 pub mod python;
 pub mod red;
@@ -53,14 +68,20 @@ pub mod scala;
 pub mod shell;
 pub mod shlvl;
 pub mod singularity;
+pub mod slow_modules;
 mod starship_root;
 pub mod status;
+pub mod sudo;
 pub mod swift;
 pub mod terraform;
 pub mod time;
+pub mod uptime;
 pub mod username;
 pub mod vagrant;
 pub mod vcsh;
+pub mod vpn;
+pub mod weather;
+pub mod wifi;
 pub mod zig;
 
 pub use starship_root::*;
@@ -70,9 +91,31 @@ pub use starship_root::*;
 pub struct FullConfig<'a> {
     // Root config
     pub format: &'a str,
+    pub right_format: &'a str,
+    pub transient_format: &'a str,
+    pub continuation_format: &'a str,
+    pub window_title: &'a str,
     pub scan_timeout: u64,
+    pub scan_max_files: usize,
+    pub follow_symlinks: bool,
+    pub ignore_globs: Vec<&'a str>,
     pub command_timeout: u64,
     pub add_newline: bool,
+    pub ambiguous_width: usize,
+    pub color_depth: &'a str,
+    pub links: bool,
+    pub semantic_prompt: bool,
+    pub symbol_set: &'a str,
+    pub symbol_fallback: &'a str,
+    pub metrics: metrics::MetricsConfig<'a>,
+    pub history_log: history_log::HistoryLogConfig<'a>,
+    pub slow_modules: slow_modules::SlowModulesConfig,
+    pub module_cache: module_cache::ModuleCacheConfig<'a>,
+    pub network_fs: network_fs::NetworkFsConfig<'a>,
+    pub async_modules: Vec<&'a str>,
+    pub env_allowlist: Vec<&'a str>,
+    pub segment_separator: &'a str,
+    pub segment_subseparator: &'a str,
     // modules
     aws: aws::AwsConfig<'a>,
     battery: battery::BatteryDisplayConfig<'a>,
@@ -80,16 +123,20 @@ pub struct FullConfig<'a> {
     cmake: cmake::CMakeConfig<'a>,
     cmd_duration: cmd_duration::CmdDurationConfig<'a>,
     conda: conda::CondaConfig<'a>,
+    container: container::ContainerConfig<'a>,
+    cpu_temp: cpu_temp::CpuTempConfig<'a>,
     crystal: crystal::CrystalConfig<'a>,
     dart: dart::DartConfig<'a>,
     deno: deno::DenoConfig<'a>,
     directory: directory::DirectoryConfig<'a>,
+    disk_usage: disk_usage::DiskUsageConfig<'a>,
     docker_context: docker_context::DockerContextConfig<'a>,
     dotnet: dotnet::DotnetConfig<'a>,
     elixir: elixir::ElixirConfig<'a>,
     elm: elm::ElmConfig<'a>,
     env_var: env_var::EnvVarConfig<'a>,
     erlang: erlang::ErlangConfig<'a>,
+    fill: fill::FillConfig<'a>,
     gcloud: gcloud::GcloudConfig<'a>,
     git_branch: git_branch::GitBranchConfig<'a>,
     git_commit: git_commit::GitCommitConfig<'a>,
@@ -98,12 +145,15 @@ pub struct FullConfig<'a> {
     golang: go::GoConfig<'a>,
     helm: helm::HelmConfig<'a>,
     hg_branch: hg_branch::HgBranchConfig<'a>,
+    history: history::HistoryConfig<'a>,
     hostname: hostname::HostnameConfig<'a>,
     java: java::JavaConfig<'a>,
     jobs: jobs::JobsConfig<'a>,
     julia: julia::JuliaConfig<'a>,
     kotlin: kotlin::KotlinConfig<'a>,
     kubernetes: kubernetes::KubernetesConfig<'a>,
+    locale: locale::LocaleConfig<'a>,
+    localip: localip::LocalipConfig<'a>,
     lua: lua::LuaConfig<'a>,
     memory_usage: memory_usage::MemoryConfig<'a>,
     nim: nim::NimConfig<'a>,
@@ -111,9 +161,12 @@ pub struct FullConfig<'a> {
     nodejs: nodejs::NodejsConfig<'a>,
     ocaml: ocaml::OCamlConfig<'a>,
     openstack: openstack::OspConfig<'a>,
+    os: os::OsConfig<'a>,
     package: package::PackageConfig<'a>,
     perl: perl::PerlConfig<'a>,
     php: php::PhpConfig<'a>,
+    proxy: proxy::ProxyConfig<'a>,
+    public_ip: public_ip::PublicIpConfig<'a>,
     purescript: purescript::PureScriptConfig<'a>,
     python: python::PythonConfig<'a>,
     red: red::RedConfig<'a>,
@@ -124,22 +177,50 @@ pub struct FullConfig<'a> {
     shlvl: shlvl::ShLvlConfig<'a>,
     singularity: singularity::SingularityConfig<'a>,
     status: status::StatusConfig<'a>,
+    sudo: sudo::SudoConfig<'a>,
     swift: swift::SwiftConfig<'a>,
     terraform: terraform::TerraformConfig<'a>,
     time: time::TimeConfig<'a>,
+    uptime: uptime::UptimeConfig<'a>,
     username: username::UsernameConfig<'a>,
     vagrant: vagrant::VagrantConfig<'a>,
+    vpn: vpn::VpnConfig<'a>,
+    weather: weather::WeatherConfig<'a>,
+    wifi: wifi::WifiConfig<'a>,
     zig: zig::ZigConfig<'a>,
     custom: IndexMap<String, custom::CustomConfig<'a>>,
+    plugins: IndexMap<String, plugin::PluginConfig<'a>>,
 }
 
 impl<'a> Default for FullConfig<'a> {
     fn default() -> Self {
         Self {
             format: "$all",
+            right_format: "",
+            transient_format: "$character",
+            continuation_format: "[∙](bright-black) ",
+            window_title: "",
             scan_timeout: 30,
+            scan_max_files: 10000,
+            follow_symlinks: false,
+            ignore_globs: vec![],
             command_timeout: 500,
             add_newline: true,
+            ambiguous_width: 1,
+            color_depth: "auto",
+            links: false,
+            semantic_prompt: false,
+            symbol_set: "emoji",
+            symbol_fallback: "ascii",
+            metrics: Default::default(),
+            history_log: Default::default(),
+            slow_modules: Default::default(),
+            module_cache: Default::default(),
+            network_fs: Default::default(),
+            async_modules: vec![],
+            env_allowlist: vec![],
+            segment_separator: "",
+            segment_subseparator: "",
 
             aws: Default::default(),
             battery: Default::default(),
@@ -147,16 +228,20 @@ impl<'a> Default for FullConfig<'a> {
             cmake: Default::default(),
             cmd_duration: Default::default(),
             conda: Default::default(),
+            container: Default::default(),
+            cpu_temp: Default::default(),
             crystal: Default::default(),
             dart: Default::default(),
             deno: Default::default(),
             directory: Default::default(),
+            disk_usage: Default::default(),
             docker_context: Default::default(),
             dotnet: Default::default(),
             elixir: Default::default(),
             elm: Default::default(),
             env_var: Default::default(),
             erlang: Default::default(),
+            fill: Default::default(),
             gcloud: Default::default(),
             git_branch: Default::default(),
             git_commit: Default::default(),
@@ -165,12 +250,15 @@ impl<'a> Default for FullConfig<'a> {
             golang: Default::default(),
             helm: Default::default(),
             hg_branch: Default::default(),
+            history: Default::default(),
             hostname: Default::default(),
             java: Default::default(),
             jobs: Default::default(),
             julia: Default::default(),
             kotlin: Default::default(),
             kubernetes: Default::default(),
+            locale: Default::default(),
+            localip: Default::default(),
             lua: Default::default(),
             memory_usage: Default::default(),
             nim: Default::default(),
@@ -178,9 +266,12 @@ impl<'a> Default for FullConfig<'a> {
             nodejs: Default::default(),
             ocaml: Default::default(),
             openstack: Default::default(),
+            os: Default::default(),
             package: Default::default(),
             perl: Default::default(),
             php: Default::default(),
+            proxy: Default::default(),
+            public_ip: Default::default(),
             purescript: Default::default(),
             python: Default::default(),
             red: Default::default(),
@@ -191,13 +282,19 @@ impl<'a> Default for FullConfig<'a> {
             shlvl: Default::default(),
             singularity: Default::default(),
             status: Default::default(),
+            sudo: Default::default(),
             swift: Default::default(),
             terraform: Default::default(),
             time: Default::default(),
+            uptime: Default::default(),
             username: Default::default(),
             vagrant: Default::default(),
+            vpn: Default::default(),
+            weather: Default::default(),
+            wifi: Default::default(),
             zig: Default::default(),
             custom: Default::default(),
+            plugins: Default::default(),
         }
     }
 }
@@ -0,0 +1,53 @@
+use crate::config::ModuleConfig;
+
+use serde::Serialize;
+use starship_module_config_derive::ModuleConfig;
+
+#[derive(Clone, ModuleConfig, Serialize)]
+pub struct ModuleCacheConfig<'a> {
+    pub ttl: u64,
+    pub modules: Vec<&'a str>,
+    pub env: Vec<&'a str>,
+    pub disabled: bool,
+}
+
+impl<'a> Default for ModuleCacheConfig<'a> {
+    fn default() -> Self {
+        ModuleCacheConfig {
+            ttl: 3600,
+            modules: vec![
+                "cmake",
+                "crystal",
+                "dart",
+                "deno",
+                "dotnet",
+                "elixir",
+                "elm",
+                "erlang",
+                "golang",
+                "helm",
+                "java",
+                "julia",
+                "kotlin",
+                "lua",
+                "nim",
+                "nodejs",
+                "ocaml",
+                "perl",
+                "php",
+                "purescript",
+                "python",
+                "red",
+                "ruby",
+                "rust",
+                "scala",
+                "swift",
+                "terraform",
+                "vagrant",
+                "zig",
+            ],
+            env: vec!["PATH", "VIRTUAL_ENV", "CONDA_PREFIX", "RUSTUP_TOOLCHAIN"],
+            disabled: true,
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use crate::config::ModuleConfig;
+
+use serde::Serialize;
+use starship_module_config_derive::ModuleConfig;
+
+#[derive(Clone, ModuleConfig, Serialize)]
+pub struct NetworkFsConfig<'a> {
+    pub modules: Vec<&'a str>,
+    pub disabled: bool,
+}
+
+impl<'a> Default for NetworkFsConfig<'a> {
+    fn default() -> Self {
+        NetworkFsConfig {
+            modules: vec!["git_status"],
+            disabled: false,
+        }
+    }
+}
@@ -11,6 +11,7 @@ pub struct NodejsConfig<'a> {
     pub style: &'a str,
     pub disabled: bool,
     pub not_capable_style: &'a str,
+    pub mismatch_style: &'a str,
     pub detect_extensions: Vec<&'a str>,
     pub detect_files: Vec<&'a str>,
     pub detect_folders: Vec<&'a str>,
@@ -25,6 +26,7 @@ impl<'a> Default for NodejsConfig<'a> {
             style: "bold green",
             disabled: false,
             not_capable_style: "bold red",
+            mismatch_style: "bold red",
             detect_extensions: vec!["js", "mjs", "cjs", "ts"],
             detect_files: vec!["package.json", ".node-version", ".nvmrc"],
             detect_folders: vec!["node_modules"],
@@ -0,0 +1,24 @@
+use crate::config::ModuleConfig;
+
+use serde::Serialize;
+use starship_module_config_derive::ModuleConfig;
+use std::collections::HashMap;
+
+#[derive(Clone, ModuleConfig, Serialize)]
+pub struct OsConfig<'a> {
+    pub format: &'a str,
+    pub style: &'a str,
+    pub symbols: HashMap<String, &'a str>,
+    pub disabled: bool,
+}
+
+impl<'a> Default for OsConfig<'a> {
+    fn default() -> Self {
+        OsConfig {
+            format: "[$symbol$name]($style) ",
+            style: "bold white",
+            symbols: HashMap::new(),
+            disabled: true,
+        }
+    }
+}
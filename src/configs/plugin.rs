@@ -0,0 +1,32 @@
+use crate::config::{ModuleConfig, VecOr};
+
+use indexmap::IndexMap;
+use serde::Serialize;
+use starship_module_config_derive::ModuleConfig;
+
+#[derive(Clone, ModuleConfig, Serialize)]
+pub struct PluginConfig<'a> {
+    pub format: &'a str,
+    pub command: VecOr<&'a str>,
+    pub wasm: &'a str,
+    pub env: Vec<&'a str>,
+    pub options: IndexMap<String, toml::Value>,
+    pub style: &'a str,
+    pub description: &'a str,
+    pub disabled: bool,
+}
+
+impl<'a> Default for PluginConfig<'a> {
+    fn default() -> Self {
+        PluginConfig {
+            format: "[($output )]($style)",
+            command: VecOr::default(),
+            wasm: "",
+            env: Vec::default(),
+            options: IndexMap::default(),
+            style: "bold",
+            description: "<plugin>",
+            disabled: false,
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use crate::config::ModuleConfig;
+
+use serde::Serialize;
+use starship_module_config_derive::ModuleConfig;
+
+#[derive(Clone, ModuleConfig, Serialize)]
+pub struct ProxyConfig<'a> {
+    pub format: &'a str,
+    pub symbol: &'a str,
+    pub style: &'a str,
+    pub variables: Vec<&'a str>,
+    pub redact: bool,
+    pub disabled: bool,
+}
+
+impl<'a> Default for ProxyConfig<'a> {
+    fn default() -> Self {
+        ProxyConfig {
+            format: "via [$symbol$host]($style) ",
+            symbol: "🔀 ",
+            style: "bold purple",
+            variables: vec![
+                "https_proxy",
+                "HTTPS_PROXY",
+                "http_proxy",
+                "HTTP_PROXY",
+                "all_proxy",
+                "ALL_PROXY",
+            ],
+            redact: false,
+            disabled: true,
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use crate::config::ModuleConfig;
+
+use serde::Serialize;
+use starship_module_config_derive::ModuleConfig;
+
+#[derive(Clone, ModuleConfig, Serialize)]
+pub struct PublicIpConfig<'a> {
+    pub format: &'a str,
+    pub symbol: &'a str,
+    pub style: &'a str,
+    pub url: &'a str,
+    pub ttl: u64,
+    pub disabled: bool,
+}
+
+impl<'a> Default for PublicIpConfig<'a> {
+    fn default() -> Self {
+        PublicIpConfig {
+            format: "[$symbol$ip]($style) ",
+            symbol: "🌐 ",
+            style: "bold purple",
+            url: "https://api.ipify.org",
+            ttl: 600,
+            disabled: true,
+        }
+    }
+}
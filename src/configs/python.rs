@@ -8,6 +8,7 @@ pub struct PythonConfig<'a> {
     pub pyenv_version_name: bool,
     pub pyenv_prefix: &'a str,
     pub python_binary: VecOr<&'a str>,
+    pub prefer: Vec<&'a str>,
     pub format: &'a str,
     pub version_format: &'a str,
     pub style: &'a str,
@@ -24,6 +25,7 @@ impl<'a> Default for PythonConfig<'a> {
             pyenv_version_name: false,
             pyenv_prefix: "pyenv ",
             python_binary: VecOr(vec!["python", "python3", "python2"]),
+            prefer: vec!["venv", "pyenv", "system"],
             format: "via [${symbol}${pyenv_prefix}(${version} )(\\($virtualenv\\) )]($style)",
             version_format: "v${raw}",
             style: "yellow bold",
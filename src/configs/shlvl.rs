@@ -9,7 +9,10 @@ pub struct ShLvlConfig<'a> {
     pub format: &'a str,
     pub symbol: &'a str,
     pub repeat: bool,
+    pub repeat_max: i64,
     pub style: &'a str,
+    pub threshold_style: &'a str,
+    pub threshold_style_level: i64,
     pub disabled: bool,
 }
 
@@ -20,7 +23,10 @@ impl<'a> Default for ShLvlConfig<'a> {
             format: "[$symbol$shlvl]($style) ",
             symbol: "↕️  ", // extra space for emoji
             repeat: false,
+            repeat_max: 0,
             style: "bold yellow",
+            threshold_style: "bold red",
+            threshold_style_level: 0,
             disabled: true,
         }
     }
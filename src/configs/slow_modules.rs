@@ -0,0 +1,21 @@
+use crate::config::ModuleConfig;
+
+use serde::Serialize;
+use starship_module_config_derive::ModuleConfig;
+
+#[derive(Clone, ModuleConfig, Serialize)]
+pub struct SlowModulesConfig {
+    pub timeout: u64,
+    pub threshold: usize,
+    pub disabled: bool,
+}
+
+impl Default for SlowModulesConfig {
+    fn default() -> Self {
+        SlowModulesConfig {
+            timeout: 500,
+            threshold: 3,
+            disabled: true,
+        }
+    }
+}
@@ -1,3 +1,8 @@
+use crate::configs::history_log::HistoryLogConfig;
+use crate::configs::metrics::MetricsConfig;
+use crate::configs::module_cache::ModuleCacheConfig;
+use crate::configs::network_fs::NetworkFsConfig;
+use crate::configs::slow_modules::SlowModulesConfig;
 use crate::{config::ModuleConfig, module::ALL_MODULES};
 
 use serde::Serialize;
@@ -7,9 +12,31 @@ use std::cmp::Ordering;
 #[derive(Clone, Serialize)]
 pub struct StarshipRootConfig<'a> {
     pub format: &'a str,
+    pub right_format: &'a str,
+    pub transient_format: &'a str,
+    pub continuation_format: &'a str,
+    pub window_title: &'a str,
     pub scan_timeout: u64,
+    pub scan_max_files: usize,
+    pub follow_symlinks: bool,
+    pub ignore_globs: Vec<&'a str>,
     pub command_timeout: u64,
     pub add_newline: bool,
+    pub ambiguous_width: usize,
+    pub color_depth: &'a str,
+    pub links: bool,
+    pub semantic_prompt: bool,
+    pub symbol_set: &'a str,
+    pub symbol_fallback: &'a str,
+    pub metrics: MetricsConfig<'a>,
+    pub history_log: HistoryLogConfig<'a>,
+    pub slow_modules: SlowModulesConfig,
+    pub module_cache: ModuleCacheConfig<'a>,
+    pub network_fs: NetworkFsConfig<'a>,
+    pub async_modules: Vec<&'a str>,
+    pub env_allowlist: Vec<&'a str>,
+    pub segment_separator: &'a str,
+    pub segment_subseparator: &'a str,
 }
 
 // List of default prompt order
@@ -17,7 +44,17 @@ pub struct StarshipRootConfig<'a> {
 // prompt heading of config docs needs to be updated according to changes made here.
 pub const PROMPT_ORDER: &[&str] = &[
     "username",
+    "sudo",
     "hostname",
+    "uptime",
+    "localip",
+    "public_ip",
+    "vpn",
+    "proxy",
+    "locale",
+    "wifi",
+    "container",
+    "weather",
     "shlvl",
     "singularity",
     "kubernetes",
@@ -64,19 +101,24 @@ pub const PROMPT_ORDER: &[&str] = &[
     "nix_shell",
     "conda",
     "memory_usage",
+    "cpu_temp",
+    "disk_usage",
     "aws",
     "gcloud",
     "openstack",
     "env_var",
     "crystal",
     "custom",
+    "plugins",
     "cmd_duration",
     "line_break",
     "jobs",
+    "history",
     #[cfg(feature = "battery")]
     "battery",
     "time",
     "status",
+    "os",
     "shell",
     "character",
 ];
@@ -86,9 +128,31 @@ impl<'a> Default for StarshipRootConfig<'a> {
     fn default() -> Self {
         StarshipRootConfig {
             format: "$all",
+            right_format: "",
+            transient_format: "$character",
+            continuation_format: "[∙](bright-black) ",
+            window_title: "",
             scan_timeout: 30,
+            scan_max_files: 10000,
+            follow_symlinks: false,
+            ignore_globs: vec![],
             command_timeout: 500,
             add_newline: true,
+            ambiguous_width: 1,
+            color_depth: "auto",
+            links: false,
+            semantic_prompt: false,
+            symbol_set: "emoji",
+            symbol_fallback: "ascii",
+            metrics: MetricsConfig::default(),
+            history_log: HistoryLogConfig::default(),
+            slow_modules: SlowModulesConfig::default(),
+            module_cache: ModuleCacheConfig::default(),
+            network_fs: NetworkFsConfig::default(),
+            async_modules: vec![],
+            env_allowlist: vec![],
+            segment_separator: "",
+            segment_subseparator: "",
         }
     }
 }
@@ -98,21 +162,93 @@ impl<'a> ModuleConfig<'a> for StarshipRootConfig<'a> {
         if let toml::Value::Table(config) = config {
             config.iter().for_each(|(k, v)| match k.as_str() {
                 "format" => self.format.load_config(v),
+                "right_format" => self.right_format.load_config(v),
+                "transient_format" => self.transient_format.load_config(v),
+                "continuation_format" => self.continuation_format.load_config(v),
+                "window_title" => self.window_title.load_config(v),
                 "scan_timeout" => self.scan_timeout.load_config(v),
+                "scan_max_files" => self.scan_max_files.load_config(v),
+                "follow_symlinks" => self.follow_symlinks.load_config(v),
+                "ignore_globs" => self.ignore_globs.load_config(v),
                 "command_timeout" => self.command_timeout.load_config(v),
                 "add_newline" => self.add_newline.load_config(v),
+                "links" => self.links.load_config(v),
+                "semantic_prompt" => self.semantic_prompt.load_config(v),
+                "symbol_set" => {
+                    self.symbol_set.load_config(v);
+                    if !matches!(self.symbol_set, "emoji" | "nerd" | "ascii") {
+                        log::warn!(
+                            "\"symbol_set\" should be \"emoji\", \"nerd\" or \"ascii\", found {}",
+                            self.symbol_set
+                        );
+                        self.symbol_set = "emoji";
+                    }
+                }
+                "symbol_fallback" => self.symbol_fallback.load_config(v),
+                "ambiguous_width" => {
+                    self.ambiguous_width.load_config(v);
+                    if !matches!(self.ambiguous_width, 1 | 2) {
+                        log::warn!(
+                            "\"ambiguous_width\" should be 1 or 2, found {}",
+                            self.ambiguous_width
+                        );
+                        self.ambiguous_width = 1;
+                    }
+                }
+                "color_depth" => {
+                    self.color_depth.load_config(v);
+                    if !matches!(self.color_depth, "auto" | "truecolor" | "256" | "16") {
+                        log::warn!(
+                            "\"color_depth\" should be \"auto\", \"truecolor\", \"256\" or \"16\", found {}",
+                            self.color_depth
+                        );
+                        self.color_depth = "auto";
+                    }
+                }
+                "metrics" => self.metrics.load_config(v),
+                "history_log" => self.history_log.load_config(v),
+                "slow_modules" => self.slow_modules.load_config(v),
+                "module_cache" => self.module_cache.load_config(v),
+                "network_fs" => self.network_fs.load_config(v),
+                "async_modules" => self.async_modules.load_config(v),
+                "env_allowlist" => self.env_allowlist.load_config(v),
+                "segment_separator" => self.segment_separator.load_config(v),
+                "segment_subseparator" => self.segment_subseparator.load_config(v),
                 unknown => {
-                    if !ALL_MODULES.contains(&unknown) && unknown != "custom" {
+                    if !ALL_MODULES.contains(&unknown) && !matches!(unknown, "custom" | "plugins") {
                         log::warn!("Unknown config key '{}'", unknown);
 
                         let did_you_mean = &[
                             // Root options
                             "format",
+                            "right_format",
+                            "transient_format",
+                            "continuation_format",
+                            "window_title",
                             "scan_timeout",
+                            "scan_max_files",
+                            "follow_symlinks",
+                            "ignore_globs",
                             "command_timeout",
                             "add_newline",
+                            "ambiguous_width",
+                            "color_depth",
+                            "links",
+                            "semantic_prompt",
+                            "symbol_set",
+                            "symbol_fallback",
+                            "metrics",
+                            "history_log",
+                            "slow_modules",
+                            "module_cache",
+                            "network_fs",
+                            "async_modules",
+                            "env_allowlist",
+                            "segment_separator",
+                            "segment_subseparator",
                             // Modules
                             "custom",
+                            "plugins",
                         ]
                         .iter()
                         .chain(ALL_MODULES.iter())
@@ -7,6 +7,7 @@ use starship_module_config_derive::ModuleConfig;
 pub struct StatusConfig<'a> {
     pub format: &'a str,
     pub symbol: &'a str,
+    pub success_symbol: &'a str,
     pub not_executable_symbol: &'a str,
     pub not_found_symbol: &'a str,
     pub sigint_symbol: &'a str,
@@ -14,6 +15,10 @@ pub struct StatusConfig<'a> {
     pub style: &'a str,
     pub map_symbol: bool,
     pub recognize_signal_code: bool,
+    pub pipestatus: bool,
+    pub pipestatus_separator: &'a str,
+    pub pipestatus_format: &'a str,
+    pub pipestatus_segment_format: Option<&'a str>,
     pub disabled: bool,
 }
 
@@ -22,6 +27,7 @@ impl<'a> Default for StatusConfig<'a> {
         StatusConfig {
             format: "[$symbol$status]($style) ",
             symbol: "✖",
+            success_symbol: "",
             not_executable_symbol: "🚫",
             not_found_symbol: "🔍",
             sigint_symbol: "🧱",
@@ -29,6 +35,11 @@ impl<'a> Default for StatusConfig<'a> {
             style: "bold red",
             map_symbol: false,
             recognize_signal_code: true,
+            pipestatus: false,
+            pipestatus_separator: "|",
+            pipestatus_format:
+                "\\[$pipestatus\\] => [$symbol$common_meaning$signal_name$maybe_int]($style) ",
+            pipestatus_segment_format: None,
             disabled: true,
         }
     }
@@ -0,0 +1,29 @@
+use crate::config::ModuleConfig;
+
+use serde::Serialize;
+use starship_module_config_derive::ModuleConfig;
+
+#[derive(Clone, ModuleConfig, Serialize)]
+pub struct UptimeConfig<'a> {
+    pub format: &'a str,
+    pub symbol: &'a str,
+    pub style: &'a str,
+    pub recent_threshold: u64,
+    pub recent_style: &'a str,
+    pub ssh_only: bool,
+    pub disabled: bool,
+}
+
+impl<'a> Default for UptimeConfig<'a> {
+    fn default() -> Self {
+        UptimeConfig {
+            format: "up [$symbol$uptime]($style) ",
+            symbol: "",
+            style: "bold dimmed white",
+            recent_threshold: 3600,
+            recent_style: "bold yellow",
+            ssh_only: false,
+            disabled: true,
+        }
+    }
+}
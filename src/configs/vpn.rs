@@ -0,0 +1,25 @@
+use crate::config::ModuleConfig;
+
+use serde::Serialize;
+use starship_module_config_derive::ModuleConfig;
+
+#[derive(Clone, ModuleConfig, Serialize)]
+pub struct VpnConfig<'a> {
+    pub format: &'a str,
+    pub symbol: &'a str,
+    pub style: &'a str,
+    pub interfaces: Vec<&'a str>,
+    pub disabled: bool,
+}
+
+impl<'a> Default for VpnConfig<'a> {
+    fn default() -> Self {
+        VpnConfig {
+            format: "[$symbol$name]($style) ",
+            symbol: "🔒 ",
+            style: "bold green",
+            interfaces: vec!["wg*", "tun*", "utun*", "ppp*", "ipsec*"],
+            disabled: true,
+        }
+    }
+}
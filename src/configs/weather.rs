@@ -0,0 +1,38 @@
+use crate::config::ModuleConfig;
+
+use serde::Serialize;
+use starship_module_config_derive::ModuleConfig;
+use std::collections::HashMap;
+
+#[derive(Clone, ModuleConfig, Serialize)]
+pub struct WeatherConfig<'a> {
+    pub format: &'a str,
+    pub symbol: &'a str,
+    pub symbols: HashMap<String, &'a str>,
+    pub style: &'a str,
+    pub provider: &'a str,
+    pub location: &'a str,
+    pub api_key: &'a str,
+    pub units: &'a str,
+    pub ttl: u64,
+    pub timeout: u64,
+    pub disabled: bool,
+}
+
+impl<'a> Default for WeatherConfig<'a> {
+    fn default() -> Self {
+        WeatherConfig {
+            format: "[$symbol$temperature]($style) ",
+            symbol: "🌡️ ",
+            symbols: HashMap::new(),
+            style: "bold blue",
+            provider: "wttr.in",
+            location: "",
+            api_key: "",
+            units: "metric",
+            ttl: 1800,
+            timeout: 2000,
+            disabled: true,
+        }
+    }
+}
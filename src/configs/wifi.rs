@@ -0,0 +1,27 @@
+use crate::config::ModuleConfig;
+
+use serde::Serialize;
+use starship_module_config_derive::ModuleConfig;
+
+#[derive(Clone, ModuleConfig, Serialize)]
+pub struct WifiConfig<'a> {
+    pub format: &'a str,
+    pub symbol: &'a str,
+    pub style: &'a str,
+    pub trusted: Vec<&'a str>,
+    pub untrusted: Vec<&'a str>,
+    pub disabled: bool,
+}
+
+impl<'a> Default for WifiConfig<'a> {
+    fn default() -> Self {
+        WifiConfig {
+            format: "[$symbol$ssid( $signal)]($style) ",
+            symbol: "📶 ",
+            style: "bold blue",
+            trusted: vec![],
+            untrusted: vec![],
+            disabled: true,
+        }
+    }
+}
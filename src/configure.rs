@@ -5,9 +5,13 @@ use std::process;
 use std::process::Command;
 
 use crate::config::RootModuleConfig;
-use crate::config::StarshipConfig;
+use crate::config::{read_session_toggles, session_toggles_path, validate, StarshipConfig};
+use crate::symbol_sets;
+use crate::utils;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write;
+use std::path::Path;
 use toml::map::Map;
 use toml::value::Table;
 use toml::Value;
@@ -61,7 +65,9 @@ pub fn print_configuration(use_default: bool) {
         toml::value::Value::try_from(default_config).unwrap()
     } else {
         // Get config as toml::Value
-        let user_config = get_configuration();
+        let user_config = StarshipConfig::initialize()
+            .config
+            .expect("Failed to load starship config");
         // Convert into FullConfig and fill in default values
         let user_config = crate::configs::FullConfig::try_load(Some(&user_config));
         // Convert back to Value because toml can't serialize FullConfig directly
@@ -74,6 +80,162 @@ pub fn print_configuration(use_default: bool) {
     println!("{}", string_config);
 }
 
+/// Prints only the keys of the computed config which differ from the defaults
+pub fn print_configuration_diff() {
+    let user_config = StarshipConfig::initialize()
+        .config
+        .expect("Failed to load starship config");
+    let user_config = crate::configs::FullConfig::try_load(Some(&user_config));
+    let user_config = toml::value::Value::try_from(user_config).unwrap();
+
+    let mut default_config = default_configuration(&user_config, &|key| env::var(key).ok());
+    // Custom modules and plugins have no defaults, so compare them to the defaults of their
+    // options
+    let tables = [
+        (
+            "custom",
+            toml::value::Value::try_from(crate::configs::custom::CustomConfig::default()),
+        ),
+        (
+            "plugins",
+            toml::value::Value::try_from(crate::configs::plugin::PluginConfig::default()),
+        ),
+    ];
+    for (table, table_default) in tables.iter() {
+        let table_default = table_default.as_ref().unwrap();
+        if let (Some(modules), Some(default)) = (
+            user_config.get(table).and_then(Value::as_table),
+            default_config.as_table_mut(),
+        ) {
+            let defaults = modules
+                .keys()
+                .map(|name| (name.clone(), table_default.clone()))
+                .collect();
+            default.insert(table.to_string(), Value::Table(defaults));
+        }
+    }
+
+    match diff_configuration(&user_config, &default_config) {
+        Some(diff) => println!("{}", toml::to_string_pretty(&diff).unwrap()),
+        None => println!("# The config is the same as the default config"),
+    }
+}
+
+/// The default config for the `symbol_set` of `config`, so that the symbols of the set
+/// aren't reported as changes
+fn default_configuration(config: &Value, get_env: &dyn Fn(&str) -> Option<String>) -> Value {
+    let mut defaults = Table::new();
+    for key in ["symbol_set", "symbol_fallback"] {
+        if let Some(value) = config.get(key) {
+            defaults.insert(key.to_string(), value.clone());
+        }
+    }
+    let mut defaults = Value::Table(defaults);
+    symbol_sets::apply(&mut defaults, get_env);
+    let default_config = crate::configs::FullConfig::try_load(Some(&defaults));
+    toml::value::Value::try_from(default_config).unwrap()
+}
+
+/// The keys of `config` with other values than in `default`
+fn diff_configuration(config: &Value, default: &Value) -> Option<Value> {
+    match (config, default) {
+        (Value::Table(config), Value::Table(default)) => {
+            let diff: Table = config
+                .iter()
+                .filter_map(|(key, value)| {
+                    let diff = match default.get(key) {
+                        Some(default) => diff_configuration(value, default)?,
+                        None => value.clone(),
+                    };
+                    Some((key.clone(), diff))
+                })
+                .collect();
+            (!diff.is_empty()).then(|| Value::Table(diff))
+        }
+        (config, default) if config == default => None,
+        (config, _) => Some(config.clone()),
+    }
+}
+
+/// Prints the default config of a module, with all of its options commented out
+pub fn print_default_module_configuration(name: &str) {
+    let default_config =
+        toml::value::Value::try_from(crate::configs::FullConfig::default()).unwrap();
+    match default_config.get(name) {
+        Some(module) => {
+            let descriptions = option_descriptions(CONFIG_DOCS, name);
+            println!("{}", commented_configuration(name, module, &descriptions))
+        }
+        None => {
+            log::error!("Unknown module '{}'", name);
+            process::exit(1);
+        }
+    }
+}
+
+/// The documentation of all options, whose tables describe the options of the modules
+const CONFIG_DOCS: &str = include_str!("../docs/config/README.md");
+
+/// The descriptions of the options of a module from the table of its options in `docs`,
+/// which is the one with the row that disables the module
+fn option_descriptions(docs: &str, name: &str) -> HashMap<String, String> {
+    let disables = format!("Disables the `{}` module", name);
+    let section = match docs
+        .split("\n## ")
+        .find(|section| section.contains(&disables))
+    {
+        Some(section) => section,
+        None => return HashMap::new(),
+    };
+    section
+        .split("### Options")
+        .nth(1)
+        .unwrap_or_default()
+        .lines()
+        .skip_while(|line| !line.starts_with('|'))
+        .take_while(|line| line.starts_with('|'))
+        .filter_map(|line| {
+            let cells: Vec<&str> = line.trim_matches('|').split(" | ").collect();
+            let option = cells.first()?.trim().strip_prefix('`')?.strip_suffix('`')?;
+            let description = cells.last()?.trim();
+            Some((option.to_string(), description.to_string()))
+        })
+        .collect()
+}
+
+fn commented_configuration(
+    name: &str,
+    module: &Value,
+    descriptions: &HashMap<String, String>,
+) -> String {
+    let mut table = Table::new();
+    table.insert(name.to_string(), module.clone());
+    let config = toml::to_string_pretty(&table).unwrap();
+
+    let header = format!("[{}]", name);
+    let mut out = format!("# The default config of `{}`\n", name);
+    let mut in_module = false;
+    for line in config.lines() {
+        if line.starts_with('[') {
+            in_module = line == header;
+        }
+        let option = line.split(" = ").next().unwrap_or_default();
+        if let Some(description) = descriptions.get(option).filter(|_| in_module) {
+            out.push_str("# ");
+            out.push_str(description);
+            out.push('\n');
+        }
+        if line == header || line.is_empty() {
+            out.push_str(line);
+        } else {
+            out.push_str("# ");
+            out.push_str(line);
+        }
+        out.push('\n');
+    }
+    out
+}
+
 pub fn toggle_configuration(name: &str, key: &str) {
     if let Some(table) = get_configuration().as_table_mut() {
         match table.get(name) {
@@ -113,12 +275,74 @@ pub fn toggle_configuration(name: &str, key: &str) {
     }
 }
 
-pub fn get_configuration() -> Value {
-    let starship_config = StarshipConfig::initialize();
+/// Toggles a boolean option of a module for the current shell session only
+pub fn toggle_session_configuration(name: &str, key: &str) {
+    let path = match session_toggles_path() {
+        Some(path) => path,
+        None => {
+            log::error!("No shell session found, STARSHIP_SESSION_KEY is not set");
+            process::exit(1);
+        }
+    };
 
-    starship_config
-        .config
-        .expect("Failed to load starship config")
+    // The current value includes the session toggles, falling back to the module's default
+    let config = StarshipConfig::initialize().config;
+    let default = Value::try_from(crate::configs::FullConfig::default()).ok();
+    let current = [config, default]
+        .iter()
+        .flatten()
+        .find_map(|config| config.get(name)?.get(key))
+        .and_then(Value::as_bool);
+    let current = match current {
+        Some(current) => current,
+        None => {
+            log::error!(
+                "Given config key '{}' of module '{}' must exist in 'boolean' format",
+                key,
+                name
+            );
+            process::exit(1);
+        }
+    };
+
+    // The first toggle of a session clears those of sessions which ended long ago
+    if !path.exists() {
+        if let Some(dir) = path.parent() {
+            utils::remove_stale_files(dir, "toggles_", utils::SESSION_FILE_TTL);
+        }
+    }
+
+    let mut toggles = read_session_toggles(&path).unwrap_or_else(|| Value::Table(Table::new()));
+    set_toggle(&mut toggles, name, key, !current);
+
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| {
+            let content = toml::to_string_pretty(&toggles)
+                .map_err(|error| std::io::Error::new(ErrorKind::InvalidData, error))?;
+            std::fs::write(&path, content)
+        });
+    if let Err(error) = result {
+        log::error!("Unable to write the session toggles {:?}: {}", path, error);
+        process::exit(1);
+    }
+}
+
+fn set_toggle(toggles: &mut Value, name: &str, key: &str, value: bool) {
+    if let Some(toggles) = toggles.as_table_mut() {
+        let module = toggles
+            .entry(name.to_string())
+            .or_insert_with(|| Value::Table(Table::new()));
+        if let Some(module) = module.as_table_mut() {
+            module.insert(key.to_string(), Value::Boolean(value));
+        }
+    }
+}
+
+/// The config file as it is written, to be edited and written back
+pub fn get_configuration() -> Value {
+    StarshipConfig::config_from_file().unwrap_or_else(|| Value::Table(Table::new()))
 }
 
 pub fn write_configuration(table: &mut Table) {
@@ -158,6 +382,94 @@ pub fn edit_configuration() {
     };
 }
 
+/// Checks the config file against the configs of all modules and prints its problems.
+/// Exits with an error if there are any.
+pub fn validate_configuration() {
+    let config_path = get_config_path();
+    let display_path = Path::new(&config_path).display();
+    let content = match utils::read_file(&config_path) {
+        Ok(content) => content,
+        Err(error) => {
+            eprintln!("Unable to read {}: {}", display_path, error);
+            process::exit(1);
+        }
+    };
+
+    let problems = check_configuration(&content);
+    for (line, column, problem) in &problems {
+        println!("{}:{}:{}: {}", display_path, line, column, problem);
+    }
+    if !problems.is_empty() {
+        process::exit(1);
+    }
+    println!("{} is valid", display_path);
+}
+
+/// The problems of a config file, with the line and column they are at
+fn check_configuration(content: &str) -> Vec<(usize, usize, String)> {
+    let config = match toml::from_str::<Value>(content) {
+        Ok(config) => config,
+        Err(error) => {
+            let (line, column) = error.line_col().unwrap_or((0, 0));
+            return vec![(line + 1, column + 1, format!("invalid TOML: {}", error))];
+        }
+    };
+
+    let mut problems: Vec<_> = validate(&config)
+        .into_iter()
+        .map(|problem| {
+            let (line, column) = find_position(content, &problem.path).unwrap_or((1, 1));
+            (line, column, problem.to_string())
+        })
+        .collect();
+    problems.sort();
+    problems
+}
+
+/// Finds the line and column of a dotted key path in a TOML file, or else of the closest
+/// of its parents. Doesn't handle dots in quoted keys.
+fn find_position(content: &str, path: &str) -> Option<(usize, usize)> {
+    let path: Vec<&str> = path.split('.').collect();
+    let split_key = |key: &str| -> Vec<String> {
+        key.split('.')
+            .map(|part| {
+                part.trim()
+                    .trim_matches(|c| c == '"' || c == '\'')
+                    .to_owned()
+            })
+            .collect()
+    };
+
+    let mut table: Vec<String> = Vec::new();
+    let mut best: Option<(usize, (usize, usize))> = None;
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let column = line.len() - trimmed.len() + 1;
+        let key_path = if trimmed.starts_with('[') {
+            let header = trimmed.trim_start_matches('[');
+            table = split_key(header.split(']').next().unwrap_or_default());
+            table.clone()
+        } else if let Some((key, _)) = trimmed.split_once('=') {
+            if trimmed.starts_with('#') {
+                continue;
+            }
+            table.iter().cloned().chain(split_key(key)).collect()
+        } else {
+            continue;
+        };
+
+        // How much of the path this line matches, preferring the most specific line
+        let matched = key_path.len();
+        if matched <= path.len()
+            && key_path.iter().zip(&path).all(|(a, b)| a == b)
+            && !matches!(best, Some((best, _)) if best >= matched)
+        {
+            best = Some((matched, (index + 1, column)));
+        }
+    }
+    best.map(|(_, position)| position)
+}
+
 fn get_editor() -> String {
     get_editor_internal(env::var("VISUAL").ok(), env::var("EDITOR").ok())
 }
@@ -174,7 +486,7 @@ fn get_editor_internal(visual: Option<String>, editor: Option<String>) -> String
     STD_EDITOR.into()
 }
 
-fn get_config_path() -> OsString {
+pub(crate) fn get_config_path() -> OsString {
     if let Some(config_path) = env::var_os("STARSHIP_CONFIG") {
         return config_path;
     }
@@ -189,6 +501,165 @@ fn get_config_path() -> OsString {
 mod tests {
     use super::*;
 
+    #[test]
+    fn check_configuration_with_positions() {
+        let content = r#"
+add_newline = "no"
+
+[directory]
+truncaton_length = 3
+style = "bold blu"
+
+[custom.foo]
+when = true
+
+[profiles.work.character]
+success_symbol = 1
+"#;
+
+        assert_eq!(
+            vec![
+                (
+                    2,
+                    1,
+                    "`add_newline`: expected a boolean, found \"no\"".to_string()
+                ),
+                (
+                    5,
+                    1,
+                    "`directory.truncaton_length`: unknown key, did you mean `truncation_length`?"
+                        .to_string()
+                ),
+                (
+                    6,
+                    1,
+                    "`directory.style`: invalid style string \"bold blu\"".to_string()
+                ),
+                (
+                    9,
+                    1,
+                    "`custom.foo.when`: expected a string, found true".to_string()
+                ),
+                (
+                    12,
+                    1,
+                    "`profiles.work.character.success_symbol`: expected a string, found 1"
+                        .to_string()
+                ),
+            ],
+            check_configuration(content)
+        );
+    }
+
+    #[test]
+    fn diff_with_defaults() {
+        let default = toml::toml! {
+            add_newline = true
+            [directory]
+            truncation_length = 3
+            style = "cyan"
+            [rust]
+            disabled = false
+        };
+        let config = toml::toml! {
+            add_newline = true
+            [directory]
+            truncation_length = 3
+            style = "bold"
+            [rust]
+            disabled = false
+            [custom.foo]
+            command = "echo foo"
+        };
+
+        assert_eq!(
+            Some(toml::toml! {
+                [directory]
+                style = "bold"
+                [custom.foo]
+                command = "echo foo"
+            }),
+            diff_configuration(&config, &default)
+        );
+        assert_eq!(None, diff_configuration(&default, &default));
+    }
+
+    #[test]
+    fn comment_out_module_configuration() {
+        let module = toml::toml! {
+            format = "[$symbol]($style)"
+            disabled = false
+        };
+
+        let descriptions = [("format".to_string(), "The format.".to_string())].into();
+        assert_eq!(
+            "# The default config of `rust`\n\
+             [rust]\n\
+             # The format.\n\
+             # format = '[$symbol]($style)'\n\
+             # disabled = false\n",
+            commented_configuration("rust", &module, &descriptions)
+        );
+    }
+
+    #[test]
+    fn describe_options_from_the_docs() {
+        let descriptions = option_descriptions(CONFIG_DOCS, "rust");
+        let rust = toml::value::Value::try_from(crate::configs::rust::RustConfig::default());
+        for option in rust.unwrap().as_table().unwrap().keys() {
+            assert!(
+                descriptions.contains_key(option),
+                "{} has no description",
+                option
+            );
+        }
+        assert_eq!(
+            Some("Disables the `rust` module."),
+            descriptions.get("disabled").map(String::as_str)
+        );
+        assert!(option_descriptions(CONFIG_DOCS, "unknown").is_empty());
+    }
+
+    #[test]
+    fn diff_with_the_defaults_of_the_symbol_set() {
+        let config = toml::toml! {
+            symbol_set = "ascii"
+            [rust]
+            symbol = "rs "
+        };
+        let default = default_configuration(&config, &|_| None);
+
+        assert_eq!(
+            Some("rs "),
+            default["rust"].get("symbol").and_then(Value::as_str)
+        );
+    }
+
+    #[test]
+    fn set_session_toggles() {
+        let mut toggles = Value::Table(Table::new());
+        set_toggle(&mut toggles, "rust", "disabled", true);
+        set_toggle(&mut toggles, "git_status", "disabled", true);
+        set_toggle(&mut toggles, "rust", "disabled", false);
+
+        assert_eq!(
+            toml::toml! {
+                [git_status]
+                disabled = true
+                [rust]
+                disabled = false
+            },
+            toggles
+        );
+    }
+
+    #[test]
+    fn check_invalid_toml() {
+        let problems = check_configuration("format = \n");
+        assert_eq!(1, problems.len());
+        assert_eq!((1, 10), (problems[0].0, problems[0].1));
+    }
+
     // This is every possible permutation, 3² = 9.
     #[test]
     fn visual_set_editor_set() {
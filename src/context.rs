@@ -1,19 +1,33 @@
-use crate::config::StarshipConfig;
+use crate::async_modules::AsyncResults;
+use crate::config::{StarshipConfig, Underline};
+use crate::daemon::{ClientEnv, DaemonCache};
+use crate::metrics::StatsdSink;
 use crate::module::Module;
-use crate::utils::{exec_cmd, CommandOutput};
+use crate::module_cache::ModuleCache;
+use crate::slow_modules::SlowModuleGuard;
+use crate::utils::{exec_cmd, exec_cmd_for, CommandOutput};
 
 use crate::modules;
+use ansi_term::{Color, Style};
 use clap::ArgMatches;
 use dirs_next::home_dir;
 use git2::{ErrorCode::UnbornBranch, Repository, RepositoryState};
 use once_cell::sync::OnceCell;
-use std::collections::{HashMap, HashSet};
+use path_slash::PathExt;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
 use std::env;
 use std::ffi::OsString;
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::string::String;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
+use wildmatch::WildMatch;
 
 /// Context contains data or common methods that may be used by multiple modules.
 /// The data contained within Context will be relevant to this particular rendering
@@ -33,15 +47,30 @@ pub struct Context<'a> {
     /// A struct containing directory contents in a lookup-optimised format.
     dir_contents: OnceCell<DirContents>,
 
+    /// The scans of modules with their own scan limits, by their limits
+    limited_scans: Mutex<HashMap<ScanLimits, Arc<DirContents>>>,
+
     /// Properties to provide to modules.
     pub properties: HashMap<&'a str, String>,
 
     /// Private field to store Git information for modules who need it
     repo: OnceCell<Repo>,
 
+    /// Whether the shell runs in the Windows Subsystem for Linux
+    pub(crate) wsl: OnceCell<Option<Wsl>>,
+
+    /// The type of the network filesystem the current directory is on, if it is on one
+    network_fs: OnceCell<Option<String>>,
+
     /// The shell the user is assumed to be running
     pub shell: Shell,
 
+    /// Which prompt is being rendered
+    pub target: Target,
+
+    /// Width of the terminal, in columns
+    pub width: usize,
+
     /// A HashMap of environment variable mocks
     #[cfg(test)]
     pub env: HashMap<&'a str, String>,
@@ -51,7 +80,37 @@ pub struct Context<'a> {
     pub cmd: HashMap<&'a str, Option<CommandOutput>>,
 
     /// Timeout for the execution of commands
-    cmd_timeout: Duration,
+    pub(crate) cmd_timeout: Duration,
+
+    /// Sink for prompt latency metrics, if enabled in the configuration
+    pub metrics: Option<StatsdSink>,
+
+    /// Skips chronically slow modules, if enabled in the configuration
+    pub(crate) slow_modules: Option<SlowModuleGuard>,
+
+    /// Caches the output of modules across prompts, if enabled in the configuration
+    pub(crate) module_cache: Option<ModuleCache>,
+
+    /// The output of `async_modules` rendered in the background, if rendering a prompt
+    /// for a shell that redraws it once they are done
+    pub(crate) async_results: Option<AsyncResults>,
+
+    /// The caches kept between prompts, if rendered by `starship daemon`
+    pub(crate) daemon: Option<Arc<DaemonCache>>,
+
+    /// The working directory and environment variables of the shell, if they are not the
+    /// ones of this process
+    pub(crate) client_env: Option<ClientEnv>,
+
+    /// Whether modules record what they did, for `starship explain`
+    pub(crate) explain: bool,
+
+    /// The time each module took to render, collected by `starship compare`
+    pub(crate) module_timings: Option<Arc<Mutex<BTreeMap<String, Duration>>>>,
+
+    /// The variables of modules referenced by the formats of other modules, like
+    /// `${git_branch.branch}`, so each of them is rendered once per prompt
+    pub(crate) referenced_variables: Mutex<HashMap<String, Arc<HashMap<String, String>>>>,
 }
 
 impl<'a> Context<'a> {
@@ -59,28 +118,53 @@ impl<'a> Context<'a> {
     /// for it. "logical-path" is used when a shell allows the "current working directory"
     /// to be something other than a file system path (like powershell provider specific paths).
     pub fn new(arguments: ArgMatches) -> Context {
-        let shell = Context::get_shell();
+        Context::new_in(arguments, None)
+    }
+
+    /// Create an instance of Context for a shell whose working directory and environment
+    /// variables are not the ones of this process, e.g. a prompt sent to the daemon
+    pub fn new_for_client(arguments: ArgMatches, client: ClientEnv) -> Context {
+        Context::new_in(arguments, Some(client))
+    }
+
+    fn new_in(arguments: ArgMatches, client: Option<ClientEnv>) -> Context {
+        let get_env = |key: &str| match &client {
+            Some(client) => client.env.get(key).cloned(),
+            None => env::var(key).ok(),
+        };
+        let shell = Shell::from_name(&get_env("STARSHIP_SHELL").unwrap_or_default());
 
         // Retrieve the "current directory".
         // If the path argument is not set fall back to the OS current directory.
         let path = arguments
             .value_of("path")
             .map(PathBuf::from)
-            .or_else(|| env::current_dir().ok())
-            .or_else(|| env::var("PWD").map(PathBuf::from).ok())
+            .or_else(|| match &client {
+                Some(client) => Some(client.cwd.clone()),
+                None => env::current_dir().ok(),
+            })
+            .or_else(|| get_env("PWD").map(PathBuf::from))
             .or_else(|| arguments.value_of("logical_path").map(PathBuf::from))
             .unwrap_or_default();
 
         // Retrive the "logical directory".
         // If the path argument is not set fall back to the PWD env variable set by many shells
-        // or to the other path.
+        // or to the other path. $PWD is skipped when an explicit path was given, since it
+        // describes the process directory rather than the one being rendered.
         let logical_path = arguments
             .value_of("logical_path")
             .map(PathBuf::from)
-            .or_else(|| env::var("PWD").map(PathBuf::from).ok())
+            .or_else(|| {
+                if arguments.is_present("path") {
+                    None
+                } else {
+                    get_env("PWD").map(PathBuf::from)
+                }
+            })
             .unwrap_or_else(|| path.clone());
 
-        Context::new_with_shell_and_path(arguments, shell, path, logical_path)
+        let config = StarshipConfig::initialize_with_env(&get_env);
+        Context::new_with_config(arguments, shell, path, logical_path, config, client)
     }
 
     /// Create a new instance of Context for the provided directory
@@ -90,8 +174,29 @@ impl<'a> Context<'a> {
         path: PathBuf,
         logical_path: PathBuf,
     ) -> Context {
-        let config = StarshipConfig::initialize();
+        Context::new_with_config(
+            arguments,
+            shell,
+            path,
+            logical_path,
+            StarshipConfig::initialize(),
+            None,
+        )
+    }
+
+    /// Start building a Context without command line arguments, e.g. to embed starship
+    pub fn builder() -> ContextBuilder {
+        ContextBuilder::default()
+    }
 
+    fn new_with_config(
+        arguments: ArgMatches,
+        shell: Shell,
+        path: PathBuf,
+        logical_path: PathBuf,
+        config: StarshipConfig,
+        client_env: Option<ClientEnv>,
+    ) -> Context {
         // Unwrap the clap arguments into a simple hashtable
         // we only care about single arguments at this point, there isn't a
         // use-case for a list of arguments yet.
@@ -104,37 +209,104 @@ impl<'a> Context<'a> {
 
         // Canonicalize the current path to resolve symlinks, etc.
         // NOTE: On Windows this converts the path to extended-path syntax.
-        let current_dir = Context::expand_tilde(path);
+        let home = client_env
+            .as_ref()
+            .and_then(|client| client.env.get("HOME"))
+            .map(PathBuf::from)
+            .or_else(home_dir);
+        let current_dir = Context::expand_tilde_to(path, home);
         let current_dir = current_dir.canonicalize().unwrap_or(current_dir);
         let logical_dir = logical_path;
 
-        let cmd_timeout = Duration::from_millis(config.get_root_config().command_timeout);
+        let target = Context::get_target(&arguments);
 
-        Context {
+        let width = properties
+            .get("terminal_width")
+            .and_then(|width| width.parse().ok())
+            .or_else(|| term_size::dimensions().map(|(width, _)| width))
+            .unwrap_or(80);
+
+        let root_config = config.get_root_config();
+        let cmd_timeout = Duration::from_millis(root_config.command_timeout);
+        let metrics = StatsdSink::new(&root_config.metrics);
+
+        let mut context = Context {
             config,
             properties,
             current_dir,
             logical_dir,
             dir_contents: OnceCell::new(),
+            limited_scans: Mutex::default(),
             repo: OnceCell::new(),
+            wsl: OnceCell::new(),
+            network_fs: OnceCell::new(),
             shell,
+            target,
+            width,
             #[cfg(test)]
             env: HashMap::new(),
             #[cfg(test)]
             cmd: HashMap::new(),
             cmd_timeout,
-        }
+            metrics,
+            slow_modules: None,
+            module_cache: None,
+            async_results: None,
+            daemon: None,
+            client_env,
+            explain: false,
+            module_timings: None,
+            referenced_variables: Mutex::default(),
+        };
+        context.slow_modules = SlowModuleGuard::new(&context);
+        context.module_cache = ModuleCache::new(&context);
+        context.async_results = AsyncResults::new(&context);
+        context
     }
 
     // Tries to retrieve home directory from a table in testing mode or else retrieves it from the os
     pub fn get_home(&self) -> Option<PathBuf> {
-        if cfg!(test) {
+        // The daemon renders prompts for shells which may have another home directory
+        if cfg!(test) || self.client_env.is_some() {
             return self.get_env("HOME").map(PathBuf::from).or_else(home_dir);
         }
 
         home_dir()
     }
 
+    /// Get the directory used for starship's caches and logs, respecting `STARSHIP_CACHE`
+    pub fn get_cache_dir(&self) -> Option<PathBuf> {
+        self.get_env_os("STARSHIP_CACHE")
+            .map(PathBuf::from)
+            .or_else(|| Some(self.get_home()?.join(".cache/starship")))
+    }
+
+    /// The number of colors the terminal can show, from the `color_depth` option or else
+    /// from `COLORTERM` and `TERM`. Many terminals show all colors even with `TERM=xterm` or
+    /// `TERM=xterm-256color`, so colors are only downgraded by default for terminals which
+    /// are known to be limited, like the Linux console.
+    pub fn color_depth(&self) -> ColorDepth {
+        match self.config.get_root_config().color_depth {
+            "truecolor" => return ColorDepth::TrueColor,
+            "256" => return ColorDepth::Ansi256,
+            "16" => return ColorDepth::Ansi16,
+            _ => (),
+        }
+
+        if let Some("truecolor" | "24bit") = self.get_env("COLORTERM").as_deref() {
+            return ColorDepth::TrueColor;
+        }
+        let term = self.get_env("TERM").unwrap_or_default();
+        if matches!(term.as_str(), "linux" | "dumb")
+            || term.ends_with("-16color")
+            || term.ends_with("-8color")
+        {
+            ColorDepth::Ansi16
+        } else {
+            ColorDepth::TrueColor
+        }
+    }
+
     // Retrives a environment variable from the os or from a table if in testing mode
     #[cfg(test)]
     pub fn get_env<K: AsRef<str>>(&self, key: K) -> Option<String> {
@@ -144,7 +316,10 @@ impl<'a> Context<'a> {
     #[cfg(not(test))]
     #[inline]
     pub fn get_env<K: AsRef<str>>(&self, key: K) -> Option<String> {
-        env::var(key.as_ref()).ok()
+        match &self.client_env {
+            Some(client) => client.env.get(key.as_ref()).cloned(),
+            None => env::var(key.as_ref()).ok(),
+        }
     }
 
     // Retrives a environment variable from the os or from a table if in testing mode (os version)
@@ -156,14 +331,32 @@ impl<'a> Context<'a> {
     #[cfg(not(test))]
     #[inline]
     pub fn get_env_os<K: AsRef<str>>(&self, key: K) -> Option<OsString> {
-        env::var_os(key.as_ref())
+        match &self.client_env {
+            Some(client) => client.env.get(key.as_ref()).map(OsString::from),
+            None => env::var_os(key.as_ref()),
+        }
+    }
+
+    /// The values of the environment variables in `env_allowlist`, as the `env:VAR` variables
+    /// format strings can show
+    pub fn allowed_env(&self) -> HashMap<String, String> {
+        let config = self.config.get_root_config();
+        config
+            .env_allowlist
+            .iter()
+            .filter_map(|key| Some((format!("env:{}", key), self.get_env(key)?)))
+            .collect()
     }
 
     /// Convert a `~` in a path to the home directory
     pub fn expand_tilde(dir: PathBuf) -> PathBuf {
-        if dir.starts_with("~") {
-            let without_home = dir.strip_prefix("~").unwrap();
-            return dirs_next::home_dir().unwrap().join(without_home);
+        Context::expand_tilde_to(dir, home_dir())
+    }
+
+    /// Convert a `~` in a path to the given home directory, e.g. the one of the shell
+    pub(crate) fn expand_tilde_to(dir: PathBuf, home: Option<PathBuf>) -> PathBuf {
+        if let (Ok(without_home), Some(home)) = (dir.strip_prefix("~"), home) {
+            return home.join(without_home);
         }
         dir
     }
@@ -184,6 +377,8 @@ impl<'a> Context<'a> {
         let disabled = config.and_then(|table| table.as_table()?.get("disabled")?.as_bool());
 
         disabled == Some(true)
+            || is_hidden(config, self.width)
+            || self.is_disabled_on_network_fs(name)
     }
 
     /// Return whether the specified custom module has a `disabled` option set to true.
@@ -192,14 +387,50 @@ impl<'a> Context<'a> {
         let config = self.config.get_custom_module_config(name)?;
         let disabled = Some(config).and_then(|table| table.as_table()?.get("disabled")?.as_bool());
 
-        Some(disabled == Some(true))
+        Some(
+            disabled == Some(true)
+                || is_hidden(Some(config), self.width)
+                || self.is_disabled_on_network_fs(&format!("custom.{}", name)),
+        )
+    }
+
+    /// Whether the module is listed in `network_fs.modules` and the current directory is
+    /// on a network filesystem, where it would slow down the prompt
+    fn is_disabled_on_network_fs(&self, name: &str) -> bool {
+        let config = self.config.get_root_config().network_fs;
+        if config.disabled || !config.modules.contains(&name) {
+            return false;
+        }
+        match self.get_network_fs() {
+            Some(fs_type) => {
+                log::debug!("Skipping {:?} on a {} filesystem", name, fs_type);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Return whether the specified plugin has a `disabled` option set to true.
+    /// If it doesn't exist, `None` is returned.
+    pub fn is_plugin_disabled_in_config(&self, name: &str) -> Option<bool> {
+        let config = self.config.get_plugin_config(name)?;
+        let disabled = Some(config).and_then(|table| table.as_table()?.get("disabled")?.as_bool());
+
+        Some(disabled == Some(true) || is_hidden(Some(config), self.width))
     }
 
     // returns a new ScanDir struct with reference to current dir_files of context
     // see ScanDir for methods
     pub fn try_begin_scan(&'a self) -> Option<ScanDir<'a>> {
+        // Modules with their own limits scan the directory again, once for each limits
+        let overrides = MODULE_OVERRIDES.with(Cell::get);
+        let dir_contents = if overrides.has_scan_limits() {
+            ScannedContents::Shared(self.limited_scan(overrides)?)
+        } else {
+            ScannedContents::Borrowed(self.dir_contents().ok()?)
+        };
         Some(ScanDir {
-            dir_contents: self.dir_contents().ok()?,
+            dir_contents,
             files: &[],
             folders: &[],
             extensions: &[],
@@ -207,13 +438,45 @@ impl<'a> Context<'a> {
     }
 
     /// Will lazily get repo root and branch when a module requests it.
+    /// Detects the Windows Subsystem for Linux, which runs a Linux kernel with `microsoft` in
+    /// its release
+    pub fn get_wsl(&self) -> Option<&Wsl> {
+        self.wsl
+            .get_or_init(|| {
+                if !cfg!(target_os = "linux") {
+                    return None;
+                }
+                let kernel_release = fs::read_to_string("/proc/sys/kernel/osrelease").ok()?;
+                Wsl::detect(&kernel_release, self.get_env("WSL_DISTRO_NAME"))
+            })
+            .as_ref()
+    }
+
+    /// Detects whether the current directory is on a network or FUSE filesystem, returning
+    /// its type, e.g. `nfs4` or `fuse.sshfs`
+    pub fn get_network_fs(&self) -> Option<&str> {
+        self.network_fs
+            .get_or_init(|| {
+                filesystem_type(&self.current_dir).filter(|fs_type| is_network_fs_type(fs_type))
+            })
+            .as_deref()
+    }
+
     pub fn get_repo(&self) -> Result<&Repo, std::io::Error> {
         self.repo
             .get_or_try_init(|| -> Result<Repo, std::io::Error> {
-                let repository = if env::var("GIT_DIR").is_ok() {
-                    Repository::open_from_env().ok()
-                } else {
-                    Repository::discover(&self.current_dir).ok()
+                let git_dir = self.get_env("GIT_DIR");
+                let daemon = self.daemon.as_ref().filter(|_| git_dir.is_none());
+                if let Some(repo) = daemon.and_then(|daemon| daemon.repo(&self.current_dir)) {
+                    return Ok(repo);
+                }
+
+                let repository = match (git_dir, &self.client_env) {
+                    (Some(git_dir), Some(client)) => {
+                        Repository::open(client.cwd.join(git_dir)).ok()
+                    }
+                    (Some(_), None) => Repository::open_from_env().ok(),
+                    (None, _) => Repository::discover(&self.current_dir).ok(),
                 };
                 let branch = repository
                     .as_ref()
@@ -221,47 +484,131 @@ impl<'a> Context<'a> {
                 let root = repository
                     .as_ref()
                     .and_then(|repo| repo.workdir().map(Path::to_path_buf));
+                let path = repository.as_ref().map(|repo| repo.path().to_path_buf());
                 let state = repository.as_ref().map(|repo| repo.state());
                 let remote = repository
                     .as_ref()
                     .and_then(|repo| get_remote_repository_info(repo));
-                Ok(Repo {
+                let (is_sparse, is_shallow) = match repository.as_ref() {
+                    Some(repo) => (
+                        is_sparse_checkout(repo),
+                        repo.path().join("shallow").exists(),
+                    ),
+                    None => (false, false),
+                };
+                let repo = Repo {
                     branch,
                     root,
+                    path,
                     state,
                     remote,
-                })
+                    is_sparse,
+                    is_shallow,
+                };
+                if let (Some(daemon), Some(repository)) = (daemon, &repository) {
+                    daemon.store_repo(&self.current_dir, repository.path(), &repo);
+                }
+                Ok(repo)
             })
     }
 
     pub fn dir_contents(&self) -> Result<&DirContents, std::io::Error> {
         self.dir_contents.get_or_try_init(|| {
-            let timeout = Duration::from_millis(self.config.get_root_config().scan_timeout);
-            DirContents::from_path_with_timeout(&self.current_dir, timeout)
+            let options = self.scan_options(ModuleOverrides::default());
+            DirContents::from_path_with_options(&self.current_dir, &options)
         })
     }
 
+    /// The options of directory scans from the root options, unless overridden
+    /// The scan of the current directory with the limits a module overrides
+    fn limited_scan(&self, overrides: ModuleOverrides) -> Option<Arc<DirContents>> {
+        let limits = ScanLimits {
+            timeout: overrides.scan_timeout,
+            max_depth: overrides.max_depth,
+            max_files: overrides.max_files,
+        };
+        if let Some(scan) = self.limited_scans.lock().unwrap().get(&limits) {
+            return Some(scan.clone());
+        }
+        let options = self.scan_options(overrides);
+        let scan = DirContents::from_path_with_options(&self.current_dir, &options).ok()?;
+        let mut scans = self.limited_scans.lock().unwrap();
+        Some(
+            scans
+                .entry(limits)
+                .or_insert_with(|| Arc::new(scan))
+                .clone(),
+        )
+    }
+
+    fn scan_options(&self, overrides: ModuleOverrides) -> ScanOptions {
+        let config = self.config.get_root_config();
+        ScanOptions {
+            timeout: overrides
+                .scan_timeout
+                .unwrap_or_else(|| Duration::from_millis(config.scan_timeout)),
+            max_depth: overrides.max_depth.unwrap_or(1),
+            max_files: overrides.max_files.unwrap_or(config.scan_max_files),
+            follow_symlinks: config.follow_symlinks,
+            ignore_globs: config
+                .ignore_globs
+                .iter()
+                .map(|glob| WildMatch::new(glob))
+                .collect(),
+        }
+    }
+
+    fn get_target(arguments: &ArgMatches) -> Target {
+        if arguments.is_present("transient") {
+            Target::Transient
+        } else if arguments.is_present("continuation") {
+            Target::Continuation
+        } else if arguments.is_present("right") {
+            Target::Right
+        } else {
+            Target::Main
+        }
+    }
+
     fn get_shell() -> Shell {
-        let shell = env::var("STARSHIP_SHELL").unwrap_or_default();
-        match shell.as_str() {
-            "bash" => Shell::Bash,
-            "fish" => Shell::Fish,
-            "ion" => Shell::Ion,
-            "powershell" => Shell::PowerShell,
-            "zsh" => Shell::Zsh,
-            "elvish" => Shell::Elvish,
-            "tcsh" => Shell::Tcsh,
-            _ => Shell::Unknown,
+        Shell::from_name(&env::var("STARSHIP_SHELL").unwrap_or_default())
+    }
+
+    /// Finds a program in the `PATH` of the shell
+    pub fn which(&self, program: &str) -> which::Result<PathBuf> {
+        let cwd = self
+            .client_env
+            .as_ref()
+            .map_or(self.current_dir.as_path(), |client| client.cwd.as_path());
+        which::which_in(program, self.get_env_os("PATH"), cwd)
+    }
+
+    /// Creates a command which runs with the working directory and environment variables
+    /// of the shell
+    pub fn command<S: AsRef<std::ffi::OsStr>>(&self, program: S) -> Command {
+        let mut command = Command::new(program);
+        if let Some(client) = &self.client_env {
+            client.apply(&mut command);
         }
+        command
     }
 
     pub fn get_cmd_duration(&self) -> Option<u128> {
         self.properties.get("cmd_duration")?.parse::<u128>().ok()
     }
 
+    /// The timeout for commands run by the module being rendered
+    pub fn command_timeout(&self) -> Duration {
+        MODULE_OVERRIDES
+            .with(Cell::get)
+            .command_timeout
+            .unwrap_or(self.cmd_timeout)
+    }
+
     /// Execute a command and return the output on stdout and stderr if successful
     #[inline]
     pub fn exec_cmd(&self, cmd: &str, args: &[&str]) -> Option<CommandOutput> {
+        let timeout = self.command_timeout();
         #[cfg(test)]
         {
             let command = match args.len() {
@@ -272,12 +619,370 @@ impl<'a> Context<'a> {
                 return output.clone();
             }
         }
-        exec_cmd(cmd, args, self.cmd_timeout)
+        let start = Instant::now();
+        let output = match &self.client_env {
+            Some(client) => exec_cmd_for(cmd, args, timeout, client),
+            None => exec_cmd(cmd, args, timeout),
+        };
+        record_command(start.elapsed(), || match args.len() {
+            0 => cmd.to_owned(),
+            _ => format!("{} {}", cmd, args.join(" ")),
+        });
+        output
+    }
+}
+
+/// Builds a [`Context`] from typed values rather than the arguments of `starship prompt`
+///
+/// Unset values are found like `starship prompt` does: the directory is the current one,
+/// the shell is read from `STARSHIP_SHELL` and the config from `starship.toml`.
+#[derive(Default)]
+pub struct ContextBuilder {
+    path: Option<PathBuf>,
+    logical_path: Option<PathBuf>,
+    shell: Option<Shell>,
+    config: Option<StarshipConfig>,
+    target: Option<Target>,
+    width: Option<usize>,
+    properties: HashMap<&'static str, String>,
+    client_env: Option<ClientEnv>,
+}
+
+impl ContextBuilder {
+    /// The directory to render the prompt for
+    pub fn path<T: Into<PathBuf>>(mut self, path: T) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// The directory as shown to the user, e.g. without symlinks resolved
+    pub fn logical_path<T: Into<PathBuf>>(mut self, path: T) -> Self {
+        self.logical_path = Some(path.into());
+        self
+    }
+
+    pub fn shell(mut self, shell: Shell) -> Self {
+        self.shell = Some(shell);
+        self
+    }
+
+    pub fn config(mut self, config: StarshipConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Which prompt to render, the main one by default
+    pub fn target(mut self, target: Target) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    /// The width of the terminal, in columns
+    pub fn terminal_width(mut self, width: usize) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    /// The exit code of the previous command
+    pub fn status_code(mut self, status_code: i32) -> Self {
+        self.properties
+            .insert("status_code", status_code.to_string());
+        self
+    }
+
+    /// The exit codes of the commands of the previous pipeline
+    pub fn pipestatus(mut self, pipestatus: &[i32]) -> Self {
+        let pipestatus: Vec<String> = pipestatus.iter().map(i32::to_string).collect();
+        self.properties.insert("pipestatus", pipestatus.join(" "));
+        self
+    }
+
+    /// The history event number of the next command
+    pub fn history_number(mut self, number: u64) -> Self {
+        self.properties.insert("history_number", number.to_string());
+        self
+    }
+
+    /// How long the previous command took, in milliseconds
+    pub fn cmd_duration(mut self, duration: u128) -> Self {
+        self.properties.insert("cmd_duration", duration.to_string());
+        self
+    }
+
+    /// The number of jobs running in the background
+    pub fn jobs(mut self, jobs: u64) -> Self {
+        self.properties.insert("jobs", jobs.to_string());
+        self
+    }
+
+    /// The number of jobs which are running, rather than stopped
+    pub fn running_jobs(mut self, jobs: u64) -> Self {
+        self.properties.insert("running_jobs", jobs.to_string());
+        self
+    }
+
+    /// The number of stopped jobs, e.g. suspended with Ctrl-Z
+    pub fn stopped_jobs(mut self, jobs: u64) -> Self {
+        self.properties.insert("stopped_jobs", jobs.to_string());
+        self
+    }
+
+    /// The keymap of the shell, e.g. `vicmd` for the normal mode of vi keybindings
+    pub fn keymap<T: Into<String>>(mut self, keymap: T) -> Self {
+        self.properties.insert("keymap", keymap.into());
+        self
+    }
+
+    /// The working directory and environment variables of the shell the prompt is for, when
+    /// they aren't the ones of this process
+    pub fn client_env(mut self, client: ClientEnv) -> Self {
+        self.client_env = Some(client);
+        self
+    }
+
+    pub fn build<'a>(self) -> Context<'a> {
+        let client_cwd = self.client_env.as_ref().map(|client| client.cwd.clone());
+        let path = self
+            .path
+            .or(client_cwd)
+            .or_else(|| env::current_dir().ok())
+            .unwrap_or_default();
+        let logical_path = self.logical_path.unwrap_or_else(|| path.clone());
+        let mut context = Context::new_with_config(
+            ArgMatches::default(),
+            self.shell.unwrap_or_else(Context::get_shell),
+            path,
+            logical_path,
+            self.config.unwrap_or_else(StarshipConfig::initialize),
+            self.client_env,
+        );
+        context.properties.extend(self.properties);
+        context.target = self.target.unwrap_or(Target::Main);
+        if let Some(width) = self.width {
+            context.width = width;
+        }
+        context
+    }
+}
+
+/// Limits set in the table of a module, overriding the root options
+#[derive(Clone, Copy, Default)]
+pub struct ModuleOverrides {
+    pub command_timeout: Option<Duration>,
+    pub scan_timeout: Option<Duration>,
+    pub max_depth: Option<usize>,
+    pub max_files: Option<usize>,
+}
+
+impl ModuleOverrides {
+    const NONE: Self = Self {
+        command_timeout: None,
+        scan_timeout: None,
+        max_depth: None,
+        max_files: None,
+    };
+
+    pub fn from_config(config: Option<&toml::Value>) -> Self {
+        let get = |key: &str| {
+            config?
+                .get(key)?
+                .as_integer()
+                .and_then(|value| u64::try_from(value).ok())
+        };
+        Self {
+            command_timeout: get("command_timeout").map(Duration::from_millis),
+            scan_timeout: get("scan_timeout").map(Duration::from_millis),
+            max_depth: get("max_depth").map(|depth| depth as usize),
+            max_files: get("max_files").map(|files| files as usize),
+        }
+    }
+
+    fn has_scan_limits(&self) -> bool {
+        self.scan_timeout.is_some() || self.max_depth.is_some() || self.max_files.is_some()
+    }
+}
+
+/// The scan limits a module overrides, which modules with the same ones share a scan for
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct ScanLimits {
+    timeout: Option<Duration>,
+    max_depth: Option<usize>,
+    max_files: Option<usize>,
+}
+
+thread_local! {
+    /// The overrides of the module being rendered on this thread
+    static MODULE_OVERRIDES: Cell<ModuleOverrides> = const { Cell::new(ModuleOverrides::NONE) };
+    /// What the module being rendered on this thread did, if measured
+    static MODULE_TRACE: RefCell<Option<Arc<ModuleTrace>>> = const { RefCell::new(None) };
+    /// The variables the module being rendered on this thread can show besides its own
+    static FORMAT_VARIABLES: RefCell<Option<Arc<HashMap<String, String>>>> = const { RefCell::new(None) };
+}
+
+/// What a module did while being rendered, shown by `starship explain`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ModuleDetails {
+    /// The file, folder or extension which made the module show
+    pub detected_by: Option<String>,
+    /// The values of the variables in the format string
+    pub variables: BTreeMap<String, String>,
+    /// The commands the module ran, with how long they took
+    pub commands: Vec<(String, Duration)>,
+}
+
+#[derive(Default)]
+struct ModuleTrace {
+    /// The total time of the commands run by the module, in nanoseconds
+    command_time: AtomicU64,
+    /// Only recorded for `starship explain`
+    details: Option<Mutex<ModuleDetails>>,
+}
+
+impl ModuleTrace {
+    fn record_details(&self, record: impl FnOnce(&mut ModuleDetails)) {
+        if let Some(details) = &self.details {
+            if let Ok(mut details) = details.lock() {
+                record(&mut details);
+            }
+        }
+    }
+}
+
+fn with_trace(record: impl FnOnce(&ModuleTrace)) {
+    MODULE_TRACE.with(|cell| {
+        if let Some(trace) = &*cell.borrow() {
+            record(trace);
+        }
+    });
+}
+
+/// The state of the module being rendered on this thread, to render parts of it on other
+/// threads, e.g. the variables of its format string
+#[derive(Clone)]
+pub struct ModuleScope {
+    overrides: ModuleOverrides,
+    trace: Option<Arc<ModuleTrace>>,
+    variables: Option<Arc<HashMap<String, String>>>,
+}
+
+impl ModuleScope {
+    pub fn current() -> Self {
+        ModuleScope {
+            overrides: MODULE_OVERRIDES.with(Cell::get),
+            trace: MODULE_TRACE.with(|cell| cell.borrow().clone()),
+            variables: FORMAT_VARIABLES.with(|cell| cell.borrow().clone()),
+        }
+    }
+
+    /// Runs `render` on the current thread as part of the module of this scope
+    pub fn enter<T>(&self, render: impl FnOnce() -> T) -> T {
+        let previous_trace = MODULE_TRACE.with(|cell| cell.replace(self.trace.clone()));
+        let previous_variables = FORMAT_VARIABLES.with(|cell| cell.replace(self.variables.clone()));
+        let result = with_overrides(self.overrides, render);
+        FORMAT_VARIABLES.with(|cell| cell.replace(previous_variables));
+        MODULE_TRACE.with(|cell| cell.replace(previous_trace));
+        result
     }
 }
 
-#[derive(Debug)]
+/// Renders a module with `render`, also returning how long the commands it ran took, and
+/// what it did if `explain` is set
+pub fn measure_commands<T>(
+    explain: bool,
+    render: impl FnOnce() -> T,
+) -> (T, Duration, Option<ModuleDetails>) {
+    let trace = Arc::new(ModuleTrace {
+        command_time: AtomicU64::new(0),
+        details: explain.then(Default::default),
+    });
+    let previous = MODULE_TRACE.with(|cell| cell.replace(Some(Arc::clone(&trace))));
+    let result = render();
+    MODULE_TRACE.with(|cell| cell.replace(previous));
+
+    let command_time = Duration::from_nanos(trace.command_time.load(Ordering::Relaxed));
+    let details = trace
+        .details
+        .as_ref()
+        .and_then(|details| Some(std::mem::take(&mut *details.lock().ok()?)));
+    (result, command_time, details)
+}
+
+/// Adds a command to the module being rendered, if it is measured. `describe` gives the
+/// command line, for `starship explain`.
+pub fn record_command(elapsed: Duration, describe: impl FnOnce() -> String) {
+    with_trace(|trace| {
+        let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        trace.command_time.fetch_add(nanos, Ordering::Relaxed);
+        trace.record_details(|details| details.commands.push((describe(), elapsed)));
+    });
+}
+
+/// Records why the module being rendered is shown, if it wasn't already
+pub fn record_detection(describe: impl FnOnce() -> String) {
+    with_trace(|trace| {
+        trace.record_details(|details| {
+            details.detected_by.get_or_insert_with(describe);
+        })
+    });
+}
+
+/// Records the values of the variables of the module being rendered, if explaining it
+pub fn record_variables<I: IntoIterator<Item = (String, String)>>(variables: impl FnOnce() -> I) {
+    with_trace(|trace| trace.record_details(|details| details.variables.extend(variables())));
+}
+
+/// Renders a module with `render`, applying the limits it overrides to its commands and
+/// directory scans
+pub fn with_overrides<T>(overrides: ModuleOverrides, render: impl FnOnce() -> T) -> T {
+    let previous = MODULE_OVERRIDES.with(|cell| cell.replace(overrides));
+    let result = render();
+    MODULE_OVERRIDES.with(|cell| cell.set(previous));
+    result
+}
+
+/// Renders a module with `render`, letting its format strings show `variables` besides its
+/// own, like `$env:VAR` or `${git_branch.branch}`
+pub fn with_format_variables<T>(
+    variables: Arc<HashMap<String, String>>,
+    render: impl FnOnce() -> T,
+) -> T {
+    let previous = FORMAT_VARIABLES.with(|cell| cell.replace(Some(variables)));
+    let result = render();
+    FORMAT_VARIABLES.with(|cell| cell.replace(previous));
+    result
+}
+
+/// The value of a variable the module being rendered can show besides its own
+pub fn format_variable(key: &str) -> Option<String> {
+    FORMAT_VARIABLES.with(|cell| cell.borrow().as_ref()?.get(key).cloned())
+}
+
+/// How much of a directory is scanned
+pub struct ScanOptions {
+    pub timeout: Duration,
+    /// How many levels of folders are scanned, 1 being only the directory itself
+    pub max_depth: usize,
+    /// How many entries are scanned at most
+    pub max_files: usize,
+    /// Whether symlinked folders are scanned like other folders, or only listed
+    pub follow_symlinks: bool,
+    /// Entries skipped by their name or path relative to the directory
+    pub ignore_globs: Vec<WildMatch>,
+}
+
+/// Whether the entry at `path`, relative to the scanned directory, matches one of the globs
+fn is_ignored(ignore_globs: &[WildMatch], path: &Path) -> bool {
+    let path = path.to_slash_lossy();
+    let name = path.rsplit('/').next().unwrap_or_default();
+    ignore_globs
+        .iter()
+        .any(|glob| glob.is_match(name) || glob.is_match(&path))
+}
+
+#[derive(Debug, Clone)]
 pub struct DirContents {
+    // The directory the contents were scanned from.
+    base: PathBuf,
     // HashSet of all files, no folders, relative to the base directory given at construction.
     files: HashSet<PathBuf>,
     // HashSet of all file names, e.g. the last section without any folders, as strings.
@@ -286,35 +991,70 @@ pub struct DirContents {
     folders: HashSet<PathBuf>,
     // HashSet of all extensions found, without dots, e.g. "js" instead of ".js".
     extensions: HashSet<String>,
+    // Whether the scan stopped early, at the time or file limit.
+    truncated: bool,
+    // Whether symlinked folders were scanned, and the entries skipped, for entries missed by
+    // the scan.
+    follow_symlinks: bool,
+    ignore_globs: Vec<WildMatch>,
 }
 
 impl DirContents {
     #[cfg(test)]
     fn from_path(base: &Path) -> Result<Self, std::io::Error> {
-        Self::from_path_with_timeout(base, Duration::from_secs(30))
+        Self::from_path_with_options(
+            base,
+            &ScanOptions {
+                timeout: Duration::from_secs(30),
+                max_depth: 1,
+                max_files: usize::MAX,
+                follow_symlinks: true,
+                ignore_globs: Vec::new(),
+            },
+        )
     }
 
-    fn from_path_with_timeout(base: &Path, timeout: Duration) -> Result<Self, std::io::Error> {
+    fn from_path_with_options(base: &Path, options: &ScanOptions) -> Result<Self, std::io::Error> {
         let start = Instant::now();
 
         let mut folders: HashSet<PathBuf> = HashSet::new();
         let mut files: HashSet<PathBuf> = HashSet::new();
         let mut file_names: HashSet<String> = HashSet::new();
         let mut extensions: HashSet<String> = HashSet::new();
+        let mut truncated = false;
+
+        // Scan folders breadth-first, so the closest entries are found before a limit is hit
+        let mut count: usize = 0;
+        let mut pending = VecDeque::new();
+        let mut current = Some((fs::read_dir(base)?, 1));
+        'scan: while let Some((entries, depth)) = current.take() {
+            for entry in entries.filter_map(Result::ok) {
+                // only check timeout once every 2^8 entries
+                if count >= options.max_files
+                    || (count & 0xFF == 0 && start.elapsed() >= options.timeout)
+                {
+                    truncated = true;
+                    break 'scan;
+                }
+                count += 1;
 
-        fs::read_dir(base)?
-            .enumerate()
-            .take_while(|(n, _)| {
-                n & 0xFF != 0 // only check timeout once every 2^8 entries
-                || start.elapsed() < timeout
-            })
-            .filter_map(|(_, entry)| entry.ok())
-            .for_each(|entry| {
                 let path = PathBuf::from(entry.path().strip_prefix(base).unwrap());
+                let is_symlink = entry
+                    .file_type()
+                    .map(|file_type| file_type.is_symlink())
+                    .unwrap_or(true);
+                if is_ignored(&options.ignore_globs, &path) {
+                    continue;
+                }
+                let is_hidden = entry.file_name().to_string_lossy().starts_with('.');
                 if entry.path().is_dir() {
+                    let is_followed = !is_symlink || options.follow_symlinks;
+                    if depth < options.max_depth && !is_hidden && is_followed {
+                        pending.push_back((entry.path(), depth + 1));
+                    }
                     folders.insert(path);
                 } else {
-                    if !path.to_string_lossy().starts_with('.') {
+                    if !is_hidden {
                         path.extension()
                             .map(|ext| extensions.insert(ext.to_string_lossy().to_string()));
                     }
@@ -323,18 +1063,33 @@ impl DirContents {
                     }
                     files.insert(path);
                 }
-            });
+            }
+            current = std::iter::from_fn(|| pending.pop_front())
+                .find_map(|(dir, depth)| Some((fs::read_dir(dir).ok()?, depth)));
+        }
 
         log::trace!(
             "Building HashSets of directory files, folders and extensions took {:?}",
             start.elapsed()
         );
+        if truncated {
+            log::debug!(
+                "Stopped scanning {:?} after {} entries and {:?}",
+                base,
+                count,
+                start.elapsed()
+            );
+        }
 
         Ok(DirContents {
+            base: base.to_path_buf(),
             files,
             file_names,
             folders,
             extensions,
+            truncated,
+            follow_symlinks: options.follow_symlinks,
+            ignore_globs: options.ignore_globs.clone(),
         })
     }
 
@@ -342,12 +1097,40 @@ impl DirContents {
         self.files.iter()
     }
 
+    // Files and folders missed by a truncated scan are looked up directly, skipping the same
+    // entries as the scan. Extensions can't be, so only the ones found before the scan stopped
+    // are known.
+    fn missed_entry(&self, path: &str) -> Option<fs::Metadata> {
+        if !self.truncated {
+            return None;
+        }
+        let mut entry = PathBuf::new();
+        let mut components = Path::new(path).components().peekable();
+        while let Some(component) = components.next() {
+            entry.push(component);
+            let is_symlink = fs::symlink_metadata(self.base.join(&entry))
+                .ok()?
+                .file_type()
+                .is_symlink();
+            // Symlinks are only skipped as folders to scan, the entry itself is still found
+            let is_scanned_folder = components.peek().is_some();
+            if (is_symlink && is_scanned_folder && !self.follow_symlinks)
+                || is_ignored(&self.ignore_globs, &entry)
+            {
+                return None;
+            }
+        }
+        fs::metadata(self.base.join(path)).ok()
+    }
+
     pub fn has_file(&self, path: &str) -> bool {
         self.files.contains(Path::new(path))
+            || matches!(self.missed_entry(path), Some(metadata) if metadata.is_file())
     }
 
     pub fn has_file_name(&self, name: &str) -> bool {
         self.file_names.contains(name)
+            || matches!(self.missed_entry(name), Some(metadata) if metadata.is_file())
     }
 
     pub fn has_any_file_name(&self, names: &[&str]) -> bool {
@@ -356,6 +1139,7 @@ impl DirContents {
 
     pub fn has_folder(&self, path: &str) -> bool {
         self.folders.contains(Path::new(path))
+            || matches!(self.missed_entry(path), Some(metadata) if metadata.is_dir())
     }
 
     pub fn has_any_folder(&self, paths: &[&str]) -> bool {
@@ -371,6 +1155,7 @@ impl DirContents {
     }
 }
 
+#[derive(Clone)]
 pub struct Repo {
     /// If `current_dir` is a git repository or is contained within one,
     /// this is the current branch name of that repo.
@@ -380,23 +1165,53 @@ pub struct Repo {
     /// this is the path to the root of that repo.
     pub root: Option<PathBuf>,
 
+    /// If `current_dir` is a git repository or is contained within one,
+    /// this is the path to its git dir, e.g. `.git` or the git dir of a worktree.
+    pub path: Option<PathBuf>,
+
     /// State
     pub state: Option<RepositoryState>,
 
     /// Remote repository
     pub remote: Option<Remote>,
+
+    /// Whether the repository uses a sparse checkout
+    pub is_sparse: bool,
+
+    /// Whether the repository is a shallow clone
+    pub is_shallow: bool,
 }
 
 /// Remote repository
+#[derive(Clone)]
 pub struct Remote {
     pub branch: Option<String>,
     pub name: Option<String>,
+    pub url: Option<String>,
 }
 
 // A struct of Criteria which will be used to verify current PathBuf is
 // of X language, criteria can be set via the builder pattern
+/// The contents of the current directory, scanned for all modules or shared by the ones
+/// with the same scan limits
+enum ScannedContents<'a> {
+    Borrowed(&'a DirContents),
+    Shared(Arc<DirContents>),
+}
+
+impl std::ops::Deref for ScannedContents<'_> {
+    type Target = DirContents;
+
+    fn deref(&self) -> &DirContents {
+        match self {
+            ScannedContents::Borrowed(contents) => contents,
+            ScannedContents::Shared(contents) => contents,
+        }
+    }
+}
+
 pub struct ScanDir<'a> {
-    dir_contents: &'a DirContents,
+    dir_contents: ScannedContents<'a>,
     files: &'a [&'a str],
     folders: &'a [&'a str],
     extensions: &'a [&'a str],
@@ -421,10 +1236,153 @@ impl<'a> ScanDir<'a> {
     /// based on the current PathBuf check to see
     /// if any of this criteria match or exist and returning a boolean
     pub fn is_match(&self) -> bool {
-        self.dir_contents.has_any_extension(self.extensions)
-            || self.dir_contents.has_any_folder(self.folders)
-            || self.dir_contents.has_any_file_name(self.files)
+        let contents = &self.dir_contents;
+        if let Some(extension) = self
+            .extensions
+            .iter()
+            .find(|ext| contents.has_extension(ext))
+        {
+            record_detection(|| format!("*.{} files", extension));
+        } else if let Some(folder) = self.folders.iter().find(|path| contents.has_folder(path)) {
+            record_detection(|| format!("the {} folder", folder));
+        } else if let Some(file) = self.files.iter().find(|name| contents.has_file_name(name)) {
+            record_detection(|| format!("the {} file", file));
+        } else {
+            return false;
+        }
+        true
+    }
+}
+
+/// Whether a module's config hides it with `display = "hidden"`, or with a
+/// `min_terminal_width` wider than the terminal
+pub(crate) fn is_hidden(config: Option<&toml::Value>, width: usize) -> bool {
+    let table = match config.and_then(toml::Value::as_table) {
+        Some(table) => table,
+        None => return false,
+    };
+    let min_width = table
+        .get("min_terminal_width")
+        .and_then(toml::Value::as_integer)
+        .unwrap_or_default();
+    table.get("display").and_then(toml::Value::as_str) == Some("hidden") || min_width > width as i64
+}
+
+/// The type of the filesystem containing `path`, from the mount table
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn filesystem_type(path: &Path) -> Option<String> {
+    let mounts = fs::read_to_string("/proc/self/mounts").ok()?;
+    mount_fs_type(&mounts, path).map(String::from)
+}
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "openbsd"
+))]
+fn filesystem_type(path: &Path) -> Option<String> {
+    let stats = nix::sys::statfs::statfs(path).ok()?;
+    Some(stats.filesystem_type_name().to_string())
+}
+
+/// Windows doesn't name the filesystems of shares, but paths on them canonicalize to UNC
+/// paths, also through mapped drives
+#[cfg(windows)]
+fn filesystem_type(path: &Path) -> Option<String> {
+    path.to_str()?
+        .starts_with(r"\\?\UNC\")
+        .then(|| String::from("smb"))
+}
+
+#[cfg(not(any(
+    windows,
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "openbsd"
+)))]
+fn filesystem_type(_path: &Path) -> Option<String> {
+    None
+}
+
+/// The type of the innermost mount containing `path` in a mount table like
+/// `/proc/self/mounts`, which escapes spaces in mount points as `\040`
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn mount_fs_type<'a>(mounts: &'a str, path: &Path) -> Option<&'a str> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ');
+            let mount_point = unescape_mount_point(fields.nth(1)?);
+            Some((PathBuf::from(mount_point), fields.next()?))
+        })
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        // Later mounts on the same mount point hide the earlier ones
+        .max_by_key(|(mount_point, _)| mount_point.components().count())
+        .map(|(_, fs_type)| fs_type)
+}
+
+/// Replaces the octal escapes of a mount point, e.g. `\040` for a space
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn unescape_mount_point(field: &str) -> String {
+    let mut unescaped = String::new();
+    let mut rest = field;
+    while let Some(i) = rest.find('\\') {
+        unescaped.push_str(&rest[..i]);
+        match rest
+            .get(i + 1..i + 4)
+            .and_then(|code| u8::from_str_radix(code, 8).ok())
+        {
+            Some(byte) => {
+                unescaped.push(char::from(byte));
+                rest = &rest[i + 4..];
+            }
+            None => {
+                unescaped.push('\\');
+                rest = &rest[i + 1..];
+            }
+        }
     }
+    unescaped + rest
+}
+
+/// Filesystems on which scanning the directory or running git can take seconds. FUSE
+/// filesystems are only included if they are known to be remote, since many are local.
+fn is_network_fs_type(fs_type: &str) -> bool {
+    fs_type.starts_with("nfs")
+        || matches!(
+            fs_type,
+            "sshfs"
+                | "fuse.sshfs"
+                | "fuse.rclone"
+                | "fuse.s3fs"
+                | "fuse.gcsfuse"
+                | "fuse.goofys"
+                | "fuse.blobfuse"
+                | "fuse.blobfuse2"
+                | "fuse.juicefs"
+                | "fuse.curlftpfs"
+                | "fuse.smbnetfs"
+                | "fuse.davfs"
+                | "fuse.glusterfs"
+                | "fuse.ceph-fuse"
+                | "cifs"
+                | "smb"
+                | "smb3"
+                | "smbfs"
+                | "afpfs"
+                | "webdav"
+                | "davfs"
+                | "afs"
+                | "9p"
+                | "ceph"
+                | "glusterfs"
+                | "lustre"
+                | "gpfs"
+        )
 }
 
 fn get_current_branch(repository: &Repository) -> Option<String> {
@@ -468,16 +1426,84 @@ fn get_remote_repository_info(repository: &Repository) -> Option<Remote> {
             let mut v = remote_ref.splitn(4, '/');
             let remote_name = v.nth(2)?.to_owned();
             let remote_branch = v.last()?.to_owned();
+            let remote_url = repository
+                .find_remote(&remote_name)
+                .ok()
+                .and_then(|remote| remote.url().map(ToOwned::to_owned));
 
             return Some(Remote {
                 branch: Some(remote_branch),
                 name: Some(remote_name),
+                url: remote_url,
             });
         }
     }
     None
 }
 
+/// A sparse checkout needs both the pattern file and `core.sparseCheckout` enabled,
+/// since the pattern file is left behind after `git sparse-checkout disable`
+fn is_sparse_checkout(repository: &Repository) -> bool {
+    repository
+        .path()
+        .join("info")
+        .join("sparse-checkout")
+        .exists()
+        && repository
+            .config()
+            .and_then(|config| config.get_bool("core.sparseCheckout"))
+            .unwrap_or(false)
+}
+
+/// Which prompt is being rendered
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    /// The main (left) prompt, rendered from `format`
+    Main,
+    /// The right prompt, rendered from `right_format`
+    Right,
+    /// The collapsed prompt of an accepted command line, rendered from `transient_format`
+    Transient,
+    /// The prompt shown on continuation lines of a multi-line command, rendered from
+    /// `continuation_format`
+    Continuation,
+}
+
+/// The Windows Subsystem for Linux the shell runs in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wsl {
+    /// The name of the distribution in Windows, from `$WSL_DISTRO_NAME`
+    pub distro: Option<String>,
+    /// 1 for the translation layer, or 2 for the virtual machine
+    pub version: u8,
+}
+
+impl Wsl {
+    /// The kernel release is e.g. `4.4.0-19041-Microsoft` on WSL 1, and
+    /// `5.15.90.1-microsoft-standard-WSL2` on WSL 2
+    fn detect(kernel_release: &str, distro: Option<String>) -> Option<Self> {
+        let version = if kernel_release.contains("Microsoft") {
+            1
+        } else if kernel_release.to_lowercase().contains("microsoft") {
+            2
+        } else {
+            return None;
+        };
+        Some(Wsl { distro, version })
+    }
+}
+
+impl fmt::Display for Wsl {
+    /// Formats it like `WSL2 Ubuntu`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WSL{}", self.version)?;
+        match &self.distro {
+            Some(distro) => write!(f, " {}", distro),
+            None => Ok(()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Shell {
     Bash,
@@ -490,6 +1516,147 @@ pub enum Shell {
     Unknown,
 }
 
+impl Shell {
+    /// The shell with the name used by `starship init` and `STARSHIP_SHELL`
+    pub fn from_name(name: &str) -> Shell {
+        match name {
+            "bash" => Shell::Bash,
+            "fish" => Shell::Fish,
+            "ion" => Shell::Ion,
+            "powershell" => Shell::PowerShell,
+            "zsh" => Shell::Zsh,
+            "elvish" => Shell::Elvish,
+            "tcsh" => Shell::Tcsh,
+            _ => Shell::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorDepth {
+    /// 24-bit colors
+    TrueColor,
+    /// The 256 colors of xterm
+    Ansi256,
+    /// The 16 basic colors
+    Ansi16,
+}
+
+impl ColorDepth {
+    /// Replaces the colors of a style which the terminal can't show with the closest ones it can
+    pub fn downgrade(self, style: Style) -> Style {
+        Style {
+            foreground: style.foreground.map(|color| self.downgrade_color(color)),
+            background: style.background.map(|color| self.downgrade_color(color)),
+            ..style
+        }
+    }
+
+    /// Downgrades the color of an underline like `downgrade`. Terminals with only the 16 basic
+    /// colors rarely support colored underlines, so they show them in the color of the text.
+    pub fn downgrade_underline(self, underline: Underline) -> Underline {
+        Underline {
+            color: match self {
+                ColorDepth::Ansi16 => None,
+                _ => underline.color.map(|color| self.downgrade_color(color)),
+            },
+            ..underline
+        }
+    }
+
+    fn downgrade_color(self, color: Color) -> Color {
+        match (self, color) {
+            (ColorDepth::Ansi256, Color::RGB(r, g, b)) => Color::Fixed(nearest_256(r, g, b)),
+            (ColorDepth::Ansi16, Color::RGB(r, g, b)) => nearest_16(r, g, b),
+            (ColorDepth::Ansi16, Color::Fixed(n)) if n >= 16 => {
+                let (r, g, b) = xterm_rgb(n);
+                nearest_16(r, g, b)
+            }
+            _ => color,
+        }
+    }
+}
+
+/// The levels of each channel in the 6x6x6 color cube of xterm
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The RGB value of an xterm color
+fn xterm_rgb(n: u8) -> (u8, u8, u8) {
+    const BASIC: [(u8, u8, u8); 16] = [
+        (0, 0, 0),
+        (205, 0, 0),
+        (0, 205, 0),
+        (205, 205, 0),
+        (0, 0, 238),
+        (205, 0, 205),
+        (0, 205, 205),
+        (229, 229, 229),
+        (127, 127, 127),
+        (255, 0, 0),
+        (0, 255, 0),
+        (255, 255, 0),
+        (92, 92, 255),
+        (255, 0, 255),
+        (0, 255, 255),
+        (255, 255, 255),
+    ];
+    match n {
+        0..=15 => BASIC[usize::from(n)],
+        16..=231 => {
+            let n = usize::from(n - 16);
+            (
+                CUBE_LEVELS[n / 36],
+                CUBE_LEVELS[n / 6 % 6],
+                CUBE_LEVELS[n % 6],
+            )
+        }
+        _ => {
+            let level = 8 + (n - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let square = |a: u8, b: u8| u32::from(a.abs_diff(b)).pow(2);
+    square(r1, r2) + square(g1, g2) + square(b1, b2)
+}
+
+fn nearest_256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |value: u8| {
+        (0..6)
+            .min_by_key(|&i| CUBE_LEVELS[i].abs_diff(value))
+            .unwrap_or(0) as u8
+    };
+    let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+
+    let average = ((u16::from(r) + u16::from(g) + u16::from(b)) / 3) as u8;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23);
+
+    if distance(xterm_rgb(gray), (r, g, b)) < distance(xterm_rgb(cube), (r, g, b)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+fn nearest_16(r: u8, g: u8, b: u8) -> Color {
+    let n = (0..16)
+        .min_by_key(|&n| distance(xterm_rgb(n), (r, g, b)))
+        .unwrap_or(0);
+    match n {
+        0 => Color::Black,
+        1 => Color::Red,
+        2 => Color::Green,
+        3 => Color::Yellow,
+        4 => Color::Blue,
+        5 => Color::Purple,
+        6 => Color::Cyan,
+        7 => Color::White,
+        n => Color::Fixed(n),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -514,7 +1681,7 @@ mod tests {
 
         assert_eq!(
             ScanDir {
-                dir_contents: &empty_dc,
+                dir_contents: ScannedContents::Borrowed(&empty_dc),
                 files: &["package.json"],
                 extensions: &["js"],
                 folders: &["node_modules"],
@@ -528,7 +1695,7 @@ mod tests {
         let rust_dc = DirContents::from_path(rust.path())?;
         assert_eq!(
             ScanDir {
-                dir_contents: &rust_dc,
+                dir_contents: ScannedContents::Borrowed(&rust_dc),
                 files: &["package.json"],
                 extensions: &["js"],
                 folders: &["node_modules"],
@@ -542,7 +1709,7 @@ mod tests {
         let java_dc = DirContents::from_path(java.path())?;
         assert_eq!(
             ScanDir {
-                dir_contents: &java_dc,
+                dir_contents: ScannedContents::Borrowed(&java_dc),
                 files: &["package.json"],
                 extensions: &["js"],
                 folders: &["node_modules"],
@@ -556,7 +1723,7 @@ mod tests {
         let node_dc = DirContents::from_path(node.path())?;
         assert_eq!(
             ScanDir {
-                dir_contents: &node_dc,
+                dir_contents: ScannedContents::Borrowed(&node_dc),
                 files: &["package.json"],
                 extensions: &["js"],
                 folders: &["node_modules"],
@@ -569,6 +1736,141 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_scan_limits() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = testdir(&["a.md", "b.md", "c.md", "package.json", "src/lib/main.rs"])?;
+        let limits = |max_depth, max_files| ScanOptions {
+            timeout: Duration::from_secs(30),
+            max_depth,
+            max_files,
+            follow_symlinks: true,
+            ignore_globs: Vec::new(),
+        };
+
+        let shallow = DirContents::from_path_with_options(dir.path(), &limits(1, usize::MAX))?;
+        assert!(!shallow.has_extension("rs"));
+        let deep = DirContents::from_path_with_options(dir.path(), &limits(3, usize::MAX))?;
+        assert!(deep.has_extension("rs"));
+        assert!(deep.has_file("src/lib/main.rs"));
+
+        // Files missed by a truncated scan are still found by name
+        let truncated = DirContents::from_path_with_options(dir.path(), &limits(1, 1))?;
+        assert!(truncated.has_file_name("package.json"));
+        assert!(truncated.has_folder("src"));
+        assert!(!truncated.has_file_name("Cargo.toml"));
+        dir.close()?;
+        Ok(())
+    }
+
+    #[test]
+    fn modules_with_the_same_scan_limits_share_a_scan() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = testdir(&["src/main.rs"])?;
+        let context = Context::new_with_shell_and_path(
+            ArgMatches::default(),
+            Shell::Unknown,
+            dir.path().to_path_buf(),
+            dir.path().to_path_buf(),
+        );
+        let deep = ModuleOverrides {
+            max_depth: Some(2),
+            ..ModuleOverrides::NONE
+        };
+        let scan = || with_overrides(deep, || context.try_begin_scan().unwrap().dir_contents);
+        let (first, second) = match (scan(), scan()) {
+            (ScannedContents::Shared(first), ScannedContents::Shared(second)) => (first, second),
+            _ => panic!("modules with scan limits should scan the directory again"),
+        };
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(first.has_extension("rs"));
+        assert!(!context.dir_contents()?.has_extension("rs"));
+        dir.close()?;
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_scan_ignores_globs_and_symlinks() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = testdir(&["package.json", "node_modules/a/Cargo.toml", "src/main.py"])?;
+        let target = testdir(&["go.mod"])?;
+        std::os::unix::fs::symlink(target.path(), dir.path().join("linked"))?;
+        let options = |follow_symlinks, ignore_globs: &[&str]| ScanOptions {
+            timeout: Duration::from_secs(30),
+            max_depth: 3,
+            max_files: usize::MAX,
+            follow_symlinks,
+            ignore_globs: ignore_globs
+                .iter()
+                .map(|glob| WildMatch::new(glob))
+                .collect(),
+        };
+
+        let all = DirContents::from_path_with_options(dir.path(), &options(true, &[]))?;
+        assert!(all.has_file("node_modules/a/Cargo.toml"));
+        assert!(all.has_file("linked/go.mod"));
+
+        let ignored = DirContents::from_path_with_options(
+            dir.path(),
+            &options(false, &["node_modules", "src/*.py"]),
+        )?;
+        assert!(ignored.has_file_name("package.json"));
+        assert!(!ignored.has_folder("node_modules"));
+        assert!(!ignored.has_file("node_modules/a/Cargo.toml"));
+        assert!(!ignored.has_extension("py"));
+        assert!(ignored.has_folder("linked"));
+        assert!(!ignored.has_file("linked/go.mod"));
+
+        // Entries missed by a truncated scan are skipped the same way
+        let truncated = DirContents::from_path_with_options(
+            dir.path(),
+            &ScanOptions {
+                max_files: 0,
+                ..options(false, &["node_modules"])
+            },
+        )?;
+        assert!(truncated.has_file_name("package.json"));
+        assert!(!truncated.has_folder("node_modules"));
+        assert!(!truncated.has_file("node_modules/a/Cargo.toml"));
+        assert!(!truncated.has_file("linked/go.mod"));
+        assert!(truncated.has_folder("linked"));
+        dir.close()?;
+        target.close()?;
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_scan_finds_symlinked_files() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = testdir(&[])?;
+        let target = testdir(&["package.json", ".nvmrc"])?;
+        for name in &["package.json", ".nvmrc"] {
+            std::os::unix::fs::symlink(target.path().join(name), dir.path().join(name))?;
+        }
+        let options = ScanOptions {
+            timeout: Duration::from_secs(30),
+            max_depth: 1,
+            max_files: usize::MAX,
+            follow_symlinks: false,
+            ignore_globs: Vec::new(),
+        };
+
+        let dir_contents = DirContents::from_path_with_options(dir.path(), &options)?;
+        assert!(dir_contents.has_file_name("package.json"));
+        assert!(dir_contents.has_file_name(".nvmrc"));
+        assert!(dir_contents.has_extension("json"));
+
+        let truncated = DirContents::from_path_with_options(
+            dir.path(),
+            &ScanOptions {
+                max_files: 0,
+                ..options
+            },
+        )?;
+        assert!(truncated.has_file_name("package.json"));
+        dir.close()?;
+        target.close()?;
+        Ok(())
+    }
+
     #[test]
     fn context_constructor_should_canonicalize_current_dir() -> io::Result<()> {
         #[cfg(not(windows))]
@@ -608,6 +1910,37 @@ mod tests {
         tmp_dir.close()
     }
 
+    #[test]
+    fn context_constructor_should_use_path_as_logical_path() -> io::Result<()> {
+        let tmp_dir = tempfile::TempDir::new()?;
+        let path = tmp_dir.path().to_string_lossy().to_string();
+        let path_arg = clap::Arg::with_name("path").long("path").takes_value(true);
+        let logical_path_arg = clap::Arg::with_name("logical_path")
+            .long("logical-path")
+            .takes_value(true);
+        let app = clap::App::new("starship")
+            .arg(path_arg)
+            .arg(logical_path_arg);
+
+        let arguments = app
+            .clone()
+            .get_matches_from(vec!["starship", "--path", &path]);
+        let context = Context::new(arguments);
+        assert_eq!(tmp_dir.path(), context.logical_dir);
+
+        let arguments = app.get_matches_from(vec![
+            "starship",
+            "--path",
+            &path,
+            "--logical-path",
+            "/virtual/dir",
+        ]);
+        let context = Context::new(arguments);
+        assert_eq!(Path::new("/virtual/dir"), context.logical_dir);
+
+        tmp_dir.close()
+    }
+
     #[test]
     fn context_constructor_should_fail_gracefully_when_canonicalization_fails() {
         // Mock navigation to a directory which does not exist on disk
@@ -647,4 +1980,134 @@ mod tests {
         let expected_logical_dir = test_path;
         assert_eq!(expected_logical_dir, context.logical_dir);
     }
+
+    #[test]
+    fn detect_color_depth() {
+        let mut context = Context::new_with_shell_and_path(
+            ArgMatches::default(),
+            Shell::Unknown,
+            PathBuf::new(),
+            PathBuf::new(),
+        );
+        assert_eq!(ColorDepth::TrueColor, context.color_depth());
+
+        context.env.insert("TERM", "xterm".to_string());
+        assert_eq!(ColorDepth::TrueColor, context.color_depth());
+
+        context.env.insert("TERM", "linux".to_string());
+        assert_eq!(ColorDepth::Ansi16, context.color_depth());
+
+        context.env.insert("COLORTERM", "truecolor".to_string());
+        assert_eq!(ColorDepth::TrueColor, context.color_depth());
+
+        context.env.remove("COLORTERM");
+        context.env.insert("TERM", "screen-256color".to_string());
+        assert_eq!(ColorDepth::TrueColor, context.color_depth());
+
+        context.config = StarshipConfig {
+            config: Some(toml::toml! { color_depth = "256" }),
+        };
+        assert_eq!(ColorDepth::Ansi256, context.color_depth());
+    }
+
+    #[test]
+    fn downgrade_colors() {
+        let style = Color::RGB(255, 135, 0).on(Color::RGB(18, 18, 18)).bold();
+        assert_eq!(
+            Color::Fixed(208).on(Color::Fixed(233)).bold(),
+            ColorDepth::Ansi256.downgrade(style)
+        );
+        assert_eq!(
+            Color::Yellow.on(Color::Black).bold(),
+            ColorDepth::Ansi16.downgrade(style)
+        );
+        assert_eq!(
+            Color::Fixed(9).normal(),
+            ColorDepth::Ansi16.downgrade(Color::Fixed(196).normal())
+        );
+        assert_eq!(style, ColorDepth::TrueColor.downgrade(style));
+
+        let underline = Underline {
+            double: true,
+            color: Some(Color::RGB(255, 135, 0)),
+        };
+        assert_eq!(
+            Some(Color::Fixed(208)),
+            ColorDepth::Ansi256.downgrade_underline(underline).color
+        );
+        assert_eq!(
+            Underline {
+                double: true,
+                color: None,
+            },
+            ColorDepth::Ansi16.downgrade_underline(underline)
+        );
+    }
+
+    #[test]
+    fn detect_wsl() {
+        let ubuntu = Some("Ubuntu".to_string());
+        let wsl = Wsl::detect("5.15.90.1-microsoft-standard-WSL2\n", ubuntu.clone()).unwrap();
+        assert_eq!(2, wsl.version);
+        assert_eq!("WSL2 Ubuntu", wsl.to_string());
+        let wsl = Wsl::detect("4.4.0-19041-Microsoft", None).unwrap();
+        assert_eq!("WSL1", wsl.to_string());
+        assert_eq!(None, Wsl::detect("6.1.0-18-amd64", ubuntu));
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn finds_filesystem_of_path() {
+        let mounts = "\
+/dev/sda1 / ext4 rw,relatime 0 0
+server:/export /home/user/work nfs4 rw,relatime 0 0
+user@host:/ /mnt/remote\\040box fuse.sshfs rw,nosuid 0 0
+tmpfs /home/user/work/tmp tmpfs rw 0 0
+";
+        let fs_type = |path: &str| mount_fs_type(mounts, Path::new(path));
+        assert_eq!(Some("ext4"), fs_type("/home/user"));
+        assert_eq!(Some("nfs4"), fs_type("/home/user/work/project"));
+        assert_eq!(Some("ext4"), fs_type("/home/user/workspace"));
+        assert_eq!(Some("fuse.sshfs"), fs_type("/mnt/remote box/src"));
+        assert_eq!(Some("tmpfs"), fs_type("/home/user/work/tmp"));
+    }
+
+    #[test]
+    fn detects_network_filesystems() {
+        assert!(is_network_fs_type("nfs4"));
+        assert!(is_network_fs_type("cifs"));
+        assert!(is_network_fs_type("fuse.sshfs"));
+        assert!(is_network_fs_type("fuse.rclone"));
+        assert!(!is_network_fs_type("ext4"));
+        assert!(!is_network_fs_type("fuseblk"));
+        assert!(!is_network_fs_type("fuse"));
+        assert!(!is_network_fs_type("fuse.mergerfs"));
+        assert!(!is_network_fs_type("fuse.gocryptfs"));
+        assert!(!is_network_fs_type("overlay"));
+    }
+
+    #[test]
+    fn measures_command_time_of_modules() {
+        let ((), outside, details) = measure_commands(false, || ());
+        assert_eq!(Duration::ZERO, outside);
+        assert_eq!(None, details);
+
+        let ((), measured, details) = measure_commands(true, || {
+            record_command(Duration::from_millis(2), || "git status".to_string());
+            let scope = ModuleScope::current();
+            std::thread::spawn(move || {
+                scope.enter(|| record_command(Duration::from_millis(3), || "node -v".to_string()));
+            })
+            .join()
+            .unwrap();
+        });
+        assert_eq!(Duration::from_millis(5), measured);
+        assert_eq!(
+            vec![
+                ("git status".to_string(), Duration::from_millis(2)),
+                ("node -v".to_string(), Duration::from_millis(3))
+            ],
+            details.unwrap().commands
+        );
+    }
 }
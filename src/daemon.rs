@@ -0,0 +1,337 @@
+use clap::ArgMatches;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::context::Repo;
+use crate::module_cache::SharedModuleCache;
+
+/// How long a prompt waits for the daemon to take its request before rendering itself,
+/// e.g. while the daemon is busy with the prompt of another shell
+#[cfg(unix)]
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(20);
+
+/// How long a prompt waits for the daemon to render it, once it took the request
+#[cfg(unix)]
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The daemon sends this once it took a request, before rendering the prompt
+#[cfg(unix)]
+const ACCEPTED: &[u8] = b"\n";
+
+/// A prompt to render, sent by `starship prompt` to the daemon
+#[derive(Serialize, Deserialize)]
+struct Request {
+    /// The arguments of the `starship` command
+    args: Vec<String>,
+    #[serde(flatten)]
+    client: ClientEnv,
+}
+
+/// The working directory and environment variables of the shell a prompt is rendered for,
+/// when they are not the ones of this process, e.g. in the daemon
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ClientEnv {
+    pub cwd: PathBuf,
+    pub env: HashMap<String, String>,
+}
+
+impl ClientEnv {
+    /// The working directory and environment variables of this process
+    pub fn current() -> Option<Self> {
+        Some(ClientEnv {
+            cwd: env::current_dir().ok()?,
+            env: env::vars_os()
+                .filter_map(|(key, value)| {
+                    Some((key.into_string().ok()?, value.into_string().ok()?))
+                })
+                .collect(),
+        })
+    }
+
+    /// Finds a program in the `PATH` of the client
+    pub fn which(&self, program: &str) -> which::Result<PathBuf> {
+        which::which_in(program, self.env.get("PATH"), &self.cwd)
+    }
+
+    /// Runs the command in the working directory and with the environment variables of
+    /// the client
+    pub fn apply<'a>(&self, command: &'a mut Command) -> &'a mut Command {
+        command.current_dir(&self.cwd).env_clear().envs(&self.env)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Response {
+    output: String,
+}
+
+/// A repo found for a directory, valid while its git dir is unchanged. Git replaces
+/// files like `HEAD` or `index` by renaming, which changes the modification time of the
+/// git dir.
+struct CachedRepo {
+    git_dir: PathBuf,
+    modified: SystemTime,
+    repo: Repo,
+}
+
+/// The caches the daemon keeps between prompts
+#[derive(Default)]
+pub struct DaemonCache {
+    /// The output of the modules in `module_cache`, if enabled
+    pub(crate) modules: SharedModuleCache,
+    /// The repo containing each directory a prompt was rendered for
+    repos: Mutex<HashMap<PathBuf, CachedRepo>>,
+}
+
+impl DaemonCache {
+    /// The repo found for `dir` by an earlier prompt, if it didn't change since
+    pub fn repo(&self, dir: &Path) -> Option<Repo> {
+        let repos = self.repos.lock().ok()?;
+        let cached = repos.get(dir)?;
+        if modified(&cached.git_dir)? != cached.modified {
+            return None;
+        }
+        Some(cached.repo.clone())
+    }
+
+    pub fn store_repo(&self, dir: &Path, git_dir: &Path, repo: &Repo) {
+        let modified = match modified(git_dir) {
+            Some(modified) => modified,
+            None => return,
+        };
+        if let Ok(mut repos) = self.repos.lock() {
+            repos.insert(
+                dir.to_path_buf(),
+                CachedRepo {
+                    git_dir: git_dir.to_path_buf(),
+                    modified,
+                    repo: repo.clone(),
+                },
+            );
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).ok()?.modified().ok()
+}
+
+/// The socket the daemon listens on, in the cache dir
+fn socket_path() -> Option<PathBuf> {
+    env::var_os("STARSHIP_CACHE")
+        .map(PathBuf::from)
+        .or_else(|| Some(dirs_next::home_dir()?.join(".cache/starship")))
+        .map(|dir| dir.join("daemon.sock"))
+}
+
+/// The arguments to send to the daemon for the current `starship prompt` call. The daemon
+/// has no terminal, so the width of the current one is added.
+#[cfg(unix)]
+fn request_args(args: &ArgMatches) -> Vec<String> {
+    let mut request_args: Vec<String> = env::args().collect();
+    if !args.is_present("terminal_width") {
+        if let Some((width, _)) = term_size::dimensions() {
+            request_args.push(format!("--terminal-width={}", width));
+        }
+    }
+    request_args
+}
+
+/// Renders the prompt with a running daemon. Returns `None` if no daemon is running, or it
+/// didn't respond in time.
+#[cfg(unix)]
+pub fn request(args: &ArgMatches) -> Option<String> {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+
+    let stream = UnixStream::connect(socket_path()?).ok()?;
+    stream.set_read_timeout(Some(CONNECT_TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(CONNECT_TIMEOUT)).ok()?;
+
+    let request = Request {
+        args: request_args(args),
+        client: ClientEnv::current()?,
+    };
+    serde_json::to_writer(&stream, &request).ok()?;
+    (&stream).write_all(b"\n").ok()?;
+
+    // Render the prompt here if the daemon is busy or stuck
+    let mut accepted = [0; ACCEPTED.len()];
+    if let Err(error) = (&stream).read_exact(&mut accepted) {
+        log::debug!("The daemon didn't take the prompt in time: {}", error);
+        return None;
+    }
+    stream.set_read_timeout(Some(RESPONSE_TIMEOUT)).ok()?;
+
+    match serde_json::from_reader::<_, Response>(&stream) {
+        Ok(response) => Some(response.output),
+        Err(error) => {
+            log::warn!("Unable to render the prompt with the daemon: {}", error);
+            None
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn request(_args: &ArgMatches) -> Option<String> {
+    None
+}
+
+/// Runs the daemon, rendering prompts sent by `starship prompt` until it is stopped. `parse`
+/// is given the arguments of the `starship` command and returns the ones to render the
+/// prompt with, or `None` if they are not for a prompt. `render` is given them, and the
+/// working directory and environment variables of the shell the prompt is for.
+///
+/// Each prompt is rendered on its own thread, so a slow prompt doesn't hold up the prompts
+/// of other shells.
+#[cfg(unix)]
+pub fn run<T, P, F>(parse: P, render: F) -> io::Result<()>
+where
+    T: Send + 'static,
+    P: Fn(Vec<String>) -> Option<T>,
+    F: Fn(T, ClientEnv, &Arc<DaemonCache>) -> String + Send + Sync + 'static,
+{
+    use nix::sys::stat::{umask, Mode};
+    use std::io::Write;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    let path = socket_path().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "Unable to find the cache directory",
+        )
+    })?;
+    if UnixStream::connect(&path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("A daemon is already listening on {:?}", path),
+        ));
+    }
+    // Remove the socket of a daemon which didn't exit cleanly
+    fs::remove_file(&path).ok();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Prompts run commands from the PATH they send, so only the user may send them. The
+    // socket is created without permissions for others, rather than changing them once it
+    // is already listening.
+    let previous_umask = umask(Mode::from_bits_truncate(0o177));
+    let listener = UnixListener::bind(&path);
+    umask(previous_umask);
+    let listener = listener?;
+    log::info!("Listening on {:?}", path);
+
+    let render = Arc::new(render);
+    let cache = Arc::new(DaemonCache::default());
+    for stream in listener.incoming() {
+        let result = stream.and_then(|stream| {
+            // Clients send their request right away, so a stuck one can't hold up the others
+            stream.set_read_timeout(Some(RESPONSE_TIMEOUT))?;
+            let Request { args, client } = read_request(&stream)?;
+            let args = parse(args).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "Not a prompt to render")
+            })?;
+            (&stream).write_all(ACCEPTED)?;
+
+            let render = render.clone();
+            let cache = cache.clone();
+            std::thread::spawn(move || {
+                let output = render(args, client, &cache);
+                if let Err(error) = serde_json::to_writer(&stream, &Response { output }) {
+                    log::warn!("Unable to send a prompt to a client: {}", error);
+                }
+            });
+            Ok(())
+        });
+        match result {
+            // A client only checking whether a daemon is running
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => (),
+            Err(error) => log::warn!("Unable to render a prompt for a client: {}", error),
+            Ok(()) => (),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn read_request(stream: &std::os::unix::net::UnixStream) -> io::Result<Request> {
+    use std::io::BufRead;
+
+    let mut line = String::new();
+    io::BufReader::new(stream).read_line(&mut line)?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+#[cfg(not(unix))]
+pub fn run<T, P, F>(_parse: P, _render: F) -> io::Result<()>
+where
+    T: Send + 'static,
+    P: Fn(Vec<String>) -> Option<T>,
+    F: Fn(T, ClientEnv, &Arc<DaemonCache>) -> String + Send + Sync + 'static,
+{
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "The daemon is only supported on unix systems",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_repos_until_the_git_dir_changes() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let git_dir = dir.path().join(".git");
+        fs::create_dir(&git_dir)?;
+        let repo = Repo {
+            branch: Some("main".to_string()),
+            root: Some(dir.path().to_path_buf()),
+            path: Some(git_dir.clone()),
+            state: None,
+            remote: None,
+            is_sparse: false,
+            is_shallow: false,
+        };
+
+        let cache = DaemonCache::default();
+        assert!(cache.repo(dir.path()).is_none());
+        cache.store_repo(dir.path(), &git_dir, &repo);
+        let cached = cache.repo(dir.path()).unwrap();
+        assert_eq!(Some("main".to_string()), cached.branch);
+
+        fs::remove_dir(&git_dir)?;
+        assert!(cache.repo(dir.path()).is_none());
+        dir.close()
+    }
+
+    #[test]
+    fn sends_the_environment_of_the_client() {
+        let request = Request {
+            args: vec!["starship".to_string(), "prompt".to_string()],
+            client: ClientEnv {
+                cwd: PathBuf::from("/home/user"),
+                env: vec![("SHELL".to_string(), "zsh".to_string())]
+                    .into_iter()
+                    .collect(),
+            },
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "args": ["starship", "prompt"],
+                "cwd": "/home/user",
+                "env": { "SHELL": "zsh" },
+            }),
+            json
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use std::borrow::Cow;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::string_formatter::StringFormatterError;
+use crate::print::UnicodeWidthGraphemes;
+
+/// Applies a function of a format string, like `truncate` in `${truncate(branch, 20)}`, to a
+/// value with the arguments following it
+pub fn apply(
+    function: &str,
+    value: String,
+    arguments: &[Cow<str>],
+) -> Result<String, StringFormatterError> {
+    match function {
+        "truncate" => {
+            check_arguments(function, arguments, 1, 2)?;
+            let length = number_argument(function, &arguments[0])?;
+            let symbol = arguments.get(1).map_or("…", |symbol| symbol.as_ref());
+            Ok(truncate(value, length, symbol))
+        }
+        "pad_left" | "pad_right" => {
+            check_arguments(function, arguments, 1, 2)?;
+            let width = number_argument(function, &arguments[0])?;
+            let fill = arguments.get(1).map_or(" ", |fill| fill.as_ref());
+            let padding = padding(&value, width, fill);
+            Ok(if function == "pad_left" {
+                padding + &value
+            } else {
+                value + &padding
+            })
+        }
+        "upper" => {
+            check_arguments(function, arguments, 0, 0)?;
+            Ok(value.to_uppercase())
+        }
+        "lower" => {
+            check_arguments(function, arguments, 0, 0)?;
+            Ok(value.to_lowercase())
+        }
+        "title" => {
+            check_arguments(function, arguments, 0, 0)?;
+            Ok(title_case(&value))
+        }
+        "human" => {
+            check_arguments(function, arguments, 0, 1)?;
+            let units = match arguments.first().map(|unit| unit.as_ref()) {
+                None => COUNT_UNITS,
+                Some("bytes") => BYTE_UNITS,
+                Some(unit) => {
+                    return Err(StringFormatterError::Custom(format!(
+                        "`human` expects no unit or `bytes`, found {:?}",
+                        unit
+                    )))
+                }
+            };
+            Ok(match value.trim().parse::<f64>() {
+                Ok(number) if number.is_finite() => humanize(number, units),
+                _ => value,
+            })
+        }
+        _ => Err(StringFormatterError::Custom(format!(
+            "Unknown function `{}`",
+            function
+        ))),
+    }
+}
+
+fn check_arguments(
+    function: &str,
+    arguments: &[Cow<str>],
+    min: usize,
+    max: usize,
+) -> Result<(), StringFormatterError> {
+    if (min..=max).contains(&arguments.len()) {
+        return Ok(());
+    }
+    let expected = if min == max {
+        min.to_string()
+    } else {
+        format!("{} to {}", min, max)
+    };
+    Err(StringFormatterError::Custom(format!(
+        "`{}` takes {} arguments after the variable, found {}",
+        function,
+        expected,
+        arguments.len()
+    )))
+}
+
+fn number_argument(function: &str, argument: &str) -> Result<usize, StringFormatterError> {
+    argument.parse().map_err(|_| {
+        StringFormatterError::Custom(format!(
+            "`{}` expects a positive number, found {:?}",
+            function, argument
+        ))
+    })
+}
+
+/// Keeps the first graphemes of the value which fit in `length` columns, followed by `symbol`
+/// if any were removed
+fn truncate(value: String, length: usize, symbol: &str) -> String {
+    if value.width_graphemes() <= length {
+        return value;
+    }
+    let mut used = 0;
+    let kept: String = value
+        .graphemes(true)
+        .take_while(|grapheme| {
+            used += grapheme.width_graphemes();
+            used <= length
+        })
+        .collect();
+    kept + symbol
+}
+
+/// Repeats `fill` to fill the columns the value is narrower than `width`. A fill wider than
+/// one column is cut to fit, and columns no grapheme of it fits in are filled with spaces.
+fn padding(value: &str, width: usize, fill: &str) -> String {
+    let missing = width.saturating_sub(value.width_graphemes());
+    let mut padding = String::new();
+    let mut used = 0;
+    for grapheme in fill.graphemes(true).cycle() {
+        let grapheme_width = grapheme.width_graphemes();
+        if grapheme_width == 0 || used + grapheme_width > missing {
+            break;
+        }
+        padding.push_str(grapheme);
+        used += grapheme_width;
+    }
+    padding + &" ".repeat(missing - used)
+}
+
+/// The base and suffixes of humanized counts, e.g. `12k`
+const COUNT_UNITS: (f64, &[&str]) = (1000.0, &["", "k", "M", "G", "T", "P"]);
+/// The base and suffixes of humanized byte sizes, e.g. `1.2GiB`
+const BYTE_UNITS: (f64, &[&str]) = (1024.0, &["B", "KiB", "MiB", "GiB", "TiB", "PiB"]);
+
+/// Shortens a number with the largest unit it is at least one of. Numbers below 10 of
+/// a unit keep one decimal, e.g. `1.2k` or `12k`.
+fn humanize(number: f64, (base, suffixes): (f64, &[&str])) -> String {
+    let mut value = number.abs();
+    let mut suffix = suffixes[0];
+    for next_suffix in &suffixes[1..] {
+        if value < base {
+            break;
+        }
+        value /= base;
+        suffix = next_suffix;
+    }
+    let sign = if number < 0.0 { "-" } else { "" };
+    let value = if value < 10.0 && suffix != suffixes[0] {
+        let value = format!("{:.1}", (value * 10.0).floor() / 10.0);
+        value.trim_end_matches(".0").to_string()
+    } else {
+        format!("{:.0}", value.floor())
+    };
+    format!("{}{}{}", sign, value, suffix)
+}
+
+/// Capitalizes the first letter of every word and lowercases the others. Words are separated
+/// by anything but letters and numbers, like `-` in `feature-branch`.
+fn title_case(value: &str) -> String {
+    let mut previous_alphanumeric = false;
+    value
+        .chars()
+        .flat_map(|c| {
+            let capitalize = !previous_alphanumeric;
+            previous_alphanumeric = c.is_alphanumeric();
+            if capitalize {
+                c.to_uppercase().collect::<Vec<_>>()
+            } else {
+                c.to_lowercase().collect()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(
+        function: &str,
+        value: &str,
+        arguments: &[&str],
+    ) -> Result<String, StringFormatterError> {
+        let arguments: Vec<Cow<str>> = arguments.iter().map(|&argument| argument.into()).collect();
+        apply(function, value.to_string(), &arguments)
+    }
+
+    #[test]
+    fn truncates_values() {
+        assert_eq!(Ok("feat…".to_string()), call("truncate", "feature", &["4"]));
+        assert_eq!(
+            Ok("fea...".to_string()),
+            call("truncate", "feature", &["3", "..."])
+        );
+        assert_eq!(
+            Ok("feature".to_string()),
+            call("truncate", "feature", &["7"])
+        );
+        assert_eq!(
+            Ok("🚀🚀".to_string()),
+            call("truncate", "🚀🚀🚀", &["5", ""])
+        );
+        assert_eq!(Ok("日本…".to_string()), call("truncate", "日本語", &["4"]));
+    }
+
+    #[test]
+    fn pads_values() {
+        assert_eq!(Ok("   42".to_string()), call("pad_left", "42", &["5"]));
+        assert_eq!(Ok("42   ".to_string()), call("pad_right", "42", &["5"]));
+        assert_eq!(Ok("00042".to_string()), call("pad_left", "42", &["5", "0"]));
+        assert_eq!(Ok("日本 ".to_string()), call("pad_right", "日本", &["5"]));
+        assert_eq!(Ok("abax".to_string()), call("pad_left", "x", &["4", "ab"]));
+        assert_eq!(
+            Ok("xabab".to_string()),
+            call("pad_right", "x", &["5", "ab"])
+        );
+        assert_eq!(
+            Ok("日本 x".to_string()),
+            call("pad_left", "x", &["6", "日本"])
+        );
+        assert_eq!(
+            Ok("toolong".to_string()),
+            call("pad_left", "toolong", &["3"])
+        );
+    }
+
+    #[test]
+    fn changes_case() {
+        assert_eq!(Ok("MY-HOST".to_string()), call("upper", "my-Host", &[]));
+        assert_eq!(Ok("my-host".to_string()), call("lower", "MY-Host", &[]));
+        assert_eq!(
+            Ok("Feature/Add-Login".to_string()),
+            call("title", "feature/add-LOGIN", &[])
+        );
+        assert_eq!(Ok("Straße Éa".to_string()), call("title", "straße éA", &[]));
+    }
+
+    #[test]
+    fn humanizes_numbers() {
+        assert_eq!(Ok("999".to_string()), call("human", "999", &[]));
+        assert_eq!(Ok("1.2k".to_string()), call("human", "1234", &[]));
+        assert_eq!(Ok("12k".to_string()), call("human", "12345", &[]));
+        assert_eq!(Ok("1M".to_string()), call("human", "1000000", &[]));
+        assert_eq!(Ok("-3.4k".to_string()), call("human", "-3456", &[]));
+        assert_eq!(Ok("512B".to_string()), call("human", "512", &["bytes"]));
+        assert_eq!(
+            Ok("1.2GiB".to_string()),
+            call("human", "1288490189", &["bytes"])
+        );
+        assert_eq!(Ok("n/a".to_string()), call("human", "n/a", &[]));
+    }
+
+    #[test]
+    fn rejects_invalid_calls() {
+        assert!(call("nope", "value", &[]).is_err());
+        assert!(call("truncate", "value", &[]).is_err());
+        assert!(call("truncate", "value", &["-1"]).is_err());
+        assert!(call("truncate", "value", &["1", "a", "b"]).is_err());
+        assert!(call("pad_left", "value", &["wide"]).is_err());
+        assert!(call("upper", "value", &["1"]).is_err());
+        assert!(call("human", "1", &["meters"]).is_err());
+    }
+}
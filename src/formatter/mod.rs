@@ -1,3 +1,4 @@
+mod functions;
 pub mod model;
 mod parser;
 pub mod string_formatter;
@@ -21,8 +21,105 @@ pub struct TextGroup<'a> {
 pub enum FormatElement<'a> {
     Text(Cow<'a, str>),
     Variable(Cow<'a, str>),
+    /// A variable and the text rendered if it is empty
+    Fallback(Cow<'a, str>, Cow<'a, str>),
     TextGroup(TextGroup<'a>),
     Conditional(Vec<FormatElement<'a>>),
+    Guarded(Condition<'a>, TextGroup<'a>),
+    Ternary(Ternary<'a>),
+    Call(Call<'a>),
+}
+
+/// Renders `then` if the variable is set and not empty, otherwise `otherwise`
+#[derive(Clone)]
+pub struct Ternary<'a> {
+    pub variable: Cow<'a, str>,
+    pub then: Vec<FormatElement<'a>>,
+    pub otherwise: Vec<FormatElement<'a>>,
+}
+
+/// A condition on the values of variables, deciding whether a guarded text group renders
+#[derive(Clone)]
+pub enum Condition<'a> {
+    /// The variable is set and not empty
+    Set(Cow<'a, str>),
+    Compare(Cow<'a, str>, Operator, Operand<'a>),
+    Any(Vec<Condition<'a>>),
+    All(Vec<Condition<'a>>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// The right side of a comparison
+#[derive(Clone)]
+pub enum Operand<'a> {
+    Text(Cow<'a, str>),
+    Variable(Cow<'a, str>),
+}
+
+/// A function applied to a variable or the result of another call, e.g. `truncate(branch, 20)`
+#[derive(Clone)]
+pub struct Call<'a> {
+    pub function: Cow<'a, str>,
+    pub input: CallInput<'a>,
+    pub arguments: Vec<Cow<'a, str>>,
+}
+
+#[derive(Clone)]
+pub enum CallInput<'a> {
+    Variable(Cow<'a, str>),
+    Call(Box<Call<'a>>),
+}
+
+impl<'a> Call<'a> {
+    /// The variable the innermost call is applied to
+    pub fn variable(&self) -> &Cow<'a, str> {
+        match &self.input {
+            CallInput::Variable(variable) => variable,
+            CallInput::Call(call) => call.variable(),
+        }
+    }
+}
+
+/// Whether some elements always render text, since they contain a variable with a fallback
+pub fn has_fallback(format: &[FormatElement]) -> bool {
+    format.iter().any(|element| match element {
+        FormatElement::Fallback(..) => true,
+        FormatElement::TextGroup(textgroup) => has_fallback(&textgroup.format),
+        _ => false,
+    })
+}
+
+impl<'a> VariableHolder<Cow<'a, str>> for Condition<'a> {
+    fn get_variables(&self) -> BTreeSet<Cow<'a, str>> {
+        match self {
+            Condition::Set(variable) => {
+                let mut variables = BTreeSet::new();
+                variables.insert(variable.clone());
+                variables
+            }
+            Condition::Compare(variable, _, operand) => {
+                let mut variables = BTreeSet::new();
+                variables.insert(variable.clone());
+                if let Operand::Variable(other) = operand {
+                    variables.insert(other.clone());
+                }
+                variables
+            }
+            Condition::Any(conditions) | Condition::All(conditions) => conditions
+                .iter()
+                .flat_map(|condition| condition.get_variables())
+                .collect(),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -34,13 +131,30 @@ pub enum StyleElement<'a> {
 impl<'a> VariableHolder<Cow<'a, str>> for FormatElement<'a> {
     fn get_variables(&self) -> BTreeSet<Cow<'a, str>> {
         match self {
-            FormatElement::Variable(var) => {
+            FormatElement::Variable(var) | FormatElement::Fallback(var, _) => {
                 let mut variables = BTreeSet::new();
                 variables.insert(var.clone());
                 variables
             }
             FormatElement::TextGroup(textgroup) => textgroup.format.get_variables(),
             FormatElement::Conditional(format) => format.get_variables(),
+            FormatElement::Guarded(condition, textgroup) => {
+                let mut variables = condition.get_variables();
+                variables.extend(textgroup.format.get_variables());
+                variables
+            }
+            FormatElement::Ternary(ternary) => {
+                let mut variables = BTreeSet::new();
+                variables.insert(ternary.variable.clone());
+                variables.extend(ternary.then.get_variables());
+                variables.extend(ternary.otherwise.get_variables());
+                variables
+            }
+            FormatElement::Call(call) => {
+                let mut variables = BTreeSet::new();
+                variables.insert(call.variable().clone());
+                variables
+            }
             _ => Default::default(),
         }
     }
@@ -97,6 +211,15 @@ impl<'a> StyleVariableHolder<Cow<'a, str>> for Vec<FormatElement<'a>> {
                 acc.extend(format.get_style_variables());
                 acc
             }
+            FormatElement::Guarded(_, textgroup) => {
+                acc.extend(textgroup.style.get_style_variables());
+                acc
+            }
+            FormatElement::Ternary(ternary) => {
+                acc.extend(ternary.then.get_style_variables());
+                acc.extend(ternary.otherwise.get_style_variables());
+                acc
+            }
             _ => acc,
         })
     }
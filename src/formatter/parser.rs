@@ -12,16 +12,60 @@ struct IdentParser;
 
 fn parse_value(value: Pair<Rule>) -> FormatElement {
     match value.as_rule() {
-        Rule::text => FormatElement::Text(parse_text(value).into()),
+        Rule::text | Rule::branch_text => FormatElement::Text(parse_text(value).into()),
         Rule::variable => FormatElement::Variable(parse_variable(value).into()),
         Rule::textgroup => FormatElement::TextGroup(parse_textgroup(value)),
         Rule::conditional => {
             FormatElement::Conditional(parse_format(value.into_inner().next().unwrap()))
         }
+        Rule::guarded => {
+            let mut inner_rules = value.into_inner();
+            let condition = parse_condition(inner_rules.next().unwrap());
+            FormatElement::Guarded(condition, parse_textgroup(inner_rules.next().unwrap()))
+        }
+        Rule::ternary => {
+            let mut inner_rules = value.into_inner();
+            let variable = inner_rules.next().unwrap().as_str();
+            let then = parse_format(inner_rules.next().unwrap());
+            let otherwise = parse_format(inner_rules.next().unwrap());
+            FormatElement::Ternary(Ternary {
+                variable: variable.into(),
+                then,
+                otherwise,
+            })
+        }
+        Rule::fallback => {
+            let mut inner_rules = value.into_inner();
+            let name = inner_rules.next().unwrap().as_str();
+            let fallback = parse_text(inner_rules.next().unwrap());
+            FormatElement::Fallback(name.into(), fallback.into())
+        }
+        Rule::call => FormatElement::Call(parse_function(value.into_inner().next().unwrap())),
         _ => unreachable!(),
     }
 }
 
+fn parse_function(function: Pair<Rule>) -> Call {
+    let mut inner_rules = function.into_inner();
+    let name = inner_rules.next().unwrap().as_str();
+    let input = inner_rules.next().unwrap();
+    let input = match input.as_rule() {
+        Rule::function => CallInput::Call(Box::new(parse_function(input))),
+        _ => CallInput::Variable(input.as_str().into()),
+    };
+    let arguments = inner_rules
+        .map(|argument| match argument.as_rule() {
+            Rule::quoted_operand => argument.into_inner().next().unwrap().as_str().into(),
+            _ => argument.as_str().into(),
+        })
+        .collect();
+    Call {
+        function: name.into(),
+        input,
+        arguments,
+    }
+}
+
 fn parse_textgroup(textgroup: Pair<Rule>) -> TextGroup {
     let mut inner_rules = textgroup.into_inner();
     let format = inner_rules.next().unwrap();
@@ -33,6 +77,38 @@ fn parse_textgroup(textgroup: Pair<Rule>) -> TextGroup {
     }
 }
 
+fn parse_condition(condition: Pair<Rule>) -> Condition {
+    match condition.as_rule() {
+        Rule::comparison => {
+            let mut inner_rules = condition.into_inner();
+            let variable = parse_variable(inner_rules.next().unwrap());
+            let operator = match inner_rules.next().unwrap().as_str() {
+                "==" => Operator::Equal,
+                "!=" => Operator::NotEqual,
+                "<" => Operator::Less,
+                "<=" => Operator::LessOrEqual,
+                ">" => Operator::Greater,
+                ">=" => Operator::GreaterOrEqual,
+                _ => unreachable!(),
+            };
+            let operand = inner_rules.next().unwrap();
+            let operand = match operand.as_rule() {
+                Rule::variable => Operand::Variable(parse_variable(operand).into()),
+                Rule::quoted_operand => {
+                    Operand::Text(operand.into_inner().next().unwrap().as_str().into())
+                }
+                Rule::operand_text => Operand::Text(operand.as_str().into()),
+                _ => unreachable!(),
+            };
+            Condition::Compare(variable.into(), operator, operand)
+        }
+        Rule::any => Condition::Any(condition.into_inner().map(parse_condition).collect()),
+        Rule::all => Condition::All(condition.into_inner().map(parse_condition).collect()),
+        Rule::variable => Condition::Set(parse_variable(condition).into()),
+        _ => unreachable!(),
+    }
+}
+
 fn parse_variable(variable: Pair<Rule>) -> &str {
     variable.into_inner().next().unwrap().as_str()
 }
@@ -52,7 +128,7 @@ fn parse_style(style: Pair<Rule>) -> Vec<StyleElement> {
     style
         .into_inner()
         .map(|pair| match pair.as_rule() {
-            Rule::string => StyleElement::Text(pair.as_str().into()),
+            Rule::string | Rule::style_arguments => StyleElement::Text(pair.as_str().into()),
             Rule::variable => StyleElement::Variable(parse_variable(pair).into()),
             _ => unreachable!(),
         })
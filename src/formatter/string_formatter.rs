@@ -2,13 +2,20 @@ use ansi_term::Style;
 use pest::error::Error as PestError;
 use rayon::prelude::*;
 use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
 use std::fmt;
 
-use crate::config::parse_style_string;
+use crate::config::{
+    parse_gradient_string, parse_style_string, parse_underline_string, Gradient, GradientColors,
+};
+use crate::context::{format_variable, record_variables, ModuleScope};
 use crate::segment::Segment;
+use ansi_term::Color;
+use unicode_segmentation::UnicodeSegmentation;
 
+use super::functions;
 use super::model::*;
 use super::parser::{parse, Rule};
 
@@ -105,11 +112,15 @@ impl<'a> StringFormatter<'a> {
         T: Into<Cow<'a, str>>,
         M: Fn(&str) -> Option<Result<T, StringFormatterError>> + Sync,
     {
+        // Variables are mapped on other threads, which run the commands of the module
+        let scope = ModuleScope::current();
         self.variables
             .par_iter_mut()
             .filter(|(_, value)| value.is_none())
             .for_each(|(key, value)| {
-                *value = mapper(key).map(|var| var.map(|var| VariableValue::Plain(var.into())));
+                *value = scope
+                    .enter(|| mapper(key))
+                    .map(|var| var.map(|var| VariableValue::Plain(var.into())));
             });
         self
     }
@@ -165,11 +176,14 @@ impl<'a> StringFormatter<'a> {
     where
         M: Fn(&str) -> Option<Result<Vec<Segment>, StringFormatterError>> + Sync,
     {
+        let scope = ModuleScope::current();
         self.variables
             .par_iter_mut()
             .filter(|(_, value)| value.is_none())
             .for_each(|(key, value)| {
-                *value = mapper(key).map(|var| var.map(VariableValue::Styled));
+                *value = scope
+                    .enter(|| mapper(key))
+                    .map(|var| var.map(VariableValue::Styled));
             });
         self
     }
@@ -197,25 +211,39 @@ impl<'a> StringFormatter<'a> {
     ///
     /// - Format string in meta variables fails to parse
     /// - Variable mapper returns an error.
-    pub fn parse(self, default_style: Option<Style>) -> Result<Vec<Segment>, StringFormatterError> {
+    pub fn parse(
+        mut self,
+        default_style: Option<Style>,
+    ) -> Result<Vec<Segment>, StringFormatterError> {
         fn parse_textgroup<'a>(
             textgroup: TextGroup<'a>,
             variables: &'a VariableMapType<'a>,
             style_variables: &'a StyleVariableMapType<'a>,
         ) -> Result<Vec<Segment>, StringFormatterError> {
-            let style = parse_style(textgroup.style, style_variables);
-            parse_format(
+            let style_string = parse_style(textgroup.style, style_variables)?;
+            let segments = parse_format(
                 textgroup.format,
-                style.transpose()?,
+                parse_style_string(&style_string),
                 &variables,
                 &style_variables,
-            )
+            )?;
+            let mut segments = match parse_gradient_string(&style_string) {
+                Some(gradient) => apply_gradient(segments, gradient),
+                None => segments,
+            };
+            // Underlines of nested text groups take precedence
+            if let Some(underline) = parse_underline_string(&style_string) {
+                segments.iter_mut().for_each(|segment| {
+                    segment.underline.get_or_insert(underline);
+                });
+            }
+            Ok(segments)
         }
 
         fn parse_style<'a>(
             style: Vec<StyleElement>,
             variables: &'a StyleVariableMapType<'a>,
-        ) -> Option<Result<Style, StringFormatterError>> {
+        ) -> Result<String, StringFormatterError> {
             let style_strings = style
                 .into_iter()
                 .map(|style| match style {
@@ -229,13 +257,7 @@ impl<'a> StringFormatter<'a> {
                     }
                 })
                 .collect::<Result<Vec<Cow<str>>, StringFormatterError>>();
-            style_strings
-                .map(|style_strings| {
-                    let style_string: String =
-                        style_strings.iter().flat_map(|s| s.chars()).collect();
-                    parse_style_string(&style_string)
-                })
-                .transpose()
+            style_strings.map(|style_strings| style_strings.concat())
         }
 
         fn parse_format<'a>(
@@ -318,7 +340,8 @@ impl<'a> StringFormatter<'a> {
                                 })
                             }
 
-                            let should_show: bool = should_show_elements(&format, variables);
+                            let should_show: bool =
+                                has_fallback(&format) || should_show_elements(&format, variables);
 
                             if should_show {
                                 parse_format(format, style, variables, style_variables)
@@ -326,12 +349,66 @@ impl<'a> StringFormatter<'a> {
                                 Ok(Vec::new())
                             }
                         }
+                        FormatElement::Fallback(name, fallback) => {
+                            if is_set(&name, variables) {
+                                let format = vec![FormatElement::Variable(name)];
+                                parse_format(format, style, variables, style_variables)
+                            } else {
+                                Ok(vec![Segment::new(style, fallback)])
+                            }
+                        }
+                        FormatElement::Ternary(ternary) => {
+                            let format = if is_set(&ternary.variable, variables) {
+                                ternary.then
+                            } else {
+                                ternary.otherwise
+                            };
+                            parse_format(format, style, variables, style_variables)
+                        }
+                        FormatElement::Call(call) => Ok(evaluate_call(&call, variables)?
+                            .map(|text| vec![Segment::new(style, text)])
+                            .unwrap_or_default()),
+                        FormatElement::Guarded(condition, textgroup) => {
+                            if condition_holds(&condition, variables) {
+                                parse_textgroup(textgroup, variables, style_variables)
+                            } else {
+                                Ok(Vec::new())
+                            }
+                        }
                     }
                 })
                 .collect();
             Ok(results?.into_iter().flatten().collect())
         }
 
+        // Variables no mapper set, like environment variables or the variables of other modules
+        self.variables
+            .iter_mut()
+            .filter(|(_, value)| value.is_none())
+            .for_each(|(key, value)| {
+                *value = if key == "align" {
+                    // Aligns the lines of module formats written as arrays, like in the prompt
+                    Some(Ok(VariableValue::Styled(vec![Segment::fill(None, " ")])))
+                } else {
+                    format_variable(key).map(|text| Ok(VariableValue::Plain(text.into())))
+                };
+            });
+        record_variables(|| {
+            self.variables
+                .iter()
+                .filter_map(|(name, value)| {
+                    let value = match value.as_ref()?.as_ref().ok()? {
+                        VariableValue::Plain(text) => text.to_string(),
+                        VariableValue::Styled(segments) => segments
+                            .iter()
+                            .map(|segment| segment.value.as_str())
+                            .collect(),
+                        VariableValue::Meta(_) => return None,
+                    };
+                    Some((name.clone(), value))
+                })
+                .collect::<Vec<_>>()
+        });
         parse_format(
             self.format,
             default_style,
@@ -370,9 +447,178 @@ fn clone_without_meta<'a>(variables: &VariableMapType<'a>) -> VariableMapType<'a
         .collect()
 }
 
+/// The text of a variable, or `None` if it is unset, failed or a meta variable
+fn variable_text(name: &str, variables: &VariableMapType) -> Option<String> {
+    match variables.get(name)?.as_ref()?.as_ref().ok()? {
+        VariableValue::Plain(text) => Some(text.to_string()),
+        VariableValue::Styled(segments) => Some(
+            segments
+                .iter()
+                .map(|segment| segment.value.as_str())
+                .collect(),
+        ),
+        VariableValue::Meta(_) => None,
+    }
+}
+
+/// Whether a variable is set and not empty
+fn is_set(name: &str, variables: &VariableMapType) -> bool {
+    matches!(variable_text(name, variables), Some(text) if !text.is_empty())
+}
+
+/// The result of a function call, or `None` if the variable it is applied to is unset
+fn evaluate_call(
+    call: &Call,
+    variables: &VariableMapType,
+) -> Result<Option<String>, StringFormatterError> {
+    let value = match &call.input {
+        CallInput::Variable(name) => variable_text(name, variables),
+        CallInput::Call(inner) => evaluate_call(inner, variables)?,
+    };
+    value
+        .map(|value| functions::apply(&call.function, value, &call.arguments))
+        .transpose()
+}
+
+/// Whether the condition of a guarded text group holds. Comparisons with unset variables
+/// never hold.
+fn condition_holds(condition: &Condition, variables: &VariableMapType) -> bool {
+    match condition {
+        Condition::Set(name) => is_set(name, variables),
+        Condition::Any(conditions) => conditions
+            .iter()
+            .any(|condition| condition_holds(condition, variables)),
+        Condition::All(conditions) => conditions
+            .iter()
+            .all(|condition| condition_holds(condition, variables)),
+        Condition::Compare(name, operator, operand) => {
+            let left = match variable_text(name, variables) {
+                Some(left) => left,
+                None => return false,
+            };
+            let right = match operand {
+                Operand::Text(text) => text.to_string(),
+                Operand::Variable(name) => match variable_text(name, variables) {
+                    Some(right) => right,
+                    None => return false,
+                },
+            };
+            let ordering = compare_values(&left, &right);
+            match operator {
+                Operator::Equal => ordering == Some(Ordering::Equal),
+                Operator::NotEqual => ordering != Some(Ordering::Equal),
+                Operator::Less => ordering == Some(Ordering::Less),
+                Operator::LessOrEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+                Operator::Greater => ordering == Some(Ordering::Greater),
+                Operator::GreaterOrEqual => {
+                    matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+                }
+            }
+        }
+    }
+}
+
+/// Compares values as numbers or durations (like `1m30s`) if both sides are, otherwise as
+/// strings
+fn compare_values(left: &str, right: &str) -> Option<Ordering> {
+    if let (Ok(left), Ok(right)) = (left.trim().parse::<f64>(), right.trim().parse::<f64>()) {
+        return left.partial_cmp(&right);
+    }
+    if let (Some(left), Some(right)) = (parse_duration(left), parse_duration(right)) {
+        return Some(left.cmp(&right));
+    }
+    Some(left.cmp(right))
+}
+
+/// Parses a duration like the ones `cmd_duration` renders, e.g. `1h2m3s` or `450ms`, into
+/// milliseconds
+fn parse_duration(duration: &str) -> Option<u128> {
+    let mut rest = duration.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    let mut millis = 0;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let amount: u128 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+        let unit_length = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let factor = match &rest[..unit_length] {
+            "ms" => 1,
+            "s" => 1000,
+            "m" => 60 * 1000,
+            "h" => 60 * 60 * 1000,
+            "d" => 24 * 60 * 60 * 1000,
+            _ => return None,
+        };
+        millis += amount * factor;
+        rest = &rest[unit_length..];
+    }
+    Some(millis)
+}
+
+/// Splits the segments of a text group into one segment per character, with colors
+/// interpolated from the start to the end of the gradient. Fill segments are kept as they are.
+fn apply_gradient(segments: Vec<Segment>, gradient: Gradient) -> Vec<Segment> {
+    fn interpolate(colors: GradientColors, ratio: f64) -> Color {
+        let ((r1, g1, b1), (r2, g2, b2)) = colors;
+        let mix = |start: u8, end: u8| {
+            (f64::from(start) + (f64::from(end) - f64::from(start)) * ratio).round() as u8
+        };
+        Color::RGB(mix(r1, r2), mix(g1, g2), mix(b1, b2))
+    }
+
+    let total = segments
+        .iter()
+        .filter(|segment| !segment.fill)
+        .map(|segment| segment.value.graphemes(true).count())
+        .sum::<usize>();
+    let mut index = 0;
+
+    segments
+        .into_iter()
+        .flat_map(|segment| {
+            if segment.fill {
+                return vec![segment];
+            }
+            segment
+                .value
+                .graphemes(true)
+                .map(|grapheme| {
+                    let ratio = if total > 1 {
+                        index as f64 / (total - 1) as f64
+                    } else {
+                        0.0
+                    };
+                    index += 1;
+
+                    let mut style = segment.style.unwrap_or_default();
+                    if let Some(colors) = gradient.foreground {
+                        style.foreground = Some(interpolate(colors, ratio));
+                    }
+                    if let Some(colors) = gradient.background {
+                        style.background = Some(interpolate(colors, ratio));
+                    }
+                    Segment {
+                        style: Some(style),
+                        value: grapheme.to_string(),
+                        fill: false,
+                        link: segment.link.clone(),
+                        underline: segment.underline,
+                        module: segment.module.clone(),
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::Underline;
     use ansi_term::Color;
 
     // match_next(result: IterMut<Segment>, value, style)
@@ -476,6 +722,42 @@ mod tests {
         match_next!(result_iter, "inner", inner_style);
     }
 
+    #[test]
+    fn test_gradient_style() {
+        const FORMAT_STR: &str = "[a$var](bold fg:gradient(#ff0000, #0000ff))";
+
+        let formatter = StringFormatter::new(FORMAT_STR)
+            .unwrap()
+            .map(|variable| match variable {
+                "var" => Some(Ok("bc".to_owned())),
+                _ => None,
+            });
+        let result = formatter.parse(None).unwrap();
+        let mut result_iter = result.iter();
+        match_next!(result_iter, "a", Some(Color::RGB(255, 0, 0).bold()));
+        match_next!(result_iter, "b", Some(Color::RGB(128, 0, 128).bold()));
+        match_next!(result_iter, "c", Some(Color::RGB(0, 0, 255).bold()));
+        assert!(result_iter.next().is_none());
+    }
+
+    #[test]
+    fn test_double_colored_underline() {
+        const FORMAT_STR: &str = "[a[b](underline:red)](double-underline underline:#00ff00)";
+
+        let formatter = StringFormatter::new(FORMAT_STR).unwrap();
+        let result = formatter.parse(None).unwrap();
+        let underline = |double, color| Some(Underline { double, color });
+        assert_eq!(
+            underline(true, Some(Color::RGB(0, 255, 0))),
+            result[0].underline
+        );
+        assert_eq!(underline(false, Some(Color::Red)), result[1].underline);
+        assert_eq!(
+            "\u{1b}[4m\u{1b}[4:2m\u{1b}[58;2;0;255;0ma\u{1b}[4m\u{1b}[59m\u{1b}[58;5;1mb\u{1b}[59m\u{1b}[0m",
+            ansi_term::ANSIStrings(&[result[0].ansi_string(), result[1].ansi_string()]).to_string()
+        );
+    }
+
     #[test]
     fn test_styled_variable_as_text() {
         const FORMAT_STR: &str = "[$var](red bold)";
@@ -612,6 +894,174 @@ mod tests {
         match_next!(result_iter, " ", None);
     }
 
+    #[test]
+    fn test_guarded_comparison() {
+        const FORMAT_STR: &str =
+            "(${status} != 0)[✗$status](red)($jobs > 1)[$jobs](blue)($duration >= 1m)[slow]()";
+        let red_style = Some(Color::Red.normal());
+
+        let render = |status: &'static str, jobs: &'static str, duration: &'static str| {
+            StringFormatter::new(FORMAT_STR)
+                .unwrap()
+                .map(|var| match var {
+                    "status" => Some(Ok(status)),
+                    "jobs" => Some(Ok(jobs)),
+                    "duration" => Some(Ok(duration)),
+                    _ => None,
+                })
+                .parse(None)
+                .unwrap()
+        };
+
+        let result = render("1", "10", "1m30s");
+        let mut result_iter = result.iter();
+        match_next!(result_iter, "✗", red_style);
+        match_next!(result_iter, "1", red_style);
+        match_next!(result_iter, "10", Some(Color::Blue.normal()));
+        match_next!(result_iter, "slow", Some(Style::new()));
+        assert!(result_iter.next().is_none());
+
+        assert!(render("0", "1", "59s").is_empty());
+    }
+
+    #[test]
+    fn test_guarded_comparison_operands() {
+        const FORMAT_STR: &str =
+            r#"($branch == "main")[on main]()($branch != $default)[!]()($unset != 0)[unset]()"#;
+
+        let formatter = StringFormatter::new(FORMAT_STR)
+            .unwrap()
+            .map(|var| match var {
+                "branch" => Some(Ok("main")),
+                "default" => Some(Ok("master")),
+                _ => None,
+            });
+        let result = formatter.parse(None).unwrap();
+        let mut result_iter = result.iter();
+        match_next!(result_iter, "on main", Some(Style::new()));
+        match_next!(result_iter, "!", Some(Style::new()));
+        assert!(result_iter.next().is_none());
+    }
+
+    #[test]
+    fn test_guarded_logic() {
+        const FORMAT_STR: &str =
+            "(${ahead}|${behind})[⇕]()($ahead & $behind)[⇕]()($none | $ahead > 5 & $behind)[!]()";
+
+        let render = |ahead: &'static str, behind: &'static str| {
+            StringFormatter::new(FORMAT_STR)
+                .unwrap()
+                .map(|var| match var {
+                    "ahead" if !ahead.is_empty() => Some(Ok(ahead)),
+                    "behind" if !behind.is_empty() => Some(Ok(behind)),
+                    _ => None,
+                })
+                .parse(None)
+                .unwrap()
+                .iter()
+                .map(|segment| segment.value.as_str())
+                .collect::<String>()
+        };
+
+        assert_eq!("", render("", ""));
+        assert_eq!("⇕", render("1", ""));
+        assert_eq!("⇕⇕", render("1", "2"));
+        assert_eq!("⇕⇕!", render("6", "2"));
+        assert_eq!("⇕", render("6", ""));
+    }
+
+    #[test]
+    fn test_ternary() {
+        const FORMAT_STR: &str =
+            r#"${staged ? "+$staged" : "clean"} ${$none ? "set" : "\"[unset](red)\""}"#;
+
+        let formatter = StringFormatter::new(FORMAT_STR)
+            .unwrap()
+            .map(|var| match var {
+                "staged" => Some(Ok("3")),
+                "none" => Some(Ok("")),
+                _ => None,
+            });
+        let result = formatter.parse(None).unwrap();
+        let mut result_iter = result.iter();
+        match_next!(result_iter, "+", None);
+        match_next!(result_iter, "3", None);
+        match_next!(result_iter, " ", None);
+        match_next!(result_iter, "\"", None);
+        match_next!(result_iter, "unset", Some(Color::Red.normal()));
+        match_next!(result_iter, "\"", None);
+        assert!(result_iter.next().is_none());
+    }
+
+    #[test]
+    fn test_function_call() {
+        const FORMAT_STR: &str = r#"[${truncate(branch, 7, "…")}](red)(${truncate($none, 1)}) ${truncate(truncate(branch, 4), 2, ".")}"#;
+
+        let formatter = StringFormatter::new(FORMAT_STR)
+            .unwrap()
+            .map(|var| match var {
+                "branch" => Some(Ok("feature/long")),
+                _ => None,
+            });
+        let result = formatter.parse(None).unwrap();
+        let mut result_iter = result.iter();
+        match_next!(result_iter, "feature…", Some(Color::Red.normal()));
+        match_next!(result_iter, " ", None);
+        match_next!(result_iter, "fe.", None);
+        assert!(result_iter.next().is_none());
+
+        let formatter = StringFormatter::new("${nope(branch)}")
+            .unwrap()
+            .map(|_| Some(Ok("value")));
+        assert!(formatter.parse(None).is_err());
+    }
+
+    #[test]
+    fn test_fallback() {
+        const FORMAT_STR: &str = "(via [${virtualenv:-no-env}](red)) ${branch:-\\$} ${env:HOST:-}";
+
+        let formatter = StringFormatter::new(FORMAT_STR)
+            .unwrap()
+            .map(|var| match var {
+                "branch" => Some(Ok("main")),
+                _ => None,
+            });
+        let result = formatter.parse(None).unwrap();
+        let mut result_iter = result.iter();
+        match_next!(result_iter, "via ", None);
+        match_next!(result_iter, "no-env", Some(Color::Red.normal()));
+        match_next!(result_iter, " ", None);
+        match_next!(result_iter, "main", None);
+        match_next!(result_iter, " ", None);
+        match_next!(result_iter, "", None);
+        assert!(result_iter.next().is_none());
+    }
+
+    #[test]
+    fn test_env_variable() {
+        use crate::context::with_format_variables;
+        use std::collections::HashMap;
+        use std::sync::Arc;
+
+        const FORMAT_STR: &str = "$env:PANE ($env:UNSET)${env:PANE}";
+        let env: HashMap<String, String> = vec![("env:PANE".to_string(), "%1".to_string())]
+            .into_iter()
+            .collect();
+
+        let result = with_format_variables(Arc::new(env), || {
+            StringFormatter::new(FORMAT_STR)
+                .unwrap()
+                .map(empty_mapper)
+                .parse(None)
+                .unwrap()
+        });
+        let mut result_iter = result.iter();
+        match_next!(result_iter, "%1", None);
+        match_next!(result_iter, " ", None);
+        match_next!(result_iter, "%1", None);
+        assert!(result_iter.next().is_none());
+    }
+
     #[test]
     fn test_variable_holder() {
         const FORMAT_STR: &str = "($a [($b) $c](none $s)) $d [t]($t)";
@@ -0,0 +1,237 @@
+use clap::ArgMatches;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::configs::history_log::HistoryLogConfig;
+use crate::context::Context;
+
+/// A single line of the prompt history log
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub timestamp: String,
+    pub cwd: String,
+    pub status: Option<i64>,
+    pub duration: Option<u128>,
+    pub branch: Option<String>,
+}
+
+impl HistoryEntry {
+    fn from_context(context: &Context) -> Self {
+        Self {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            cwd: context.logical_dir.to_string_lossy().to_string(),
+            status: context
+                .properties
+                .get("status_code")
+                .and_then(|status| status.parse().ok()),
+            duration: context.get_cmd_duration(),
+            branch: context.get_repo().ok().and_then(|repo| repo.branch.clone()),
+        }
+    }
+}
+
+/// Appends an entry for the prompt being rendered to the history log, if enabled
+pub fn record(context: &Context) {
+    let config = context.config.get_root_config().history_log;
+    if config.disabled {
+        return;
+    }
+
+    let path = match log_path(context, &config) {
+        Some(path) => path,
+        None => return,
+    };
+
+    let entry = HistoryEntry::from_context(context);
+    let result = serde_json::to_string(&entry)
+        .map_err(io::Error::from)
+        .and_then(|line| append_line(&path, &line, config.max_size, config.max_files));
+
+    if let Err(error) = result {
+        log::warn!("Unable to write to history log {:?}: {}", path, error);
+    }
+}
+
+/// Prints all entries of the history log, oldest first
+pub fn export(args: ArgMatches) {
+    let context = Context::new(args.clone());
+    let config = context.config.get_root_config().history_log;
+    let path = match log_path(&context, &config) {
+        Some(path) => path,
+        None => return,
+    };
+
+    let entries = rotated_paths(&path, config.max_files)
+        .into_iter()
+        .rev()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .flat_map(|content| {
+            content
+                .lines()
+                .filter_map(|line| serde_json::from_str::<HistoryEntry>(line).ok())
+                .collect::<Vec<_>>()
+        });
+
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    let result = match args.value_of("format") {
+        Some("csv") => export_csv(&mut handle, entries),
+        _ => entries
+            .map(|entry| serde_json::to_string(&entry).map_err(io::Error::from))
+            .try_for_each(|line| writeln!(handle, "{}", line?)),
+    };
+
+    if let Err(error) = result {
+        log::error!("Unable to export history log: {}", error);
+    }
+}
+
+fn export_csv<W: Write>(
+    out: &mut W,
+    entries: impl Iterator<Item = HistoryEntry>,
+) -> io::Result<()> {
+    fn escape(value: &str) -> String {
+        if value.contains(&[',', '"', '\n'][..]) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+    fn or_empty<T: ToString>(value: Option<T>) -> String {
+        value.map(|value| value.to_string()).unwrap_or_default()
+    }
+
+    writeln!(out, "timestamp,cwd,status,duration,branch")?;
+    for entry in entries {
+        writeln!(
+            out,
+            "{},{},{},{},{}",
+            escape(&entry.timestamp),
+            escape(&entry.cwd),
+            or_empty(entry.status),
+            or_empty(entry.duration),
+            escape(&or_empty(entry.branch)),
+        )?;
+    }
+    Ok(())
+}
+
+fn log_path(context: &Context, config: &HistoryLogConfig) -> Option<PathBuf> {
+    if config.path.is_empty() {
+        Some(context.get_cache_dir()?.join("history.jsonl"))
+    } else {
+        Some(Context::expand_tilde_to(
+            PathBuf::from(config.path),
+            context.get_home(),
+        ))
+    }
+}
+
+/// The log file followed by its rotated copies, newest first
+fn rotated_paths(path: &Path, max_files: usize) -> Vec<PathBuf> {
+    let mut paths = vec![path.to_path_buf()];
+    paths.extend((1..max_files).map(|n| {
+        let mut rotated = path.as_os_str().to_owned();
+        rotated.push(format!(".{}", n));
+        PathBuf::from(rotated)
+    }));
+    paths
+}
+
+/// Appends a line to the log, first rotating it if it grew larger than `max_size` bytes.
+/// At most `max_files` files (including the current one) are kept.
+fn append_line(path: &Path, line: &str, max_size: u64, max_files: usize) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let size = fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+    if size >= max_size {
+        let paths = rotated_paths(path, max_files);
+        if paths.len() > 1 {
+            // Shift every file one position back, dropping the oldest
+            for pair in paths.windows(2).rev() {
+                if pair[0].exists() {
+                    fs::rename(&pair[0], &pair[1])?;
+                }
+            }
+        } else {
+            fs::remove_file(path)?;
+        }
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appends_lines() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("history.jsonl");
+
+        append_line(&path, "one", 1024, 3)?;
+        append_line(&path, "two", 1024, 3)?;
+
+        assert_eq!("one\ntwo\n", fs::read_to_string(&path)?);
+        dir.close()
+    }
+
+    #[test]
+    fn rotates_when_full() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("history.jsonl");
+
+        for line in &["one", "two", "three", "four"] {
+            append_line(&path, line, 1, 3)?;
+        }
+
+        assert_eq!("four\n", fs::read_to_string(&path)?);
+        assert_eq!(
+            "three\n",
+            fs::read_to_string(dir.path().join("history.jsonl.1"))?
+        );
+        assert_eq!(
+            "two\n",
+            fs::read_to_string(dir.path().join("history.jsonl.2"))?
+        );
+        assert!(!dir.path().join("history.jsonl.3").exists());
+        dir.close()
+    }
+
+    #[test]
+    fn exports_csv() -> io::Result<()> {
+        let entries = vec![
+            HistoryEntry {
+                timestamp: "2021-05-01T10:00:00+00:00".to_string(),
+                cwd: "/home/user/a,b".to_string(),
+                status: Some(1),
+                duration: Some(120),
+                branch: Some("main".to_string()),
+            },
+            HistoryEntry {
+                timestamp: "2021-05-01T10:01:00+00:00".to_string(),
+                cwd: "/tmp".to_string(),
+                status: None,
+                duration: None,
+                branch: None,
+            },
+        ];
+
+        let mut out = Vec::new();
+        export_csv(&mut out, entries.into_iter())?;
+        let expected = "timestamp,cwd,status,duration,branch\n\
+                        2021-05-01T10:00:00+00:00,\"/home/user/a,b\",1,120,main\n\
+                        2021-05-01T10:01:00+00:00,/tmp,,,\n";
+
+        assert_eq!(expected, String::from_utf8_lossy(&out));
+        Ok(())
+    }
+}
@@ -1,6 +1,10 @@
 use std::ffi::OsStr;
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::{env, io};
+use std::process::{Command, Stdio};
+use std::{env, fs, io};
+
+use crate::config::StarshipConfig;
 
 /* We use a two-phase init here: the first phase gives a simple command to the
 shell. This command evaluates a more complicated script using `source` and
@@ -207,10 +211,142 @@ pub fn init_main(shell_name: &str) -> io::Result<()> {
 
 fn print_script(script: &str, path: &str) {
     let starship_path_string = format!("\"{}\"", path);
-    let script = script.replace("::STARSHIP::", &starship_path_string);
+    let config = StarshipConfig::initialize();
+    let root_config = config.get_root_config();
+    let async_prompt = !root_config.async_modules.is_empty();
+    let right_prompt = !root_config.right_format.is_empty();
+    let script = script
+        .replace("::STARSHIP::", &starship_path_string)
+        .replace(
+            "::SEMANTIC_PROMPT::",
+            &root_config.semantic_prompt.to_string(),
+        )
+        .replace("::ASYNC_PROMPT::", &async_prompt.to_string())
+        .replace("::RIGHT_PROMPT::", &right_prompt.to_string());
     print!("{}", script);
 }
 
+/* `starship init --verify <shell>` runs the init script in a real shell and checks
+that the exit status and duration of a command reach the prompt. The shell renders
+a prompt with `VERIFY_CONFIG` after running a command which sleeps for a second and
+exits with status 3, so the prompt is expected to contain `<3|1s>`.
+
+bash is run interactively, with commands read from stdin, so its own PROMPT_COMMAND
+and DEBUG trap drive the hooks. zsh and fish only draw prompts on a terminal, so they are
+run in one made by `script`, with the commands typed into it. In every shell, the hooks of
+the generated init script are invoked by the shell itself. */
+const VERIFY_CONFIG: &str = r#"
+add_newline = false
+format = "<$status|$cmd_duration>"
+
+[status]
+disabled = false
+format = "$status"
+
+[cmd_duration]
+min_time = 0
+format = "$duration"
+"#;
+
+const VERIFY_EXPECTED: &str = "<3|1s>";
+
+fn to_strings(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+/// The arguments for `script` to run `command` in a terminal of its own, without a log file
+fn script_args(command: &[&str]) -> Vec<String> {
+    if cfg!(target_os = "linux") {
+        // util-linux takes the command as a single string
+        to_strings(&["-qefc", &shell_words::join(command), "/dev/null"])
+    } else {
+        // BSD and macOS take the command as the remaining arguments
+        to_strings(&[&["-q", "/dev/null"], command].concat())
+    }
+}
+
+pub fn verify(shell_name: &str) -> io::Result<()> {
+    let starship = StarshipPath::init()?.sprint_posix()?;
+    let (program, args, script): (&str, Vec<String>, String) = match shell_name {
+        "bash" => (
+            "bash",
+            to_strings(&["--norc", "--noprofile", "-i"]),
+            format!(
+                "eval \"$(\"{0}\" init bash)\"\nsleep 1; (exit 3)\nexit 0\n",
+                starship
+            ),
+        ),
+        "zsh" => (
+            "script",
+            script_args(&["zsh", "-f", "-i"]),
+            format!(
+                "eval \"$(\"{0}\" init zsh)\"\nsleep 1; (exit 3)\nexit 0\n",
+                starship
+            ),
+        ),
+        "fish" => (
+            "script",
+            script_args(&["fish", "--no-config", "--interactive"]),
+            format!(
+                "\"{0}\" init fish | source\nsleep 1; sh -c 'exit 3'\nexit 0\n",
+                starship
+            ),
+        ),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Verifying the init script is not supported for {}",
+                    shell_name
+                ),
+            ))
+        }
+    };
+
+    let dir = env::temp_dir().join(format!("starship-verify-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let config_path = dir.join("starship.toml");
+    fs::write(&config_path, VERIFY_CONFIG)?;
+
+    let output = Command::new(program)
+        .args(args)
+        .env("STARSHIP_CONFIG", &config_path)
+        .env("STARSHIP_CACHE", &dir)
+        .env("TERM", "xterm")
+        .current_dir(&dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            if let Some(stdin) = child.stdin.as_mut() {
+                stdin.write_all(script.as_bytes())?;
+            }
+            child.wait_with_output()
+        });
+    fs::remove_dir_all(&dir).ok();
+
+    let output = output.map_err(|error| {
+        io::Error::new(
+            error.kind(),
+            format!("Unable to run {}: {}", program, error),
+        )
+    })?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stdout.contains(VERIFY_EXPECTED) || stderr.contains(VERIFY_EXPECTED) {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Expected the {} prompt to contain {:?}\nstdout:\n{}\nstderr:\n{}",
+                shell_name, VERIFY_EXPECTED, stdout, stderr
+            ),
+        ))
+    }
+}
+
 /* GENERAL INIT SCRIPT NOTES
 
 Each init script will be passed as-is. Global notes for init scripts are in this
@@ -223,7 +359,10 @@ to avoid the cost of an additional shell fork every shell draw.
 Note that the init scripts are not in their final form--they are processed by
 `starship init` prior to emitting the final form. In this processing, some tokens
 are replaced, e.g. `::STARSHIP::` is replaced by the full path to the
-starship binary.
+starship binary, and `::SEMANTIC_PROMPT::` by `true` or `false` (which are
+commands in every shell using it) depending on the `semantic_prompt` option.
+`::ASYNC_PROMPT::` is replaced the same way depending on whether there are
+`async_modules`, and `::RIGHT_PROMPT::` depending on whether `right_format` is set.
 */
 
 const BASH_INIT: &str = include_str!("starship.bash");
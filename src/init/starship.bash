@@ -21,6 +21,8 @@ starship_preexec() {
     if [ "$STARSHIP_PREEXEC_READY" = "true" ]; then
         STARSHIP_PREEXEC_READY=false
         STARSHIP_START_TIME=$(::STARSHIP:: time)
+        # Mark the start of the command output for terminals with semantic prompt support
+        if ::SEMANTIC_PROMPT::; then printf '\e]133;C\a'; fi
     fi
 
     : "$PREV_LAST_ARG"
@@ -29,13 +31,19 @@ starship_preexec() {
 # Will be run before the prompt is drawn
 starship_precmd() {
     # Save the status, because commands in this pipeline will change $?
-    STARSHIP_CMD_STATUS=$?
+    STARSHIP_CMD_STATUS=$? STARSHIP_PIPE_STATUS=(${PIPESTATUS[@]})
+    # bash-preexec runs commands before ours, but saves the pipestatus
+    if [[ ${BP_PIPESTATUS-} && ${#BP_PIPESTATUS[@]} -gt ${#STARSHIP_PIPE_STATUS[@]} ]]; then
+        STARSHIP_PIPE_STATUS=(${BP_PIPESTATUS[@]})
+    fi
 
-    local NUM_JOBS=0
+    local NUM_JOBS=0 NUM_RUNNING_JOBS=0 NUM_STOPPED_JOBS=0
     # Evaluate the number of jobs before running the preseved prompt command, so that tools
     # like z/autojump, which background certain jobs, do not cause spurious background jobs
     # to be displayed by starship. Also avoids forking to run `wc`, slightly improving perf.
     for job in $(jobs -p); do [[ $job ]] && ((NUM_JOBS++)); done
+    for job in $(jobs -pr); do [[ $job ]] && ((NUM_RUNNING_JOBS++)); done
+    for job in $(jobs -ps); do [[ $job ]] && ((NUM_STOPPED_JOBS++)); done
 
     # Run the bash precmd function, if it's set. If not set, evaluates to no-op
     "${starship_precmd_user_func-:}"
@@ -46,10 +54,10 @@ starship_precmd() {
     if [[ $STARSHIP_START_TIME ]]; then
         STARSHIP_END_TIME=$(::STARSHIP:: time)
         STARSHIP_DURATION=$((STARSHIP_END_TIME - STARSHIP_START_TIME))
-        PS1="$(::STARSHIP:: prompt --status=$STARSHIP_CMD_STATUS --jobs="$NUM_JOBS" --cmd-duration=$STARSHIP_DURATION)"
+        PS1="$(::STARSHIP:: prompt --status=$STARSHIP_CMD_STATUS --pipestatus="${STARSHIP_PIPE_STATUS[*]}" --history-number="$HISTCMD" --jobs="$NUM_JOBS" --running-jobs="$NUM_RUNNING_JOBS" --stopped-jobs="$NUM_STOPPED_JOBS" --cmd-duration=$STARSHIP_DURATION)"
         unset STARSHIP_START_TIME
     else
-        PS1="$(::STARSHIP:: prompt --status=$STARSHIP_CMD_STATUS --jobs="$NUM_JOBS")"
+        PS1="$(::STARSHIP:: prompt --status=$STARSHIP_CMD_STATUS --pipestatus="${STARSHIP_PIPE_STATUS[*]}" --history-number="$HISTCMD" --jobs="$NUM_JOBS" --running-jobs="$NUM_RUNNING_JOBS" --stopped-jobs="$NUM_STOPPED_JOBS")"
     fi
     STARSHIP_PREEXEC_READY=true  # Signal that we can safely restart the timer
 }
@@ -93,6 +101,9 @@ fi
 STARSHIP_START_TIME=$(::STARSHIP:: time)
 export STARSHIP_SHELL="bash"
 
+# Set the continuation prompt
+PS2="$(::STARSHIP:: prompt --continuation)"
+
 # Set up the session key that will be used to store logs
 STARSHIP_SESSION_KEY="$RANDOM$RANDOM$RANDOM$RANDOM$RANDOM"; # Random generates a number b/w 0 - 32767
 STARSHIP_SESSION_KEY="${STARSHIP_SESSION_KEY}0000000000000000" # Pad it to 16+ chars.
@@ -5,10 +5,105 @@ function fish_prompt
         case '*'
             set STARSHIP_KEYMAP insert
     end
+    set STARSHIP_CMD_PIPESTATUS $pipestatus
     set STARSHIP_CMD_STATUS $status
     # Account for changes in variable name between v2.7 and v3.0
     set STARSHIP_DURATION "$CMD_DURATION$cmd_duration"
-    ::STARSHIP:: prompt --status=$STARSHIP_CMD_STATUS --keymap=$STARSHIP_KEYMAP --cmd-duration=$STARSHIP_DURATION --jobs=(count (jobs -p))
+    set STARSHIP_JOBS (count (jobs -p))
+    # The job table has a tab-separated `stopped` state column for stopped jobs
+    set STARSHIP_STOPPED_JOBS (count (jobs | string match -r '\tstopped\t'))
+    if test "$STARSHIP_TRANSIENT" = 1
+        set -g STARSHIP_TRANSIENT 0
+        ::STARSHIP:: prompt --transient --async-id=$STARSHIP_ASYNC_ID --status=$STARSHIP_CMD_STATUS --pipestatus="$STARSHIP_CMD_PIPESTATUS" --history-number=(math (count $history) + 1) --keymap=$STARSHIP_KEYMAP --cmd-duration=$STARSHIP_DURATION --jobs=$STARSHIP_JOBS --running-jobs=(math $STARSHIP_JOBS - $STARSHIP_STOPPED_JOBS) --stopped-jobs=$STARSHIP_STOPPED_JOBS
+        return
+    end
+    ::STARSHIP:: prompt --async-id=$STARSHIP_ASYNC_ID --status=$STARSHIP_CMD_STATUS --pipestatus="$STARSHIP_CMD_PIPESTATUS" --history-number=(math (count $history) + 1) --keymap=$STARSHIP_KEYMAP --cmd-duration=$STARSHIP_DURATION --jobs=$STARSHIP_JOBS --running-jobs=(math $STARSHIP_JOBS - $STARSHIP_STOPPED_JOBS) --stopped-jobs=$STARSHIP_STOPPED_JOBS
+end
+
+# Only spawn starship for the right prompt when `right_format` is set
+if ::RIGHT_PROMPT::
+    function fish_right_prompt
+        if test "$STARSHIP_RIGHT_TRANSIENT" = 1
+            set -g STARSHIP_RIGHT_TRANSIENT 0
+            return
+        end
+        switch "$fish_key_bindings"
+            case fish_hybrid_key_bindings fish_vi_key_bindings
+                set STARSHIP_KEYMAP "$fish_bind_mode"
+            case '*'
+                set STARSHIP_KEYMAP insert
+        end
+        set STARSHIP_CMD_PIPESTATUS $pipestatus
+        set STARSHIP_CMD_STATUS $status
+        # Account for changes in variable name between v2.7 and v3.0
+        set STARSHIP_DURATION "$CMD_DURATION$cmd_duration"
+        set STARSHIP_JOBS (count (jobs -p))
+        # The job table has a tab-separated `stopped` state column for stopped jobs
+        set STARSHIP_STOPPED_JOBS (count (jobs | string match -r '\tstopped\t'))
+        ::STARSHIP:: prompt --right --async-id=$STARSHIP_ASYNC_ID --status=$STARSHIP_CMD_STATUS --pipestatus="$STARSHIP_CMD_PIPESTATUS" --history-number=(math (count $history) + 1) --keymap=$STARSHIP_KEYMAP --cmd-duration=$STARSHIP_DURATION --jobs=$STARSHIP_JOBS --running-jobs=(math $STARSHIP_JOBS - $STARSHIP_STOPPED_JOBS) --stopped-jobs=$STARSHIP_STOPPED_JOBS
+    end
+end
+
+# Collapse the prompt of an accepted command line to `transient_format`.
+# Call `enable_transience` after the init line in config.fish to use it.
+function __starship_transient_execute
+    set -l cmdline (commandline)
+    if commandline --is-valid; or test -z "$cmdline"
+        set -g STARSHIP_TRANSIENT 1
+        set -g STARSHIP_RIGHT_TRANSIENT 1
+        commandline -f repaint
+    end
+    commandline -f execute
+end
+
+function enable_transience
+    bind \r __starship_transient_execute
+    bind -M insert \r __starship_transient_execute
+end
+
+function disable_transience
+    bind \r execute
+    bind -M insert \r execute
+end
+
+# Render the modules in `async_modules` in the background for every new prompt, and
+# redraw the prompt once they are done. The prompt leaves them out until then. The
+# background fish signals that it is done through a universal variable.
+if ::ASYNC_PROMPT::
+    set -g STARSHIP_ASYNC_ID 0
+
+    function __starship_async_start --on-event fish_prompt
+        set -l cmd_status $status
+        set -l cmd_pipestatus $pipestatus
+        set -l jobs_count (count (jobs -p))
+        set -l stopped_jobs_count (count (jobs | string match -r '\tstopped\t'))
+        # Stop rendering the modules of the previous prompt, the background fish runs in its
+        # own process group when job control is enabled
+        if set -q __starship_async_pid
+            command kill -- -$__starship_async_pid 2>/dev/null
+            or command kill $__starship_async_pid 2>/dev/null
+        end
+        set -g STARSHIP_ASYNC_ID (math $STARSHIP_ASYNC_ID + 1)
+        set -l render_async (string escape -- ::STARSHIP:: render-async --async-id=$STARSHIP_ASYNC_ID --status=$cmd_status "--pipestatus=$cmd_pipestatus" --cmd-duration="$CMD_DURATION$cmd_duration" --jobs=$jobs_count --running-jobs=(math $jobs_count - $stopped_jobs_count) --stopped-jobs=$stopped_jobs_count)
+        command fish --private --no-config --command "$render_async >/dev/null 2>&1; set -U __starship_async_$fish_pid $STARSHIP_ASYNC_ID" </dev/null &
+        set -g __starship_async_pid $last_pid
+        disown
+    end
+
+    function __starship_async_repaint --on-variable __starship_async_$fish_pid
+        commandline -f repaint
+    end
+
+    function __starship_async_cleanup --on-event fish_exit
+        set -e -U __starship_async_$fish_pid
+    end
+end
+
+# Mark the start of the command output for terminals with semantic prompt support
+if ::SEMANTIC_PROMPT::
+    function __starship_preexec --on-event fish_preexec
+        printf '\e]133;C\a'
+    end
 end
 
 # Disable virtualenv prompt, it breaks starship
@@ -19,5 +114,7 @@ builtin functions -e fish_mode_prompt
 
 set -gx STARSHIP_SHELL "fish"
 
+# Fish has no continuation prompt to set, see `continuation_format` in the docs
+
 # Set up the session key that will be used to store logs
 set -gx STARSHIP_SESSION_KEY (random 10000000000000 9999999999999999)
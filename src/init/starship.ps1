@@ -67,13 +67,18 @@ function global:prompt {
 
     # @ makes sure the result is an array even if single or no values are returned
     $jobs = @(Get-Job | Where-Object { $_.State -eq 'Running' }).Count
+    $stoppedJobs = @(Get-Job | Where-Object { $_.State -eq 'Suspended' }).Count
     
     $cwd = Get-Cwd
     $arguments = @(
         "prompt"
+        if ($global:_starship_transient) { "--transient" }
         "--path=$($cwd.Path)",
         "--logical-path=$($cwd.LogicalPath)",
-        "--jobs=$($jobs)"
+        "--jobs=$($jobs + $stoppedJobs)",
+        "--running-jobs=$($jobs)",
+        "--stopped-jobs=$($stoppedJobs)",
+        "--terminal-width=$($Host.UI.RawUI.WindowSize.Width)"
     )
     
     # Whe start from the premise that the command executed correctly, which covers also the fresh console.
@@ -95,7 +100,14 @@ function global:prompt {
     $arguments += "--status=$($lastExitCodeForPrompt)"
 
     # Invoke Starship
-    Invoke-Native -Executable ::STARSHIP:: -Arguments $arguments
+    $promptText = Invoke-Native -Executable ::STARSHIP:: -Arguments $arguments
+
+    # PowerShell has no native right prompt, so starship positions it at the end of the last line
+    if ('::RIGHT_PROMPT::' -eq 'true' -and -not $global:_starship_transient) {
+        $promptText += Invoke-Native -Executable ::STARSHIP:: -Arguments ($arguments + "--right")
+    }
+    $global:_starship_transient = $false
+    $promptText
 
     # Propagate the original $LASTEXITCODE from before the prompt function was invoked.
     $global:LASTEXITCODE = $origLastExitCode
@@ -121,6 +133,24 @@ function global:prompt {
 
 }
 
+# Collapse the prompt of an accepted command line to `transient_format`.
+# Call `Enable-TransientPrompt` after the init line in your profile to use it (requires PSReadLine).
+function global:Enable-TransientPrompt {
+    Set-PSReadLineKeyHandler -Key Enter -ScriptBlock {
+        $parseErrors = $null
+        [Microsoft.PowerShell.PSConsoleReadLine]::GetBufferState([ref]$null, [ref]$null, [ref]$parseErrors, [ref]$null)
+        if ($parseErrors.Count -eq 0) {
+            $global:_starship_transient = $true
+            [Microsoft.PowerShell.PSConsoleReadLine]::InvokePrompt()
+        }
+        [Microsoft.PowerShell.PSConsoleReadLine]::AcceptLine()
+    }
+}
+
+function global:Disable-TransientPrompt {
+    Set-PSReadLineKeyHandler -Key Enter -Function AcceptLine
+}
+
 # Disable virtualenv prompt, it breaks starship
 $ENV:VIRTUAL_ENV_DISABLE_PROMPT=1
 
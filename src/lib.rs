@@ -1,22 +1,65 @@
+//! Starship renders the prompt of a shell. Besides the `starship` command, the prompt can
+//! be rendered by programs embedding this crate, like TUI apps or shells written in Rust.
+//!
+//! ```no_run
+//! use starship::{render_module, render_prompt, Context, Shell};
+//!
+//! let context = Context::builder()
+//!     .path("/home/user/project")
+//!     .shell(Shell::Zsh)
+//!     .status_code(1)
+//!     .terminal_width(120)
+//!     .build();
+//! let prompt = render_prompt(context);
+//!
+//! let branch = render_module("git_branch", Context::builder().build());
+//! ```
+//!
+//! Only `render_prompt`, `render_module` and the items re-exported here are a stable API.
+
 #[macro_use]
 extern crate shadow_rs;
 
 shadow!(shadow);
 
-// Lib is present to allow for benchmarking
+// Lib is present to allow for embedding and benchmarking
+mod async_modules;
 pub mod bug_report;
+pub mod completions;
 pub mod config;
 pub mod configs;
 pub mod configure;
 pub mod context;
+pub mod daemon;
 pub mod formatter;
+pub mod history;
 pub mod init;
 pub mod logger;
+pub mod metrics;
 pub mod module;
+mod module_cache;
 mod modules;
+pub mod presets;
 pub mod print;
 mod segment;
+pub mod serve;
+mod slow_modules;
+mod symbol_sets;
 mod utils;
 
 #[cfg(test)]
 mod test;
+
+pub use crate::config::StarshipConfig;
+pub use crate::context::{Context, ContextBuilder, Shell, Target};
+
+/// Renders the prompt for `context`, the main one unless another target was set
+pub fn render_prompt(context: Context) -> String {
+    print::get_prompt(context)
+}
+
+/// Renders a single module for `context`, e.g. `git_branch`, `custom.<name>` or
+/// `plugins.<name>`. Returns `None` if the module is unknown, disabled or empty.
+pub fn render_module(name: &str, context: Context) -> Option<String> {
+    print::render_module(name, context)
+}
@@ -5,16 +5,117 @@ use std::{
     collections::HashSet,
     env,
     fs::{self, File, OpenOptions},
-    io::Write,
-    path::PathBuf,
+    io::{self, Write},
+    path::{Path, PathBuf},
     sync::Mutex,
 };
 
+/// The size after which the file of `STARSHIP_LOG_FILE` is rotated
+const MAX_LOG_FILE_SIZE: u64 = 1024 * 1024;
+/// How many rotated log files are kept, as `starship.log.1` and so on
+const ROTATED_LOG_FILES: usize = 3;
+
 pub struct StarshipLogger {
     log_file: OnceCell<Mutex<File>>,
     log_file_path: PathBuf,
     log_file_content: HashSet<String>,
-    log_level: Level,
+    directives: Vec<Directive>,
+    /// The file records are written to, if `STARSHIP_LOG_FILE` is set
+    structured_log: OnceCell<Option<Mutex<File>>>,
+    structured_log_path: Option<PathBuf>,
+    /// The levels of the records written to the file, from `STARSHIP_LOG_FILE_LEVEL`
+    structured_log_directives: Vec<Directive>,
+}
+
+/// The minimum level of the records of a module, e.g. `git_status=trace` in `STARSHIP_LOG`,
+/// or of all records if there's no name
+#[derive(Debug, PartialEq)]
+struct Directive {
+    name: Option<String>,
+    level: LevelFilter,
+}
+
+/// Parses a comma-separated list of levels for modules, like `warn,git_status=trace`
+fn parse_directives(spec: &str) -> Vec<Directive> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|directive| !directive.is_empty())
+        .map(|directive| {
+            let (name, level) = match directive.split_once('=') {
+                Some((name, level)) if !name.trim().is_empty() => {
+                    (Some(name.trim().to_string()), level)
+                }
+                Some((_, level)) => (None, level),
+                None => (None, directive),
+            };
+            let level = match level.trim().to_lowercase().as_str() {
+                "trace" => LevelFilter::Trace,
+                "debug" => LevelFilter::Debug,
+                "info" => LevelFilter::Info,
+                "warn" => LevelFilter::Warn,
+                "error" => LevelFilter::Error,
+                "off" => LevelFilter::Off,
+                _ => LevelFilter::Warn,
+            };
+            Directive { name, level }
+        })
+        .collect()
+}
+
+/// The level of records from `target`, set by the directive whose name matches the deepest
+/// segments of the module path, e.g. `git_status` over `starship::modules` for
+/// `starship::modules::git_status`. Records match directives without a name too.
+fn level_for(directives: &[Directive], target: &str) -> LevelFilter {
+    let segments: Vec<&str> = target.split("::").collect();
+    // How deep into the module path the name matches, if it does
+    let depth = |name: &str| {
+        let name: Vec<&str> = name.split("::").collect();
+        segments
+            .windows(name.len())
+            .rposition(|window| window == name.as_slice())
+            .map(|start| start + name.len())
+    };
+    directives
+        .iter()
+        .filter_map(|directive| match &directive.name {
+            Some(name) => Some((depth(name)?, directive.level)),
+            None => Some((0, directive.level)),
+        })
+        .max_by_key(|(depth, _)| *depth)
+        .map_or(LevelFilter::Warn, |(_, level)| level)
+}
+
+/// The directory for logs which persist across sessions, `$XDG_STATE_HOME/starship`
+fn state_dir() -> Option<PathBuf> {
+    env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| Some(dirs_next::home_dir()?.join(".local/state")))
+        .map(|dir| dir.join("starship"))
+}
+
+/// Opens the log file at `path` for appending, first rotating it if it's too large
+fn open_rotated(path: &Path) -> io::Result<File> {
+    if matches!(fs::metadata(path), Ok(metadata) if metadata.len() >= MAX_LOG_FILE_SIZE) {
+        let with_suffix = |suffix: String| {
+            let mut name = path.as_os_str().to_owned();
+            name.push(suffix);
+            PathBuf::from(name)
+        };
+        let rotated = |index: usize| with_suffix(format!(".{}", index));
+        // Prompts of other shells may be rotating the file at the same time. Only the one
+        // which gets to move it aside rotates, so the older files are only shifted once.
+        let claimed = with_suffix(format!(".{}.tmp", std::process::id()));
+        if fs::rename(path, &claimed).is_ok() {
+            for index in (1..ROTATED_LOG_FILES).rev() {
+                fs::rename(rotated(index), rotated(index + 1)).ok();
+            }
+            fs::rename(&claimed, rotated(1))?;
+        }
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)
 }
 
 impl Default for StarshipLogger {
@@ -42,24 +143,28 @@ impl Default for StarshipLogger {
                 .collect(),
             log_file: OnceCell::new(),
             log_file_path: session_log_file,
-            log_level: env::var("STARSHIP_LOG")
-                .map(|level| match level.to_lowercase().as_str() {
-                    "trace" => Level::Trace,
-                    "debug" => Level::Debug,
-                    "info" => Level::Info,
-                    "warn" => Level::Warn,
-                    "error" => Level::Error,
-                    _ => Level::Warn,
-                })
-                .unwrap_or_else(|_| Level::Warn),
+            directives: parse_directives(&env::var("STARSHIP_LOG").unwrap_or_default()),
+            structured_log_directives: parse_directives(
+                &env::var("STARSHIP_LOG_FILE_LEVEL").unwrap_or_else(|_| "info".to_string()),
+            ),
+            structured_log: OnceCell::new(),
+            structured_log_path: env::var_os("STARSHIP_LOG_FILE").and_then(|path| {
+                match path.to_str() {
+                    Some("") | Some("1") | Some("true") => Some(state_dir()?.join("starship.log")),
+                    _ => Some(PathBuf::from(path)),
+                }
+            }),
         }
     }
 }
 
 impl StarshipLogger {
-    /// Override the minimum log level
+    /// Override the minimum log level, for all modules
     pub fn set_log_level(&mut self, level: log::Level) {
-        self.log_level = level;
+        self.directives = vec![Directive {
+            name: None,
+            level: level.to_level_filter(),
+        }];
     }
 
     /// Override the log level path
@@ -69,9 +174,43 @@ impl StarshipLogger {
     }
 }
 
+impl StarshipLogger {
+    /// Writes the record as a line of JSON to the file of `STARSHIP_LOG_FILE`, if set
+    fn write_structured(&self, record: &Record) {
+        let file = self.structured_log.get_or_init(|| {
+            let path = self.structured_log_path.as_ref()?;
+            match open_rotated(path) {
+                Ok(file) => Some(Mutex::new(file)),
+                Err(error) => {
+                    eprintln!("Unable to open the log file {:?}: {}", path, error);
+                    None
+                }
+            }
+        });
+        if let Some(Ok(mut file)) = file.as_ref().map(Mutex::lock) {
+            let line = serde_json::json!({
+                "time": chrono::Local::now().to_rfc3339(),
+                "pid": std::process::id(),
+                "level": record.level().as_str(),
+                "module": record.module_path().unwrap_or_default(),
+                "message": record.args().to_string(),
+            });
+            writeln!(file, "{}", line).ok();
+        }
+    }
+}
+
+impl StarshipLogger {
+    /// Whether the record is written to the file of `STARSHIP_LOG_FILE`
+    fn structured_enabled(&self, metadata: &Metadata) -> bool {
+        self.structured_log_path.is_some()
+            && metadata.level() <= level_for(&self.structured_log_directives, metadata.target())
+    }
+}
+
 impl log::Log for StarshipLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= self.log_level
+        metadata.level() <= level_for(&self.directives, metadata.target())
     }
 
     fn log(&self, record: &Record) {
@@ -105,6 +244,10 @@ impl log::Log for StarshipLogger {
                 .expect("Unable to write to the log file!");
         }
 
+        if self.structured_enabled(record.metadata()) {
+            self.write_structured(record);
+        }
+
         if self.enabled(record.metadata()) && !self.log_file_content.contains(to_print.as_str()) {
             eprintln!(
                 "[{}] - ({}): {}",
@@ -122,6 +265,12 @@ impl log::Log for StarshipLogger {
     }
 
     fn flush(&self) {
+        if let Some(Some(m)) = self.structured_log.get() {
+            m.lock()
+                .map(|mut writer| writer.flush())
+                .expect("Log file writer mutex was poisoned!")
+                .ok();
+        }
         if let Some(m) = self.log_file.get() {
             m.lock()
                 .map(|mut writer| writer.flush())
@@ -135,3 +284,77 @@ pub fn init() {
     log::set_boxed_logger(Box::new(StarshipLogger::default())).unwrap();
     log::set_max_level(LevelFilter::Trace);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_are_set_per_module() {
+        let directives = parse_directives("info, git_status=trace,aws=warn,starship::modules=off");
+
+        assert_eq!(
+            LevelFilter::Trace,
+            level_for(&directives, "starship::modules::git_status")
+        );
+        assert_eq!(
+            LevelFilter::Warn,
+            level_for(&directives, "starship::modules::aws")
+        );
+        assert_eq!(
+            LevelFilter::Off,
+            level_for(&directives, "starship::modules::python")
+        );
+        assert_eq!(
+            LevelFilter::Info,
+            level_for(&directives, "starship::context")
+        );
+        assert_eq!(LevelFilter::Warn, level_for(&[], "starship::context"));
+        assert_eq!(
+            LevelFilter::Warn,
+            level_for(&parse_directives("verbose"), "starship::context")
+        );
+    }
+
+    #[test]
+    fn log_file_has_its_own_levels() {
+        let logger = StarshipLogger {
+            log_file: OnceCell::new(),
+            log_file_path: PathBuf::new(),
+            log_file_content: HashSet::new(),
+            directives: parse_directives("warn"),
+            structured_log: OnceCell::new(),
+            structured_log_path: Some(PathBuf::from("starship.log")),
+            structured_log_directives: parse_directives("info,git_status=trace"),
+        };
+        let metadata = |level, target| Metadata::builder().level(level).target(target).build();
+
+        let info = metadata(Level::Info, "starship::context");
+        assert!(!log::Log::enabled(&logger, &info));
+        assert!(logger.structured_enabled(&info));
+        let trace = metadata(Level::Trace, "starship::modules::git_status");
+        assert!(logger.structured_enabled(&trace));
+        assert!(!logger.structured_enabled(&metadata(Level::Debug, "starship::context")));
+    }
+
+    #[test]
+    fn log_files_are_rotated() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("starship.log");
+        fs::write(&path, vec![b'a'; MAX_LOG_FILE_SIZE as usize])?;
+        fs::write(dir.path().join("starship.log.1"), "older")?;
+
+        let mut file = open_rotated(&path)?;
+        writeln!(file, "new")?;
+        assert_eq!("new\n", fs::read_to_string(&path)?);
+        assert_eq!(
+            MAX_LOG_FILE_SIZE,
+            fs::metadata(dir.path().join("starship.log.1"))?.len()
+        );
+        assert_eq!(
+            "older",
+            fs::read_to_string(dir.path().join("starship.log.2"))?
+        );
+        dir.close()
+    }
+}
@@ -21,6 +21,18 @@ fn main() {
         .help("The status code of the previously run command")
         .takes_value(true);
 
+    let history_number_arg = Arg::with_name("history_number")
+        .long("history-number")
+        .value_name("NUMBER")
+        .help("The history event number of the next command")
+        .takes_value(true);
+
+    let pipestatus_arg = Arg::with_name("pipestatus")
+        .long("pipestatus")
+        .value_name("PIPESTATUS")
+        .help("The status codes of the commands of the previous pipeline, separated by spaces")
+        .takes_value(true);
+
     let path_arg = Arg::with_name("path")
         .short("p")
         .long("path")
@@ -67,6 +79,47 @@ fn main() {
         .help("The number of currently running jobs")
         .takes_value(true);
 
+    let running_jobs_arg = Arg::with_name("running_jobs")
+        .long("running-jobs")
+        .value_name("JOBS")
+        .help("The number of jobs running in the background")
+        .takes_value(true);
+
+    let stopped_jobs_arg = Arg::with_name("stopped_jobs")
+        .long("stopped-jobs")
+        .value_name("JOBS")
+        .help("The number of stopped jobs")
+        .takes_value(true);
+
+    let terminal_width_arg = Arg::with_name("terminal_width")
+        .short("w")
+        .long("terminal-width")
+        .value_name("WIDTH")
+        .help("The width of the current interactive terminal.")
+        .takes_value(true);
+
+    let async_id_arg = Arg::with_name("async_id")
+        .long("async-id")
+        .value_name("ID")
+        .help("The id of the prompt whose `async_modules` are rendered in the background")
+        .takes_value(true);
+
+    let list_status_arg = Arg::with_name("status_column")
+        .long("with-status")
+        .help("List modules with their descriptions and whether they are shown in this directory");
+
+    let verify_arg = Arg::with_name("verify")
+        .long("verify")
+        .help("Runs the init script in the given shell and checks the prompt it draws");
+
+    let transient_arg = Arg::with_name("transient")
+        .long("transient")
+        .help("Print the transient prompt shown in place of accepted command lines");
+
+    let continuation_arg = Arg::with_name("continuation")
+        .long("continuation")
+        .help("Print the continuation prompt (instead of the standard left prompt)");
+
     let init_scripts_arg = Arg::with_name("print_full_init")
         .long("print-full-init")
         .help("Print the main initialization script (as opposed to the init stub)");
@@ -85,17 +138,60 @@ fn main() {
             SubCommand::with_name("init")
                 .about("Prints the shell function used to execute starship")
                 .arg(&shell_arg)
-                .arg(&init_scripts_arg),
+                .arg(&init_scripts_arg)
+                .arg(&verify_arg),
         )
         .subcommand(
             SubCommand::with_name("prompt")
                 .about("Prints the full starship prompt")
+                .arg(
+                    Arg::with_name("right")
+                        .long("right")
+                        .help("Print the right prompt (instead of the standard left prompt)"),
+                )
+                .arg(&transient_arg)
+                .arg(&continuation_arg)
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .possible_values(&["text", "json"])
+                        .default_value("text")
+                        .help("Print the prompt as text, or as JSON segments with their styles"),
+                )
                 .arg(&status_code_arg)
+                .arg(&pipestatus_arg)
+                .arg(&history_number_arg)
                 .arg(&path_arg)
                 .arg(&logical_path_arg)
                 .arg(&cmd_duration_arg)
                 .arg(&keymap_arg)
-                .arg(&jobs_arg),
+                .arg(&jobs_arg)
+                .arg(&running_jobs_arg)
+                .arg(&stopped_jobs_arg)
+                .arg(&terminal_width_arg)
+                .arg(&async_id_arg),
+        )
+        .subcommand(
+            SubCommand::with_name("statusline")
+                .about("Prints the prompt as plain text for editor status lines")
+                .arg(
+                    Arg::with_name("format")
+                        .short("f")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("The format to render instead of the configured `format`")
+                        .takes_value(true),
+                )
+                .arg(&status_code_arg)
+                .arg(&pipestatus_arg)
+                .arg(&history_number_arg)
+                .arg(&path_arg)
+                .arg(&logical_path_arg)
+                .arg(&cmd_duration_arg)
+                .arg(&jobs_arg)
+                .arg(&running_jobs_arg)
+                .arg(&stopped_jobs_arg),
         )
         .subcommand(
             SubCommand::with_name("module")
@@ -112,12 +208,24 @@ fn main() {
                         .long("list")
                         .help("List out all supported modules"),
                 )
+                .arg(&list_status_arg)
                 .arg(&status_code_arg)
+                .arg(&pipestatus_arg)
+                .arg(&history_number_arg)
                 .arg(&path_arg)
                 .arg(&logical_path_arg)
                 .arg(&cmd_duration_arg)
                 .arg(&keymap_arg)
-                .arg(&jobs_arg),
+                .arg(&jobs_arg)
+                .arg(&running_jobs_arg)
+                .arg(&stopped_jobs_arg),
+        )
+        .subcommand(
+            SubCommand::with_name("modules")
+                .about("Lists all modules with their descriptions")
+                .arg(&list_status_arg)
+                .arg(&path_arg)
+                .arg(&logical_path_arg),
         )
         .subcommand(
             SubCommand::with_name("config")
@@ -129,7 +237,28 @@ fn main() {
                         .required(false)
                         .requires("value"),
                 )
-                .arg(Arg::with_name("value").help("Value to place into that key")),
+                .arg(Arg::with_name("value").help("Value to place into that key"))
+                .subcommand(
+                    SubCommand::with_name("validate")
+                        .about("Check the configuration for unknown keys and invalid values"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("preset")
+                .about("Lists the bundled presets, or prints or installs one of them")
+                .arg(Arg::with_name("name").help("The preset to print or install"))
+                .arg(
+                    Arg::with_name("install")
+                        .long("install")
+                        .requires("name")
+                        .help("Write the preset to the config file instead of printing it"),
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .long("force")
+                        .requires("install")
+                        .help("Replace an existing config file with the preset"),
+                ),
         )
         .subcommand(
             SubCommand::with_name("print-config")
@@ -138,7 +267,20 @@ fn main() {
                     Arg::with_name("default")
                         .short("d")
                         .long("default")
-                        .help("Print the default instead of the computed config")
+                        .value_name("MODULE")
+                        .help(
+                            "Print the default instead of the computed config, \
+                             or the commented default config of MODULE",
+                        )
+                        .takes_value(true)
+                        .min_values(0)
+                        .max_values(1),
+                )
+                .arg(
+                    Arg::with_name("diff")
+                        .long("diff")
+                        .help("Print only the keys which differ from the default config")
+                        .conflicts_with("default")
                         .takes_value(false),
                 ),
         )
@@ -155,6 +297,31 @@ fn main() {
                         .help("The key of the config to be toggled")
                         .required(false)
                         .required_unless("name"),
+                )
+                .arg(
+                    Arg::with_name("session")
+                        .short("s")
+                        .long("session")
+                        .help("Only toggle the key for the current shell session")
+                        .takes_value(false),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("history")
+                .about("Work with the prompt history log")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("export")
+                        .about("Prints all entries of the prompt history log, oldest first")
+                        .arg(
+                            Arg::with_name("format")
+                                .long("format")
+                                .value_name("FORMAT")
+                                .help("The output format")
+                                .possible_values(&["jsonl", "csv"])
+                                .default_value("jsonl")
+                                .takes_value(true),
+                        ),
                 ),
         )
         .subcommand(
@@ -167,10 +334,99 @@ fn main() {
                 .about("Prints time in milliseconds")
                 .settings(&[AppSettings::Hidden]),
         )
+        .subcommand(
+            SubCommand::with_name("refresh-git-status")
+                .about("Refreshes the cached git status used by the async git_status mode")
+                .arg(&path_arg)
+                .settings(&[AppSettings::Hidden]),
+        )
+        .subcommand(
+            SubCommand::with_name("refresh-public-ip")
+                .about("Fetches the public IP address shown by the public_ip module")
+                .settings(&[AppSettings::Hidden]),
+        )
+        .subcommand(
+            SubCommand::with_name("refresh-weather")
+                .about("Fetches the weather shown by the weather module")
+                .settings(&[AppSettings::Hidden]),
+        )
+        .subcommand(
+            SubCommand::with_name("daemon")
+                .about("Renders prompts in a long-running process with warm caches"),
+        )
+        .subcommand(
+            SubCommand::with_name("serve")
+                .about("Renders prompts for JSON-RPC requests on stdin, one per line"),
+        )
+        .subcommand(
+            SubCommand::with_name("render-async")
+                .about("Renders the modules in `async_modules` for the prompt with the given id")
+                .arg(&status_code_arg)
+                .arg(&pipestatus_arg)
+                .arg(&history_number_arg)
+                .arg(&path_arg)
+                .arg(&logical_path_arg)
+                .arg(&cmd_duration_arg)
+                .arg(&keymap_arg)
+                .arg(&jobs_arg)
+                .arg(&running_jobs_arg)
+                .arg(&stopped_jobs_arg)
+                .arg(&terminal_width_arg)
+                .arg(async_id_arg.clone().required(true))
+                .settings(&[AppSettings::Hidden]),
+        )
         .subcommand(
             SubCommand::with_name("explain").about("Explains the currently showing modules"),
         )
-        .subcommand(SubCommand::with_name("timings").about("Prints timings of all active modules"))
+        .subcommand(
+            SubCommand::with_name("timings")
+                .about("Prints timings of all active modules")
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .possible_values(&["text", "json"])
+                        .default_value("text")
+                        .help("Print the timings as text, or as JSON with command times"),
+                )
+                .arg(
+                    Arg::with_name("threshold")
+                        .long("threshold")
+                        .value_name("MS")
+                        .validator(|value| {
+                            value
+                                .parse::<u64>()
+                                .map(|_| ())
+                                .map_err(|error| error.to_string())
+                        })
+                        .help("Only print modules which took at least this many milliseconds"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("compare")
+                .about("Renders the prompt with two configs and compares output and timings")
+                .arg(
+                    Arg::with_name("old")
+                        .value_name("OLD")
+                        .help("The config file to compare against")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("new")
+                        .value_name("NEW")
+                        .help("The config file to compare")
+                        .required(true),
+                )
+                .arg(&status_code_arg)
+                .arg(&pipestatus_arg)
+                .arg(&history_number_arg)
+                .arg(&path_arg)
+                .arg(&logical_path_arg)
+                .arg(&cmd_duration_arg)
+                .arg(&jobs_arg)
+                .arg(&running_jobs_arg)
+                .arg(&stopped_jobs_arg),
+        )
         .subcommand(
             SubCommand::with_name("completions")
                 .about("Generate starship shell completions for your shell to stdout")
@@ -184,6 +440,16 @@ fn main() {
                         .env("STARSHIP_SHELL"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("complete")
+                .about("Prints the values to complete an argument with, for shell completions")
+                .arg(
+                    Arg::with_name("kind")
+                        .possible_values(completions::KINDS)
+                        .required(true),
+                )
+                .settings(&[AppSettings::Hidden]),
+        )
         .subcommand(SubCommand::with_name("session").about("Generate random session key"));
 
     let matches = app.clone().get_matches();
@@ -191,47 +457,82 @@ fn main() {
     match matches.subcommand() {
         ("init", Some(sub_m)) => {
             let shell_name = sub_m.value_of("shell").expect("Shell name missing.");
-            if sub_m.is_present("print_full_init") {
+            if sub_m.is_present("verify") {
+                match init::verify(shell_name) {
+                    Ok(()) => println!("The {} init script works", shell_name),
+                    Err(error) => {
+                        eprintln!("{}", error);
+                        std::process::exit(1);
+                    }
+                }
+            } else if sub_m.is_present("print_full_init") {
                 init::init_main(shell_name).expect("can't init_main");
             } else {
                 init::init_stub(shell_name).expect("can't init_stub");
             }
         }
         ("prompt", Some(sub_m)) => print::prompt(sub_m.clone()),
+        ("statusline", Some(sub_m)) => print::statusline(sub_m.clone()),
+        ("modules", Some(sub_m)) => print::list_modules(sub_m.clone()),
         ("module", Some(sub_m)) => {
-            if sub_m.is_present("list") {
+            if sub_m.is_present("list") && !sub_m.is_present("status_column") {
                 println!("Supported modules list");
                 println!("----------------------");
                 for modules in ALL_MODULES {
                     println!("{}", modules);
                 }
-            }
-            if let Some(module_name) = sub_m.value_of("name") {
+            } else if sub_m.is_present("list") {
+                print::list_modules(sub_m.clone());
+            } else if let Some(module_name) = sub_m.value_of("name") {
                 print::module(module_name, sub_m.clone());
             }
         }
         ("config", Some(sub_m)) => {
-            if let Some(name) = sub_m.value_of("name") {
+            if let ("validate", Some(_)) = sub_m.subcommand() {
+                configure::validate_configuration()
+            } else if let Some(name) = sub_m.value_of("name") {
                 if let Some(value) = sub_m.value_of("value") {
-                    configure::update_configuration(name, value)
+                    configure::update_configuration(name, value);
+                    config::warn_invalid_values();
                 }
             } else {
-                configure::edit_configuration()
+                configure::edit_configuration();
+                config::warn_invalid_values();
             }
         }
+        ("preset", Some(sub_m)) => match sub_m.value_of("name") {
+            None => presets::list(),
+            Some(name) if sub_m.is_present("install") => {
+                presets::install(name, sub_m.is_present("force"))
+            }
+            Some(name) => presets::print(name),
+        },
         ("print-config", Some(sub_m)) => {
-            let print_default = sub_m.is_present("default");
-            configure::print_configuration(print_default)
+            config::warn_invalid_values();
+            if sub_m.is_present("diff") {
+                configure::print_configuration_diff()
+            } else if let Some(module) = sub_m.value_of("default") {
+                configure::print_default_module_configuration(module)
+            } else {
+                let print_default = sub_m.is_present("default");
+                configure::print_configuration(print_default)
+            }
         }
         ("toggle", Some(sub_m)) => {
             if let Some(name) = sub_m.value_of("name") {
-                if let Some(value) = sub_m.value_of("key") {
-                    configure::toggle_configuration(name, value)
+                let key = sub_m.value_of("key").unwrap_or("disabled");
+                if sub_m.is_present("session") {
+                    configure::toggle_session_configuration(name, key)
                 } else {
-                    configure::toggle_configuration(name, "disabled")
+                    configure::toggle_configuration(name, key)
                 }
             }
         }
+        ("history", Some(sub_m)) => {
+            if let ("export", Some(sub_m)) = sub_m.subcommand() {
+                history::export(sub_m.clone())
+            }
+        }
         ("bug-report", Some(_)) => bug_report::create(),
         ("time", _) => {
             match SystemTime::now()
@@ -242,8 +543,36 @@ fn main() {
                 None => println!("{}", -1),
             }
         }
+        ("refresh-git-status", Some(sub_m)) => print::refresh_git_status(sub_m.clone()),
+        ("refresh-public-ip", Some(sub_m)) => print::refresh_public_ip(sub_m.clone()),
+        ("refresh-weather", Some(sub_m)) => print::refresh_weather(sub_m.clone()),
+        ("daemon", Some(_)) => {
+            let result = daemon::run(
+                |args| {
+                    let matches = app.clone().get_matches_from_safe(args).ok()?;
+                    match matches.subcommand() {
+                        ("prompt", Some(sub_m)) => Some(sub_m.clone()),
+                        _ => None,
+                    }
+                },
+                print::daemon_prompt,
+            );
+            if let Err(error) = result {
+                eprintln!("Unable to run the daemon: {}", error);
+                std::process::exit(1);
+            }
+        }
+        ("serve", Some(_)) => {
+            let stdin = io::stdin();
+            if let Err(error) = serve::run(stdin.lock(), io::stdout()) {
+                eprintln!("Unable to serve prompts: {}", error);
+                std::process::exit(1);
+            }
+        }
+        ("render-async", Some(sub_m)) => print::render_async(sub_m.clone()),
         ("explain", Some(sub_m)) => print::explain(sub_m.clone()),
         ("timings", Some(sub_m)) => print::timings(sub_m.clone()),
+        ("compare", Some(sub_m)) => print::compare(sub_m.clone()),
         ("completions", Some(sub_m)) => {
             let shell: Shell = sub_m
                 .value_of("shell")
@@ -251,7 +580,16 @@ fn main() {
                 .parse()
                 .expect("Invalid shell");
 
-            app.gen_completions_to("starship", shell, &mut io::stdout().lock());
+            let mut script = Vec::new();
+            app.gen_completions_to("starship", shell, &mut script);
+            let script = String::from_utf8_lossy(&script).into_owned();
+            print!("{}", completions::add_dynamic_completions(shell, script));
+        }
+        ("complete", Some(sub_m)) => {
+            let kind = sub_m.value_of("kind").expect("kind missing");
+            for candidate in completions::candidates(kind) {
+                println!("{}", candidate);
+            }
         }
         ("session", _) => println!(
             "{}",
@@ -0,0 +1,134 @@
+use crate::configs::metrics::MetricsConfig;
+
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Collects prompt latency measurements and sends them to a statsd endpoint.
+///
+/// Measurements are buffered and sent as a single datagram when the sink is flushed
+/// (or dropped), so a prompt never waits on more than one non-blocking UDP send.
+pub struct StatsdSink {
+    socket: UdpSocket,
+    prefix: String,
+    lines: Mutex<Vec<String>>,
+}
+
+impl StatsdSink {
+    /// Creates a sink for the configured endpoint. Returns `None` if metrics are not
+    /// configured or the endpoint can't be reached.
+    pub fn new(config: &MetricsConfig) -> Option<Self> {
+        if config.statsd_address.is_empty() {
+            return None;
+        }
+
+        let address = match config.statsd_address.to_socket_addrs() {
+            Ok(mut addresses) => addresses.next()?,
+            Err(error) => {
+                log::warn!(
+                    "Unable to resolve metrics address {:?}: {}",
+                    config.statsd_address,
+                    error
+                );
+                return None;
+            }
+        };
+        let bind_address = if address.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+
+        let socket = UdpSocket::bind(bind_address)
+            .and_then(|socket| socket.connect(address).map(|_| socket))
+            .and_then(|socket| socket.set_nonblocking(true).map(|_| socket));
+        match socket {
+            Ok(socket) => Some(Self {
+                socket,
+                prefix: config.prefix.to_string(),
+                lines: Mutex::new(Vec::new()),
+            }),
+            Err(error) => {
+                log::warn!("Unable to open metrics socket to {}: {}", address, error);
+                None
+            }
+        }
+    }
+
+    /// Records how long it took to render the given part of the prompt
+    pub fn timing(&self, name: &str, duration: Duration) {
+        let line = format!(
+            "{}.{}.duration:{:.3}|ms",
+            self.prefix,
+            sanitize_name(name),
+            duration.as_secs_f64() * 1000.0
+        );
+        if let Ok(mut lines) = self.lines.lock() {
+            lines.push(line);
+        }
+    }
+
+    /// Sends all buffered measurements
+    pub fn flush(&self) {
+        let payload = match self.lines.lock() {
+            Ok(mut lines) if !lines.is_empty() => lines.drain(..).collect::<Vec<_>>().join("\n"),
+            _ => return,
+        };
+
+        if let Err(error) = self.socket.send(payload.as_bytes()) {
+            log::debug!("Unable to send metrics: {}", error);
+        }
+    }
+}
+
+impl Drop for StatsdSink {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// Replaces characters that have a special meaning in the statsd line protocol
+fn sanitize_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            ':' | '|' | '@' | '\n' | ' ' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disabled_without_address() {
+        let config = MetricsConfig::default();
+        assert!(StatsdSink::new(&config).is_none());
+    }
+
+    #[test]
+    fn sends_buffered_timings() -> std::io::Result<()> {
+        let server = UdpSocket::bind("127.0.0.1:0")?;
+        server.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let address = server.local_addr()?.to_string();
+
+        let config = MetricsConfig {
+            statsd_address: &address,
+            prefix: "test",
+        };
+        let sink = StatsdSink::new(&config).expect("sink should be created");
+        sink.timing("module.git_status", Duration::from_millis(12));
+        sink.timing("custom.foo bar", Duration::from_micros(500));
+        drop(sink);
+
+        let mut buf = [0; 512];
+        let len = server.recv(&mut buf)?;
+        let actual = String::from_utf8_lossy(&buf[..len]);
+        let expected = "test.module.git_status.duration:12.000|ms\n\
+                        test.custom.foo_bar.duration:0.500|ms";
+
+        assert_eq!(expected, actual);
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use crate::context::Shell;
+use crate::context::{ModuleDetails, Shell};
 use crate::segment::Segment;
 use crate::utils::wrap_colorseq_for_shell;
 use ansi_term::{ANSIString, ANSIStrings};
@@ -16,15 +16,19 @@ pub const ALL_MODULES: &[&str] = &[
     "cmake",
     "cmd_duration",
     "conda",
+    "container",
+    "cpu_temp",
     "dart",
     "deno",
     "directory",
+    "disk_usage",
     "docker_context",
     "dotnet",
     "elixir",
     "elm",
     "erlang",
     "env_var",
+    "fill",
     "gcloud",
     "git_branch",
     "git_commit",
@@ -33,6 +37,7 @@ pub const ALL_MODULES: &[&str] = &[
     "golang",
     "helm",
     "hg_branch",
+    "history",
     "hostname",
     "java",
     "scala",
@@ -41,6 +46,8 @@ pub const ALL_MODULES: &[&str] = &[
     "kotlin",
     "kubernetes",
     "line_break",
+    "locale",
+    "localip",
     "lua",
     "memory_usage",
     "nim",
@@ -48,8 +55,11 @@ pub const ALL_MODULES: &[&str] = &[
     "nodejs",
     "ocaml",
     "openstack",
+    "os",
     "package",
     "perl",
+    "proxy",
+    "public_ip",
     "purescript",
     "python",
     "red",
@@ -63,10 +73,15 @@ pub const ALL_MODULES: &[&str] = &[
     "shlvl",
     "singularity",
     "status",
+    "sudo",
     "time",
+    "uptime",
     "username",
     "vcsh",
     "vagrant",
+    "vpn",
+    "weather",
+    "wifi",
     "zig",
 ];
 
@@ -87,6 +102,12 @@ pub struct Module<'a> {
 
     /// the time it took to compute this module
     pub duration: Duration,
+
+    /// the part of `duration` spent running commands
+    pub command_duration: Duration,
+
+    /// What the module did while rendering, if recorded for `starship explain`
+    pub details: Option<ModuleDetails>,
 }
 
 impl<'a> Module<'a> {
@@ -98,6 +119,8 @@ impl<'a> Module<'a> {
             description: desc.to_string(),
             segments: Vec::new(),
             duration: Duration::default(),
+            command_duration: Duration::default(),
+            details: None,
         }
     }
 
@@ -185,6 +208,8 @@ mod tests {
             description: desc.to_string(),
             segments: Vec::new(),
             duration: Duration::default(),
+            command_duration: Duration::default(),
+            details: None,
         };
 
         assert!(module.is_empty());
@@ -200,6 +225,8 @@ mod tests {
             description: desc.to_string(),
             segments: vec![Segment::new(None, "")],
             duration: Duration::default(),
+            command_duration: Duration::default(),
+            details: None,
         };
 
         assert!(module.is_empty());
@@ -215,6 +242,8 @@ mod tests {
             description: desc.to_string(),
             segments: vec![Segment::new(None, "\n")],
             duration: Duration::default(),
+            command_duration: Duration::default(),
+            details: None,
         };
 
         assert!(!module.is_empty());
@@ -230,6 +259,8 @@ mod tests {
             description: desc.to_string(),
             segments: vec![Segment::new(None, " ")],
             duration: Duration::default(),
+            command_duration: Duration::default(),
+            details: None,
         };
 
         assert!(!module.is_empty());
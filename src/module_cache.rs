@@ -0,0 +1,269 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::context::Context;
+use crate::segment::Segment;
+use crate::utils::fnv1a;
+
+/// A cached module output, `None` if the module wasn't shown
+#[derive(Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    created: u64,
+    segments: Option<Vec<Segment>>,
+}
+
+/// Cached module outputs by key, see `ModuleCache::key`
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct CacheState(BTreeMap<String, CacheEntry>);
+
+/// Cached module outputs kept in memory by the daemon
+pub type SharedModuleCache = Arc<Mutex<CacheState>>;
+
+/// Where the cached outputs are kept between prompts
+enum Store {
+    /// A file in the cache dir
+    File(PathBuf),
+    /// The memory of the daemon rendering the prompts
+    Shared(SharedModuleCache),
+}
+
+/// Modules whose output depends on the last command, the jobs or the time, so it can't be
+/// reused by a later prompt
+const UNCACHEABLE_MODULES: &[&str] = &["character", "cmd_duration", "jobs", "status", "time"];
+
+/// Caches the output of modules, e.g. the versions of toolchains, so repeated prompts
+/// don't run their commands again.
+///
+/// A cached output is used until the current directory, the git HEAD or index, one of the
+/// configured environment variables or the module's config change, or it gets older than
+/// `ttl` seconds.
+pub struct ModuleCache {
+    store: Store,
+    modules: Vec<String>,
+    /// Digest of everything but the module the outputs depend on
+    base_key: u64,
+    ttl: u64,
+    now: u64,
+    state: CacheState,
+    computed: Mutex<BTreeMap<String, CacheEntry>>,
+}
+
+impl ModuleCache {
+    /// Creates a cache for the current prompt. Returns `None` if the cache is disabled.
+    pub fn new(context: &Context) -> Option<Self> {
+        let config = context.config.get_root_config().module_cache;
+        if config.disabled {
+            return None;
+        }
+
+        let mut base = vec![
+            context.current_dir.to_string_lossy().to_string(),
+            git_state(context).unwrap_or_default(),
+        ];
+        for name in &config.env {
+            base.push(format!("{}={:?}", name, context.get_env(name)));
+        }
+
+        let modules = config
+            .modules
+            .iter()
+            .filter(|module| {
+                let cacheable = !UNCACHEABLE_MODULES.contains(module);
+                if !cacheable {
+                    log::warn!(
+                        "The output of the {} module changes with every prompt, it can't be cached",
+                        module
+                    );
+                }
+                cacheable
+            })
+            .map(|module| module.to_string())
+            .collect();
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs())
+            .unwrap_or_default();
+        let store = match &context.daemon {
+            Some(daemon) => Store::Shared(Arc::clone(&daemon.modules)),
+            None => Store::File(context.get_cache_dir()?.join("module_cache.json")),
+        };
+        Some(Self::load(
+            store,
+            modules,
+            fnv1a(base.join("\0").as_bytes()),
+            config.ttl,
+            now,
+        ))
+    }
+
+    fn load(store: Store, modules: Vec<String>, base_key: u64, ttl: u64, now: u64) -> Self {
+        let state = match &store {
+            Store::File(path) => read_state(path),
+            Store::Shared(shared) => shared.lock().map(|state| state.clone()).unwrap_or_default(),
+        };
+        Self {
+            store,
+            modules,
+            base_key,
+            ttl,
+            now,
+            state,
+            computed: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Whether the output of `module` is cached
+    pub fn is_cached(&self, module: &str) -> bool {
+        self.modules.iter().any(|cached| cached == module)
+    }
+
+    /// The cached output of `module`, if there is a fresh one. The outer `Option` tells
+    /// whether there was one, the inner one whether the module was shown.
+    pub fn get(&self, module: &str, config: Option<&toml::Value>) -> Option<Option<Vec<Segment>>> {
+        let entry = self.state.0.get(&self.key(module, config))?;
+        if self.now.saturating_sub(entry.created) > self.ttl {
+            return None;
+        }
+        Some(entry.segments.clone())
+    }
+
+    /// Keeps the output of `module` to be saved
+    pub fn insert(&self, module: &str, config: Option<&toml::Value>, segments: Option<&[Segment]>) {
+        if let Ok(mut computed) = self.computed.lock() {
+            computed.insert(
+                self.key(module, config),
+                CacheEntry {
+                    created: self.now,
+                    segments: segments.map(|segments| segments.to_vec()),
+                },
+            );
+        }
+    }
+
+    /// Adds the outputs computed for this prompt to the cache, dropping expired ones
+    pub fn save(&self) {
+        let computed = match self.computed.lock() {
+            Ok(computed) if !computed.is_empty() => computed,
+            _ => return,
+        };
+        let merge = |state: &mut CacheState| {
+            state.0.extend(
+                computed
+                    .iter()
+                    .map(|(key, entry)| (key.clone(), entry.clone())),
+            );
+            state
+                .0
+                .retain(|_, entry| self.now.saturating_sub(entry.created) <= self.ttl);
+        };
+
+        let path = match &self.store {
+            Store::File(path) => path,
+            Store::Shared(shared) => {
+                if let Ok(mut state) = shared.lock() {
+                    merge(&mut state);
+                }
+                return;
+            }
+        };
+
+        // Re-read the state, since the right prompt may have been rendered in the meantime
+        let mut state = read_state(path);
+        merge(&mut state);
+        let result = serde_json::to_string(&state)
+            .map_err(io::Error::from)
+            .and_then(|content| {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, content)
+            });
+        if let Err(error) = result {
+            log::debug!("Unable to save module cache to {:?}: {}", path, error);
+        }
+    }
+
+    fn key(&self, module: &str, config: Option<&toml::Value>) -> String {
+        let config = format!(
+            "{:x}\0{:?}",
+            self.base_key,
+            config.map(toml::Value::to_string)
+        );
+        format!("{}-{:x}", module, fnv1a(config.as_bytes()))
+    }
+}
+
+/// When the HEAD and index of the current git repo were last changed
+fn git_state(context: &Context) -> Option<String> {
+    let git_dir = context.get_repo().ok()?.path.as_ref()?;
+    let modified = |name: &str| {
+        let modified = fs::metadata(git_dir.join(name)).ok()?.modified().ok()?;
+        Some(modified.duration_since(UNIX_EPOCH).ok()?.as_nanos())
+    };
+    Some(format!("{}:{:?}", modified("HEAD")?, modified("index")))
+}
+
+fn read_state(path: &Path) -> CacheState {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ansi_term::Color;
+
+    fn cache(path: &Path, base_key: u64, now: u64) -> ModuleCache {
+        ModuleCache::load(
+            Store::File(path.to_path_buf()),
+            vec!["rust".to_string()],
+            base_key,
+            60,
+            now,
+        )
+    }
+
+    #[test]
+    fn reuses_saved_outputs() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("module_cache.json");
+        let config = toml::Value::from("config");
+        let segments = vec![Segment::new(Some(Color::Red.bold()), "v1.52.0")];
+
+        let first = cache(&path, 1, 1000);
+        assert!(first.get("rust", Some(&config)).is_none());
+        first.insert("rust", Some(&config), Some(&segments));
+        first.insert("python", None, None);
+        first.save();
+
+        let second = cache(&path, 1, 1030);
+        let cached = second.get("rust", Some(&config)).unwrap().unwrap();
+        assert_eq!("v1.52.0", cached[0].value);
+        assert_eq!(Some(Color::Red.bold()), cached[0].style);
+        assert!(second.get("python", None).unwrap().is_none());
+
+        // Another directory, another config of the module, or an expired output
+        assert!(cache(&path, 2, 1030).get("rust", Some(&config)).is_none());
+        assert!(cache(&path, 1, 1030).get("rust", None).is_none());
+        assert!(cache(&path, 1, 1100).get("rust", Some(&config)).is_none());
+        dir.close()
+    }
+
+    #[test]
+    fn keys_are_stable() {
+        assert_eq!(0xcbf2_9ce4_8422_2325, fnv1a(b""));
+        assert_eq!(0xaf63_dc4c_8601_ec8c, fnv1a(b"a"));
+        assert_eq!(
+            "rust-6c1097df13aa7666",
+            cache(Path::new("module_cache.json"), 1, 0).key("rust", None)
+        );
+    }
+}
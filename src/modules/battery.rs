@@ -1,7 +1,10 @@
+use std::path::Path;
+
 use super::{Context, Module, RootModuleConfig, Shell};
 use crate::configs::battery::BatteryConfig;
 
 use crate::formatter::StringFormatter;
+use crate::utils;
 
 /// Creates a module for the battery percentage and charging state
 pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
@@ -13,7 +16,13 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     };
 
     let battery_status = get_battery_status()?;
-    let BatteryStatus { state, percentage } = battery_status;
+    let BatteryStatus {
+        state,
+        percentage,
+        time_remaining,
+        power_source,
+        batteries,
+    } = battery_status;
 
     let mut module = context.new_module("battery");
     let config: BatteryConfig = BatteryConfig::try_load(module.config);
@@ -54,7 +63,14 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
                 })
                 .map(|variable| match variable {
                     "percentage" => Some(Ok(format!("{}{}", percentage.round(), percentage_char))),
-                    _ => None,
+                    "time_remaining" => time_remaining.map(|seconds| Ok(render_time(seconds))),
+                    "power_source" => Some(Ok(power_source.to_string())),
+                    // The batteries are numbered from 1, e.g. `percentage_2` for the second one
+                    _ => {
+                        let index: usize = variable.strip_prefix("percentage_")?.parse().ok()?;
+                        let percentage = batteries.get(index.checked_sub(1)?)?;
+                        Some(Ok(format!("{}{}", percentage.round(), percentage_char)))
+                    }
                 });
 
             match formatter.parse(None) {
@@ -85,6 +101,7 @@ fn get_battery_status() -> Option<BatteryStatus> {
                 Some(BatteryInfo {
                     energy: battery.energy().value,
                     energy_full: battery.energy_full().value,
+                    energy_rate: battery.energy_rate().value,
                     state: battery.state(),
                 })
             }
@@ -98,23 +115,34 @@ fn get_battery_status() -> Option<BatteryStatus> {
                 None
             }
         })
-        .fold(
-            BatteryInfo {
-                energy: 0.0,
-                energy_full: 0.0,
-                state: battery::State::Unknown,
-            },
-            |mut acc, x| {
-                acc.energy += x.energy;
-                acc.energy_full += x.energy_full;
-                acc.state = merge_battery_states(acc.state, x.state);
-                acc
-            },
-        );
+        .collect::<Vec<_>>();
+    let batteries = battery_contructor
+        .iter()
+        .filter(|battery| battery.energy_full != 0.0)
+        .map(|battery| battery.energy / battery.energy_full * 100.0)
+        .collect();
+    let battery_contructor = battery_contructor.into_iter().fold(
+        BatteryInfo {
+            energy: 0.0,
+            energy_full: 0.0,
+            energy_rate: 0.0,
+            state: battery::State::Unknown,
+        },
+        |mut acc, x| {
+            acc.energy += x.energy;
+            acc.energy_full += x.energy_full;
+            acc.energy_rate += x.energy_rate;
+            acc.state = merge_battery_states(acc.state, x.state);
+            acc
+        },
+    );
     if battery_contructor.energy_full != 0.0 {
         let battery = BatteryStatus {
             percentage: battery_contructor.energy / battery_contructor.energy_full * 100.0,
             state: battery_contructor.state,
+            time_remaining: time_remaining(&battery_contructor),
+            power_source: get_power_source(battery_contructor.state),
+            batteries,
         };
         log::debug!("Battery status: {:?}", battery);
         Some(battery)
@@ -145,9 +173,71 @@ fn merge_battery_states(state1: battery::State, state2: battery::State) -> batte
     }
 }
 
+/// The seconds until the batteries are empty while discharging, or full while charging.
+/// The batteries only report the rate of the moment, so this is an estimate.
+fn time_remaining(info: &BatteryInfo) -> Option<u64> {
+    // Energies are in joules and the rate in watts
+    let energy = match info.state {
+        battery::State::Discharging => info.energy,
+        battery::State::Charging => info.energy_full - info.energy,
+        _ => return None,
+    };
+    if info.energy_rate <= 0.0 || energy < 0.0 {
+        return None;
+    }
+    Some((energy / info.energy_rate) as u64)
+}
+
+/// Renders the remaining time in hours and minutes like `cmd_duration`, e.g. `1h5m`
+fn render_time(seconds: u64) -> String {
+    let (hours, minutes) = (seconds / 3600, seconds / 60 % 60);
+    if hours == 0 {
+        format!("{}m", minutes)
+    } else if minutes == 0 {
+        format!("{}h", hours)
+    } else {
+        format!("{}h{}m", hours, minutes)
+    }
+}
+
+/// Where the device gets its power from: `ac`, `battery` or `ups`
+fn get_power_source(state: battery::State) -> &'static str {
+    if cfg!(target_os = "linux") {
+        if let Some(source) = read_power_source(Path::new("/sys/class/power_supply")) {
+            return source;
+        }
+    }
+    match state {
+        battery::State::Charging | battery::State::Full => "ac",
+        _ => "battery",
+    }
+}
+
+/// Reads the power source from the power supplies of Linux, see
+/// https://www.kernel.org/doc/html/latest/power/power_supply_class.html
+fn read_power_source(supplies_dir: &Path) -> Option<&'static str> {
+    let read = |supply: &Path, file: &str| {
+        utils::read_file(supply.join(file))
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default()
+    };
+    let mut source = None;
+    for supply in supplies_dir.read_dir().ok()?.flatten() {
+        let supply = supply.path();
+        match read(&supply, "type").as_str() {
+            "UPS" if read(&supply, "status") == "Discharging" => return Some("ups"),
+            "Mains" | "USB" if read(&supply, "online") == "1" => source = Some("ac"),
+            "Battery" if source.is_none() => source = Some("battery"),
+            _ => (),
+        }
+    }
+    source
+}
+
 struct BatteryInfo {
     energy: f32,
     energy_full: f32,
+    energy_rate: f32,
     state: battery::State,
 }
 
@@ -155,4 +245,64 @@ struct BatteryInfo {
 struct BatteryStatus {
     percentage: f32,
     state: battery::State,
+    /// The seconds until the batteries are empty or full, if they are discharging or charging
+    time_remaining: Option<u64>,
+    power_source: &'static str,
+    /// The percentage of each battery
+    batteries: Vec<f32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io;
+
+    #[test]
+    fn renders_time_remaining() {
+        assert_eq!("0m", render_time(59));
+        assert_eq!("45m", render_time(45 * 60));
+        assert_eq!("2h", render_time(2 * 3600 + 30));
+        assert_eq!("1h5m", render_time(3900));
+    }
+
+    #[test]
+    fn estimates_time_remaining() {
+        let info = |state, energy_rate| BatteryInfo {
+            energy: 36000.0,
+            energy_full: 72000.0,
+            energy_rate,
+            state,
+        };
+        assert_eq!(
+            Some(3600),
+            time_remaining(&info(battery::State::Discharging, 10.0))
+        );
+        assert_eq!(
+            Some(7200),
+            time_remaining(&info(battery::State::Charging, 5.0))
+        );
+        assert_eq!(None, time_remaining(&info(battery::State::Charging, 0.0)));
+        assert_eq!(None, time_remaining(&info(battery::State::Full, 5.0)));
+    }
+
+    #[test]
+    fn reads_power_source() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let supply = |name: &str, files: &[(&str, &str)]| -> io::Result<()> {
+            let supply = dir.path().join(name);
+            fs::create_dir(&supply)?;
+            for (file, value) in files {
+                fs::write(supply.join(file), format!("{}\n", value))?;
+            }
+            Ok(())
+        };
+        supply("BAT0", &[("type", "Battery"), ("status", "Discharging")])?;
+        assert_eq!(Some("battery"), read_power_source(dir.path()));
+        supply("AC", &[("type", "Mains"), ("online", "1")])?;
+        assert_eq!(Some("ac"), read_power_source(dir.path()));
+        supply("ups", &[("type", "UPS"), ("status", "Discharging")])?;
+        assert_eq!(Some("ups"), read_power_source(dir.path()));
+        dir.close()
+    }
 }
@@ -98,6 +98,42 @@ mod test {
         }
     }
 
+    #[test]
+    fn symbol_with_variables_of_other_modules() {
+        let expected = Some(format!("{} ", Color::Green.bold().paint("~❯")));
+
+        let actual = ModuleRenderer::new("character")
+            .path(dirs_next::home_dir().unwrap())
+            .config(toml::toml! {
+                [character]
+                success_symbol = "[${directory.path}❯](bold green)"
+            })
+            .collect();
+        assert_eq!(expected, actual);
+
+        // The reference back to character is skipped
+        let actual = ModuleRenderer::new("character")
+            .path(dirs_next::home_dir().unwrap())
+            .config(toml::toml! {
+                [character]
+                success_symbol = "[${directory.path}❯](bold green)"
+                [directory]
+                format = "${character.symbol}$path"
+            })
+            .collect();
+        assert_eq!(expected, actual);
+
+        // References in function calls are found too
+        let actual = ModuleRenderer::new("character")
+            .path(dirs_next::home_dir().unwrap())
+            .config(toml::toml! {
+                [character]
+                success_symbol = "[${truncate(directory.path, 5)}❯](bold green)"
+            })
+            .collect();
+        assert_eq!(expected, actual);
+    }
+
     #[test]
     fn custom_symbol() {
         let expected_fail = Some(format!("{} ", Color::Red.bold().paint("✖")));
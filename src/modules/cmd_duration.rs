@@ -1,3 +1,7 @@
+use chrono::{DateTime, Duration, Local};
+use std::convert::TryInto;
+
+use super::time::format_time;
 use super::{Context, Module, RootModuleConfig};
 
 use crate::configs::cmd_duration::CmdDurationConfig;
@@ -26,6 +30,8 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
         return None;
     }
 
+    // The prompt is drawn as soon as the command finishes
+    let finished_at = Local::now();
     let parsed = StringFormatter::new(config.format).and_then(|formatter| {
         formatter
             .map_style(|variable| match variable {
@@ -33,7 +39,12 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
                 _ => None,
             })
             .map(|variable| match variable {
-                "duration" => Some(Ok(render_time(elapsed, config.show_milliseconds))),
+                "duration" => Some(Ok(render_configured_time(elapsed, &config))),
+                "started_at" => Some(Ok(format_time(
+                    config.time_format,
+                    started_at(finished_at, elapsed),
+                ))),
+                "finished_at" => Some(Ok(format_time(config.time_format, finished_at))),
                 _ => None,
             })
             .parse(None)
@@ -47,36 +58,137 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
         }
     });
 
-    Some(undistract_me(module, &config, elapsed))
+    Some(undistract_me(module, &config, context, elapsed))
+}
+
+/// When a command which finished at `finished_at` after `elapsed` milliseconds started
+fn started_at(finished_at: DateTime<Local>, elapsed: u128) -> DateTime<Local> {
+    let elapsed = Duration::milliseconds(elapsed.try_into().unwrap_or(i64::MAX));
+    finished_at
+        .checked_sub_signed(elapsed)
+        .unwrap_or(finished_at)
+}
+
+/// The units of durations from the largest, as their length in milliseconds, their compact
+/// suffix and their verbose name
+const UNITS: [(u128, &str, &str); 5] = [
+    (86_400_000, "d", "day"),
+    (3_600_000, "h", "hour"),
+    (60_000, "m", "min"),
+    (1000, "s", "sec"),
+    (1, "ms", "ms"),
+];
+
+/// How a duration is rounded to its smallest unit
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Rounding {
+    Floor,
+    Round,
+    Ceil,
 }
 
 // Render the time into a nice human-readable string
 fn render_time(raw_millis: u128, show_millis: bool) -> String {
-    // Calculate a simple breakdown into days/hours/minutes/seconds/milliseconds
-    let (millis, raw_seconds) = (raw_millis % 1000, raw_millis / 1000);
-    let (seconds, raw_minutes) = (raw_seconds % 60, raw_seconds / 60);
-    let (minutes, raw_hours) = (raw_minutes % 60, raw_minutes / 60);
-    let (hours, days) = (raw_hours % 24, raw_hours / 24);
-
-    let components = [days, hours, minutes, seconds];
-    let suffixes = ["d", "h", "m", "s"];
-
-    let mut rendered_components: Vec<String> = components
-        .iter()
-        .zip(&suffixes)
-        .map(render_time_component)
-        .collect();
+    render_duration(
+        raw_millis,
+        default_min_unit(raw_millis, show_millis),
+        Rounding::Floor,
+        false,
+    )
+}
+
+/// The smallest unit shown without `min_unit`, seconds or milliseconds
+fn default_min_unit(raw_millis: u128, show_millis: bool) -> usize {
     if show_millis || raw_millis < 1000 {
-        rendered_components.push(render_time_component((&millis, &"ms")));
+        4
+    } else {
+        3
     }
-    rendered_components.join("")
 }
 
-/// Render a single component of the time string, giving an empty string if component is zero
-fn render_time_component((component, suffix): (&u128, &&str)) -> String {
-    match component {
-        0 => String::new(),
-        n => format!("{}{}", n, suffix),
+/// Renders the time like `render_time`, with the configured smallest unit, rounding and format
+fn render_configured_time(raw_millis: u128, config: &CmdDurationConfig) -> String {
+    let min_unit = match config.min_unit {
+        "" => None,
+        unit => {
+            let index = UNITS.iter().position(|(_, suffix, _)| *suffix == unit);
+            if index.is_none() {
+                log::warn!(
+                    "min_unit in [cmd_duration] should be one of d, h, m, s or ms, found {:?}",
+                    unit
+                );
+            }
+            index
+        }
+    };
+    let rounding = match config.rounding {
+        "floor" => Rounding::Floor,
+        "round" => Rounding::Round,
+        "ceil" => Rounding::Ceil,
+        rounding => {
+            log::warn!(
+                "rounding in [cmd_duration] should be floor, round or ceil, found {:?}",
+                rounding
+            );
+            Rounding::Floor
+        }
+    };
+    let verbose = match config.format_duration {
+        "compact" => false,
+        "verbose" => true,
+        format => {
+            log::warn!(
+                "format_duration in [cmd_duration] should be compact or verbose, found {:?}",
+                format
+            );
+            false
+        }
+    };
+    match min_unit {
+        None if rounding == Rounding::Floor && !verbose => {
+            render_time(raw_millis, config.show_milliseconds)
+        }
+        min_unit => render_duration(
+            raw_millis,
+            min_unit.unwrap_or_else(|| default_min_unit(raw_millis, config.show_milliseconds)),
+            rounding,
+            verbose,
+        ),
+    }
+}
+
+/// Renders the non-zero units of the time down to `UNITS[min_unit]`, like `1m3s` or, if
+/// verbose, `1 min 3 secs`
+fn render_duration(raw_millis: u128, min_unit: usize, rounding: Rounding, verbose: bool) -> String {
+    let unit_millis = UNITS[min_unit].0;
+    let count = match rounding {
+        Rounding::Floor => raw_millis / unit_millis,
+        Rounding::Round => (raw_millis + unit_millis / 2) / unit_millis,
+        Rounding::Ceil => raw_millis.div_ceil(unit_millis),
+    };
+    let mut remaining = count * unit_millis;
+
+    let mut components = Vec::new();
+    for (millis, suffix, name) in &UNITS[..=min_unit] {
+        let value = remaining / millis;
+        remaining %= millis;
+        if value > 0 {
+            components.push(render_time_component(value, suffix, name, verbose));
+        }
+    }
+    if components.is_empty() {
+        let (_, suffix, name) = UNITS[min_unit];
+        components.push(render_time_component(0, suffix, name, verbose));
+    }
+    components.join(if verbose { " " } else { "" })
+}
+
+/// Renders a single component of the time string, like `3s` or `3 secs`
+fn render_time_component(value: u128, suffix: &str, name: &str, verbose: bool) -> String {
+    match verbose {
+        false => format!("{}{}", value, suffix),
+        true if value == 1 || name == "ms" => format!("{} {}", value, name),
+        true => format!("{} {}s", value, name),
     }
 }
 
@@ -84,9 +196,10 @@ fn render_time_component((component, suffix): (&u128, &&str)) -> String {
 fn undistract_me<'a, 'b>(
     module: Module<'a>,
     config: &'b CmdDurationConfig,
+    _context: &'a Context,
     _elapsed: u128,
 ) -> Module<'a> {
-    if config.show_notifications {
+    if config.notify || config.show_notifications {
         log::debug!("This version of starship was built without notification support.");
     }
 
@@ -97,12 +210,13 @@ fn undistract_me<'a, 'b>(
 fn undistract_me<'a, 'b>(
     module: Module<'a>,
     config: &'b CmdDurationConfig,
+    context: &'a Context,
     elapsed: u128,
 ) -> Module<'a> {
     use ansi_term::{unstyle, ANSIStrings};
     use notify_rust::{Notification, Timeout};
 
-    if config.show_notifications && config.min_time_to_notify as u128 <= elapsed {
+    if should_notify(config, elapsed) && !is_terminal_focused(context) {
         let body = format!(
             "Command execution {}",
             unstyle(&ANSIStrings(&module.ansi_strings()))
@@ -113,7 +227,9 @@ fn undistract_me<'a, 'b>(
             .summary("Command finished")
             .body(&body)
             .icon("utilities-terminal")
-            .timeout(Timeout::Milliseconds(750));
+            .timeout(Timeout::Milliseconds(
+                config.notify_timeout.min(u32::MAX.into()) as u32,
+            ));
 
         if let Err(err) = notification.show() {
             log::trace!("Cannot show notification: {}", err);
@@ -123,6 +239,29 @@ fn undistract_me<'a, 'b>(
     module
 }
 
+/// Whether a notification is wanted for a command which took `elapsed` milliseconds.
+/// `show_notifications` is the former name of `notify`.
+#[cfg(any(feature = "notify-rust", test))]
+fn should_notify(config: &CmdDurationConfig, elapsed: u128) -> bool {
+    (config.notify || config.show_notifications) && config.min_time_to_notify as u128 <= elapsed
+}
+
+/// Whether the terminal window running the shell is focused, in which case the user is
+/// already looking at it. This can currently only be detected on X11, other platforms
+/// are assumed to be unfocused.
+#[cfg(any(feature = "notify-rust", test))]
+fn is_terminal_focused(context: &Context) -> bool {
+    let window_id = match context.get_env("WINDOWID") {
+        Some(window_id) => window_id,
+        None => return false,
+    };
+
+    context
+        .exec_cmd("xdotool", &["getactivewindow"])
+        .map(|output| output.stdout.trim() == window_id)
+        .unwrap_or(false)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -150,6 +289,49 @@ mod tests {
         assert_eq!(render_time(86_400_000_u128, true), "1d")
     }
 
+    #[test]
+    fn notify_after_min_time_to_notify() {
+        let config = toml::toml! {
+            notify = true
+            min_time_to_notify = 1000
+        };
+        let config = CmdDurationConfig::load(&config);
+        assert!(should_notify(&config, 1000));
+        assert!(!should_notify(&config, 999));
+
+        let config = toml::toml! { show_notifications = true };
+        assert!(should_notify(&CmdDurationConfig::load(&config), 45_000));
+        assert!(!should_notify(&CmdDurationConfig::default(), 45_000));
+    }
+
+    #[test]
+    fn terminal_focus() {
+        use crate::context::Shell;
+        use crate::utils::CommandOutput;
+        use std::path::PathBuf;
+
+        let mut context = Context::new_with_shell_and_path(
+            clap::ArgMatches::default(),
+            Shell::Unknown,
+            PathBuf::new(),
+            PathBuf::new(),
+        );
+        assert!(!is_terminal_focused(&context));
+
+        context.env.insert("WINDOWID", "12345".to_string());
+        context.cmd.insert(
+            "xdotool getactivewindow",
+            Some(CommandOutput {
+                stdout: String::from("12345\n"),
+                stderr: String::default(),
+            }),
+        );
+        assert!(is_terminal_focused(&context));
+
+        context.env.insert("WINDOWID", "67890".to_string());
+        assert!(!is_terminal_focused(&context));
+    }
+
     #[test]
     fn config_blank_duration_1s() {
         let actual = ModuleRenderer::new("cmd_duration")
@@ -225,4 +407,77 @@ mod tests {
         let expected = Some(format!("underwent {} ", Color::Yellow.bold().paint("5s")));
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!(render_duration(90_600, 3, Rounding::Floor, false), "1m30s");
+        assert_eq!(render_duration(90_600, 3, Rounding::Round, false), "1m31s");
+        assert_eq!(render_duration(90_100, 3, Rounding::Ceil, false), "1m31s");
+        assert_eq!(render_duration(3_599_999, 2, Rounding::Round, false), "1h");
+        assert_eq!(render_duration(400, 3, Rounding::Floor, false), "0s");
+    }
+
+    #[test]
+    fn test_verbose() {
+        assert_eq!(
+            render_duration(63_000, 3, Rounding::Floor, true),
+            "1 min 3 secs"
+        );
+        assert_eq!(
+            render_duration(90_061_250, 4, Rounding::Floor, true),
+            "1 day 1 hour 1 min 1 sec 250 ms"
+        );
+    }
+
+    #[test]
+    fn config_min_unit_and_format() {
+        let actual = ModuleRenderer::new("cmd_duration")
+            .config(toml::toml! {
+                [cmd_duration]
+                min_time = 0
+                min_unit = "ms"
+                format_duration = "verbose"
+                format = "$duration"
+            })
+            .cmd_duration(2_500)
+            .collect();
+        assert_eq!(Some("2 secs 500 ms".to_string()), actual);
+
+        let actual = ModuleRenderer::new("cmd_duration")
+            .config(toml::toml! {
+                [cmd_duration]
+                min_unit = "m"
+                rounding = "round"
+                format = "$duration"
+            })
+            .cmd_duration(150_000)
+            .collect();
+        assert_eq!(Some("3m".to_string()), actual);
+    }
+
+    #[test]
+    fn test_started_at() {
+        use chrono::TimeZone;
+
+        let finished_at = Local.ymd(2021, 6, 1).and_hms(12, 0, 5);
+        let expected = Local.ymd(2021, 6, 1).and_hms_milli(11, 58, 59, 500);
+        assert_eq!(started_at(finished_at, 65_500), expected);
+        assert_eq!(format_time("%T", expected), "11:58:59");
+    }
+
+    #[test]
+    fn config_timestamps() {
+        let actual = ModuleRenderer::new("cmd_duration")
+            .config(toml::toml! {
+                [cmd_duration]
+                format = "$started_at $finished_at"
+                time_format = "[%Y]"
+            })
+            .cmd_duration(5000)
+            .collect()
+            .unwrap();
+        let year = format!("[{}]", Local::now().format("%Y"));
+        assert!(actual.ends_with(&year));
+        assert_eq!(actual.len(), 2 * year.len() + 1);
+    }
 }
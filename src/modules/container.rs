@@ -0,0 +1,228 @@
+use std::fs;
+use std::path::Path;
+
+use super::{Context, Module, RootModuleConfig};
+
+use crate::configs::container::ContainerConfig;
+use crate::formatter::StringFormatter;
+
+/// The container the shell runs in
+#[derive(Debug, PartialEq)]
+struct Container {
+    /// What runs the container, like `docker` or `toolbox`
+    engine: String,
+    name: Option<String>,
+    image: Option<String>,
+}
+
+/// Creates a module with the name of the container the shell runs in
+pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
+    let mut module = context.new_module("container");
+    let config: ContainerConfig = ContainerConfig::try_load(module.config);
+
+    // As we default to disabled=true, we have to check here after loading our config module,
+    // before it was only checking against whatever is in the config starship.toml
+    if config.disabled {
+        return None;
+    }
+
+    let container = detect_container(context, Path::new("/"))?;
+    // Containers without a name of their own are named after their hostname, like the id of
+    // Docker containers
+    let name = container
+        .name
+        .clone()
+        .or_else(|| gethostname::gethostname().into_string().ok())
+        .unwrap_or_else(|| container.engine.clone());
+
+    let parsed = StringFormatter::new(config.format).and_then(|formatter| {
+        formatter
+            .map_meta(|variable, _| match variable {
+                "symbol" => Some(config.symbol),
+                _ => None,
+            })
+            .map_style(|variable| match variable {
+                "style" => Some(Ok(config.style)),
+                _ => None,
+            })
+            .map(|variable| match variable {
+                "name" => Some(Ok(name.clone())),
+                "engine" => Some(Ok(container.engine.clone())),
+                "image" => container.image.clone().map(Ok),
+                _ => None,
+            })
+            .parse(None)
+    });
+
+    module.set_segments(match parsed {
+        Ok(segments) => segments,
+        Err(error) => {
+            log::warn!("Error in module `container`:\n{}", error);
+            return None;
+        }
+    });
+
+    Some(module)
+}
+
+/// Detects the container from the files its engine creates in the filesystem at `root`
+fn detect_container(context: &Context, root: &Path) -> Option<Container> {
+    // Podman, and the toolbox and distrobox tools built on it, describe the container in
+    // lines like `name="fedora-toolbox-35"`
+    if let Ok(containerenv) = fs::read_to_string(root.join("run/.containerenv")) {
+        let value = |key: &str| {
+            containerenv.lines().find_map(|line| {
+                let (name, value) = line.split_once('=')?;
+                let value = value.trim_matches('"');
+                if name == key && !value.is_empty() {
+                    Some(value.to_string())
+                } else {
+                    None
+                }
+            })
+        };
+        // distrobox sets it to the name of the container
+        let distrobox = context.get_env("CONTAINER_ID");
+        let engine = if distrobox.is_some() {
+            "distrobox"
+        } else if root.join("run/.toolboxenv").exists() {
+            "toolbox"
+        } else {
+            "podman"
+        };
+        return Some(Container {
+            engine: engine.to_string(),
+            name: value("name").or(distrobox),
+            image: value("image"),
+        });
+    }
+
+    if root.join(".dockerenv").exists() {
+        return Some(Container {
+            engine: "docker".to_string(),
+            name: None,
+            image: None,
+        });
+    }
+
+    // systemd-nspawn, LXC and other engines following the Container Interface of systemd
+    let engine = fs::read_to_string(root.join("run/systemd/container"))
+        .ok()
+        .or_else(|| context.get_env("container"))
+        .map(|engine| engine.trim().to_string())
+        .filter(|engine| !engine.is_empty())
+        .or_else(|| {
+            let cgroup = fs::read_to_string(root.join("proc/1/cgroup")).ok()?;
+            let engine = if cgroup.contains("/docker/") {
+                "docker"
+            } else if cgroup.contains("/lxc/") {
+                "lxc"
+            } else if cgroup.contains("kubepods") {
+                "kubernetes"
+            } else {
+                return None;
+            };
+            Some(engine.to_string())
+        })?;
+    Some(Container {
+        engine,
+        name: None,
+        image: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::ModuleRenderer;
+    use clap::ArgMatches;
+    use std::io;
+
+    fn detect(
+        files: &[(&str, &str)],
+        env: &[(&'static str, &str)],
+    ) -> io::Result<Option<Container>> {
+        let root = tempfile::tempdir()?;
+        for (path, content) in files {
+            let path = root.path().join(path);
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(path, content)?;
+        }
+        let mut context = Context::new(ArgMatches::default());
+        for (key, value) in env {
+            context.env.insert(key, value.to_string());
+        }
+        let container = detect_container(&context, root.path());
+        root.close()?;
+        Ok(container)
+    }
+
+    fn container(engine: &str, name: Option<&str>, image: Option<&str>) -> Option<Container> {
+        Some(Container {
+            engine: engine.to_string(),
+            name: name.map(str::to_string),
+            image: image.map(str::to_string),
+        })
+    }
+
+    #[test]
+    fn detects_podman() -> io::Result<()> {
+        let containerenv =
+            "engine=\"podman-3.4.4\"\nname=\"web\"\nimage=\"docker.io/library/nginx:latest\"\n";
+        assert_eq!(
+            container(
+                "podman",
+                Some("web"),
+                Some("docker.io/library/nginx:latest")
+            ),
+            detect(&[("run/.containerenv", containerenv)], &[])?
+        );
+        assert_eq!(
+            container(
+                "toolbox",
+                Some("web"),
+                Some("docker.io/library/nginx:latest")
+            ),
+            detect(
+                &[("run/.containerenv", containerenv), ("run/.toolboxenv", "")],
+                &[]
+            )?
+        );
+        assert_eq!(
+            container("distrobox", Some("arch"), None),
+            detect(&[("run/.containerenv", "")], &[("CONTAINER_ID", "arch")])?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn detects_other_engines() -> io::Result<()> {
+        assert_eq!(
+            container("docker", None, None),
+            detect(&[(".dockerenv", "")], &[])?
+        );
+        assert_eq!(
+            container("systemd-nspawn", None, None),
+            detect(&[("run/systemd/container", "systemd-nspawn\n")], &[])?
+        );
+        assert_eq!(
+            container("lxc", None, None),
+            detect(&[], &[("container", "lxc")])?
+        );
+        assert_eq!(
+            container("kubernetes", None, None),
+            detect(
+                &[("proc/1/cgroup", "0::/kubepods/besteffort/pod1234/abcd\n")],
+                &[]
+            )?
+        );
+        assert_eq!(None, detect(&[("proc/1/cgroup", "0::/\n")], &[])?);
+        Ok(())
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let actual = ModuleRenderer::new("container").collect();
+        assert_eq!(None, actual);
+    }
+}
@@ -0,0 +1,226 @@
+use std::path::Path;
+
+use super::{Context, Module, RootModuleConfig};
+
+use crate::configs::cpu_temp::CpuTempConfig;
+use crate::formatter::StringFormatter;
+use crate::utils;
+
+/// The hwmon chips of CPUs, whose first sensor is used unless `sensor` is set
+const CPU_CHIPS: &[&str] = &[
+    "coretemp",
+    "k10temp",
+    "zenpower",
+    "cpu_thermal",
+    "cpu-thermal",
+    "acpitz",
+];
+
+/// Creates a module with the temperature of the CPU
+pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
+    let mut module = context.new_module("cpu_temp");
+    let config = CpuTempConfig::try_load(module.config);
+
+    // As we default to disabled=true, we have to check here after loading our config module,
+    // before it was only checking against whatever is in the config starship.toml
+    if config.disabled {
+        return None;
+    }
+
+    let (label, celsius) = get_temperature(context, config.sensor)?;
+    let style = if celsius >= config.critical_threshold as f64 {
+        config.critical_style
+    } else if celsius >= config.warning_threshold as f64 {
+        config.warning_style
+    } else {
+        config.style
+    };
+    let temperature = format!("{:.0}°C", celsius);
+
+    let parsed = StringFormatter::new(config.format).and_then(|formatter| {
+        formatter
+            .map_meta(|var, _| match var {
+                "symbol" => Some(config.symbol),
+                _ => None,
+            })
+            .map_style(|variable| match variable {
+                "style" => Some(Ok(style)),
+                _ => None,
+            })
+            .map(|variable| match variable {
+                "temperature" => Some(Ok(&temperature)),
+                "label" => Some(Ok(&label)),
+                _ => None,
+            })
+            .parse(None)
+    });
+
+    module.set_segments(match parsed {
+        Ok(segments) => segments,
+        Err(error) => {
+            log::warn!("Error in module `cpu_temp`:\n{}", error);
+            return None;
+        }
+    });
+
+    Some(module)
+}
+
+/// The label of the sensor and the temperature it reads, in degrees Celsius
+fn get_temperature(context: &Context, sensor: &str) -> Option<(String, f64)> {
+    let temperature = if cfg!(target_os = "linux") {
+        read_hwmon(Path::new("/sys/class/hwmon"), sensor)
+    } else if cfg!(target_os = "freebsd") {
+        let name = if sensor.is_empty() {
+            "dev.cpu.0.temperature"
+        } else {
+            sensor
+        };
+        let output = context.exec_cmd("sysctl", &["-n", name])?;
+        parse_sysctl(&output.stdout).map(|celsius| (name.to_string(), celsius))
+    } else if cfg!(windows) {
+        let output = context.exec_cmd(
+            "wmic",
+            &[
+                r"/namespace:\\root\wmi",
+                "PATH",
+                "MSAcpi_ThermalZoneTemperature",
+                "get",
+                "CurrentTemperature",
+            ],
+        )?;
+        parse_wmi(&output.stdout).map(|celsius| ("ThermalZone".to_string(), celsius))
+    } else {
+        None
+    };
+    if temperature.is_none() {
+        log::debug!("Unable to read the temperature of the CPU");
+    }
+    temperature
+}
+
+/// Reads the sensors of Linux, see https://www.kernel.org/doc/html/latest/hwmon/sysfs-interface.html
+///
+/// The sensor is found by its label (e.g. `Package id 0`) or the name of its chip
+/// (e.g. `k10temp`). Without one, the first sensor of a known CPU chip is used.
+fn read_hwmon(hwmon_dir: &Path, sensor: &str) -> Option<(String, f64)> {
+    let read = |path: &Path| {
+        utils::read_file(path)
+            .ok()
+            .map(|value| value.trim().to_string())
+    };
+    let mut chips: Vec<_> = hwmon_dir
+        .read_dir()
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .collect();
+    chips.sort();
+
+    for chip in chips {
+        let chip_name = read(&chip.join("name")).unwrap_or_default();
+        let mut inputs: Vec<(u32, String)> = chip
+            .read_dir()
+            .ok()?
+            .flatten()
+            .filter_map(|entry| {
+                let file = entry.file_name().to_string_lossy().to_string();
+                let index = file
+                    .strip_prefix("temp")?
+                    .strip_suffix("_input")?
+                    .parse()
+                    .ok()?;
+                Some((index, file))
+            })
+            .collect();
+        inputs.sort();
+
+        for (index, input) in inputs {
+            let label = read(&chip.join(format!("temp{}_label", index)))
+                .unwrap_or_else(|| chip_name.clone());
+            let is_match = if sensor.is_empty() {
+                CPU_CHIPS.contains(&chip_name.as_str())
+            } else {
+                label.eq_ignore_ascii_case(sensor) || chip_name.eq_ignore_ascii_case(sensor)
+            };
+            if !is_match {
+                continue;
+            }
+            // Temperatures are in millidegrees Celsius
+            let millidegrees: f64 = read(&chip.join(&input))?.parse().ok()?;
+            return Some((label, millidegrees / 1000.0));
+        }
+    }
+    None
+}
+
+/// Parses a temperature printed by `sysctl` on FreeBSD, e.g. `45.0C`
+fn parse_sysctl(output: &str) -> Option<f64> {
+    output.trim().strip_suffix('C')?.parse().ok()
+}
+
+/// Parses the temperature printed by `wmic`, in tenths of degrees Kelvin
+fn parse_wmi(output: &str) -> Option<f64> {
+    let decikelvin: f64 = output.lines().find_map(|line| line.trim().parse().ok())?;
+    Some(decikelvin / 10.0 - 273.15)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io;
+
+    fn write_chip(dir: &Path, chip: &str, files: &[(&str, &str)]) -> io::Result<()> {
+        let chip = dir.join(chip);
+        fs::create_dir(&chip)?;
+        for (file, value) in files {
+            fs::write(chip.join(file), format!("{}\n", value))?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reads_cpu_sensor() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        write_chip(
+            dir.path(),
+            "hwmon0",
+            &[("name", "nvme"), ("temp1_input", "38850")],
+        )?;
+        write_chip(
+            dir.path(),
+            "hwmon1",
+            &[
+                ("name", "coretemp"),
+                ("temp1_input", "52000"),
+                ("temp1_label", "Package id 0"),
+                ("temp2_input", "49000"),
+                ("temp2_label", "Core 0"),
+            ],
+        )?;
+
+        assert_eq!(
+            Some(("Package id 0".to_string(), 52.0)),
+            read_hwmon(dir.path(), "")
+        );
+        assert_eq!(
+            Some(("Core 0".to_string(), 49.0)),
+            read_hwmon(dir.path(), "core 0")
+        );
+        assert_eq!(
+            Some(("nvme".to_string(), 38.85)),
+            read_hwmon(dir.path(), "nvme")
+        );
+        assert_eq!(None, read_hwmon(dir.path(), "gpu"));
+        dir.close()
+    }
+
+    #[test]
+    fn parses_sysctl_and_wmi() {
+        assert_eq!(Some(45.5), parse_sysctl("45.5C\n"));
+        assert_eq!(None, parse_sysctl("unknown oid"));
+        let celsius = parse_wmi("CurrentTemperature  \r\n3182  \r\n").unwrap();
+        assert!((celsius - 45.05).abs() < 0.01);
+    }
+}
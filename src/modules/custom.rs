@@ -1,27 +1,80 @@
+use std::collections::HashMap;
 use std::io::Write;
-use std::process::{Command, Output, Stdio};
-use std::time::Instant;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::OnceCell;
+use process_control::{ChildExt, Output, Timeout};
+use regex::Regex;
 
 use super::{Context, Module, RootModuleConfig};
 
-use crate::{configs::custom::CustomConfig, formatter::StringFormatter};
+use crate::configs::custom::CustomConfig;
+use crate::context::{
+    measure_commands, record_command, record_detection, with_format_variables, with_overrides,
+    ModuleOverrides,
+};
+use crate::daemon::ClientEnv;
+use crate::formatter::StringFormatter;
 
 /// Creates a custom module with some configuration
 ///
 /// The relevant TOML config will set the files, extensions, and directories needed
 /// for the module to be displayed. If none of them match, and optional "when"
-/// command can be run -- if its result is 0, the module will be shown.
+/// command can be run -- if its result is 0, the module will be shown. If "when_status"
+/// is set, the module is only shown after commands exiting with one of its codes.
 ///
-/// Finally, the content of the module itself is also set by a command.
+/// Finally, the content of the module itself is also set by a command, whose output
+/// can be split into variables by the named groups of the "parse" regex.
 pub fn module<'a>(name: &str, context: &'a Context) -> Option<Module<'a>> {
+    let variables =
+        super::format_variables(name, context.config.get_custom_module_config(name), context);
+    let (module, command_duration, details) = measure_commands(context.explain, || {
+        with_format_variables(variables, || render(name, context))
+    });
+    module.map(|mut module| {
+        module.command_duration = command_duration;
+        module.details = details;
+        module
+    })
+}
+
+fn render<'a>(name: &str, context: &'a Context) -> Option<Module<'a>> {
+    let guard_name = format!("custom.{}", name);
+    if let Some(guard) = &context.slow_modules {
+        if guard.is_skipped(&guard_name) {
+            log::debug!("Skipping slow module {:?}", guard_name);
+            return None;
+        }
+    }
     let start: Instant = Instant::now();
     let toml_config = context.config.get_custom_module_config(name).expect(
         "modules::custom::module should only be called after ensuring that the module exists",
     );
     let config = CustomConfig::load(toml_config);
+    if let Some(results) = &context.async_results {
+        if results.is_async(&guard_name) {
+            return results.get(&guard_name).map(|segments| {
+                let mut module = Module::new(name, config.description, Some(toml_config));
+                module.set_segments(segments);
+                module
+            });
+        }
+    }
 
-    let mut is_match = context
-        .try_begin_scan()?
+    if !config.when_status.is_empty() {
+        let status = context
+            .properties
+            .get("status_code")
+            .map_or(Some(0), |status| status.parse::<i64>().ok());
+        if !matches!(status, Some(status) if config.when_status.contains(&status)) {
+            return None;
+        }
+    }
+
+    let shell = get_os_shell(&config);
+    let overrides = ModuleOverrides::from_config(Some(toml_config));
+    let mut is_match = with_overrides(overrides, || context.try_begin_scan())?
         .set_files(&config.files)
         .set_extensions(&config.extensions)
         .set_folders(&config.directories)
@@ -29,7 +82,15 @@ pub fn module<'a>(name: &str, context: &'a Context) -> Option<Module<'a>> {
 
     if !is_match {
         if let Some(when) = config.when {
-            is_match = exec_when(when, &config.shell.0);
+            is_match = exec_when(
+                when,
+                shell,
+                context.client_env.as_ref(),
+                overrides.command_timeout,
+            );
+            if is_match {
+                record_detection(|| format!("the `when` command {:?}", when));
+            }
         }
 
         if !is_match {
@@ -39,6 +100,44 @@ pub fn module<'a>(name: &str, context: &'a Context) -> Option<Module<'a>> {
 
     let mut module = Module::new(name, config.description, Some(toml_config));
 
+    let parse = config.parse.and_then(|pattern| match Regex::new(pattern) {
+        Ok(parse) => Some(parse),
+        Err(error) => {
+            log::warn!("Invalid `parse` in module `custom.{}`:\n{}", name, error);
+            None
+        }
+    });
+    // The command only runs once, even if both `$output` and captures are used
+    let output = OnceCell::new();
+    let get_output = || {
+        output
+            .get_or_init(|| {
+                exec_command(
+                    config.command,
+                    shell,
+                    context.client_env.as_ref(),
+                    overrides.command_timeout,
+                )
+                .map(|output| output.trim().to_string())
+            })
+            .as_deref()
+    };
+    let captures = OnceCell::new();
+    let get_captures = |parse: &Regex| {
+        captures.get_or_init(
+            || match get_output().and_then(|output| parse.captures(output)) {
+                Some(captures) => parse
+                    .capture_names()
+                    .flatten()
+                    .filter_map(|name| {
+                        Some((name.to_string(), captures.name(name)?.as_str().to_string()))
+                    })
+                    .collect(),
+                None => HashMap::new(),
+            },
+        )
+    };
+
     let parsed = StringFormatter::new(config.format).and_then(|formatter| {
         formatter
             .map_meta(|var, _| match var {
@@ -49,18 +148,16 @@ pub fn module<'a>(name: &str, context: &'a Context) -> Option<Module<'a>> {
                 "style" => Some(Ok(config.style)),
                 _ => None,
             })
-            .map(|variable| match variable {
-                "output" => {
-                    let output = exec_command(config.command, &config.shell.0)?;
-                    let trimmed = output.trim();
-
-                    if trimmed.is_empty() {
-                        None
-                    } else {
-                        Some(Ok(trimmed.to_string()))
-                    }
-                }
-                _ => None,
+            .map(|variable| {
+                let value = match variable {
+                    "output" => get_output(),
+                    capture => get_captures(parse.as_ref()?)
+                        .get(capture)
+                        .map(String::as_str),
+                };
+                value
+                    .filter(|value| !value.is_empty())
+                    .map(|value| Ok(value.to_string()))
             })
             .parse(None)
     });
@@ -73,27 +170,83 @@ pub fn module<'a>(name: &str, context: &'a Context) -> Option<Module<'a>> {
     };
     let elapsed = start.elapsed();
     log::trace!("Took {:?} to compute custom module {:?}", elapsed, name);
+    if let Some(metrics) = &context.metrics {
+        metrics.timing(&guard_name, elapsed);
+    }
+    if let Some(guard) = &context.slow_modules {
+        guard.record(&guard_name, elapsed);
+    }
     module.duration = elapsed;
     Some(module)
 }
 
+/// The `shell` option for the current OS, `shell_windows` or `shell_unix` if set
+fn get_os_shell<'a, 'b>(config: &'b CustomConfig<'a>) -> &'b [&'a str] {
+    let os_shell = if cfg!(windows) {
+        &config.shell_windows
+    } else {
+        &config.shell_unix
+    };
+    &os_shell.as_ref().unwrap_or(&config.shell).0
+}
+
 /// Return the invoking shell, using `shell` and fallbacking in order to STARSHIP_SHELL and "sh"
 #[cfg(not(windows))]
-fn get_shell<'a, 'b>(shell_args: &'b [&'a str]) -> (std::borrow::Cow<'a, str>, &'b [&'a str]) {
+fn get_shell<'a, 'b>(
+    shell_args: &'b [&'a str],
+    client: Option<&ClientEnv>,
+) -> (std::borrow::Cow<'a, str>, &'b [&'a str]) {
     if !shell_args.is_empty() {
         (shell_args[0].into(), &shell_args[1..])
-    } else if let Ok(env_shell) = std::env::var("STARSHIP_SHELL") {
+    } else if let Some(env_shell) = get_starship_shell(client) {
         (env_shell.into(), &[] as &[&str])
     } else {
         ("sh".into(), &[] as &[&str])
     }
 }
 
+/// The shell set by the init script of the shell the prompt is for
+fn get_starship_shell(client: Option<&ClientEnv>) -> Option<String> {
+    match client {
+        Some(client) => client.env.get("STARSHIP_SHELL").cloned(),
+        None => std::env::var("STARSHIP_SHELL").ok(),
+    }
+}
+
+/// Creates a command which runs in the working directory and with the environment
+/// variables of the client, if the prompt is rendered for one
+fn new_command(program: &str, client: Option<&ClientEnv>) -> Command {
+    let mut command = Command::new(program);
+    if let Some(client) = client {
+        client.apply(&mut command);
+    }
+    command
+}
+
+/// Runs the command in a shell, counting its time towards the module being rendered. It is
+/// stopped after `timeout`, if the module sets a `command_timeout`.
+fn shell_command(
+    cmd: &str,
+    shell_args: &[&str],
+    client: Option<&ClientEnv>,
+    timeout: Option<Duration>,
+) -> Option<Output> {
+    let start = Instant::now();
+    let output = run_shell_command(cmd, shell_args, client, timeout);
+    record_command(start.elapsed(), || cmd.to_string());
+    output
+}
+
 /// Attempt to run the given command in a shell by passing it as `stdin` to `get_shell()`
 #[cfg(not(windows))]
-fn shell_command(cmd: &str, shell_args: &[&str]) -> Option<Output> {
-    let (shell, shell_args) = get_shell(shell_args);
-    let mut command = Command::new(shell.as_ref());
+fn run_shell_command(
+    cmd: &str,
+    shell_args: &[&str],
+    client: Option<&ClientEnv>,
+    timeout: Option<Duration>,
+) -> Option<Output> {
+    let (shell, shell_args) = get_shell(shell_args, client);
+    let mut command = new_command(shell.as_ref(), client);
 
     command
         .args(shell_args)
@@ -111,7 +264,7 @@ fn shell_command(cmd: &str, shell_args: &[&str]) -> Option<Output> {
                 "Could not launch command with given shell or STARSHIP_SHELL env variable, retrying with /usr/bin/env sh"
             );
 
-            Command::new("/usr/bin/env")
+            new_command("/usr/bin/env", client)
                 .arg("sh")
                 .stdin(Stdio::piped())
                 .stdout(Stdio::piped())
@@ -121,27 +274,32 @@ fn shell_command(cmd: &str, shell_args: &[&str]) -> Option<Output> {
         }
     };
 
-    child.stdin.as_mut()?.write_all(cmd.as_bytes()).ok()?;
-    child.wait_with_output().ok()
+    child.stdin.take()?.write_all(cmd.as_bytes()).ok()?;
+    wait_for_output(child, cmd, timeout)
 }
 
 /// Attempt to run the given command in a shell by passing it as `stdin` to `get_shell()`,
 /// or by invoking cmd.exe /C.
 #[cfg(windows)]
-fn shell_command(cmd: &str, shell_args: &[&str]) -> Option<Output> {
+fn run_shell_command(
+    cmd: &str,
+    shell_args: &[&str],
+    client: Option<&ClientEnv>,
+    timeout: Option<Duration>,
+) -> Option<Output> {
     let (shell, shell_args) = if !shell_args.is_empty() {
         (
             Some(std::borrow::Cow::Borrowed(shell_args[0])),
             &shell_args[1..],
         )
-    } else if let Ok(env_shell) = std::env::var("STARSHIP_SHELL") {
+    } else if let Some(env_shell) = get_starship_shell(client) {
         (Some(std::borrow::Cow::Owned(env_shell)), &[] as &[&str])
     } else {
         (None, &[] as &[&str])
     };
 
     if let Some(forced_shell) = shell {
-        let mut command = Command::new(forced_shell.as_ref());
+        let mut command = new_command(forced_shell.as_ref(), client);
 
         command
             .args(shell_args)
@@ -152,9 +310,9 @@ fn shell_command(cmd: &str, shell_args: &[&str]) -> Option<Output> {
         handle_powershell(&mut command, &forced_shell, shell_args);
 
         if let Ok(mut child) = command.spawn() {
-            child.stdin.as_mut()?.write_all(cmd.as_bytes()).ok()?;
+            child.stdin.take()?.write_all(cmd.as_bytes()).ok()?;
 
-            return child.wait_with_output().ok();
+            return wait_for_output(child, cmd, timeout);
         }
 
         log::debug!(
@@ -162,7 +320,7 @@ fn shell_command(cmd: &str, shell_args: &[&str]) -> Option<Output> {
         );
     }
 
-    let command = Command::new("cmd.exe")
+    let command = new_command("cmd.exe", client)
         .arg("/C")
         .arg(cmd)
         .stdin(Stdio::piped())
@@ -170,14 +328,38 @@ fn shell_command(cmd: &str, shell_args: &[&str]) -> Option<Output> {
         .stderr(Stdio::piped())
         .spawn();
 
-    command.ok()?.wait_with_output().ok()
+    wait_for_output(command.ok()?, cmd, timeout)
+}
+
+/// Waits for a shell command to exit, terminating it after `timeout` if one is set
+fn wait_for_output(child: Child, cmd: &str, timeout: Option<Duration>) -> Option<Output> {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return child.wait_with_output().ok().map(Output::from),
+    };
+    match child.with_output_timeout(timeout).terminating().wait() {
+        Ok(Some(output)) => Some(output),
+        Ok(None) => {
+            log::warn!("Executing command {:?} timed out after {:?}", cmd, timeout);
+            None
+        }
+        Err(error) => {
+            log::info!("Executing command {:?} failed by: {:?}", cmd, error);
+            None
+        }
+    }
 }
 
 /// Execute the given command capturing all output, and return whether it return 0
-fn exec_when(cmd: &str, shell_args: &[&str]) -> bool {
+fn exec_when(
+    cmd: &str,
+    shell_args: &[&str],
+    client: Option<&ClientEnv>,
+    timeout: Option<Duration>,
+) -> bool {
     log::trace!("Running '{}'", cmd);
 
-    if let Some(output) = shell_command(cmd, shell_args) {
+    if let Some(output) = shell_command(cmd, shell_args, client, timeout) {
         if !output.status.success() {
             log::trace!("non-zero exit code '{:?}'", output.status.code());
             log::trace!(
@@ -199,10 +381,15 @@ fn exec_when(cmd: &str, shell_args: &[&str]) -> bool {
 }
 
 /// Execute the given command, returning its output on success
-fn exec_command(cmd: &str, shell_args: &[&str]) -> Option<String> {
+fn exec_command(
+    cmd: &str,
+    shell_args: &[&str],
+    client: Option<&ClientEnv>,
+    timeout: Option<Duration>,
+) -> Option<String> {
     log::trace!("Running '{}'", cmd);
 
-    if let Some(output) = shell_command(cmd, shell_args) {
+    if let Some(output) = shell_command(cmd, shell_args, client, timeout) {
         if !output.status.success() {
             log::trace!("Non-zero exit code '{:?}'", output.status.code());
             log::trace!(
@@ -238,6 +425,8 @@ fn handle_powershell(command: &mut Command, shell: &str, shell_args: &[&str]) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::StarshipConfig;
+    use crate::context::Shell;
 
     #[cfg(not(windows))]
     const SHELL: &[&str] = &["/bin/sh"];
@@ -253,21 +442,24 @@ mod tests {
 
     #[test]
     fn when_returns_right_value() {
-        assert!(exec_when("echo hello", SHELL));
-        assert!(!exec_when(FAILING_COMMAND, SHELL));
+        assert!(exec_when("echo hello", SHELL, None, None));
+        assert!(!exec_when(FAILING_COMMAND, SHELL, None, None));
     }
 
     #[test]
     fn when_returns_false_if_invalid_command() {
-        assert!(!exec_when(UNKNOWN_COMMAND, SHELL));
+        assert!(!exec_when(UNKNOWN_COMMAND, SHELL, None, None));
     }
 
     #[test]
     #[cfg(not(windows))]
     fn command_returns_right_string() {
-        assert_eq!(exec_command("echo hello", SHELL), Some("hello\n".into()));
         assert_eq!(
-            exec_command("echo 강남스타일", SHELL),
+            exec_command("echo hello", SHELL, None, None),
+            Some("hello\n".into())
+        );
+        assert_eq!(
+            exec_command("echo 강남스타일", SHELL, None, None),
             Some("강남스타일\n".into())
         );
     }
@@ -275,9 +467,12 @@ mod tests {
     #[test]
     #[cfg(windows)]
     fn command_returns_right_string() {
-        assert_eq!(exec_command("echo hello", SHELL), Some("hello\r\n".into()));
         assert_eq!(
-            exec_command("echo 강남스타일", SHELL),
+            exec_command("echo hello", SHELL, None, None),
+            Some("hello\r\n".into())
+        );
+        assert_eq!(
+            exec_command("echo 강남스타일", SHELL, None, None),
             Some("강남스타일\r\n".into())
         );
     }
@@ -286,31 +481,178 @@ mod tests {
     #[cfg(not(windows))]
     fn command_ignores_stderr() {
         assert_eq!(
-            exec_command("echo foo 1>&2; echo bar", SHELL),
+            exec_command("echo foo 1>&2; echo bar", SHELL, None, None),
             Some("bar\n".into())
         );
         assert_eq!(
-            exec_command("echo foo; echo bar 1>&2", SHELL),
+            exec_command("echo foo; echo bar 1>&2", SHELL, None, None),
             Some("foo\n".into())
         );
     }
 
+    #[test]
+    #[cfg(not(windows))]
+    fn command_runs_for_client() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cwd = dir.path().canonicalize()?;
+        let client = ClientEnv {
+            cwd: cwd.clone(),
+            env: vec![("NAME".to_string(), "client".to_string())]
+                .into_iter()
+                .collect(),
+        };
+        assert_eq!(
+            exec_command("pwd; echo $NAME", SHELL, Some(&client), None),
+            Some(format!("{}\nclient\n", cwd.display()))
+        );
+        dir.close()
+    }
+
     #[test]
     #[cfg(windows)]
     fn command_ignores_stderr() {
         assert_eq!(
-            exec_command("echo foo 1>&2 & echo bar", SHELL),
+            exec_command("echo foo 1>&2 & echo bar", SHELL, None, None),
             Some("bar\r\n".into())
         );
         assert_eq!(
-            exec_command("echo foo& echo bar 1>&2", SHELL),
+            exec_command("echo foo& echo bar 1>&2", SHELL, None, None),
             Some("foo\r\n".into())
         );
     }
 
+    fn render_custom(config: toml::Value, status: i32) -> Option<String> {
+        let dir = tempfile::tempdir().unwrap();
+        let mut context = Context::new_with_shell_and_path(
+            clap::ArgMatches::default(),
+            Shell::Unknown,
+            dir.path().to_path_buf(),
+            dir.path().to_path_buf(),
+        );
+        context.config = StarshipConfig {
+            config: Some(config),
+        };
+        context.properties.insert("status_code", status.to_string());
+        let output = module("test", &context).map(|module| module.to_string());
+        dir.close().unwrap();
+        output
+    }
+
+    #[test]
+    fn when_status_matches_previous_exit_code() {
+        let config = toml::toml! {
+            [custom.test]
+            command = "echo hello"
+            when = "echo"
+            when_status = [1, 127]
+            format = "$output"
+        };
+
+        assert_eq!(render_custom(config.clone(), 1), Some("hello".to_string()));
+        assert_eq!(
+            render_custom(config.clone(), 127),
+            Some("hello".to_string())
+        );
+        assert_eq!(render_custom(config, 0), None);
+    }
+
+    #[test]
+    fn parse_exposes_named_captures() {
+        let config = toml::toml! {
+            [custom.test]
+            command = "echo admin@prod-cluster"
+            when = "echo"
+            parse = "(?P<user>\\w+)@(?P<cluster>[\\w-]+)(?P<namespace>/\\w+)?"
+            format = "$cluster as $user( in $namespace)"
+        };
+        assert_eq!(
+            render_custom(config, 0),
+            Some("prod-cluster as admin".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_without_match_hides_captures() {
+        let config = toml::toml! {
+            [custom.test]
+            command = "echo unknown"
+            when = "echo"
+            parse = "(?P<user>\\w+)@(?P<cluster>\\w+)"
+            format = "$output( on $cluster)"
+        };
+        assert_eq!(render_custom(config, 0), Some("unknown".to_string()));
+    }
+
+    #[test]
+    fn os_shell_overrides_shell() {
+        let config = toml::toml! {
+            shell = ["sh"]
+            shell_windows = ["pwsh", "-NoProfile"]
+            shell_unix = ["bash"]
+        };
+        let config = CustomConfig::load(&config);
+        #[cfg(windows)]
+        assert_eq!(get_os_shell(&config), &["pwsh", "-NoProfile"]);
+        #[cfg(not(windows))]
+        assert_eq!(get_os_shell(&config), &["bash"]);
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn records_details_for_explain() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::File::create(dir.path().join("Tiltfile")).unwrap();
+        let mut context = Context::new_with_shell_and_path(
+            clap::ArgMatches::default(),
+            Shell::Unknown,
+            dir.path().to_path_buf(),
+            dir.path().to_path_buf(),
+        );
+        context.config = StarshipConfig {
+            config: Some(toml::toml! {
+                [custom.test]
+                command = "echo up"
+                files = ["Tiltfile"]
+                shell = ["/bin/sh"]
+                format = "$output"
+            }),
+        };
+        context.explain = true;
+
+        let details = module("test", &context).unwrap().details.unwrap();
+        assert_eq!(Some("the Tiltfile file".to_string()), details.detected_by);
+        assert_eq!(
+            Some("up"),
+            details.variables.get("output").map(String::as_str)
+        );
+        let commands: Vec<_> = details
+            .commands
+            .iter()
+            .map(|(cmd, _)| cmd.as_str())
+            .collect();
+        assert_eq!(vec!["echo up"], commands);
+        dir.close().unwrap();
+    }
+
     #[test]
     fn command_can_fail() {
-        assert_eq!(exec_command(FAILING_COMMAND, SHELL), None);
-        assert_eq!(exec_command(UNKNOWN_COMMAND, SHELL), None);
+        assert_eq!(exec_command(FAILING_COMMAND, SHELL, None, None), None);
+        assert_eq!(exec_command(UNKNOWN_COMMAND, SHELL, None, None), None);
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn command_stops_at_the_module_timeout() {
+        let timeout = Some(Duration::from_millis(100));
+        let start = Instant::now();
+        assert_eq!(
+            exec_command("sleep 5; echo late", SHELL, None, timeout),
+            None
+        );
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(
+            exec_command("echo early", SHELL, None, timeout),
+            Some("early\n".to_string())
+        );
     }
 }
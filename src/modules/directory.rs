@@ -5,16 +5,20 @@ use super::utils::directory_win as directory_utils;
 use super::utils::path::PathExt as SPathExt;
 use indexmap::IndexMap;
 use path_slash::PathExt;
+use regex::Regex;
+use std::fs;
 use std::iter::FromIterator;
 use std::path::{Path, PathBuf};
 use unicode_segmentation::UnicodeSegmentation;
 
 use super::{Context, Module};
 
-use super::utils::directory::truncate;
-use crate::config::RootModuleConfig;
+use super::utils::directory::{truncate, truncate_to_width};
+use crate::config::{parse_style_string, RootModuleConfig};
 use crate::configs::directory::DirectoryConfig;
 use crate::formatter::StringFormatter;
+use crate::print::UnicodeWidthGraphemes;
+use crate::segment::Segment;
 
 /// Creates a module with the current logical or physical directory
 ///
@@ -29,7 +33,8 @@ use crate::formatter::StringFormatter;
 /// Paths will undergo user-provided substitutions of substrings
 ///
 /// **Truncation**
-/// Paths will be limited in length to `3` path components by default.
+/// Paths will be limited in length to `3` path components by default, and
+/// optionally to a part of the terminal width.
 pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     let mut module = context.new_module("directory");
     let config: DirectoryConfig = DirectoryConfig::try_load(module.config);
@@ -69,44 +74,94 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     let dir_string = remove_extended_path_prefix(dir_string);
 
     // Apply path substitutions
-    let dir_string = substitute_path(dir_string, &config.substitutions);
+    let dir_string = if config.regex_substitutions {
+        substitute_path_regex(dir_string, &config.substitutions)
+    } else {
+        substitute_path(dir_string, &config.substitutions)
+    };
 
     // Truncate the dir string to the maximum number of path components
     let dir_string = truncate(dir_string, config.truncation_length as usize);
 
-    let prefix = if is_truncated(&dir_string, &home_symbol) {
+    // Truncate the dir string further to fit in a part of the terminal width
+    let dir_string = if config.truncate_to_width > 0.0 {
+        let width = (context.width as f64 * config.truncate_to_width) as usize;
+        truncate_to_width(
+            dir_string,
+            width,
+            config.truncation_symbol.width_graphemes(),
+        )
+    } else {
+        dir_string
+    };
+
+    let (symbol, prefix) = if is_truncated(&dir_string, &home_symbol) {
         // Substitutions could have changed the prefix, so don't allow them and
         // fish-style path contraction together
         if config.fish_style_pwd_dir_length > 0 && config.substitutions.is_empty() {
             // If user is using fish style path, we need to add the segment first
             let contracted_home_dir = contract_path(&display_dir, &home_dir, &home_symbol);
-            to_fish_style(
+            let fish_prefix = to_fish_style(
                 config.fish_style_pwd_dir_length as usize,
                 contracted_home_dir,
                 &dir_string,
-            )
+            );
+            ("", fish_prefix)
         } else {
-            String::from(config.truncation_symbol)
+            (config.truncation_symbol, String::from(""))
         }
     } else {
-        String::from("")
+        ("", String::from(""))
     };
 
-    let displayed_path = prefix + &dir_string;
+    let path = prefix + &dir_string;
+    let displayed_path = String::from(symbol) + &path;
+    let styled_path = config.separator_style.is_some()
+        || config.basename_style.is_some()
+        || config.truncation_symbol_style.is_some();
     let lock_symbol = String::from(config.read_only);
 
-    let parsed = StringFormatter::new(config.format).and_then(|formatter| {
+    // The name of the git repo and the path inside of it, untruncated
+    let repo_path = context
+        .get_repo()
+        .ok()
+        .and_then(|r| r.root.as_ref())
+        .and_then(|root| contract_repo_path(display_dir, root));
+    let (repo_root, path_in_repo) = match &repo_path {
+        Some(repo_path) => {
+            let mut parts = repo_path.splitn(2, '/');
+            (parts.next(), parts.next())
+        }
+        None => (None, None),
+    };
+
+    // The repo root format is only used in repos, once its style is set
+    let format = if repo_root.is_some() && config.repo_root_style.is_some() {
+        config.repo_root_format
+    } else {
+        config.format
+    };
+
+    let parsed = StringFormatter::new(format).and_then(|formatter| {
         formatter
             .map_style(|variable| match variable {
                 "style" => Some(Ok(config.style)),
                 "read_only_style" => Some(Ok(config.read_only_style)),
+                "repo_root_style" => Some(Ok(config.repo_root_style.unwrap_or(config.style))),
+                "path_in_repo_style" => Some(Ok(config.path_in_repo_style.unwrap_or(config.style))),
+                _ => None,
+            })
+            .map_variables_to_segments(|variable| match variable {
+                "path" if styled_path => Some(Ok(path_segments(symbol, &path, &config))),
                 _ => None,
             })
             .map(|variable| match variable {
-                "path" => Some(Ok(&displayed_path)),
+                "path" => Some(Ok(displayed_path.as_str())),
+                "repo_root" => repo_root.map(Ok),
+                "path_in_repo" => path_in_repo.map(Ok),
                 "read_only" => {
-                    if is_readonly_dir(&physical_dir) {
-                        Some(Ok(&lock_symbol))
+                    if is_readonly_dir(physical_dir, config.read_only_detection) {
+                        Some(Ok(lock_symbol.as_str()))
                     } else {
                         None
                     }
@@ -127,6 +182,35 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     Some(module)
 }
 
+/// Splits the path into segments, so that the truncation symbol, the separators and the
+/// last component can be styled independently. Unstyled parts use the style of `$path`.
+fn path_segments(symbol: &str, path: &str, config: &DirectoryConfig) -> Vec<Segment> {
+    let separator_style = config.separator_style.and_then(parse_style_string);
+    let basename_style = config.basename_style.and_then(parse_style_string);
+
+    let mut segments = Vec::new();
+    if !symbol.is_empty() {
+        let symbol_style = config.truncation_symbol_style.and_then(parse_style_string);
+        segments.push(Segment::new(symbol_style, symbol));
+    }
+    let components: Vec<&str> = path.split('/').collect();
+    for (i, component) in components.iter().enumerate() {
+        if i > 0 {
+            segments.push(Segment::new(separator_style, "/"));
+        }
+        if component.is_empty() {
+            continue;
+        }
+        let style = if i == components.len() - 1 {
+            basename_style
+        } else {
+            None
+        };
+        segments.push(Segment::new(style, *component));
+    }
+    segments
+}
+
 #[cfg(windows)]
 fn remove_extended_path_prefix(path: String) -> String {
     fn try_trim_prefix<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
@@ -151,7 +235,17 @@ fn is_truncated(path: &str, home_symbol: &str) -> bool {
         || (cfg!(target_os = "windows") && PathBuf::from(String::from(path) + r"\").has_root()))
 }
 
-fn is_readonly_dir(path: &Path) -> bool {
+/// Checks if the directory is read only, by its read only attribute or by whether the
+/// current user may write to it
+fn is_readonly_dir(path: &Path, detection: &str) -> bool {
+    match detection {
+        "attribute" => return is_readonly_attribute(path),
+        "access" => {}
+        _ => log::warn!(
+            "\"read_only_detection\" should be \"attribute\" or \"access\", found {}",
+            detection
+        ),
+    }
     match directory_utils::is_write_allowed(path) {
         Ok(res) => !res,
         Err(e) => {
@@ -165,6 +259,18 @@ fn is_readonly_dir(path: &Path) -> bool {
     }
 }
 
+/// Checks the read only attribute of the directory on Windows, or whether nobody may
+/// write to it on other systems
+fn is_readonly_attribute(path: &Path) -> bool {
+    match fs::metadata(path) {
+        Ok(metadata) => metadata.permissions().readonly(),
+        Err(e) => {
+            log::debug!("Failed to read the attributes of '{:?}': {}", path, e);
+            false
+        }
+    }
+}
+
 /// Contract the root component of a path
 ///
 /// Replaces the `top_level_path` in a given `full_path` with the provided
@@ -259,6 +365,27 @@ fn substitute_path(dir_string: String, substitutions: &IndexMap<String, &str>) -
     substituted_dir
 }
 
+/// Perform a list of regex substitutions on the path
+///
+/// Like `substitute_path`, but the keys are regexes and `$1` or `${name}` in the
+/// replacements are replaced with the groups of each match. Invalid regexes are skipped.
+fn substitute_path_regex(dir_string: String, substitutions: &IndexMap<String, &str>) -> String {
+    let mut substituted_dir = dir_string;
+    for (pattern, replacement) in substitutions.iter() {
+        let regex = match Regex::new(pattern) {
+            Ok(regex) => regex,
+            Err(error) => {
+                log::warn!("Invalid directory substitution {:?}: {}", pattern, error);
+                continue;
+            }
+        };
+        substituted_dir = regex
+            .replace_all(&substituted_dir, *replacement)
+            .into_owned();
+    }
+    substituted_dir
+}
+
 /// Takes part before contracted path and replaces it with fish style path
 ///
 /// Will take the first letter of each directory before the contracted path and
@@ -298,7 +425,7 @@ fn to_fish_style(pwd_dir_length: usize, dir_string: String, truncated_dir_string
 mod tests {
     use super::*;
     use crate::test::ModuleRenderer;
-    use ansi_term::Color;
+    use ansi_term::{ANSIStrings, Color, Style};
     use dirs_next::home_dir;
     #[cfg(not(target_os = "windows"))]
     use std::os::unix::fs::symlink;
@@ -398,6 +525,18 @@ mod tests {
         assert_eq!(output, "/foo/baz");
     }
 
+    #[test]
+    fn substitute_regex_with_groups_in_order() {
+        let mut substitutions = IndexMap::new();
+        substitutions.insert(r"^~/work/(.*)/src".to_string(), "\u{2699} $1");
+        substitutions.insert(r"(?P<name>\w+)-service".to_string(), "${name}");
+        substitutions.insert("(".to_string(), "invalid");
+
+        let path = "~/work/billing-service/src/main";
+        let output = substitute_path_regex(path.to_string(), &substitutions);
+        assert_eq!(output, "\u{2699} billing/main");
+    }
+
     #[test]
     fn fish_style_with_user_home_contracted_path() {
         let path = "~/starship/engines/booster/rocket";
@@ -525,6 +664,29 @@ mod tests {
 
             assert_eq!(expected, actual);
         }
+
+        #[test]
+        fn read_only_attribute_detection() -> io::Result<()> {
+            use std::os::unix::fs::PermissionsExt;
+
+            let tmp_dir = TempDir::new()?;
+            let dir = tmp_dir.path().join("read-only");
+            fs::create_dir(&dir)?;
+            fs::set_permissions(&dir, fs::Permissions::from_mode(0o555))?;
+
+            let actual = ModuleRenderer::new("directory")
+                .config(toml::toml! {
+                    [directory]
+                    format = "$read_only"
+                    read_only_detection = "attribute"
+                })
+                .path(&dir)
+                .collect();
+            assert_eq!(Some("🔒".to_string()), actual);
+
+            fs::set_permissions(&dir, fs::Permissions::from_mode(0o755))?;
+            tmp_dir.close()
+        }
     }
 
     #[test]
@@ -537,6 +699,23 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn home_directory_with_allowed_env_vars() {
+        let actual = ModuleRenderer::new("directory")
+            .path(home_dir().unwrap())
+            .env("TMUX_PANE", "%3")
+            .env("SECRET_TOKEN", "hunter2")
+            .config(toml::toml! {
+                env_allowlist = ["TMUX_PANE"]
+                [directory]
+                format = "[$path]($style)( $env:TMUX_PANE)($env:SECRET_TOKEN) "
+            })
+            .collect();
+        let expected = Some(format!("{} %3 ", Color::Cyan.bold().paint("~")));
+
+        assert_eq!(expected, actual);
+    }
+
     #[test]
     fn home_directory_custom_home_symbol() {
         let actual = ModuleRenderer::new("directory")
@@ -848,6 +1027,40 @@ mod tests {
         tmp_dir.close()
     }
 
+    #[test]
+    #[ignore]
+    fn repo_root_and_path_in_repo_styles() -> io::Result<()> {
+        let tmp_dir = TempDir::new()?;
+        let repo_dir = tmp_dir.path().join("rocket-controls");
+        let dir = repo_dir.join("src/meters");
+        fs::create_dir_all(&dir)?;
+        init_repo(&repo_dir).unwrap();
+
+        let config = toml::toml! {
+            [directory]
+            repo_root_style = "bold blue"
+            path_in_repo_style = "dimmed"
+        };
+        let actual = ModuleRenderer::new("directory")
+            .config(config.clone())
+            .path(dir)
+            .collect();
+        let expected = Some(format!(
+            "{}{} ",
+            Color::Blue.bold().paint("rocket-controls"),
+            Style::new().dimmed().paint("/src/meters")
+        ));
+        assert_eq!(expected, actual);
+
+        let actual = ModuleRenderer::new("directory")
+            .config(config)
+            .path(&repo_dir)
+            .collect();
+        let expected = Some(format!("{} ", Color::Blue.bold().paint("rocket-controls")));
+        assert_eq!(expected, actual);
+        tmp_dir.close()
+    }
+
     #[test]
     #[ignore]
     fn truncated_directory_in_git_repo() -> io::Result<()> {
@@ -1261,6 +1474,52 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn truncate_to_width_of_terminal() {
+        let actual = ModuleRenderer::new("directory")
+            .config(toml::toml! {
+                [directory]
+                truncation_length = 0
+                truncate_to_width = 0.5
+                truncation_symbol = "…/"
+            })
+            .terminal_width(44)
+            .path(Path::new("/src/main/java/com/example/project/app"))
+            .collect();
+        let expected = Some(format!(
+            "{} ",
+            Color::Cyan.bold().paint("…/example/project/app")
+        ));
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn path_component_styles() {
+        let actual = ModuleRenderer::new("directory")
+            .config(toml::toml! {
+                [directory]
+                truncation_symbol = "…/"
+                truncation_symbol_style = "red"
+                separator_style = "dimmed"
+                basename_style = "bold"
+            })
+            .path(Path::new("/a/four/element/path"))
+            .collect();
+        let separator = Style::new().dimmed().paint("/");
+        let expected = Some(format!(
+            "{} ",
+            ANSIStrings(&[
+                Color::Red.paint("…/"),
+                Color::Cyan.bold().paint("four"),
+                separator.clone(),
+                Color::Cyan.bold().paint("element"),
+                separator,
+                Style::new().bold().paint("path"),
+            ])
+        ));
+        assert_eq!(expected, actual);
+    }
+
     #[test]
     fn truncation_symbol_truncated_home() -> io::Result<()> {
         let (tmp_dir, name) = make_known_tempdir(home_dir().unwrap().as_path())?;
@@ -0,0 +1,194 @@
+use std::iter;
+use std::path::Path;
+
+use byte_unit::Byte;
+
+use super::{Context, Module, RootModuleConfig, Shell};
+
+use crate::configs::disk_usage::DiskUsageConfig;
+use crate::formatter::string_formatter::StringFormatterError;
+use crate::formatter::StringFormatter;
+use crate::segment::Segment;
+
+#[cfg(not(target_os = "windows"))]
+use super::utils::disk_space_nix::disk_space;
+
+#[cfg(target_os = "windows")]
+use super::utils::disk_space_win::disk_space;
+
+/// Creates a module with the free space of the filesystem of the current directory and of
+/// the `mounts` in the config, shown when they are almost full
+pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
+    let mut module = context.new_module("disk_usage");
+    let config = DiskUsageConfig::try_load(module.config);
+
+    // As we default to disabled=true, we have to check here after loading our config module,
+    // before it was only checking against whatever is in the config starship.toml
+    if config.disabled {
+        return None;
+    }
+
+    // TODO: Update when v1.0 printing refactor is implemented to only
+    // print escapes in a prompt context.
+    let pct_sign = match context.shell {
+        Shell::Zsh => "%%", // % is an escape in zsh, see PROMPT in `man zshmisc`
+        _ => "%",
+    };
+
+    let filesystems = iter::once(("", context.current_dir.as_path()))
+        .chain(config.mounts.iter().map(|mount| (*mount, Path::new(mount))));
+    let mut seen = Vec::new();
+    let mut segments = Vec::new();
+    for (name, path) in filesystems {
+        let space = match disk_space(path) {
+            Some(space) => space,
+            None => continue,
+        };
+        // Mounts on a filesystem which is already shown have the same sizes
+        if seen.contains(&space) {
+            continue;
+        }
+        seen.push(space);
+        match render_filesystem(&config, name, space, pct_sign) {
+            Ok(filesystem) => segments.extend(filesystem),
+            Err(error) => {
+                log::warn!("Error in module `disk_usage`:\n{}", error);
+                return None;
+            }
+        }
+    }
+
+    if segments.is_empty() {
+        return None;
+    }
+    module.set_segments(segments);
+    Some(module)
+}
+
+/// Renders the format for a filesystem with `total` bytes, of which `available` are free,
+/// or nothing if less than `threshold` percent are used
+fn render_filesystem(
+    config: &DiskUsageConfig,
+    path: &str,
+    (total, available): (u64, u64),
+    pct_sign: &str,
+) -> Result<Vec<Segment>, StringFormatterError> {
+    if total == 0 {
+        return Ok(Vec::new());
+    }
+    let used = total.saturating_sub(available);
+    let used_pct = used as f64 / total as f64 * 100.0;
+    if used_pct.round() < config.threshold as f64 {
+        return Ok(Vec::new());
+    }
+    let style = if used_pct.round() >= config.critical_threshold as f64 {
+        config.critical_style
+    } else {
+        config.style
+    };
+
+    StringFormatter::new(config.format).and_then(|formatter| {
+        formatter
+            .map_meta(|var, _| match var {
+                "symbol" => Some(config.symbol),
+                _ => None,
+            })
+            .map_style(|variable| match variable {
+                "style" => Some(Ok(style)),
+                _ => None,
+            })
+            .map(|variable| match variable {
+                "free" => Some(Ok(format_bytes(available))),
+                "used" => Some(Ok(format_bytes(used))),
+                "total" => Some(Ok(format_bytes(total))),
+                "used_pct" => Some(Ok(format!("{:.0}{}", used_pct, pct_sign))),
+                "path" if !path.is_empty() => Some(Ok(path.to_string())),
+                _ => None,
+            })
+            .parse(None)
+    })
+}
+
+fn format_bytes(bytes: u64) -> String {
+    let mut display_bytes = Byte::from_bytes(u128::from(bytes))
+        .get_appropriate_unit(true)
+        .format(0);
+    display_bytes.retain(|c| c != ' ');
+    display_bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::ModuleRenderer;
+    use ansi_term::{Color, Style};
+
+    const GIB: u64 = 1 << 30;
+
+    fn render(config: toml::Value, path: &str, space: (u64, u64)) -> (String, Option<Style>) {
+        let config = DiskUsageConfig::load(&config);
+        let segments = render_filesystem(&config, path, space, "%").unwrap();
+        let text = segments
+            .iter()
+            .map(|segment| segment.value.as_str())
+            .collect();
+        (text, segments.first().and_then(|segment| segment.style))
+    }
+
+    #[test]
+    fn hidden_below_threshold() {
+        let config = toml::Value::Table(toml::value::Table::new());
+        assert_eq!("", render(config.clone(), "", (100 * GIB, 20 * GIB)).0);
+        assert_eq!("", render(config, "", (0, 0)).0);
+    }
+
+    #[test]
+    fn shows_free_space() {
+        let config = toml::Value::Table(toml::value::Table::new());
+        assert_eq!(
+            ("💾 8GiB ".to_string(), Some(Color::Yellow.bold())),
+            render(config.clone(), "", (100 * GIB, 8 * GIB))
+        );
+        assert_eq!(
+            ("💾 2GiB on /data ".to_string(), Some(Color::Red.bold())),
+            render(config, "/data", (100 * GIB, 2 * GIB))
+        );
+    }
+
+    #[test]
+    fn shows_usage() {
+        let config = toml::toml! {
+            format = "$used/$total $used_pct"
+            threshold = 0
+        };
+        assert_eq!("3GiB/4GiB 75%", render(config, "", (4 * GIB, GIB)).0);
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let actual = ModuleRenderer::new("disk_usage").collect();
+        assert_eq!(None, actual);
+    }
+
+    #[test]
+    fn shows_current_filesystem() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = |threshold: i64| {
+            toml::toml! {
+                [disk_usage]
+                disabled = false
+                threshold = threshold
+                format = "$free"
+            }
+        };
+        let renderer = |threshold| {
+            ModuleRenderer::new("disk_usage")
+                .path(dir.path())
+                .config(config(threshold))
+                .collect()
+        };
+        assert!(renderer(0).is_some());
+        assert_eq!(None, renderer(101));
+        dir.close().unwrap();
+    }
+}
@@ -5,6 +5,7 @@ use git2::Repository;
 
 use crate::configs::git_branch::GitBranchConfig;
 use crate::formatter::StringFormatter;
+use crate::segment::Segment;
 
 /// Creates a module with the Git branch in the current directory
 ///
@@ -66,6 +67,16 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
         }
     }
 
+    // Link the branch name to the remote branch's web page
+    let branch_link = if context.config.get_root_config().links {
+        repo.remote.as_ref().and_then(|remote| {
+            let url = remote_web_url(remote.url.as_ref()?)?;
+            Some(format!("{}/tree/{}", url, remote.branch.as_ref()?))
+        })
+    } else {
+        None
+    };
+
     let show_remote = config.always_show_remote
         || (!graphemes.eq(&remote_branch_graphemes) && !remote_branch_graphemes.is_empty());
 
@@ -73,12 +84,22 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
         formatter
             .map_meta(|var, _| match var {
                 "symbol" => Some(config.symbol),
+                "is_sparse" if repo.is_sparse => Some(config.sparse),
+                "is_shallow" if repo.is_shallow => Some(config.shallow),
                 _ => None,
             })
             .map_style(|variable| match variable {
                 "style" => Some(Ok(config.style)),
                 _ => None,
             })
+            .map_variables_to_segments(|variable| match variable {
+                "branch" => branch_link.as_ref().map(|url| {
+                    Ok(vec![
+                        Segment::new(None, graphemes.concat()).with_link(url.as_str())
+                    ])
+                }),
+                _ => None,
+            })
             .map(|variable| match variable {
                 "branch" => Some(Ok(graphemes.concat())),
                 "remote_branch" => {
@@ -111,6 +132,33 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     Some(module)
 }
 
+/// Converts the URL of a git remote into the URL of its web page, e.g.
+/// `git@github.com:starship/starship.git` into `https://github.com/starship/starship`
+fn remote_web_url(url: &str) -> Option<String> {
+    let url = url.trim_end_matches('/').trim_end_matches(".git");
+    let (host, path) = if let Some(url) = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .or_else(|| url.strip_prefix("ssh://"))
+        .or_else(|| url.strip_prefix("git://"))
+    {
+        let (host, path) = url.split_at(url.find('/')?);
+        (host, path.trim_start_matches('/'))
+    } else {
+        // scp-like syntax: `user@host:path`
+        let (host, path) = url.split_at(url.find(':')?);
+        (host, &path[1..])
+    };
+
+    // Drop the user and port
+    let host = host.rsplit('@').next()?;
+    let host = host.split(':').next()?;
+    if host.is_empty() || path.is_empty() {
+        return None;
+    }
+    Some(format!("https://{}/{}", host, path))
+}
+
 fn get_first_grapheme(text: &str) -> &str {
     UnicodeSegmentation::graphemes(text, true)
         .next()
@@ -344,6 +392,100 @@ mod tests {
         repo_dir.close()
     }
 
+    #[test]
+    fn test_render_sparse_and_shallow() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+        let git_dir = repo_dir.path().join(".git");
+
+        Command::new("git")
+            .args(["config", "core.sparseCheckout", "true"])
+            .current_dir(repo_dir.path())
+            .output()?;
+        std::fs::create_dir_all(git_dir.join("info"))?;
+        std::fs::write(git_dir.join("info").join("sparse-checkout"), "/*\n")?;
+        std::fs::write(git_dir.join("shallow"), "")?;
+
+        let actual = ModuleRenderer::new("git_branch")
+            .config(toml::toml! {
+                [git_branch]
+                    format = "$branch$is_sparse$is_shallow"
+            })
+            .path(repo_dir.path())
+            .collect();
+
+        let expected = Some(String::from("master⊂≈"));
+
+        assert_eq!(expected, actual);
+        repo_dir.close()
+    }
+
+    #[test]
+    fn test_sparse_requires_config() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+        let info_dir = repo_dir.path().join(".git").join("info");
+
+        std::fs::create_dir_all(&info_dir)?;
+        std::fs::write(info_dir.join("sparse-checkout"), "/*\n")?;
+
+        let actual = ModuleRenderer::new("git_branch")
+            .config(toml::toml! {
+                [git_branch]
+                    format = "$branch$is_sparse$is_shallow"
+            })
+            .path(repo_dir.path())
+            .collect();
+
+        let expected = Some(String::from("master"));
+
+        assert_eq!(expected, actual);
+        repo_dir.close()
+    }
+
+    #[test]
+    fn test_remote_web_url() {
+        let expected = Some(String::from("https://github.com/starship/starship"));
+        for url in &[
+            "https://github.com/starship/starship.git",
+            "https://user@github.com/starship/starship",
+            "git@github.com:starship/starship.git",
+            "ssh://git@github.com:22/starship/starship.git",
+        ] {
+            assert_eq!(expected, super::remote_web_url(url));
+        }
+        assert_eq!(None, super::remote_web_url("/path/to/repo"));
+    }
+
+    #[test]
+    fn test_branch_link() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+
+        Command::new("git")
+            .args([
+                "remote",
+                "set-url",
+                "origin",
+                "git@github.com:starship/starship.git",
+            ])
+            .current_dir(repo_dir.path())
+            .output()?;
+
+        let actual = ModuleRenderer::new("git_branch")
+            .config(toml::toml! {
+                links = true
+                [git_branch]
+                    format = "$branch"
+            })
+            .path(repo_dir.path())
+            .collect();
+
+        let expected = Some(String::from(
+            "\x1b]8;;https://github.com/starship/starship/tree/master\x07master\x1b]8;;\x07",
+        ));
+
+        assert_eq!(expected, actual);
+        repo_dir.close()
+    }
+
     #[test]
     fn test_works_in_bare_repo() -> io::Result<()> {
         let repo_dir = tempfile::tempdir()?;
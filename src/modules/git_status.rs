@@ -1,5 +1,6 @@
 use once_cell::sync::OnceCell;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
 use super::{Context, Module, RootModuleConfig};
 
@@ -7,11 +8,18 @@ use crate::configs::git_status::GitStatusConfig;
 use crate::context::Repo;
 use crate::formatter::StringFormatter;
 use crate::segment::Segment;
-use std::path::Path;
+use crate::utils::fnv1a;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 const ALL_STATUS_FORMAT: &str = "$conflicted$stashed$deleted$renamed$modified$staged$untracked";
 
+/// How long a background refresh may take before another one is started in its place
+const REFRESH_LOCK_TIMEOUT: Duration = Duration::from_secs(60);
+
 /// Creates a module with the Git branch in the current directory
 ///
 /// Will display the branch name if the current directory is a git repo
@@ -26,13 +34,30 @@ const ALL_STATUS_FORMAT: &str = "$conflicted$stashed$deleted$renamed$modified$st
 ///   - `+` — A new file has been added to the staging area
 ///   - `»` — A renamed file has been added to the staging area
 ///   - `✘` — A file's deletion has been added to the staging area
+///
+/// With `async_refresh` enabled, the status computed by a background process for the
+/// previous prompt is shown instead, together with `stale` while a refresh is running.
 pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     let repo = context.get_repo().ok()?;
-    let info = Arc::new(GitStatusInfo::load(context, repo));
 
     let mut module = context.new_module("git_status");
     let config: GitStatusConfig = GitStatusConfig::try_load(module.config);
 
+    let (info, is_stale) = if config.async_refresh {
+        let cache = StatusCache::new(context, repo.root.as_ref()?)?;
+        let cached = cache.read();
+        let is_refreshing = cache.is_refreshing();
+        if !is_refreshing {
+            cache.spawn_refresh(context);
+        }
+
+        let is_stale = cached.is_none() || is_refreshing;
+        (GitStatusInfo::from_cache(context, repo, cached), is_stale)
+    } else {
+        (GitStatusInfo::load(context, repo), false)
+    };
+    let info = Arc::new(info);
+
     let parsed = StringFormatter::new(config.format).and_then(|formatter| {
         formatter
             .map_meta(|variable, _| match variable {
@@ -84,6 +109,13 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
                     "untracked" => info.get_untracked().and_then(|count| {
                         format_count(config.untracked, "git_status.untracked", count)
                     }),
+                    "stale" if is_stale => format_text(config.stale, "git_status.stale", |_| None),
+                    "is_sparse" if repo.is_sparse => {
+                        format_text(config.sparse, "git_status.sparse", |_| None)
+                    }
+                    "is_shallow" if repo.is_shallow => {
+                        format_text(config.shallow, "git_status.shallow", |_| None)
+                    }
                     _ => None,
                 };
                 segments.map(Ok)
@@ -125,6 +157,14 @@ impl<'a> GitStatusInfo<'a> {
         }
     }
 
+    pub fn from_cache(context: &'a Context, repo: &'a Repo, cached: Option<CachedStatus>) -> Self {
+        let info = Self::load(context, repo);
+        // The cells are empty, so setting them can't fail
+        let _ = info.repo_status.set(cached.map(|cached| cached.status));
+        let _ = info.stashed_count.set(cached.map(|cached| cached.stashed));
+        info
+    }
+
     pub fn get_ahead_behind(&self) -> Option<(usize, usize)> {
         self.get_repo_status().map(|data| (data.ahead, data.behind))
     }
@@ -226,7 +266,134 @@ fn get_stashed_count(context: &Context, repo_root: &Path) -> Option<usize> {
     Some(stash_output.stdout.trim().lines().count())
 }
 
-#[derive(Default, Debug, Copy, Clone)]
+/// Recomputes the persisted status of the repo in the current directory.
+///
+/// This is run by the background process spawned by the module when `async_refresh` is
+/// enabled.
+pub fn refresh_cache(context: &Context) {
+    let repo_root = match context.get_repo().ok().and_then(|repo| repo.root.as_ref()) {
+        Some(repo_root) => repo_root,
+        None => return,
+    };
+    let cache = match StatusCache::new(context, repo_root) {
+        Some(cache) => cache,
+        None => return,
+    };
+
+    let status = get_repo_status(context, repo_root);
+    let stashed = get_stashed_count(context, repo_root);
+    if let (Some(status), Some(stashed)) = (status, stashed) {
+        if let Err(error) = cache.write(&CachedStatus { status, stashed }) {
+            log::warn!("Unable to write git status cache: {}", error);
+        }
+    }
+
+    cache.release();
+}
+
+/// The status of a repo, as persisted between prompts by `async_refresh`
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+struct CachedStatus {
+    status: RepoStatus,
+    stashed: usize,
+}
+
+/// The location of the persisted status of a single repo
+struct StatusCache {
+    repo_root: PathBuf,
+    path: PathBuf,
+    lock_path: PathBuf,
+}
+
+impl StatusCache {
+    fn new(context: &Context, repo_root: &Path) -> Option<Self> {
+        let name = format!("{:016x}", fnv1a(repo_root.to_string_lossy().as_bytes()));
+        let cache_dir = context.get_cache_dir()?.join("git_status");
+
+        Some(Self {
+            repo_root: repo_root.to_path_buf(),
+            path: cache_dir.join(format!("{}.json", name)),
+            lock_path: cache_dir.join(format!("{}.lock", name)),
+        })
+    }
+
+    fn read(&self) -> Option<CachedStatus> {
+        let content = fs::read_to_string(&self.path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(cached) => Some(cached),
+            Err(error) => {
+                log::debug!(
+                    "Unable to parse git status cache {:?}: {}",
+                    self.path,
+                    error
+                );
+                None
+            }
+        }
+    }
+
+    fn write(&self, cached: &CachedStatus) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Write to a temporary file first so readers never see a partial cache
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_string(cached)?)?;
+        fs::rename(tmp_path, &self.path)
+    }
+
+    /// Whether a background refresh is currently running for this repo
+    fn is_refreshing(&self) -> bool {
+        let age = fs::metadata(&self.lock_path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok());
+
+        matches!(age, Some(age) if age < REFRESH_LOCK_TIMEOUT)
+    }
+
+    fn spawn_refresh(&self, context: &Context) {
+        let locked = self
+            .lock_path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&self.lock_path, ""));
+
+        if let Err(error) = locked.and_then(|_| spawn_refresh_process(context, &self.repo_root)) {
+            log::warn!("Unable to start git status refresh: {}", error);
+            self.release();
+        }
+    }
+
+    fn release(&self) {
+        if let Err(error) = fs::remove_file(&self.lock_path) {
+            log::debug!(
+                "Unable to remove git status lock {:?}: {}",
+                self.lock_path,
+                error
+            );
+        }
+    }
+}
+
+/// Starts the refresh with the environment variables and working directory of the shell
+#[cfg(not(test))]
+fn spawn_refresh_process(context: &Context, repo_root: &Path) -> io::Result<()> {
+    crate::utils::spawn_detached(
+        context
+            .command(std::env::current_exe()?)
+            .arg("refresh-git-status")
+            .arg("--path")
+            .arg(repo_root),
+    )
+}
+
+#[cfg(test)]
+fn spawn_refresh_process(_context: &Context, _repo_root: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize)]
 struct RepoStatus {
     ahead: usize,
     behind: usize,
@@ -781,6 +948,98 @@ mod tests {
         repo_dir.close()
     }
 
+    #[test]
+    fn shows_shallow() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+        File::create(repo_dir.path().join(".git").join("shallow"))?.sync_all()?;
+
+        let actual = ModuleRenderer::new("git_status")
+            .config(toml::toml! {
+                [git_status]
+                format = "$is_sparse$is_shallow"
+            })
+            .path(repo_dir.path())
+            .collect();
+        let expected = Some(String::from("≈"));
+
+        assert_eq!(expected, actual);
+        repo_dir.close()
+    }
+
+    #[test]
+    fn shows_stale_without_cache() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+        let cache_dir = tempfile::tempdir()?;
+
+        let actual = ModuleRenderer::new("git_status")
+            .config(toml::toml! {
+                [git_status]
+                async_refresh = true
+            })
+            .env("STARSHIP_CACHE", cache_dir.path().to_string_lossy())
+            .path(repo_dir.path())
+            .collect();
+        let expected = format_output("…");
+
+        assert_eq!(expected, actual);
+        cache_dir.close()?;
+        repo_dir.close()
+    }
+
+    #[test]
+    fn shows_cached_status() -> io::Result<()> {
+        let repo_dir = fixture_repo(FixtureProvider::Git)?;
+        let cache_dir = tempfile::tempdir()?;
+
+        let render = || {
+            ModuleRenderer::new("git_status")
+                .config(toml::toml! {
+                    [git_status]
+                    async_refresh = true
+                })
+                .env("STARSHIP_CACHE", cache_dir.path().to_string_lossy())
+                .path(repo_dir.path())
+                .collect()
+        };
+
+        // Populate the cache the same way the background process does
+        create_modified(repo_dir.path())?;
+        let mut context = crate::context::Context::new_with_shell_and_path(
+            clap::ArgMatches::default(),
+            crate::context::Shell::Unknown,
+            repo_dir.path().into(),
+            repo_dir.path().into(),
+        );
+        context
+            .env
+            .insert("STARSHIP_CACHE", cache_dir.path().to_string_lossy().into());
+        super::refresh_cache(&context);
+
+        // The cached status is shown, and a refresh for the next prompt is started
+        assert_eq!(format_output("!"), render());
+        // While that refresh is running the cached status is marked as stale
+        assert_eq!(format_output("!…"), render());
+
+        cache_dir.close()?;
+        repo_dir.close()
+    }
+
+    #[test]
+    fn cache_names_are_stable() {
+        let mut context = crate::context::Context::new_with_shell_and_path(
+            clap::ArgMatches::default(),
+            crate::context::Shell::Unknown,
+            "/".into(),
+            "/".into(),
+        );
+        context.env.insert("STARSHIP_CACHE", "/cache".into());
+        let cache = super::StatusCache::new(&context, Path::new("/repo")).unwrap();
+        assert_eq!(
+            Path::new("/cache/git_status/f7556aa5a4543342.json"),
+            cache.path
+        );
+    }
+
     fn ahead(repo_dir: &Path) -> io::Result<()> {
         File::create(repo_dir.join("readme.md"))?.sync_all()?;
 
@@ -3,6 +3,11 @@ use super::{Context, Module, RootModuleConfig};
 use crate::configs::go::GoConfig;
 use crate::formatter::StringFormatter;
 use crate::formatter::VersionFormatter;
+use crate::utils;
+
+use once_cell::sync::Lazy;
+use std::ops::Deref;
+use std::path::Path;
 
 /// Creates a module with the current Go version
 pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
@@ -19,6 +24,32 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
         return None;
     }
 
+    let mod_version = Lazy::new(|| get_go_mod_version(&context.current_dir));
+    let installed_version = Lazy::new(|| {
+        let go_version = context.exec_cmd("go", &["version"])?.stdout;
+        get_go_version(&go_version)
+    });
+    let version = Lazy::new(|| {
+        if config.use_go_mod {
+            if let Some(mod_version) = mod_version.deref() {
+                return Some(mod_version.clone());
+            }
+        }
+        installed_version.deref().clone()
+    });
+    let is_mismatch = || {
+        // Checking the installed version would run `go version` again
+        if config.use_go_mod {
+            return false;
+        }
+        match (installed_version.deref(), mod_version.deref()) {
+            (Some(installed_version), Some(mod_version)) => {
+                parse_go_version(installed_version) < parse_go_version(mod_version)
+            }
+            _ => false,
+        }
+    };
+
     let parsed = StringFormatter::new(config.format).and_then(|formatter| {
         formatter
             .map_meta(|var, _| match var {
@@ -26,23 +57,30 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
                 _ => None,
             })
             .map_style(|variable| match variable {
-                "style" => Some(Ok(config.style)),
-                _ => None,
-            })
-            .map(|variable| match variable {
-                "version" => {
-                    let golang_version =
-                        get_go_version(&context.exec_cmd("go", &["version"])?.stdout)?;
-
-                    VersionFormatter::format_module_version(
-                        module.get_name(),
-                        &golang_version,
-                        config.version_format,
-                    )
-                    .map(Ok)
+                "style" => {
+                    if is_mismatch() {
+                        Some(Ok(config.mismatch_style))
+                    } else {
+                        Some(Ok(config.style))
+                    }
                 }
                 _ => None,
             })
+            .map(|variable| {
+                let golang_version = match variable {
+                    "version" => version.deref(),
+                    "mod_version" => mod_version.deref(),
+                    "installed_version" => installed_version.deref(),
+                    _ => return None,
+                };
+
+                VersionFormatter::format_module_version(
+                    module.get_name(),
+                    golang_version.as_ref()?,
+                    config.version_format,
+                )
+                .map(Ok)
+            })
             .parse(None)
     });
 
@@ -57,6 +95,48 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     Some(module)
 }
 
+/// The version of Go wanted by the closest `go.mod`, from its `toolchain` line or else its `go`
+/// directive
+fn get_go_mod_version(base_dir: &Path) -> Option<String> {
+    let contents = base_dir
+        .ancestors()
+        .find_map(|dir| utils::read_file(dir.join("go.mod")).ok())?;
+    parse_go_mod(&contents)
+}
+
+fn parse_go_mod(contents: &str) -> Option<String> {
+    let mut go_version = None;
+    let mut toolchain_version = None;
+    for line in contents.lines() {
+        let mut words = line.split("//").next()?.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("go"), Some(version)) => go_version = Some(version),
+            // `toolchain default` means the installed toolchain is used
+            (Some("toolchain"), Some(toolchain)) => {
+                toolchain_version = toolchain.strip_prefix("go");
+            }
+            _ => {}
+        }
+    }
+    toolchain_version.or(go_version).map(str::to_string)
+}
+
+/// The numeric components of a Go version, e.g. `[1, 21]` for `1.21rc2`, to compare versions
+fn parse_go_version(version: &str) -> Vec<u64> {
+    let mut components: Vec<u64> = version
+        .split('.')
+        .map_while(|component| {
+            let digits: String = component.chars().take_while(char::is_ascii_digit).collect();
+            digits.parse().ok()
+        })
+        .collect();
+    // `1.21` and `1.21.0` are the same version
+    while components.last() == Some(&0) {
+        components.pop();
+    }
+    components
+}
+
 fn get_go_version(go_stdout: &str) -> Option<String> {
     // go version output looks like this:
     // go version go1.13.3 linux/amd64
@@ -191,4 +271,76 @@ mod tests {
         let input = "go version go1.12 darwin/amd64";
         assert_eq!(get_go_version(input), Some("1.12".to_string()));
     }
+
+    #[test]
+    fn test_parse_go_mod() {
+        let go_mod =
+            "module example.com/app\n\ngo 1.21 // minimum\n\nrequire golang.org/x/text v0.3.0\n";
+        assert_eq!(parse_go_mod(go_mod), Some("1.21".to_string()));
+        let go_mod = "module example.com/app\n\ngo 1.21\ntoolchain go1.21.4\n";
+        assert_eq!(parse_go_mod(go_mod), Some("1.21.4".to_string()));
+        let go_mod = "module example.com/app\n\ngo 1.21\ntoolchain default\n";
+        assert_eq!(parse_go_mod(go_mod), Some("1.21".to_string()));
+        assert_eq!(parse_go_mod("module example.com/app\n"), None);
+    }
+
+    #[test]
+    fn test_compare_go_versions() {
+        assert!(parse_go_version("1.12.1") < parse_go_version("1.21"));
+        assert!(parse_go_version("1.21rc2") < parse_go_version("1.21.1"));
+        assert_eq!(parse_go_version("1.21"), parse_go_version("1.21.0"));
+        assert!(parse_go_version("1.13") > parse_go_version("1.12.1"));
+    }
+
+    #[test]
+    fn version_from_go_mod() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(
+            dir.path().join("go.mod"),
+            "module example.com/app\n\ngo 1.21\ntoolchain go1.21.4\n",
+        )?;
+        let subdir = dir.path().join("cmd");
+        fs::create_dir_all(&subdir)?;
+        File::create(subdir.join("main.go"))?.sync_all()?;
+
+        let actual = ModuleRenderer::new("golang")
+            .path(&subdir)
+            .config(toml::toml! {
+                [golang]
+                use_go_mod = true
+                format = "via [$symbol$version \\($installed_version\\) ]($style)"
+            })
+            .collect();
+
+        let expected = Some(format!(
+            "via {}",
+            Color::Cyan.bold().paint("🐹 v1.21.4 (v1.12.1) ")
+        ));
+        assert_eq!(expected, actual);
+        dir.close()
+    }
+
+    #[test]
+    fn installed_version_older_than_go_mod() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(
+            dir.path().join("go.mod"),
+            "module example.com/app\n\ngo 1.21\n",
+        )?;
+
+        let actual = ModuleRenderer::new("golang")
+            .path(dir.path())
+            .config(toml::toml! {
+                [golang]
+                format = "via [$symbol$version ]($style)(wants $mod_version)"
+            })
+            .collect();
+
+        let expected = Some(format!(
+            "via {}wants v1.21",
+            Color::Red.bold().paint("🐹 v1.12.1 ")
+        ));
+        assert_eq!(expected, actual);
+        dir.close()
+    }
 }
@@ -0,0 +1,88 @@
+use super::{Context, Module, RootModuleConfig};
+
+use crate::configs::history::HistoryConfig;
+use crate::formatter::StringFormatter;
+
+/// Creates a module with the history event number of the next command
+///
+/// Like `\!` in bash or `%h` in zsh, this is the number to use with `!N` history expansion.
+/// It is passed by the init scripts of bash, fish and zsh.
+pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
+    let mut module = context.new_module("history");
+    let config: HistoryConfig = HistoryConfig::try_load(module.config);
+
+    // As we default to disabled=true, we have to check here after loading our config module,
+    // before it was only checking against whatever is in the config starship.toml
+    if config.disabled {
+        return None;
+    }
+
+    let number = context.properties.get("history_number")?.trim();
+    if number.parse::<u64>().is_err() {
+        log::debug!("Invalid history number {:?}", number);
+        return None;
+    }
+
+    let parsed = StringFormatter::new(config.format).and_then(|formatter| {
+        formatter
+            .map_meta(|variable, _| match variable {
+                "symbol" => Some(config.symbol),
+                _ => None,
+            })
+            .map_style(|variable| match variable {
+                "style" => Some(Ok(config.style)),
+                _ => None,
+            })
+            .map(|variable| match variable {
+                "number" => Some(Ok(number)),
+                _ => None,
+            })
+            .parse(None)
+    });
+
+    module.set_segments(match parsed {
+        Ok(segments) => segments,
+        Err(error) => {
+            log::warn!("Error in module `history`:\n{}", error);
+            return None;
+        }
+    });
+
+    Some(module)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test::ModuleRenderer;
+    use ansi_term::Color;
+
+    #[test]
+    fn shows_history_number() {
+        let actual = ModuleRenderer::new("history")
+            .config(toml::toml! {
+                [history]
+                disabled = false
+            })
+            .history_number(42)
+            .collect();
+        let expected = Some(format!("{} ", Color::White.bold().dimmed().paint("!42")));
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn no_history_number() {
+        let actual = ModuleRenderer::new("history")
+            .config(toml::toml! {
+                [history]
+                disabled = false
+            })
+            .collect();
+        assert_eq!(None, actual);
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let actual = ModuleRenderer::new("history").history_number(42).collect();
+        assert_eq!(None, actual);
+    }
+}
@@ -1,5 +1,7 @@
 use super::{Context, Module};
+use regex::Regex;
 use std::ffi::OsString;
+use wildmatch::WildMatch;
 
 use crate::config::RootModuleConfig;
 use crate::configs::hostname::HostnameConfig;
@@ -11,7 +13,7 @@ use crate::formatter::StringFormatter;
 ///     - hostname.disabled is absent or false
 ///     - hostname.ssh_only is false OR the user is currently connected as an SSH session (`$SSH_CONNECTION`)
 pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
-    let mut module = context.new_module("hostname");
+    let module = context.new_module("hostname");
     let config: HostnameConfig = HostnameConfig::try_load(module.config);
 
     let ssh_connection = context.get_env("SSH_CONNECTION");
@@ -29,6 +31,16 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
         }
     };
 
+    let style = config
+        .class_styles
+        .iter()
+        .find(|(pattern, _)| WildMatch::new(pattern).is_match(&host))
+        .map_or(config.style, |(_, style)| *style);
+
+    if let Some(alias) = find_alias(&host, &config) {
+        return render(context, module, &config, &alias, style);
+    }
+
     //rustc doesn't let you do an "if" and an "if let" in the same if statement
     // if this changes in the future this can become a lot cleaner
     let host = if !config.trim_at.is_empty() {
@@ -41,14 +53,43 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
         host.as_ref()
     };
 
+    render(context, module, &config, host, style)
+}
+
+/// Returns the display name of the first alias whose regex matches the hostname, where
+/// `$1` and `${name}` are replaced with the groups of the match
+fn find_alias(host: &str, config: &HostnameConfig) -> Option<String> {
+    config.aliases.iter().find_map(|(pattern, alias)| {
+        let regex = match Regex::new(pattern) {
+            Ok(regex) => regex,
+            Err(error) => {
+                log::warn!("Invalid hostname alias {:?}: {}", pattern, error);
+                return None;
+            }
+        };
+        let captures = regex.captures(host)?;
+        let mut name = String::new();
+        captures.expand(alias, &mut name);
+        Some(name)
+    })
+}
+
+fn render<'a>(
+    context: &'a Context,
+    mut module: Module<'a>,
+    config: &HostnameConfig,
+    host: &str,
+    style: &str,
+) -> Option<Module<'a>> {
     let parsed = StringFormatter::new(config.format).and_then(|formatter| {
         formatter
             .map_style(|variable| match variable {
-                "style" => Some(Ok(config.style)),
+                "style" => Some(Ok(style)),
                 _ => None,
             })
             .map(|variable| match variable {
-                "hostname" => Some(Ok(host)),
+                "hostname" => Some(Ok(host.to_string())),
+                "wsl" => context.get_wsl().map(|wsl| Ok(wsl.to_string())),
                 _ => None,
             })
             .parse(None)
@@ -67,6 +108,7 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
 
 #[cfg(test)]
 mod tests {
+    use crate::context::Wsl;
     use crate::test::ModuleRenderer;
     use ansi_term::{Color, Style};
 
@@ -159,6 +201,59 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn aliases() {
+        let hostname = get_hostname!();
+        let actual = ModuleRenderer::new("hostname")
+            .config(toml::toml! {
+                [hostname]
+                ssh_only = false
+                [hostname.aliases]
+                "^nomatch-(\\d+)$" = "never"
+                "^(?P<first>.).*$" = "box-${first}"
+            })
+            .collect();
+        let first = hostname.chars().next().unwrap();
+        let expected = Some(format!("{} in ", style().paint(format!("box-{}", first))));
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn class_styles() {
+        let hostname = get_hostname!();
+        let actual = ModuleRenderer::new("hostname")
+            .config(toml::toml! {
+                [hostname]
+                ssh_only = false
+                trim_at = ""
+                [hostname.class_styles]
+                "nomatch-*" = "green"
+                "*" = "bold red"
+            })
+            .collect();
+        let expected = Some(format!("{} in ", Color::Red.bold().paint(hostname)));
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn wsl() {
+        let actual = ModuleRenderer::new("hostname")
+            .config(toml::toml! {
+                [hostname]
+                ssh_only = false
+                format = "$wsl"
+            })
+            .wsl(Wsl {
+                distro: Some("Debian".to_string()),
+                version: 2,
+            })
+            .collect();
+
+        assert_eq!(Some("WSL2 Debian".to_string()), actual);
+    }
+
     fn style() -> Style {
         Color::Green.bold().dimmed()
     }
@@ -1,6 +1,10 @@
 use crate::configs::java::JavaConfig;
 use crate::formatter::{StringFormatter, VersionFormatter};
-use std::path::PathBuf;
+use crate::utils;
+use once_cell::sync::Lazy;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
 
 use super::{Context, Module, RootModuleConfig};
 
@@ -23,6 +27,9 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
         return None;
     }
 
+    let runtime = find_java_runtime(context);
+    let java_version = Lazy::new(|| get_java_version(context, &runtime));
+
     let parsed = StringFormatter::new(config.format).and_then(|formatter| {
         formatter
             .map_meta(|var, _| match var {
@@ -30,11 +37,28 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
                 _ => None,
             })
             .map_style(|variable| match variable {
-                "style" => Some(Ok(config.style)),
+                "style" => {
+                    if runtime.mismatch.is_some() {
+                        Some(Ok(config.mismatch_style))
+                    } else {
+                        Some(Ok(config.style))
+                    }
+                }
                 _ => None,
             })
             .map(|variable| match variable {
-                "version" => get_java_version(context, &config).map(Ok),
+                "version" => {
+                    format_java_version(java_version.deref().as_ref()?, config.version_format)
+                        .map(Ok)
+                }
+                "vendor" => runtime
+                    .java_home
+                    .as_deref()
+                    .and_then(get_release_vendor)
+                    .or_else(|| get_version_vendor(java_version.deref().as_ref()?))
+                    .map(Ok),
+                "source" => Some(Ok(runtime.source.to_string())),
+                "mismatch" => runtime.mismatch.clone().map(Ok),
                 _ => None,
             })
             .parse(None)
@@ -51,27 +75,144 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     Some(module)
 }
 
-fn get_java_version(context: &Context, config: &JavaConfig) -> Option<String> {
-    let java_command = context
-        .get_env("JAVA_HOME")
+/// The `java` used in the current directory
+struct JavaRuntime {
+    /// Where it comes from: `sdkmanrc`, `JAVA_HOME` or `PATH`
+    source: &'static str,
+    /// The `java` command to run
+    java_command: String,
+    /// `$JAVA_HOME`, or else the JDK which `java` in the `PATH` links into
+    java_home: Option<PathBuf>,
+    /// The Java candidate pinned in the closest `.sdkmanrc`, if the runtime isn't it
+    mismatch: Option<String>,
+}
+
+fn find_java_runtime(context: &Context) -> JavaRuntime {
+    let (source, java_command, java_home) = match context.get_env("JAVA_HOME") {
+        Some(java_home) => {
+            let java_home = PathBuf::from(java_home);
+            let java_command = java_home.join("bin").join("java");
+            (
+                "JAVA_HOME",
+                java_command.to_string_lossy().to_string(),
+                Some(java_home),
+            )
+        }
+        None => ("PATH", String::from("java"), get_path_java_home(context)),
+    };
+    let mut runtime = JavaRuntime {
+        source,
+        java_command,
+        java_home,
+        mismatch: None,
+    };
+
+    // `sdk env` switches to the pinned candidate by setting both `JAVA_HOME` and `PATH`
+    if let Some((candidate, candidate_dir)) = get_sdkmanrc_candidate(context) {
+        let is_pinned = match (&runtime.java_home, fs::canonicalize(&candidate_dir)) {
+            (Some(java_home), Ok(candidate_dir)) => fs::canonicalize(java_home)
+                .map(|java_home| java_home.starts_with(candidate_dir))
+                .unwrap_or(false),
+            _ => false,
+        };
+        if is_pinned {
+            runtime.source = "sdkmanrc";
+        } else {
+            runtime.mismatch = Some(candidate);
+        }
+    }
+    runtime
+}
+
+/// The JDK of `java` in the `PATH`, which is usually a link to `$JAVA_HOME/bin/java`,
+/// like `/usr/bin/java`
+fn get_path_java_home(context: &Context) -> Option<PathBuf> {
+    let java = fs::canonicalize(context.which("java").ok()?).ok()?;
+    Some(java.parent()?.parent()?.to_path_buf())
+}
+
+/// The Java candidate in the closest `.sdkmanrc`, and the directory SDKMAN! installs it to
+fn get_sdkmanrc_candidate(context: &Context) -> Option<(String, PathBuf)> {
+    let sdkmanrc = context
+        .current_dir
+        .ancestors()
+        .find_map(|dir| utils::read_file(dir.join(".sdkmanrc")).ok())?;
+    // Lines look like `java=17.0.2-tem`
+    let candidate = sdkmanrc.lines().find_map(|line| {
+        let (name, version) = line.split('#').next()?.split_once('=')?;
+        Some(version.trim()).filter(|version| name.trim() == "java" && !version.is_empty())
+    })?;
+
+    let sdkman_dir = context
+        .get_env("SDKMAN_DIR")
         .map(PathBuf::from)
-        .and_then(|path| {
-            path.join("bin")
-                .join("java")
-                .into_os_string()
-                .into_string()
-                .ok()
-        })
-        .unwrap_or_else(|| String::from("java"));
-
-    let output = context.exec_cmd(&java_command, &["-Xinternalversion"])?;
+        .or_else(|| Some(context.get_home()?.join(".sdkman")))?;
+    let candidate_dir = sdkman_dir.join("candidates").join("java").join(candidate);
+    Some((candidate.to_string(), candidate_dir))
+}
+
+fn get_java_version(context: &Context, runtime: &JavaRuntime) -> Option<String> {
+    let output = context.exec_cmd(&runtime.java_command, &["-Xinternalversion"])?;
     let java_version = if output.stdout.is_empty() {
         output.stderr
     } else {
         output.stdout
     };
+    Some(java_version)
+}
 
-    format_java_version(&java_version, config.version_format)
+/// Reads the vendor from the `release` file of a JDK, e.g. `IMPLEMENTOR="Eclipse Adoptium"`
+fn get_release_vendor(java_home: &Path) -> Option<String> {
+    let release = utils::read_file(java_home.join("release")).ok()?;
+    let mut implementor = None;
+    for line in release.lines() {
+        let (key, value) = match line.split_once('=') {
+            Some(pair) => pair,
+            None => continue,
+        };
+        match key.trim() {
+            "GRAALVM_VERSION" => return Some("GraalVM".to_string()),
+            "IMPLEMENTOR" => implementor = Some(value.trim().trim_matches('"')),
+            _ => {}
+        }
+    }
+    implementor.map(vendor_name)
+}
+
+/// Shortens the names of vendors to the names of their distributions
+fn vendor_name(implementor: &str) -> String {
+    match implementor {
+        "Eclipse Adoptium" => "Temurin",
+        "Azul Systems, Inc." => "Zulu",
+        "Amazon.com Inc." => "Corretto",
+        "BellSoft" => "Liberica",
+        "SAP SE" => "SapMachine",
+        "Oracle Corporation" => "Oracle",
+        "Red Hat, Inc." => "Red Hat",
+        implementor if implementor.contains("GraalVM") => "GraalVM",
+        implementor => implementor,
+    }
+    .to_string()
+}
+
+/// Guesses the vendor from the output of `java -Xinternalversion` for JDKs without a `release` file
+fn get_version_vendor(java_version: &str) -> Option<String> {
+    let vendor = if java_version.contains("GraalVM") {
+        "GraalVM"
+    } else if java_version.contains("Zulu") {
+        "Zulu"
+    } else if java_version.contains("OpenJ9") {
+        "OpenJ9"
+    } else if java_version.contains("sapmachine") {
+        "SapMachine"
+    } else if java_version.starts_with("Java HotSpot") {
+        "Oracle"
+    } else if java_version.starts_with("OpenJDK") {
+        "OpenJDK"
+    } else {
+        return None;
+    };
+    Some(vendor.to_string())
 }
 
 fn format_java_version(java_version: &str, version_format: &str) -> Option<String> {
@@ -93,7 +234,7 @@ mod tests {
     use super::*;
     use crate::{test::ModuleRenderer, utils::CommandOutput};
     use ansi_term::Color;
-    use std::fs::File;
+    use std::fs::{self, File};
     use std::io;
 
     #[test]
@@ -199,6 +340,34 @@ mod tests {
         assert_eq!(format_java_version(unknown_jre, "v${raw}"), None);
     }
 
+    #[test]
+    fn test_get_version_vendor() {
+        let graalvm = "OpenJDK 64-Bit GraalVM CE 19.2.0.1 (25.222-b08-jvmci-19.2-b02) for linux-amd64 JRE (8u222), built on Jul 19 2019 17:37:13 by \"buildslave\" with gcc 7.3.0";
+        let zulu = "OpenJDK 64-Bit Server VM (11.0.4+11-LTS) for linux-amd64 JRE (Zulu11.33+15-CA) (11.0.4+11-LTS), built on Jul 11 2019 21:37:17 by \"zulu_re\" with gcc 4.9.2 20150212 (Red Hat 4.9.2-6)";
+        assert_eq!(get_version_vendor(graalvm), Some("GraalVM".to_string()));
+        assert_eq!(get_version_vendor(zulu), Some("Zulu".to_string()));
+        assert_eq!(get_version_vendor("Unknown JRE"), None);
+    }
+
+    #[test]
+    fn test_get_release_vendor() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let release = dir.path().join("release");
+        fs::write(
+            &release,
+            "IMPLEMENTOR=\"Eclipse Adoptium\"\nJAVA_VERSION=\"17.0.2\"\n",
+        )?;
+        assert_eq!(get_release_vendor(dir.path()), Some("Temurin".to_string()));
+        fs::write(
+            &release,
+            "IMPLEMENTOR=\"GraalVM Community\"\nGRAALVM_VERSION=\"22.3.0\"\n",
+        )?;
+        assert_eq!(get_release_vendor(dir.path()), Some("GraalVM".to_string()));
+        fs::write(&release, "IMPLEMENTOR=\"Azul Systems, Inc.\"\n")?;
+        assert_eq!(get_release_vendor(dir.path()), Some("Zulu".to_string()));
+        dir.close()
+    }
+
     #[test]
     fn folder_without_java_file() -> io::Result<()> {
         let dir = tempfile::tempdir()?;
@@ -334,4 +503,101 @@ mod tests {
         assert_eq!(expected, actual);
         dir.close()
     }
+
+    #[test]
+    fn vendor_from_java_home() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        File::create(dir.path().join("Main.java"))?.sync_all()?;
+        let java_home = tempfile::tempdir()?;
+        fs::write(
+            java_home.path().join("release"),
+            "IMPLEMENTOR=\"Eclipse Adoptium\"\n",
+        )?;
+
+        let actual = ModuleRenderer::new("java")
+            .env("JAVA_HOME", java_home.path().to_str().unwrap())
+            .config(toml::toml! {
+                [java]
+                format = "via [$symbol$vendor from $source ]($style)"
+            })
+            .path(dir.path())
+            .collect();
+        let expected = Some(format!(
+            "via {}",
+            Color::Red.dimmed().paint("☕ Temurin from JAVA_HOME ")
+        ));
+        assert_eq!(expected, actual);
+        java_home.close()?;
+        dir.close()
+    }
+
+    #[test]
+    fn java_from_sdkmanrc() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        File::create(dir.path().join("Main.java"))?.sync_all()?;
+        fs::write(
+            dir.path().join(".sdkmanrc"),
+            "# Enable auto-env through the sdkman_auto_env config\njava=22.3.r17-grl\n",
+        )?;
+        let sdkman_dir = tempfile::tempdir()?;
+        let java_home = sdkman_dir.path().join("candidates/java/22.3.r17-grl");
+        fs::create_dir_all(&java_home)?;
+        let java_bin = java_home.join("bin").join("java");
+
+        let actual = ModuleRenderer::new("java")
+            .env("SDKMAN_DIR", sdkman_dir.path().to_str().unwrap())
+            .env("JAVA_HOME", java_home.to_str().unwrap())
+            .cmd(&format!("{} -Xinternalversion", java_bin.to_str().unwrap()),
+            Some(CommandOutput {
+                stdout: "OpenJDK 64-Bit Server VM GraalVM CE 22.3.0 (17.0.5+8-jvmci-22.3-b08) for linux-amd64 JRE (17.0.5+8-jvmci-22.3-b08), built on Oct 19 2022 by \"buildslave\" with gcc 10.3.0".to_owned(),
+                stderr: String::new(),
+            }))
+            .config(toml::toml! {
+                [java]
+                format = "via [$symbol$version $vendor from $source ]($style)"
+            })
+            .path(dir.path())
+            .collect();
+        let expected = Some(format!(
+            "via {}",
+            Color::Red
+                .dimmed()
+                .paint("☕ v17.0.5 GraalVM from sdkmanrc ")
+        ));
+        assert_eq!(expected, actual);
+        sdkman_dir.close()?;
+        dir.close()
+    }
+
+    #[test]
+    fn java_sdkmanrc_mismatch() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        File::create(dir.path().join("Main.java"))?.sync_all()?;
+        fs::write(dir.path().join(".sdkmanrc"), "java=22.3.r17-grl\n")?;
+        let sdkman_dir = tempfile::tempdir()?;
+        fs::create_dir_all(sdkman_dir.path().join("candidates/java/22.3.r17-grl"))?;
+
+        let actual = ModuleRenderer::new("java")
+            .env("SDKMAN_DIR", sdkman_dir.path().to_str().unwrap())
+            .env("JAVA_HOME", "/usr/lib/jvm/default")
+            .cmd("/usr/lib/jvm/default/bin/java -Xinternalversion", Some(CommandOutput {
+                stdout: "OpenJDK 64-Bit Server VM (13.0.2+8) for bsd-amd64 JRE (13.0.2+8), built on Feb  6 2020 02:07:52 by \"brew\" with clang 4.2.1 Compatible Apple LLVM 11.0.0 (clang-1100.0.33.17)".to_owned(),
+                stderr: String::new(),
+            }))
+            .config(toml::toml! {
+                [java]
+                format = "via [$symbol$version from $source( wants $mismatch) ]($style)"
+            })
+            .path(dir.path())
+            .collect();
+        let expected = Some(format!(
+            "via {}",
+            Color::Red
+                .bold()
+                .paint("☕ v13.0.2 from JAVA_HOME wants 22.3.r17-grl ")
+        ));
+        assert_eq!(expected, actual);
+        sdkman_dir.close()?;
+        dir.close()
+    }
 }
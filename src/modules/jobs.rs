@@ -4,18 +4,25 @@ use crate::configs::jobs::JobsConfig;
 use crate::formatter::StringFormatter;
 
 /// Creates a segment to show if there are any active jobs running
+///
+/// Shells which can tell running and stopped jobs apart pass both counts, shown by the
+/// `running` and `stopped` variables.
 pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     let mut module = context.new_module("jobs");
     let config = JobsConfig::try_load(module.config);
 
-    let props = &context.properties;
-    let num_of_jobs = props
-        .get("jobs")
-        .map(String::as_str)
-        .unwrap_or("0")
-        .trim()
-        .parse::<i64>()
-        .ok()?;
+    let count = |name| -> Option<i64> { context.properties.get(name)?.trim().parse().ok() };
+    let running = count("running_jobs");
+    let stopped = count("stopped_jobs");
+    let num_of_jobs = match (context.properties.get("jobs"), running, stopped) {
+        (None, Some(running), Some(stopped)) => running + stopped,
+        (jobs, _, _) => jobs
+            .map(String::as_str)
+            .unwrap_or("0")
+            .trim()
+            .parse::<i64>()
+            .ok()?,
+    };
 
     if config.threshold < 0 {
         log::warn!(
@@ -39,6 +46,8 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
         formatter
             .map_meta(|var, _| match var {
                 "symbol" => Some(config.symbol),
+                "running_symbol" => Some(config.running_symbol),
+                "stopped_symbol" => Some(config.stopped_symbol),
                 _ => None,
             })
             .map_style(|variable| match variable {
@@ -47,6 +56,9 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
             })
             .map(|variable| match variable {
                 "number" => Some(Ok(module_number.clone())),
+                // Hidden without jobs, so groups like `($stopped_symbol$stopped)` disappear
+                "running" => running.filter(|n| *n > 0).map(|n| Ok(n.to_string())),
+                "stopped" => stopped.filter(|n| *n > 0).map(|n| Ok(n.to_string())),
                 _ => None,
             })
             .parse(None)
@@ -147,4 +159,26 @@ mod test {
         let expected = Some(format!("{} ", Color::Blue.bold().paint("✦1")));
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn running_and_stopped() {
+        let config = toml::toml! {
+            [jobs]
+            format = "[$number( $running_symbol$running)( $stopped_symbol$stopped)]($style) "
+        };
+        let actual = ModuleRenderer::new("jobs")
+            .config(config.clone())
+            .job_states(2, 1)
+            .collect();
+        let expected = Some(format!("{} ", Color::Blue.bold().paint("3 ▶2 ⏸1")));
+        assert_eq!(expected, actual);
+
+        let actual = ModuleRenderer::new("jobs")
+            .config(config)
+            .jobs(2)
+            .job_states(0, 2)
+            .collect();
+        let expected = Some(format!("{} ", Color::Blue.bold().paint("2 ⏸2")));
+        assert_eq!(expected, actual);
+    }
 }
@@ -0,0 +1,126 @@
+use super::{Context, Module, RootModuleConfig};
+
+use crate::configs::locale::LocaleConfig;
+use crate::formatter::StringFormatter;
+
+/// The variables which set the character encoding, from the most to the least important
+const LOCALE_VARIABLES: &[&str] = &["LC_ALL", "LC_CTYPE", "LANG"];
+
+/// Creates a module warning about a locale without UTF-8
+///
+/// The locale is taken from `LC_ALL`, `LC_CTYPE` and `LANG` like the C library does, and
+/// is `C` when none of them is set. It is only shown when it doesn't use UTF-8, which
+/// breaks the symbols of the prompt and many tools, and often happens over SSH.
+pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
+    let mut module = context.new_module("locale");
+    let config: LocaleConfig = LocaleConfig::try_load(module.config);
+
+    // As we default to disabled=true, we have to check here after loading our config module,
+    // before it was only checking against whatever is in the config starship.toml
+    if config.disabled {
+        return None;
+    }
+
+    let locale = LOCALE_VARIABLES
+        .iter()
+        .filter_map(|name| context.get_env(name))
+        .find(|value| !value.is_empty())
+        .unwrap_or_else(|| "C".to_string());
+    if is_utf8(&locale) {
+        return None;
+    }
+
+    let parsed = StringFormatter::new(config.format).and_then(|formatter| {
+        formatter
+            .map_meta(|variable, _| match variable {
+                "symbol" => Some(config.symbol),
+                _ => None,
+            })
+            .map_style(|variable| match variable {
+                "style" => Some(Ok(config.style)),
+                _ => None,
+            })
+            .map(|variable| match variable {
+                "locale" => Some(Ok(locale.as_str())),
+                _ => None,
+            })
+            .parse(None)
+    });
+
+    module.set_segments(match parsed {
+        Ok(segments) => segments,
+        Err(error) => {
+            log::warn!("Error in module `locale`:\n{}", error);
+            return None;
+        }
+    });
+
+    Some(module)
+}
+
+/// Whether the codeset of a locale like `en_US.UTF-8@euro` is UTF-8
+fn is_utf8(locale: &str) -> bool {
+    let codeset = match locale.split_once('.') {
+        Some((_, codeset)) => codeset.split('@').next().unwrap_or_default(),
+        None => return false,
+    };
+    codeset.eq_ignore_ascii_case("utf-8") || codeset.eq_ignore_ascii_case("utf8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::ModuleRenderer;
+    use ansi_term::Color;
+
+    #[test]
+    fn detects_utf8() {
+        assert!(is_utf8("en_US.UTF-8"));
+        assert!(is_utf8("de_DE.utf8@euro"));
+        assert!(is_utf8("C.UTF-8"));
+        assert!(!is_utf8("C"));
+        assert!(!is_utf8("POSIX"));
+        assert!(!is_utf8("en_US"));
+        assert!(!is_utf8("en_US.ISO-8859-1"));
+    }
+
+    #[test]
+    fn warns_without_locale() {
+        let actual = ModuleRenderer::new("locale")
+            .config(toml::toml! {
+                [locale]
+                disabled = false
+            })
+            .collect();
+        let expected = Some(format!("{} ", Color::Red.bold().paint("🔤 C")));
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn lc_all_overrides_lang() {
+        let config = toml::toml! {
+            [locale]
+            disabled = false
+            format = "$locale"
+        };
+        let actual = ModuleRenderer::new("locale")
+            .config(config.clone())
+            .env("LANG", "en_US.UTF-8")
+            .env("LC_ALL", "POSIX")
+            .collect();
+        assert_eq!(Some("POSIX".to_string()), actual);
+
+        let actual = ModuleRenderer::new("locale")
+            .config(config)
+            .env("LANG", "en_US.ISO-8859-1")
+            .env("LC_ALL", "en_US.UTF-8")
+            .collect();
+        assert_eq!(None, actual);
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let actual = ModuleRenderer::new("locale").collect();
+        assert_eq!(None, actual);
+    }
+}
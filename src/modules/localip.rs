@@ -0,0 +1,116 @@
+use std::net::UdpSocket;
+
+use super::{Context, Module, RootModuleConfig};
+
+use crate::configs::localip::LocalipConfig;
+use crate::formatter::StringFormatter;
+
+/// Public addresses to route to, which are only used to pick the interface: connecting a UDP
+/// socket doesn't send anything.
+const IPV4_TARGET: &str = "198.51.100.1:53";
+const IPV6_TARGET: &str = "[2001:db8::1]:53";
+
+/// Creates a module with the local IP address of the primary network interface
+///
+/// Will display the address if all of the following criteria are met:
+///     - localip.disabled is false
+///     - localip.ssh_only is false OR the user is currently connected as an SSH session (`$SSH_CONNECTION`)
+///     - the interface has an address of the kind used in the format
+pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
+    let mut module = context.new_module("localip");
+    let config: LocalipConfig = LocalipConfig::try_load(module.config);
+
+    // As we default to disabled=true, we have to check here after loading our config module,
+    // before it was only checking against whatever is in the config starship.toml
+    if config.disabled {
+        return None;
+    }
+
+    let ssh_connection = context.get_env("SSH_CONNECTION");
+    if config.ssh_only && ssh_connection.is_none() {
+        return None;
+    }
+
+    let parsed = StringFormatter::new(config.format).and_then(|formatter| {
+        formatter
+            .map_style(|variable| match variable {
+                "style" => Some(Ok(config.style)),
+                _ => None,
+            })
+            .map(|variable| match variable {
+                "localipv4" => primary_address("0.0.0.0:0", IPV4_TARGET).map(Ok),
+                "localipv6" => primary_address("[::]:0", IPV6_TARGET).map(Ok),
+                _ => None,
+            })
+            .parse(None)
+    });
+
+    module.set_segments(match parsed {
+        Ok(segments) => segments,
+        Err(error) => {
+            log::warn!("Error in module `localip`:\n{}", error);
+            return None;
+        }
+    });
+
+    Some(module)
+}
+
+/// Returns the address of the interface which traffic to `target` would leave through
+fn primary_address(bind: &str, target: &str) -> Option<String> {
+    let socket = UdpSocket::bind(bind).ok()?;
+    socket.connect(target).ok()?;
+    let address = socket.local_addr().ok()?.ip();
+    if address.is_unspecified() {
+        log::debug!("No interface routes to {}", target);
+        return None;
+    }
+    Some(address.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::ModuleRenderer;
+
+    #[test]
+    fn finds_address_of_route() {
+        assert_eq!(
+            Some("127.0.0.1".to_string()),
+            primary_address("0.0.0.0:0", "127.0.0.1:53")
+        );
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let actual = ModuleRenderer::new("localip")
+            .env("SSH_CONNECTION", "something")
+            .collect();
+        assert_eq!(None, actual);
+    }
+
+    #[test]
+    fn ssh_only_without_ssh() {
+        let actual = ModuleRenderer::new("localip")
+            .config(toml::toml! {
+                [localip]
+                disabled = false
+                format = "[local]()"
+            })
+            .collect();
+        assert_eq!(None, actual);
+    }
+
+    #[test]
+    fn ssh_only_with_ssh() {
+        let actual = ModuleRenderer::new("localip")
+            .config(toml::toml! {
+                [localip]
+                disabled = false
+                format = "[local]()"
+            })
+            .env("SSH_CONNECTION", "something")
+            .collect();
+        assert_eq!(Some("local".to_string()), actual);
+    }
+}
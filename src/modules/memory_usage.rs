@@ -56,15 +56,41 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     let ram_used = (used_memory_kib as f64 / total_memory_kib as f64) * 100.;
     let ram_pct = format_pct(ram_used, pct_sign);
 
-    let threshold = config.threshold;
-    if ram_used.round() < threshold as f64 {
-        return None;
-    }
+    let (show_ram, show_swap) = match config.display {
+        "both" => (true, true),
+        "ram_only" => (true, false),
+        "swap_only" => (false, true),
+        display => {
+            log::warn!(
+                "display in [memory_usage] should be \"both\", \"ram_only\" or \"swap_only\", found {:?}",
+                display
+            );
+            (true, true)
+        }
+    };
 
-    let ram = format_usage_total(used_memory_kib, total_memory_kib);
     let total_swap_kib = system.swap_total;
     let used_swap_kib = system.swap_total - system.swap_free;
     let percent_swap_used = (used_swap_kib as f64 / total_swap_kib as f64) * 100.;
+    // swap only shown if there is swap on the system
+    let show_swap = show_swap && total_swap_kib > 0;
+
+    // Swap is only checked on its own when it has a threshold, as heavy swapping can slow
+    // down a system whose RAM usage looks fine
+    let ram_threshold = config.ram_threshold.unwrap_or(config.threshold);
+    let swap_threshold = match (config.swap_threshold, show_ram) {
+        (Some(threshold), _) => Some(threshold),
+        (None, false) => Some(config.threshold),
+        (None, true) => None,
+    };
+    let ram_exceeded = show_ram && ram_used.round() >= ram_threshold as f64;
+    let swap_exceeded = show_swap
+        && matches!(swap_threshold, Some(threshold) if percent_swap_used.round() >= threshold as f64);
+    if !ram_exceeded && !swap_exceeded {
+        return None;
+    }
+
+    let ram = format_usage_total(used_memory_kib, total_memory_kib);
     let swap_pct = format_pct(percent_swap_used, pct_sign);
     let swap = format_usage_total(used_swap_kib, total_swap_kib);
 
@@ -76,14 +102,15 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
             })
             .map_style(|variable| match variable {
                 "style" => Some(Ok(config.style)),
+                "ram_style" => Some(Ok(config.ram_style.unwrap_or(config.style))),
+                "swap_style" => Some(Ok(config.swap_style.unwrap_or(config.style))),
                 _ => None,
             })
             .map(|variable| match variable {
-                "ram" => Some(Ok(&ram)),
-                "ram_pct" => Some(Ok(&ram_pct)),
-                // swap only shown if there is swap on the system
-                "swap" if total_swap_kib > 0 => Some(Ok(&swap)),
-                "swap_pct" if total_swap_kib > 0 => Some(Ok(&swap_pct)),
+                "ram" if show_ram => Some(Ok(&ram)),
+                "ram_pct" if show_ram => Some(Ok(&ram_pct)),
+                "swap" if show_swap => Some(Ok(&swap)),
+                "swap_pct" if show_swap => Some(Ok(&swap_pct)),
                 _ => None,
             })
             .parse(None)
@@ -4,25 +4,30 @@ mod character;
 mod cmake;
 mod cmd_duration;
 mod conda;
+mod container;
+mod cpu_temp;
 mod crystal;
 pub(crate) mod custom;
 mod dart;
 mod deno;
 mod directory;
+mod disk_usage;
 mod docker_context;
 mod dotnet;
 mod elixir;
 mod elm;
 mod env_var;
 mod erlang;
+mod fill;
 mod gcloud;
 mod git_branch;
 mod git_commit;
 mod git_state;
-mod git_status;
+pub(crate) mod git_status;
 mod golang;
 mod helm;
 mod hg_branch;
+mod history;
 mod hostname;
 mod java;
 mod jobs;
@@ -30,6 +35,8 @@ mod julia;
 mod kotlin;
 mod kubernetes;
 mod line_break;
+mod locale;
+mod localip;
 mod lua;
 mod memory_usage;
 mod nim;
@@ -37,9 +44,13 @@ mod nix_shell;
 mod nodejs;
 mod ocaml;
 mod openstack;
+mod os;
 mod package;
 mod perl;
 mod php;
+pub(crate) mod plugin;
+mod proxy;
+pub(crate) mod public_ip;
 mod purescript;
 mod python;
 mod red;
@@ -50,27 +61,117 @@ mod shell;
 mod shlvl;
 mod singularity;
 mod status;
+mod sudo;
 mod swift;
 mod terraform;
 mod time;
+mod uptime;
 mod username;
 mod utils;
 mod vagrant;
 mod vcsh;
+mod vpn;
+mod wasm_plugin;
+pub(crate) mod weather;
+mod wifi;
 mod zig;
 
 #[cfg(feature = "battery")]
 mod battery;
 
 use crate::config::RootModuleConfig;
-use crate::context::{Context, Shell};
-use crate::module::Module;
-use std::time::Instant;
+use crate::context::{
+    measure_commands, with_format_variables, with_overrides, Context, ModuleDetails,
+    ModuleOverrides, Shell,
+};
+use crate::formatter::{StringFormatter, StyleVariableHolder, VariableHolder};
+use crate::module::{Module, ALL_MODULES};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 pub fn handle<'a>(module: &str, context: &'a Context) -> Option<Module<'a>> {
+    if let Some(results) = &context.async_results {
+        if results.is_async(module) {
+            return results.get(module).map(|segments| {
+                let mut m = context.new_module(module);
+                m.set_segments(segments);
+                m
+            });
+        }
+    }
+    if let Some(guard) = &context.slow_modules {
+        if guard.is_skipped(module) {
+            log::debug!("Skipping slow module {:?}", module);
+            return None;
+        }
+    }
+    let cache = context
+        .module_cache
+        .as_ref()
+        .filter(|cache| cache.is_cached(module));
+    if let Some(cache) = cache {
+        if let Some(segments) = cache.get(module, context.config.get_module_config(module)) {
+            log::debug!("Using cached output of module {:?}", module);
+            return segments.map(|segments| {
+                let mut m = context.new_module(module);
+                m.set_segments(segments);
+                m
+            });
+        }
+    }
     let start: Instant = Instant::now();
 
-    let mut m: Option<Module> = {
+    let variables = format_variables(module, context.config.get_module_config(module), context);
+    let (m, command_duration, details) = render(module, context, variables, context.explain);
+
+    let elapsed = start.elapsed();
+    log::trace!("Took {:?} to compute module {:?}", elapsed, module);
+    if let Some(metrics) = &context.metrics {
+        metrics.timing(&format!("module.{}", module), elapsed);
+    }
+    if let Some(Ok(mut timings)) = context
+        .module_timings
+        .as_ref()
+        .map(|timings| timings.lock())
+    {
+        *timings.entry(module.to_string()).or_default() += elapsed;
+    }
+    if let Some(guard) = &context.slow_modules {
+        guard.record(module, elapsed);
+    }
+    if let Some(cache) = cache {
+        let segments = m.as_ref().map(|m| m.segments.as_slice());
+        cache.insert(module, context.config.get_module_config(module), segments);
+    }
+    if elapsed.as_millis() < 1 {
+        // If we take less than 1ms to compute a None, then we will not return a module at all
+        // if we have a module: default duration is 0 so no need to change it
+        m.map(|mut m| {
+            m.details = details;
+            m
+        })
+    } else {
+        // if we took more than 1ms we want to report that and so--in case we have None currently--
+        // need to create an empty module just to hold the duration for that case
+        let mut m = m.unwrap_or_else(|| context.new_module(module));
+        m.duration = elapsed;
+        m.command_duration = command_duration;
+        m.details = details;
+        Some(m)
+    }
+}
+
+/// Renders `module` with the overrides in its config and the `variables` its format strings
+/// can show, measuring the commands it runs and, with `explain`, recording what it did
+fn render<'a>(
+    module: &str,
+    context: &'a Context,
+    variables: Arc<HashMap<String, String>>,
+    explain: bool,
+) -> (Option<Module<'a>>, Duration, Option<ModuleDetails>) {
+    let overrides = ModuleOverrides::from_config(context.config.get_module_config(module));
+    let render = || {
         match module {
             // Keep these ordered alphabetically.
             // Default ordering is handled in configs/starship_root.rs
@@ -81,14 +182,18 @@ pub fn handle<'a>(module: &str, context: &'a Context) -> Option<Module<'a>> {
             "cmake" => cmake::module(context),
             "cmd_duration" => cmd_duration::module(context),
             "conda" => conda::module(context),
+            "container" => container::module(context),
+            "cpu_temp" => cpu_temp::module(context),
             "dart" => dart::module(context),
             "deno" => deno::module(context),
             "directory" => directory::module(context),
+            "disk_usage" => disk_usage::module(context),
             "docker_context" => docker_context::module(context),
             "dotnet" => dotnet::module(context),
             "elixir" => elixir::module(context),
             "elm" => elm::module(context),
             "erlang" => erlang::module(context),
+            "fill" => fill::module(context),
             "env_var" => env_var::module(context),
             "gcloud" => gcloud::module(context),
             "git_branch" => git_branch::module(context),
@@ -98,6 +203,7 @@ pub fn handle<'a>(module: &str, context: &'a Context) -> Option<Module<'a>> {
             "golang" => golang::module(context),
             "helm" => helm::module(context),
             "hg_branch" => hg_branch::module(context),
+            "history" => history::module(context),
             "hostname" => hostname::module(context),
             "java" => java::module(context),
             "jobs" => jobs::module(context),
@@ -105,6 +211,8 @@ pub fn handle<'a>(module: &str, context: &'a Context) -> Option<Module<'a>> {
             "kotlin" => kotlin::module(context),
             "kubernetes" => kubernetes::module(context),
             "line_break" => line_break::module(context),
+            "locale" => locale::module(context),
+            "localip" => localip::module(context),
             "lua" => lua::module(context),
             "memory_usage" => memory_usage::module(context),
             "nim" => nim::module(context),
@@ -112,9 +220,12 @@ pub fn handle<'a>(module: &str, context: &'a Context) -> Option<Module<'a>> {
             "nodejs" => nodejs::module(context),
             "ocaml" => ocaml::module(context),
             "openstack" => openstack::module(context),
+            "os" => os::module(context),
             "package" => package::module(context),
             "perl" => perl::module(context),
             "php" => php::module(context),
+            "proxy" => proxy::module(context),
+            "public_ip" => public_ip::module(context),
             "purescript" => purescript::module(context),
             "python" => python::module(context),
             "red" => red::module(context),
@@ -126,12 +237,17 @@ pub fn handle<'a>(module: &str, context: &'a Context) -> Option<Module<'a>> {
             "singularity" => singularity::module(context),
             "swift" => swift::module(context),
             "status" => status::module(context),
+            "sudo" => sudo::module(context),
             "terraform" => terraform::module(context),
             "time" => time::module(context),
+            "uptime" => uptime::module(context),
             "crystal" => crystal::module(context),
             "username" => username::module(context),
             "vagrant" => vagrant::module(context),
             "vcsh" => vcsh::module(context),
+            "vpn" => vpn::module(context),
+            "weather" => weather::module(context),
+            "wifi" => wifi::module(context),
             "zig" => zig::module(context),
             _ => {
                 eprintln!("Error: Unknown module {}. Use starship module --list to list out all supported modules.", module);
@@ -139,19 +255,116 @@ pub fn handle<'a>(module: &str, context: &'a Context) -> Option<Module<'a>> {
             }
         }
     };
+    measure_commands(explain, || {
+        with_format_variables(variables, || with_overrides(overrides, render))
+    })
+}
 
-    let elapsed = start.elapsed();
-    log::trace!("Took {:?} to compute module {:?}", elapsed, module);
-    if elapsed.as_millis() < 1 {
-        // If we take less than 1ms to compute a None, then we will not return a module at all
-        // if we have a module: default duration is 0 so no need to change it
-        m
-    } else {
-        // if we took more than 1ms we want to report that and so--in case we have None currently--
-        // need to create an empty module just to hold the duration for that case
-        m.get_or_insert_with(|| context.new_module(module)).duration = elapsed;
-        m
+/// The variables the format strings of a module can show besides its own: the width of the
+/// terminal as `$width`, the environment variables in `env_allowlist`, and the variables of
+/// other modules it references, like `${git_branch.branch}`
+pub fn format_variables(
+    module: &str,
+    config: Option<&toml::Value>,
+    context: &Context,
+) -> Arc<HashMap<String, String>> {
+    let mut visiting = vec![module.to_string()];
+    Arc::new(collect_format_variables(config, context, &mut visiting))
+}
+
+fn collect_format_variables(
+    config: Option<&toml::Value>,
+    context: &Context,
+    visiting: &mut Vec<String>,
+) -> HashMap<String, String> {
+    let mut variables = context.allowed_env();
+    variables.insert("width".to_string(), context.width.to_string());
+    for referenced in referenced_modules(config) {
+        if visiting.contains(&referenced) {
+            log::warn!(
+                "Modules reference each other's variables in a cycle: {} -> {}",
+                visiting.join(" -> "),
+                referenced
+            );
+            continue;
+        }
+        let rendered = context
+            .referenced_variables
+            .lock()
+            .unwrap()
+            .get(&referenced)
+            .cloned();
+        let rendered = match rendered {
+            Some(rendered) => rendered,
+            None => {
+                visiting.push(referenced.clone());
+                let rendered = Arc::new(render_variables(&referenced, context, visiting));
+                visiting.pop();
+                context
+                    .referenced_variables
+                    .lock()
+                    .unwrap()
+                    .insert(referenced, rendered.clone());
+                rendered
+            }
+        };
+        variables.extend(
+            rendered
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone())),
+        );
+    }
+    variables
+}
+
+/// Renders a module referenced by another one, returning its variables named like
+/// `git_branch.branch`
+fn render_variables(
+    module: &str,
+    context: &Context,
+    visiting: &mut Vec<String>,
+) -> HashMap<String, String> {
+    let config = context.config.get_module_config(module);
+    let own_variables = Arc::new(collect_format_variables(config, context, visiting));
+    let (_, _, details) = render(module, context, own_variables, true);
+    details
+        .into_iter()
+        .flat_map(|details| details.variables)
+        .map(|(name, value)| (format!("{}.{}", module, name), value))
+        .collect()
+}
+
+/// The modules whose variables the format strings in the config of a module reference,
+/// like `git_branch` in `${git_branch.branch}` or `${truncate(git_branch.branch, 5)}`
+fn referenced_modules(config: Option<&toml::Value>) -> BTreeSet<String> {
+    fn collect(value: &toml::Value, modules: &mut BTreeSet<String>) {
+        match value {
+            toml::Value::String(string) => {
+                let formatter = match StringFormatter::new(string) {
+                    Ok(formatter) => formatter,
+                    Err(_) => return,
+                };
+                let variables = formatter.get_variables();
+                let style_variables = formatter.get_style_variables();
+                for name in variables.iter().chain(&style_variables) {
+                    if let Some((module, _)) = name.split_once('.') {
+                        if ALL_MODULES.contains(&module) {
+                            modules.insert(module.to_string());
+                        }
+                    }
+                }
+            }
+            toml::Value::Array(values) => values.iter().for_each(|value| collect(value, modules)),
+            toml::Value::Table(table) => table.values().for_each(|value| collect(value, modules)),
+            _ => (),
+        }
+    }
+
+    let mut modules = BTreeSet::new();
+    if let Some(config) = config {
+        collect(config, &mut modules);
     }
+    modules
 }
 
 pub fn description(module: &str) -> &'static str {
@@ -164,14 +377,18 @@ pub fn description(module: &str) -> &'static str {
         "cmake" => "The currently installed version of CMake",
         "cmd_duration" => "How long the last command took to execute",
         "conda" => "The current conda environment, if $CONDA_DEFAULT_ENV is set",
+        "container" => "The container the shell runs in",
+        "cpu_temp" => "The temperature of the CPU",
         "crystal" => "The currently installed version of Crystal",
         "dart" => "The currently installed version of Dart",
         "deno" => "The currently installed version of Deno",
         "directory" => "The current working directory",
+        "disk_usage" => "The free space of nearly full filesystems",
         "docker_context" => "The current docker context",
         "dotnet" => "The relevant version of the .NET Core SDK for the current directory",
         "env_var" => "Displays the current value of a selected environment variable",
         "erlang" => "Current OTP version",
+        "fill" => "Fills the remaining space on the line with a pad string",
         "gcloud" => "The current GCP client configuration",
         "git_branch" => "The active branch of the repo in your current directory",
         "git_commit" => "The active commit (and tag if any) of the repo in your current directory",
@@ -180,6 +397,7 @@ pub fn description(module: &str) -> &'static str {
         "golang" => "The currently installed version of Golang",
         "helm" => "The currently installed version of Helm",
         "hg_branch" => "The active branch of the repo in your current directory",
+        "history" => "The history event number of the next command",
         "hostname" => "The system hostname",
         "java" => "The currently installed version of Java",
         "jobs" => "The current number of jobs running",
@@ -187,6 +405,8 @@ pub fn description(module: &str) -> &'static str {
         "kotlin" => "The currently installed version of Kotlin",
         "kubernetes" => "The current Kubernetes context name and, if set, the namespace",
         "line_break" => "Separates the prompt into two lines",
+        "locale" => "A warning when the locale doesn't use UTF-8",
+        "localip" => "The local IP address of the primary network interface",
         "lua" => "The currently installed version of Lua",
         "memory_usage" => "Current system memory and swap usage",
         "nim" => "The currently installed version of Nim",
@@ -194,9 +414,12 @@ pub fn description(module: &str) -> &'static str {
         "nodejs" => "The currently installed version of NodeJS",
         "ocaml" => "The currently installed version of OCaml",
         "openstack" => "The current OpenStack cloud and project",
+        "os" => "The operating system, or the Linux distribution",
         "package" => "The package version of the current directory's project",
         "perl" => "The currently installed version of Perl",
         "php" => "The currently installed version of PHP",
+        "proxy" => "The proxy set in the environment",
+        "public_ip" => "The public IP address of the network",
         "purescript" => "The currently installed version of PureScript",
         "python" => "The currently installed version of Python",
         "red" => "The currently installed version of Red",
@@ -207,11 +430,16 @@ pub fn description(module: &str) -> &'static str {
         "shell" => "The currently used shell indicator",
         "shlvl" => "The current value of SHLVL",
         "status" => "The status of the last command",
+        "sudo" => "Whether sudo would run commands without a password",
         "terraform" => "The currently selected terraform workspace and version",
         "time" => "The current local time",
+        "uptime" => "How long the system has been running",
         "username" => "The active user's username",
         "vagrant" => "The currently installed version of Vagrant",
         "vcsh" => "The currently active VCSH repository",
+        "vpn" => "The active VPN connections",
+        "weather" => "The current weather at the configured location",
+        "wifi" => "The connected Wi-Fi network and its signal strength",
         "zig" => "The currently installed version of Zig",
         _ => "<no description>",
     }
@@ -37,6 +37,10 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
             .exec_cmd("node", &["--version"])
             .map(|cmd| cmd.stdout)
     });
+    let mismatch = Lazy::new(|| {
+        let nodejs_version = nodejs_version.deref().as_ref()?;
+        get_mismatch(&context.current_dir, nodejs_version)
+    });
     let parsed = StringFormatter::new(config.format).and_then(|formatter| {
         formatter
             .map_meta(|var, _| match var {
@@ -45,15 +49,13 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
             })
             .map_style(|variable| match variable {
                 "style" => {
-                    let engines_version = get_engines_version(&context.current_dir);
-                    let in_engines_range =
-                        check_engines_version(nodejs_version.deref().as_ref()?, engines_version);
-                    if in_engines_range {
+                    if mismatch.is_none() {
                         Some(Ok(config.style))
                     } else {
                         Some(Ok(config.not_capable_style))
                     }
                 }
+                "mismatch_style" => Some(Ok(config.mismatch_style)),
                 _ => None,
             })
             .map(|variable| match variable {
@@ -61,6 +63,7 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
                     nodejs_version.deref().as_ref()?,
                     config.version_format,
                 ))),
+                "mismatch" => mismatch.deref().clone().map(Ok),
                 _ => None,
             })
             .parse(None)
@@ -77,6 +80,54 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     Some(module)
 }
 
+/// The version pinned in `.nvmrc` or `.node-version`, or else the `engines` range of
+/// `package.json`, if the Node.js version doesn't match it
+fn get_mismatch(base_dir: &Path, nodejs_version: &str) -> Option<String> {
+    if let Some(pinned_version) = get_pinned_version(base_dir) {
+        if !check_pinned_version(nodejs_version, &pinned_version) {
+            return Some(pinned_version);
+        }
+    }
+    let engines_version = get_engines_version(base_dir)?;
+    if check_engines_version(nodejs_version, Some(engines_version.clone())) {
+        None
+    } else {
+        Some(engines_version)
+    }
+}
+
+/// The version which `nvm use` or other version managers would switch to
+fn get_pinned_version(base_dir: &Path) -> Option<String> {
+    [".nvmrc", ".node-version"].iter().find_map(|file| {
+        let contents = utils::read_file(base_dir.join(file)).ok()?;
+        let version = contents.lines().next()?.trim();
+        (!version.is_empty()).then(|| version.to_string())
+    })
+}
+
+/// Checks the Node.js version against a pinned version like `18` or `v18.16.0`. Aliases
+/// like `lts/*` can't be resolved without the version manager, so they always match.
+fn check_pinned_version(nodejs_version: &str, pinned_version: &str) -> bool {
+    let pinned: Option<Vec<u64>> = pinned_version
+        .trim_start_matches('v')
+        .split('.')
+        .map(|component| component.parse().ok())
+        .collect();
+    let pinned = match pinned {
+        Some(pinned) => pinned,
+        None => return true,
+    };
+    let actual = nodejs_version
+        .trim()
+        .trim_start_matches('v')
+        .split('.')
+        .map(|component| component.parse::<u64>().ok());
+    pinned
+        .iter()
+        .zip(actual)
+        .all(|(pinned, actual)| actual == Some(*pinned))
+}
+
 fn get_engines_version(base_dir: &Path) -> Option<String> {
     let json_str = utils::read_file(base_dir.join("package.json")).ok()?;
     let package_json: json::Value = json::from_str(&json_str).ok()?;
@@ -146,6 +197,24 @@ mod tests {
         dir.close()
     }
 
+    #[test]
+    fn folder_with_package_json_display_symbol() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        File::create(dir.path().join("package.json"))?.sync_all()?;
+
+        let actual = ModuleRenderer::new("nodejs")
+            .path(dir.path())
+            .config(toml::toml! {
+                [nodejs]
+                symbol = "⬢ "
+                display = "symbol"
+            })
+            .collect();
+        let expected = Some(format!("{}", Color::Green.bold().paint("⬢ ")));
+        assert_eq!(expected, actual);
+        dir.close()
+    }
+
     #[test]
     fn folder_with_package_json_and_esy_lock() -> io::Result<()> {
         let dir = tempfile::tempdir()?;
@@ -274,4 +343,41 @@ mod tests {
         assert_eq!(expected, actual);
         dir.close()
     }
+
+    #[test]
+    fn nvmrc_version_mismatch() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = toml::toml! {
+            [nodejs]
+            format = "$version( != $mismatch)"
+        };
+        let render = |nvmrc: &str| -> io::Result<Option<String>> {
+            fs::write(dir.path().join(".nvmrc"), nvmrc)?;
+            Ok(ModuleRenderer::new("nodejs")
+                .path(dir.path())
+                .config(config.clone())
+                .collect())
+        };
+
+        assert_eq!(
+            Some("v12.0.0 != v18.16.0".to_string()),
+            render("v18.16.0\n")?
+        );
+        assert_eq!(Some("v12.0.0 != 14".to_string()), render("14")?);
+        assert_eq!(Some("v12.0.0".to_string()), render("12\n")?);
+        assert_eq!(Some("v12.0.0".to_string()), render("v12.0.0")?);
+        assert_eq!(Some("v12.0.0".to_string()), render("lts/*")?);
+        dir.close()
+    }
+
+    #[test]
+    fn node_version_file_mismatch_style() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join(".node-version"), "16.20.0\n")?;
+
+        let actual = ModuleRenderer::new("nodejs").path(dir.path()).collect();
+        let expected = Some(format!("via {}", Color::Red.bold().paint(" v12.0.0 ")));
+        assert_eq!(expected, actual);
+        dir.close()
+    }
 }
@@ -0,0 +1,255 @@
+use super::{Context, Module, RootModuleConfig};
+
+use crate::configs::os::OsConfig;
+use crate::formatter::StringFormatter;
+
+/// The symbols of the operating systems and distributions, by the `ID` of os-release
+const DEFAULT_SYMBOLS: &[(&str, &str)] = &[
+    ("alpine", "🏔️ "),
+    ("amzn", "🙂 "),
+    ("android", "🤖 "),
+    ("arch", "🎗️ "),
+    ("centos", "💠 "),
+    ("debian", "🌀 "),
+    ("dragonfly", "🐉 "),
+    ("endeavouros", "🚀 "),
+    ("fedora", "🎩 "),
+    ("freebsd", "😈 "),
+    ("gentoo", "🗜️ "),
+    ("linux", "🐧 "),
+    ("linuxmint", "🌿 "),
+    ("macos", "🍎 "),
+    ("manjaro", "🥭 "),
+    ("netbsd", "🚩 "),
+    ("nixos", "❄️ "),
+    ("ol", "🦴 "),
+    ("openbsd", "🐡 "),
+    ("opensuse", "🦎 "),
+    ("pop", "🍭 "),
+    ("raspbian", "🍓 "),
+    ("rhel", "🎩 "),
+    ("solus", "⛵ "),
+    ("ubuntu", "🎯 "),
+    ("unknown", "❓ "),
+    ("windows", "🪟 "),
+];
+
+/// The operating system, or the distribution on Linux
+#[derive(Debug, PartialEq)]
+struct Os {
+    /// A lowercase identifier, like `ubuntu` or `macos`
+    id: String,
+    /// The identifiers of the distributions this one is derived from
+    id_like: Vec<String>,
+    name: String,
+    version: String,
+}
+
+/// Creates a module with the operating system, or the Linux distribution
+pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
+    let mut module = context.new_module("os");
+    let config: OsConfig = OsConfig::try_load(module.config);
+
+    // As we default to disabled=true, we have to check here after loading our config module,
+    // before it was only checking against whatever is in the config starship.toml
+    if config.disabled {
+        return None;
+    }
+
+    let os = detect_os();
+    let symbol = get_symbol(&os, &config);
+
+    let parsed = StringFormatter::new(config.format).and_then(|formatter| {
+        formatter
+            .map_meta(|variable, _| match variable {
+                "symbol" => Some(symbol),
+                _ => None,
+            })
+            .map_style(|variable| match variable {
+                "style" => Some(Ok(config.style)),
+                _ => None,
+            })
+            .map(|variable| match variable {
+                "name" => Some(Ok(os.name.clone())),
+                "id" => Some(Ok(os.id.clone())),
+                "version" if !os.version.is_empty() => Some(Ok(os.version.clone())),
+                "wsl" => context.get_wsl().map(|wsl| Ok(wsl.to_string())),
+                _ => None,
+            })
+            .parse(None)
+    });
+
+    module.set_segments(match parsed {
+        Ok(segments) => segments,
+        Err(error) => {
+            log::warn!("Error in module `os`:\n{}", error);
+            return None;
+        }
+    });
+
+    Some(module)
+}
+
+/// Returns the symbol of the distribution, or else of the first one it is derived from which
+/// has a symbol. Symbols of the `symbols` option replace the default ones.
+fn get_symbol<'a>(os: &Os, config: &OsConfig<'a>) -> &'a str {
+    let symbol = |id: &str| {
+        config.symbols.get(id).copied().or_else(|| {
+            DEFAULT_SYMBOLS
+                .iter()
+                .find(|(symbol_id, _)| *symbol_id == id)
+                .map(|(_, symbol)| *symbol)
+        })
+    };
+    let fallback = if cfg!(target_os = "linux") {
+        "linux"
+    } else {
+        "unknown"
+    };
+    std::iter::once(&os.id)
+        .chain(&os.id_like)
+        .find_map(|id| symbol(id))
+        .or_else(|| symbol(fallback))
+        .unwrap_or_default()
+}
+
+#[cfg(target_os = "linux")]
+fn detect_os() -> Os {
+    let os_release = std::fs::read_to_string("/etc/os-release")
+        .or_else(|_| std::fs::read_to_string("/usr/lib/os-release"));
+    match os_release {
+        Ok(os_release) => parse_os_release(&os_release),
+        Err(error) => {
+            log::debug!("Unable to read os-release: {}", error);
+            parse_os_release("")
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn detect_os() -> Os {
+    use os_info::{Type, Version};
+
+    let info = os_info::get();
+    let (id, name) = match info.os_type() {
+        Type::Macos => ("macos", "macOS".to_string()),
+        Type::Windows => ("windows", "Windows".to_string()),
+        Type::FreeBSD => ("freebsd", "FreeBSD".to_string()),
+        Type::DragonFly => ("dragonfly", "DragonFly BSD".to_string()),
+        Type::Android => ("android", "Android".to_string()),
+        os_type => ("unknown", os_type.to_string()),
+    };
+    let version = match info.version() {
+        Version::Unknown => String::new(),
+        version => version.to_string(),
+    };
+    Os {
+        id: id.to_string(),
+        id_like: Vec::new(),
+        name,
+        version,
+    }
+}
+
+/// Parses the `KEY=value` lines of os-release, see `man os-release`
+#[cfg(any(target_os = "linux", test))]
+fn parse_os_release(os_release: &str) -> Os {
+    let value = |key: &str| {
+        os_release.lines().find_map(|line| {
+            let (name, value) = line.trim().split_once('=')?;
+            if name == key {
+                Some(unquote(value))
+            } else {
+                None
+            }
+        })
+    };
+    Os {
+        id: value("ID").unwrap_or_else(|| "linux".to_string()),
+        id_like: value("ID_LIKE")
+            .map(|ids| ids.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default(),
+        name: value("NAME").unwrap_or_else(|| "Linux".to_string()),
+        version: value("VERSION_ID").unwrap_or_default(),
+    }
+}
+
+/// Removes the shell-like quotes and escapes of an os-release value
+#[cfg(any(target_os = "linux", test))]
+fn unquote(value: &str) -> String {
+    let quoted = value.len() >= 2
+        && (value.starts_with('"') && value.ends_with('"')
+            || value.starts_with('\'') && value.ends_with('\''));
+    if !quoted {
+        return value.to_string();
+    }
+    let mut unquoted = String::new();
+    let mut chars = value[1..value.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            _ => unquoted.push(c),
+        }
+    }
+    unquoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::ModuleRenderer;
+
+    #[test]
+    fn parses_os_release() {
+        let os_release = r#"NAME="Pop!_OS"
+VERSION="22.04 LTS"
+ID=pop
+ID_LIKE="ubuntu debian"
+PRETTY_NAME="Pop!_OS 22.04 LTS"
+VERSION_ID='22.04'
+HOME_URL="https://pop.system76.com"
+"#;
+        let expected = Os {
+            id: "pop".to_string(),
+            id_like: vec!["ubuntu".to_string(), "debian".to_string()],
+            name: "Pop!_OS".to_string(),
+            version: "22.04".to_string(),
+        };
+        assert_eq!(expected, parse_os_release(os_release));
+    }
+
+    #[test]
+    fn unquotes_values() {
+        assert_eq!("plain", unquote("plain"));
+        assert_eq!(r#"say "hi""#, unquote(r#""say \"hi\"""#));
+        assert_eq!("$HOME", unquote(r#""\$HOME""#));
+        assert_eq!("\"", unquote("\""));
+    }
+
+    #[test]
+    fn picks_symbols() {
+        let os = |id: &str, id_like: &[&str]| Os {
+            id: id.to_string(),
+            id_like: id_like.iter().map(|id| id.to_string()).collect(),
+            name: String::new(),
+            version: String::new(),
+        };
+        let default = OsConfig::default();
+        assert_eq!("🎯 ", get_symbol(&os("ubuntu", &[]), &default));
+        assert_eq!("🌀 ", get_symbol(&os("kali", &["debian"]), &default));
+
+        let mut config = OsConfig::default();
+        config.symbols.insert("debian".to_string(), "deb ");
+        assert_eq!("deb ", get_symbol(&os("kali", &["debian"]), &config));
+        assert_eq!("🎯 ", get_symbol(&os("ubuntu", &["debian"]), &config));
+        if cfg!(target_os = "linux") {
+            assert_eq!("🐧 ", get_symbol(&os("lfs", &[]), &default));
+        }
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let actual = ModuleRenderer::new("os").collect();
+        assert_eq!(None, actual);
+    }
+}
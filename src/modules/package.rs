@@ -3,6 +3,7 @@ use std::path::Path;
 use super::{Context, Module, RootModuleConfig};
 use crate::configs::package::PackageConfig;
 use crate::formatter::StringFormatter;
+use crate::segment::Segment;
 use crate::utils;
 
 use quick_xml::events::Event as QXEvent;
@@ -17,6 +18,11 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     let mut module = context.new_module("package");
     let config: PackageConfig = PackageConfig::try_load(module.config);
     let module_version = get_package_version(&context.current_dir, &config)?;
+    let registry_link = if context.config.get_root_config().links {
+        get_registry_link(&context.current_dir)
+    } else {
+        None
+    };
 
     let parsed = StringFormatter::new(config.format).and_then(|formatter| {
         formatter
@@ -28,6 +34,14 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
                 "style" => Some(Ok(config.style)),
                 _ => None,
             })
+            .map_variables_to_segments(|variable| match variable {
+                "version" => registry_link.as_ref().map(|url| {
+                    Ok(vec![
+                        Segment::new(None, module_version.as_str()).with_link(url.as_str())
+                    ])
+                }),
+                _ => None,
+            })
             .map(|variable| match variable {
                 "version" => Some(Ok(&module_version)),
                 _ => None,
@@ -203,6 +217,40 @@ fn get_package_version(base_dir: &Path, config: &PackageConfig) -> Option<String
     }
 }
 
+/// Returns the URL of the package's page in its registry, if the package is published to one
+fn get_registry_link(base_dir: &Path) -> Option<String> {
+    if let Ok(cargo_toml) = utils::read_file(base_dir.join("Cargo.toml")) {
+        let cargo_toml: toml::Value = toml::from_str(&cargo_toml).ok()?;
+        let package = cargo_toml.get("package")?;
+        if package.get("publish").and_then(toml::Value::as_bool) == Some(false) {
+            return None;
+        }
+        let name = package.get("name")?.as_str()?;
+        Some(format!("https://crates.io/crates/{}", name))
+    } else if let Ok(package_json) = utils::read_file(base_dir.join("package.json")) {
+        let package_json: json::Value = json::from_str(&package_json).ok()?;
+        if package_json.get("private").and_then(json::Value::as_bool) == Some(true) {
+            return None;
+        }
+        let name = package_json.get("name")?.as_str()?;
+        Some(format!("https://www.npmjs.com/package/{}", name))
+    } else if let Ok(poetry_toml) = utils::read_file(base_dir.join("pyproject.toml")) {
+        let poetry_toml: toml::Value = toml::from_str(&poetry_toml).ok()?;
+        let name = poetry_toml
+            .get("tool")?
+            .get("poetry")?
+            .get("name")?
+            .as_str()?;
+        Some(format!("https://pypi.org/project/{}", name))
+    } else if let Ok(composer_json) = utils::read_file(base_dir.join("composer.json")) {
+        let composer_json: json::Value = json::from_str(&composer_json).ok()?;
+        let name = composer_json.get("name")?.as_str()?;
+        Some(format!("https://packagist.org/packages/{}", name))
+    } else {
+        None
+    }
+}
+
 fn format_version(version: &str) -> String {
     let cleaned = version.replace('"', "").trim().to_string();
     if cleaned.starts_with('v') {
@@ -237,6 +285,62 @@ mod tests {
         assert_eq!(format_version("\"v0.1.0\""), "v0.1.0");
     }
 
+    #[test]
+    fn test_cargo_registry_link() -> io::Result<()> {
+        let config_name = "Cargo.toml";
+        let config_content = toml::toml! {
+            [package]
+            name = "starship"
+            version = "0.1.0"
+        }
+        .to_string();
+
+        let project_dir = create_project_dir()?;
+        fill_config(&project_dir, config_name, Some(&config_content))?;
+
+        let actual = ModuleRenderer::new("package")
+            .path(project_dir.path())
+            .config(toml::toml! {
+                links = true
+                [package]
+                format = "$version"
+            })
+            .collect();
+        let expected = Some(String::from(
+            "\x1b]8;;https://crates.io/crates/starship\x07v0.1.0\x1b]8;;\x07",
+        ));
+
+        assert_eq!(actual, expected);
+        project_dir.close()
+    }
+
+    #[test]
+    fn test_unpublished_cargo_has_no_link() -> io::Result<()> {
+        let config_name = "Cargo.toml";
+        let config_content = toml::toml! {
+            [package]
+            name = "starship"
+            version = "0.1.0"
+            publish = false
+        }
+        .to_string();
+
+        let project_dir = create_project_dir()?;
+        fill_config(&project_dir, config_name, Some(&config_content))?;
+
+        let actual = ModuleRenderer::new("package")
+            .path(project_dir.path())
+            .config(toml::toml! {
+                links = true
+                [package]
+                format = "$version"
+            })
+            .collect();
+
+        assert_eq!(actual, Some(String::from("v0.1.0")));
+        project_dir.close()
+    }
+
     #[test]
     fn test_extract_cargo_version() -> io::Result<()> {
         let config_name = "Cargo.toml";
@@ -0,0 +1,488 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::thread;
+use std::time::Instant;
+
+use indexmap::IndexMap;
+use process_control::{ChildExt, Timeout};
+use serde::{Deserialize, Serialize};
+
+use super::{wasm_plugin, Context, Module, RootModuleConfig};
+
+use crate::config::parse_style_string;
+use crate::configs::plugin::PluginConfig;
+use crate::context::{
+    measure_commands, record_command, with_format_variables, with_overrides, ModuleOverrides,
+};
+use crate::formatter::StringFormatter;
+use crate::segment::Segment;
+
+/// The version of the plugin protocol, increased on incompatible changes
+const PROTOCOL_VERSION: u32 = 1;
+
+/// What a plugin is told about the prompt, as JSON on its stdin
+#[derive(Serialize)]
+struct PluginRequest<'a> {
+    version: u32,
+    name: &'a str,
+    cwd: &'a Path,
+    logical_dir: &'a Path,
+    shell: String,
+    status: Option<i64>,
+    /// The environment variables in the `env` option which are set
+    env: HashMap<&'a str, String>,
+    options: &'a IndexMap<String, toml::Value>,
+}
+
+/// What a plugin prints, as JSON on its stdout
+#[derive(Deserialize)]
+struct PluginResponse {
+    #[serde(default)]
+    segments: Vec<PluginSegment>,
+}
+
+#[derive(Deserialize)]
+struct PluginSegment {
+    text: String,
+    style: Option<String>,
+}
+
+/// Creates a module with the output of an external plugin
+///
+/// The plugin is an executable, `starship-module-<name>` in the `PATH` unless the
+/// "command" option sets another one and its arguments. It is given the context of the
+/// prompt as JSON on stdin, and prints the segments to show as JSON on stdout.
+///
+/// With the `wasm` feature, a WebAssembly plugin is run instead if the "wasm" option is
+/// set or `<name>.wasm` is in the plugins directory, see `wasm_plugin`.
+pub fn module<'a>(name: &str, context: &'a Context) -> Option<Module<'a>> {
+    let variables = super::format_variables(name, context.config.get_plugin_config(name), context);
+    let (module, command_duration, details) = measure_commands(context.explain, || {
+        with_format_variables(variables, || render(name, context))
+    });
+    module.map(|mut module| {
+        module.command_duration = command_duration;
+        module.details = details;
+        module
+    })
+}
+
+fn render<'a>(name: &str, context: &'a Context) -> Option<Module<'a>> {
+    let guard_name = format!("plugins.{}", name);
+    if let Some(guard) = &context.slow_modules {
+        if guard.is_skipped(&guard_name) {
+            log::debug!("Skipping slow module {:?}", guard_name);
+            return None;
+        }
+    }
+    let start = Instant::now();
+    let toml_config = context.config.get_plugin_config(name).expect(
+        "modules::plugin::module should only be called after ensuring that the plugin exists",
+    );
+    let config = PluginConfig::load(toml_config);
+    if let Some(results) = &context.async_results {
+        if results.is_async(&guard_name) {
+            return results.get(&guard_name).map(|segments| {
+                let mut module = Module::new(name, config.description, Some(toml_config));
+                module.set_segments(segments);
+                module
+            });
+        }
+    }
+
+    let overrides = ModuleOverrides::from_config(Some(toml_config));
+    let output = with_overrides(overrides, || run_plugin(name, &config, context))?;
+
+    let mut module = Module::new(name, config.description, Some(toml_config));
+    let parsed = StringFormatter::new(config.format).and_then(|formatter| {
+        formatter
+            .map_style(|variable| match variable {
+                "style" => Some(Ok(config.style)),
+                _ => None,
+            })
+            .map_variables_to_segments(|variable| match variable {
+                "output" if !output.is_empty() => Some(Ok(output.clone())),
+                _ => None,
+            })
+            .parse(None)
+    });
+
+    match parsed {
+        Ok(segments) => module.set_segments(segments),
+        Err(error) => {
+            log::warn!("Error in plugin `{}`:\n{}", name, error);
+        }
+    };
+    let elapsed = start.elapsed();
+    log::trace!("Took {:?} to compute plugin {:?}", elapsed, name);
+    if let Some(metrics) = &context.metrics {
+        metrics.timing(&guard_name, elapsed);
+    }
+    if let Some(guard) = &context.slow_modules {
+        guard.record(&guard_name, elapsed);
+    }
+    module.duration = elapsed;
+    Some(module)
+}
+
+/// Runs the plugin and returns its segments, or `None` if it failed or printed invalid JSON
+fn run_plugin(name: &str, config: &PluginConfig, context: &Context) -> Option<Vec<Segment>> {
+    let request = PluginRequest {
+        version: PROTOCOL_VERSION,
+        name,
+        cwd: &context.current_dir,
+        logical_dir: &context.logical_dir,
+        shell: format!("{:?}", context.shell).to_lowercase(),
+        status: context
+            .properties
+            .get("status_code")
+            .and_then(|status| status.parse().ok()),
+        env: config
+            .env
+            .iter()
+            .filter_map(|key| Some((*key, context.get_env(key)?)))
+            .collect(),
+        options: &config.options,
+    };
+
+    let output = match get_wasm_path(name, config, context) {
+        Some(path) => run_wasm_plugin(name, &path, &request, context)?,
+        None => run_command_plugin(name, config, &request, context)?,
+    };
+    let response: PluginResponse = match serde_json::from_slice(&output) {
+        Ok(response) => response,
+        Err(error) => {
+            log::warn!("The plugin {:?} printed invalid JSON: {}", name, error);
+            return None;
+        }
+    };
+    Some(
+        response
+            .segments
+            .into_iter()
+            .map(|segment| {
+                let style = segment.style.as_deref().and_then(parse_style_string);
+                Segment::new(style, segment.text)
+            })
+            .collect(),
+    )
+}
+
+/// The WebAssembly module of the plugin, from the "wasm" option, relative to the plugins
+/// directory, or else `<name>.wasm` in the plugins directory if no command is set. Without
+/// the `wasm` feature, the command is run instead of a `<name>.wasm` which can't be.
+fn get_wasm_path(name: &str, config: &PluginConfig, context: &Context) -> Option<PathBuf> {
+    let plugins_dir = get_plugins_dir(context)?;
+    if !config.wasm.is_empty() {
+        return Some(plugins_dir.join(config.wasm));
+    }
+    let path = plugins_dir.join(format!("{}.wasm", name));
+    if cfg!(feature = "wasm") && config.command.0.is_empty() && path.is_file() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// The `starship/plugins` directory next to the config file, e.g. `~/.config/starship/plugins`
+fn get_plugins_dir(context: &Context) -> Option<PathBuf> {
+    let config_path = match context.get_env_os("STARSHIP_CONFIG") {
+        Some(config_path) => PathBuf::from(config_path),
+        None => context.get_home()?.join(".config/starship.toml"),
+    };
+    Some(config_path.parent()?.join("starship").join("plugins"))
+}
+
+/// Runs the WebAssembly plugin and returns its response, or `None` if it failed
+fn run_wasm_plugin(
+    name: &str,
+    path: &Path,
+    request: &PluginRequest,
+    context: &Context,
+) -> Option<Vec<u8>> {
+    let input = serde_json::to_vec(request).ok()?;
+    let start = Instant::now();
+    let cache_dir = context.get_cache_dir();
+    let output = wasm_plugin::run(
+        path,
+        &input,
+        context.command_timeout(),
+        cache_dir.as_deref(),
+    );
+    record_command(start.elapsed(), || path.to_string_lossy().to_string());
+    match output {
+        Ok(output) => Some(output),
+        Err(error) => {
+            log::warn!("The plugin {:?} failed: {}", name, error);
+            None
+        }
+    }
+}
+
+/// Runs the plugin as a process and returns its stdout, or `None` if it failed
+fn run_command_plugin(
+    name: &str,
+    config: &PluginConfig,
+    request: &PluginRequest,
+    context: &Context,
+) -> Option<Vec<u8>> {
+    let default_command = format!("starship-module-{}", name);
+    let (command, args) = match config.command.0.split_first() {
+        Some((command, args)) => (*command, args),
+        None => (default_command.as_str(), &[] as &[&str]),
+    };
+    let path = match context.which(command) {
+        Ok(path) => path,
+        Err(error) => {
+            log::warn!("Unable to find the plugin {:?}: {}", command, error);
+            return None;
+        }
+    };
+
+    let start = Instant::now();
+    let mut child = match context
+        .command(&path)
+        .args(args)
+        .current_dir(&context.current_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(error) => {
+            log::warn!("Unable to run the plugin {:?}: {}", path, error);
+            return None;
+        }
+    };
+    // The context is written from a thread, since a plugin which doesn't read it would
+    // otherwise block the write past the timeout. Dropping stdin closes it, so plugins can
+    // read until the end. Plugins may exit without reading it, so failing to write is fine.
+    let mut stdin = child.stdin.take()?;
+    let input = serde_json::to_vec(request).ok()?;
+    let plugin_name = name.to_owned();
+    thread::spawn(move || {
+        if let Err(error) = stdin.write_all(&input) {
+            log::debug!(
+                "Unable to send the context to the plugin {:?}: {}",
+                plugin_name,
+                error
+            );
+        }
+    });
+
+    let output = child
+        .with_output_timeout(context.command_timeout())
+        .terminating()
+        .wait();
+    record_command(start.elapsed(), || [&[command], args].concat().join(" "));
+    let output = match output {
+        Ok(Some(output)) => output,
+        Ok(None) => {
+            log::warn!("The plugin {:?} timed out", name);
+            return None;
+        }
+        Err(error) => {
+            log::warn!("The plugin {:?} failed: {}", name, error);
+            return None;
+        }
+    };
+    if !output.status.success() {
+        log::warn!(
+            "The plugin {:?} exited with {}: {}",
+            name,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return None;
+    }
+    Some(output.stdout)
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod tests {
+    use super::*;
+    use crate::config::StarshipConfig;
+    use crate::context::Shell;
+    use ansi_term::Color;
+    use std::fs;
+    use std::io;
+    #[cfg(not(feature = "wasm"))]
+    use std::os::unix::fs::PermissionsExt;
+
+    fn render_plugin(script: &str, config: toml::Value) -> io::Result<Option<String>> {
+        let dir = tempfile::tempdir()?;
+        let plugin = dir.path().join("plugin.sh");
+        fs::write(&plugin, script)?;
+
+        let mut config = config;
+        let command = vec!["/bin/sh".to_string(), plugin.to_string_lossy().to_string()];
+        config["plugins"]["test"]
+            .as_table_mut()
+            .unwrap()
+            .insert("command".to_string(), command.into());
+        let mut context = Context::new_with_shell_and_path(
+            clap::ArgMatches::default(),
+            Shell::Zsh,
+            dir.path().to_path_buf(),
+            dir.path().to_path_buf(),
+        );
+        context.config = StarshipConfig {
+            config: Some(config),
+        };
+        context.properties.insert("status_code", "1".to_string());
+        context.env.insert("KUBECONFIG", "/tmp/kube".to_string());
+        let output = module("test", &context).map(|module| module.to_string());
+        dir.close()?;
+        Ok(output)
+    }
+
+    #[test]
+    fn renders_plugin_segments() -> io::Result<()> {
+        let script = r#"echo '{"segments": [{"text": "on "}, {"text": "prod", "style": "red"}]}'
+"#;
+        let config = toml::toml! {
+            [plugins.test]
+            format = "$output"
+        };
+        let actual = render_plugin(script, config)?;
+        let expected = Some(format!("on {}", Color::Red.paint("prod")));
+        assert_eq!(expected, actual);
+        Ok(())
+    }
+
+    #[test]
+    fn sends_context_to_plugin() -> io::Result<()> {
+        // Echoes the request back as the text of a segment
+        let script = r#"request=$(cat | sed 's/\\/\\\\/g; s/"/\\"/g')
+echo "{\"segments\": [{\"text\": \"$request\"}]}"
+"#;
+        let config = toml::toml! {
+            [plugins.test]
+            format = "$output"
+            env = ["KUBECONFIG", "UNSET_VARIABLE"]
+            [plugins.test.options]
+            depth = 2
+        };
+        let actual = render_plugin(script, config)?.unwrap();
+        let request: serde_json::Value = serde_json::from_str(&actual).unwrap();
+        assert_eq!(1, request["version"]);
+        assert_eq!("test", request["name"]);
+        assert_eq!("zsh", request["shell"]);
+        assert_eq!(1, request["status"]);
+        assert_eq!(
+            serde_json::json!({ "KUBECONFIG": "/tmp/kube" }),
+            request["env"]
+        );
+        assert_eq!(2, request["options"]["depth"]);
+        Ok(())
+    }
+
+    #[test]
+    fn times_out_without_reading_the_context() -> io::Result<()> {
+        // The context is larger than a pipe buffer, so writing it would block
+        let mut config = toml::toml! {
+            [plugins.test.options]
+        };
+        config["plugins"]["test"]["options"]
+            .as_table_mut()
+            .unwrap()
+            .insert("padding".to_string(), "x".repeat(1 << 20).into());
+        let start = Instant::now();
+        assert_eq!(None, render_plugin("sleep 5", config)?);
+        assert!(start.elapsed().as_secs() < 4);
+        Ok(())
+    }
+
+    #[test]
+    fn hides_failing_plugin() -> io::Result<()> {
+        let config = toml::toml! {
+            [plugins.test]
+        };
+        assert_eq!(None, render_plugin("exit 1", config.clone())?);
+        assert_eq!(None, render_plugin("echo nope", config)?);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "wasm")]
+    fn renders_wasm_plugin() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let plugins_dir = dir.path().join("dotfiles/starship/plugins");
+        fs::create_dir_all(&plugins_dir)?;
+        fs::write(
+            plugins_dir.join("test.wat"),
+            r#"(module
+                (memory (export "memory") 1)
+                (data (i32.const 0) "{\"segments\": [{\"text\": \"wasm\"}]}")
+                (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+                (func (export "render") (param i32 i32) (result i64) (i64.const 32)))"#,
+        )?;
+
+        let mut context = Context::new_with_shell_and_path(
+            clap::ArgMatches::default(),
+            Shell::Zsh,
+            dir.path().to_path_buf(),
+            dir.path().to_path_buf(),
+        );
+        context.config = StarshipConfig {
+            config: Some(toml::toml! {
+                [plugins.test]
+                format = "$output"
+                wasm = "test.wat"
+            }),
+        };
+        context
+            .env
+            .insert("HOME", dir.path().to_string_lossy().to_string());
+        let config_path = dir.path().join("dotfiles/starship.toml");
+        context
+            .env
+            .insert("STARSHIP_CONFIG", config_path.to_string_lossy().to_string());
+        let actual = module("test", &context).map(|module| module.to_string());
+        assert_eq!(Some("wasm".to_string()), actual);
+        dir.close()
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn runs_command_without_wasm_feature() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let plugins_dir = dir.path().join(".config/starship/plugins");
+        fs::create_dir_all(&plugins_dir)?;
+        fs::write(plugins_dir.join("test.wasm"), "")?;
+        let bin_dir = dir.path().join("bin");
+        fs::create_dir_all(&bin_dir)?;
+        let plugin = bin_dir.join("starship-module-test");
+        fs::write(
+            &plugin,
+            "#!/bin/sh\necho '{\"segments\": [{\"text\": \"command\"}]}'\n",
+        )?;
+        fs::set_permissions(&plugin, fs::Permissions::from_mode(0o755))?;
+
+        let mut context = Context::new_with_shell_and_path(
+            clap::ArgMatches::default(),
+            Shell::Zsh,
+            dir.path().to_path_buf(),
+            dir.path().to_path_buf(),
+        );
+        context.config = StarshipConfig {
+            config: Some(toml::toml! {
+                [plugins.test]
+                format = "$output"
+            }),
+        };
+        context
+            .env
+            .insert("HOME", dir.path().to_string_lossy().to_string());
+        context
+            .env
+            .insert("PATH", bin_dir.to_string_lossy().to_string());
+        let actual = module("test", &context).map(|module| module.to_string());
+        assert_eq!(Some("command".to_string()), actual);
+        dir.close()
+    }
+}
@@ -0,0 +1,165 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
+use super::utils::fetch_cache::FetchCache;
+use super::{Context, Module, RootModuleConfig};
+
+use crate::configs::public_ip::PublicIpConfig;
+use crate::formatter::StringFormatter;
+
+const CACHE_NAME: &str = "public_ip";
+
+/// Creates a module with the public IP address of the network, as seen by the `url` service
+///
+/// The address is never fetched while rendering the prompt: it is read from a cache which a
+/// background process refreshes once it is older than `ttl` seconds.
+pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
+    let mut module = context.new_module("public_ip");
+    let config: PublicIpConfig = PublicIpConfig::try_load(module.config);
+
+    // As we default to disabled=true, we have to check here after loading our config module,
+    // before it was only checking against whatever is in the config starship.toml
+    if config.disabled {
+        return None;
+    }
+
+    let cache = FetchCache::new(context, CACHE_NAME)?;
+    let cached = cache.read();
+    if !matches!(cached, Some((_, age)) if age < Duration::from_secs(config.ttl)) {
+        cache.spawn_fetch(context, "refresh-public-ip");
+    }
+    let ip: IpAddr = cached?.0.trim().parse().ok()?;
+
+    let parsed = StringFormatter::new(config.format).and_then(|formatter| {
+        formatter
+            .map_meta(|variable, _| match variable {
+                "symbol" => Some(config.symbol),
+                _ => None,
+            })
+            .map_style(|variable| match variable {
+                "style" => Some(Ok(config.style)),
+                _ => None,
+            })
+            .map(|variable| match variable {
+                "ip" => Some(Ok(ip.to_string())),
+                _ => None,
+            })
+            .parse(None)
+    });
+
+    module.set_segments(match parsed {
+        Ok(segments) => segments,
+        Err(error) => {
+            log::warn!("Error in module `public_ip`:\n{}", error);
+            return None;
+        }
+    });
+
+    Some(module)
+}
+
+/// Fetches the public IP address and saves it for the module to show.
+///
+/// This is run by the background process spawned by the module when the cached address
+/// has expired.
+pub fn refresh_cache(context: &Context) {
+    let config = PublicIpConfig::try_load(context.config.get_module_config("public_ip"));
+    let cache = match FetchCache::new(context, CACHE_NAME) {
+        Some(cache) => cache,
+        None => return,
+    };
+
+    let ip = match fetch_ip(config.url, context.command_timeout()) {
+        Some(ip) => ip,
+        None => return,
+    };
+    if let Err(error) = cache.write(&ip.to_string()) {
+        log::warn!("Unable to write public IP cache: {}", error);
+    }
+    cache.release();
+}
+
+#[cfg(feature = "http")]
+fn fetch_ip(url: &str, timeout: Duration) -> Option<IpAddr> {
+    let response = attohttpc::get(url)
+        .timeout(timeout)
+        .send()
+        .and_then(|response| response.error_for_status()?.text());
+    match response {
+        Ok(text) => parse_ip(&text, url),
+        Err(error) => {
+            log::warn!("Unable to fetch the public IP from {:?}: {}", url, error);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch_ip(_url: &str, _timeout: Duration) -> Option<IpAddr> {
+    log::warn!("Fetching the public IP needs starship to be built with the `http` feature");
+    None
+}
+
+fn parse_ip(text: &str, url: &str) -> Option<IpAddr> {
+    match text.trim().parse() {
+        Ok(ip) => Some(ip),
+        Err(_) => {
+            log::warn!("The public IP service {:?} answered {:?}", url, text);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::ModuleRenderer;
+    use ansi_term::Color;
+    use std::fs;
+    use std::io;
+
+    fn render(cache_dir: &std::path::Path) -> Option<String> {
+        ModuleRenderer::new("public_ip")
+            .config(toml::toml! {
+                [public_ip]
+                disabled = false
+            })
+            .env("STARSHIP_CACHE", cache_dir.to_string_lossy())
+            .collect()
+    }
+
+    #[test]
+    fn shows_cached_ip() -> io::Result<()> {
+        let cache_dir = tempfile::tempdir()?;
+        fs::write(cache_dir.path().join("public_ip"), "203.0.113.7\n")?;
+
+        let expected = Some(format!("{} ", Color::Purple.bold().paint("🌐 203.0.113.7")));
+        assert_eq!(expected, render(cache_dir.path()));
+        // The cache is fresh, so nothing is fetched
+        assert!(!cache_dir.path().join("public_ip.lock").exists());
+        cache_dir.close()
+    }
+
+    #[test]
+    fn fetches_missing_ip_in_background() -> io::Result<()> {
+        let cache_dir = tempfile::tempdir()?;
+
+        assert_eq!(None, render(cache_dir.path()));
+        assert!(cache_dir.path().join("public_ip.lock").exists());
+        cache_dir.close()
+    }
+
+    #[test]
+    fn parses_service_answers() {
+        let url = "https://example.com";
+        assert_eq!(Some([192, 0, 2, 1].into()), parse_ip("192.0.2.1\n", url));
+        assert_eq!("2001:db8::1".parse().ok(), parse_ip("  2001:db8::1 ", url));
+        assert_eq!(None, parse_ip("<html>rate limited</html>", url));
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let actual = ModuleRenderer::new("public_ip").collect();
+        assert_eq!(None, actual);
+    }
+}
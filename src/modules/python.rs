@@ -1,10 +1,12 @@
 use ini::Ini;
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 use super::{Context, Module, RootModuleConfig};
 use crate::configs::python::PythonConfig;
 use crate::formatter::StringFormatter;
 use crate::formatter::VersionFormatter;
+use crate::utils;
 
 /// Creates a module with the current Python version and, if active, virtual environment.
 pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
@@ -47,6 +49,7 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
                     virtual_env.as_ref().map(|e| Ok(e.trim().to_string()))
                 }
                 "pyenv_prefix" => Some(Ok(pyenv_prefix.to_string())),
+                "requires" => get_required_version(context).map(Ok),
                 _ => None,
             })
             .parse(None)
@@ -63,11 +66,162 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     Some(module)
 }
 
+/// Gets the version from the first source in `prefer` which has one, only running python
+/// for the `system` source
 fn get_python_version(context: &Context, config: &PythonConfig) -> Option<String> {
     if config.pyenv_version_name {
-        let version_name = context.exec_cmd("pyenv", &["version-name"])?.stdout;
-        return Some(version_name.trim().to_string());
+        return get_pyenv_version_names(context)
+            .and_then(|names| names.into_iter().next())
+            .or_else(|| {
+                let version_name = context.exec_cmd("pyenv", &["version-name"])?.stdout;
+                Some(version_name.trim().to_string())
+            });
     };
+    config.prefer.iter().find_map(|source| match *source {
+        "pyenv" => match get_pyenv_version(context)? {
+            PyenvVersion::Version(version) => Some(format_version(&version, config.version_format)),
+            PyenvVersion::System => get_system_version(context, config),
+        },
+        "venv" => {
+            get_venv_version(context).map(|version| format_version(&version, config.version_format))
+        }
+        "system" => get_system_version(context, config),
+        _ => {
+            log::warn!(
+                "Unknown python version source {:?}, expected \"pyenv\", \"venv\" or \"system\"",
+                source
+            );
+            None
+        }
+    })
+}
+
+/// A python selected by pyenv
+#[derive(Debug, PartialEq)]
+enum PyenvVersion {
+    Version(String),
+    /// The python in the `PATH` outside of pyenv
+    System,
+}
+
+/// The version pyenv selects, without running it: the first of the selected names which
+/// is `system` or a version, skipping the names of virtualenvs. A version like `3.11` is
+/// resolved to the latest installed `3.11.x`, as pyenv does.
+fn get_pyenv_version(context: &Context) -> Option<PyenvVersion> {
+    let versions_dir = get_pyenv_root(context).map(|root| root.join("versions"));
+    get_pyenv_version_names(context)?
+        .into_iter()
+        .find_map(|name| match name.as_str() {
+            "system" => Some(PyenvVersion::System),
+            name if name.starts_with(|c: char| c.is_ascii_digit()) => {
+                let version = versions_dir
+                    .as_deref()
+                    .and_then(|dir| resolve_pyenv_prefix(dir, name))
+                    .unwrap_or_else(|| name.to_string());
+                Some(PyenvVersion::Version(version))
+            }
+            _ => None,
+        })
+}
+
+/// The names pyenv selects, from the first of `$PYENV_VERSION`, the closest
+/// `.python-version` file and the global `$PYENV_ROOT/version` file which sets any
+fn get_pyenv_version_names(context: &Context) -> Option<Vec<String>> {
+    if let Some(versions) = context.get_env("PYENV_VERSION") {
+        let names: Vec<String> = versions
+            .split(':')
+            .filter(|name| !name.is_empty())
+            .map(String::from)
+            .collect();
+        return Some(names).filter(|names| !names.is_empty());
+    }
+    let local_file = context
+        .current_dir
+        .ancestors()
+        .map(|dir| dir.join(".python-version"))
+        .find(|file| file.is_file());
+    let global_file = get_pyenv_root(context).map(|root| root.join("version"));
+    [local_file, global_file]
+        .iter()
+        .flatten()
+        .find_map(|file| read_pyenv_version_file(file))
+}
+
+/// The names in a pyenv version file, one per line or separated by whitespace, skipping
+/// comments
+fn read_pyenv_version_file(file: &Path) -> Option<Vec<String>> {
+    let contents = utils::read_file(file).ok()?;
+    let names: Vec<String> = contents
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .flat_map(str::split_whitespace)
+        .map(String::from)
+        .collect();
+    Some(names).filter(|names| !names.is_empty())
+}
+
+fn get_pyenv_root(context: &Context) -> Option<PathBuf> {
+    context
+        .get_env("PYENV_ROOT")
+        .map(PathBuf::from)
+        .or_else(|| Some(context.get_home()?.join(".pyenv")))
+}
+
+/// The latest installed version starting with `prefix`, e.g. `3.11.4` for `3.11`, or
+/// `prefix` itself if it is installed
+fn resolve_pyenv_prefix(versions_dir: &Path, prefix: &str) -> Option<String> {
+    if versions_dir.join(prefix).is_dir() {
+        return Some(prefix.to_string());
+    }
+    let parse = |version: &str| -> Option<Vec<u64>> {
+        version.split('.').map(|part| part.parse().ok()).collect()
+    };
+    fs::read_dir(versions_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter_map(|name| {
+            let patch = name.strip_prefix(prefix)?.strip_prefix('.')?;
+            // Skip prereleases like `3.11.0rc1` and builds like `3.11.4-debug`
+            let patch = parse(patch)?;
+            Some((patch, name))
+        })
+        .max()
+        .map(|(_, name)| name)
+}
+
+/// The version of the active virtual environment from its `pyvenv.cfg`, where `venv`
+/// writes e.g. `version = 3.8.6` and `virtualenv` writes `version_info = 3.8.6.final.0`
+fn get_venv_version(context: &Context) -> Option<String> {
+    let venv = context.get_env("VIRTUAL_ENV")?;
+    let cfg = Ini::load_from_file(Path::new(&venv).join("pyvenv.cfg")).ok()?;
+    let section = cfg.general_section();
+    let version = section
+        .get("version")
+        .or_else(|| section.get("version_info"))?;
+    let version: Vec<&str> = version.trim().split('.').take(3).collect();
+    Some(version.join("."))
+}
+
+/// The python versions the project supports, from `requires-python` in `pyproject.toml`
+/// or the `python` dependency of Poetry
+fn get_required_version(context: &Context) -> Option<String> {
+    let contents = utils::read_file(context.current_dir.join("pyproject.toml")).ok()?;
+    let pyproject: toml::Value = toml::from_str(&contents).ok()?;
+    pyproject
+        .get("project")
+        .and_then(|project| project.get("requires-python"))
+        .or_else(|| {
+            pyproject
+                .get("tool")?
+                .get("poetry")?
+                .get("dependencies")?
+                .get("python")
+        })?
+        .as_str()
+        .map(String::from)
+}
+
+fn get_system_version(context: &Context, config: &PythonConfig) -> Option<String> {
     let version = config
         .python_binary
         .0
@@ -91,11 +245,15 @@ fn format_python_version(python_version: &str, version_format: &str) -> Option<S
         // get down to "3.8.6"
         .nth(1)?;
 
+    Some(format_version(version, version_format))
+}
+
+fn format_version(version: &str, version_format: &str) -> String {
     match VersionFormatter::format_version(version, version_format) {
-        Ok(formatted) => Some(formatted),
+        Ok(formatted) => formatted,
         Err(error) => {
             log::warn!("Error formatting `python` version:\n{}", error);
-            Some(format!("v{}", version))
+            format!("v{}", version)
         }
     }
 }
@@ -122,10 +280,15 @@ mod tests {
     use super::*;
     use crate::test::ModuleRenderer;
     use ansi_term::Color;
-    use std::fs::{create_dir_all, File};
+    use std::fs::{self, create_dir_all, File};
     use std::io;
     use std::io::Write;
 
+    /// Renders the module without the pyenv installation of the machine running the tests
+    fn python_renderer<'a>() -> ModuleRenderer<'a> {
+        ModuleRenderer::new("python").env("PYENV_ROOT", "/nonexistent/pyenv")
+    }
+
     #[test]
     fn test_format_python_version() {
         assert_eq!(
@@ -177,7 +340,7 @@ Python 3.7.9 (7e6e2bb30ac5fbdbd443619cae28c51d5c162a02, Nov 24 2020, 10:03:59)
     #[test]
     fn folder_without_python_files() -> io::Result<()> {
         let dir = tempfile::tempdir()?;
-        let actual = ModuleRenderer::new("python").path(dir.path()).collect();
+        let actual = python_renderer().path(dir.path()).collect();
         let expected = None;
         assert_eq!(expected, actual);
 
@@ -290,10 +453,7 @@ Python 3.7.9 (7e6e2bb30ac5fbdbd443619cae28c51d5c162a02, Nov 24 2020, 10:03:59)
             [python]
             detect_extensions = []
         };
-        let actual = ModuleRenderer::new("python")
-            .path(dir.path())
-            .config(config)
-            .collect();
+        let actual = python_renderer().path(dir.path()).config(config).collect();
         assert_eq!(expected, actual);
         dir.close()
     }
@@ -341,7 +501,7 @@ Python 3.7.9 (7e6e2bb30ac5fbdbd443619cae28c51d5c162a02, Nov 24 2020, 10:03:59)
     fn with_virtual_env() -> io::Result<()> {
         let dir = tempfile::tempdir()?;
         File::create(dir.path().join("main.py"))?.sync_all()?;
-        let actual = ModuleRenderer::new("python")
+        let actual = python_renderer()
             .path(dir.path())
             .env("VIRTUAL_ENV", "/foo/bar/my_venv")
             .collect();
@@ -359,7 +519,7 @@ Python 3.7.9 (7e6e2bb30ac5fbdbd443619cae28c51d5c162a02, Nov 24 2020, 10:03:59)
     fn with_active_venv() -> io::Result<()> {
         let dir = tempfile::tempdir()?;
 
-        let actual = ModuleRenderer::new("python")
+        let actual = python_renderer()
             .path(dir.path())
             .env("VIRTUAL_ENV", "/foo/bar/my_venv")
             .collect();
@@ -386,7 +546,7 @@ prompt = 'foo'
         )?;
         venv_cfg.sync_all()?;
 
-        let actual = ModuleRenderer::new("python")
+        let actual = python_renderer()
             .path(dir.path())
             .env("VIRTUAL_ENV", dir.path().join("my_venv").to_str().unwrap())
             .collect();
@@ -413,7 +573,7 @@ prompt = '(foo)'
         )?;
         venv_cfg.sync_all()?;
 
-        let actual = ModuleRenderer::new("python")
+        let actual = python_renderer()
             .path(dir.path())
             .env("VIRTUAL_ENV", dir.path().join("my_venv").to_str().unwrap())
             .collect();
@@ -427,16 +587,195 @@ prompt = '(foo)'
         dir.close()
     }
 
+    #[test]
+    fn version_from_python_version_file() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(
+            dir.path().join(".python-version"),
+            "# pinned\n3.11.4\n3.10.12\n",
+        )?;
+        create_dir_all(dir.path().join("src"))?;
+        File::create(dir.path().join("src/main.py"))?.sync_all()?;
+
+        let actual = python_renderer().path(dir.path().join("src")).collect();
+        let expected = Some(format!("via {}", Color::Yellow.bold().paint("🐍 v3.11.4 ")));
+        assert_eq!(expected, actual);
+
+        let actual = python_renderer()
+            .path(dir.path())
+            .env("PYENV_VERSION", "3.9.1:3.8.0")
+            .collect();
+        let expected = Some(format!("via {}", Color::Yellow.bold().paint("🐍 v3.9.1 ")));
+        assert_eq!(expected, actual);
+
+        let actual = python_renderer()
+            .path(dir.path())
+            .config(toml::toml! {
+                [python]
+                prefer = ["system"]
+            })
+            .collect();
+        let expected = Some(format!("via {}", Color::Yellow.bold().paint("🐍 v3.8.0 ")));
+        assert_eq!(expected, actual);
+        dir.close()
+    }
+
+    #[test]
+    fn pyenv_system_falls_through_to_system_version() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join(".python-version"), "system\n")?;
+
+        let actual = python_renderer()
+            .path(dir.path())
+            .env("PYENV_ROOT", dir.path().join("pyenv").to_str().unwrap())
+            .config(toml::toml! {
+                [python]
+                prefer = ["pyenv"]
+            })
+            .collect();
+        let expected = Some(format!("via {}", Color::Yellow.bold().paint("🐍 v3.8.0 ")));
+        assert_eq!(expected, actual);
+        dir.close()
+    }
+
+    #[test]
+    fn pyenv_skips_virtualenv_names() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join(".python-version"), "my-env 3.10.12\n")?;
+
+        let renderer = || {
+            python_renderer()
+                .path(dir.path())
+                .env("PYENV_ROOT", dir.path().join("pyenv").to_str().unwrap())
+        };
+        let actual = renderer()
+            .config(toml::toml! {
+                [python]
+                prefer = ["pyenv"]
+            })
+            .collect();
+        let expected = Some(format!(
+            "via {}",
+            Color::Yellow.bold().paint("🐍 v3.10.12 ")
+        ));
+        assert_eq!(expected, actual);
+
+        // The name of the virtualenv is still shown as the pyenv version name
+        let actual = renderer()
+            .config(toml::toml! {
+                [python]
+                pyenv_version_name = true
+            })
+            .collect();
+        let expected = Some(format!(
+            "via {}",
+            Color::Yellow.bold().paint("🐍 pyenv my-env ")
+        ));
+        assert_eq!(expected, actual);
+        dir.close()
+    }
+
+    #[test]
+    fn pyenv_global_version_file() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let pyenv_root = dir.path().join("pyenv");
+        create_dir_all(&pyenv_root)?;
+        fs::write(pyenv_root.join("version"), "3.9.7\n")?;
+        File::create(dir.path().join("main.py"))?.sync_all()?;
+
+        let actual = python_renderer()
+            .path(dir.path())
+            .env("PYENV_ROOT", pyenv_root.to_str().unwrap())
+            .collect();
+        let expected = Some(format!("via {}", Color::Yellow.bold().paint("🐍 v3.9.7 ")));
+        assert_eq!(expected, actual);
+        dir.close()
+    }
+
+    #[test]
+    fn pyenv_resolves_version_prefix() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let pyenv_root = dir.path().join("pyenv");
+        for version in &["3.11.2", "3.11.10", "3.11.11rc1", "3.12.0"] {
+            create_dir_all(pyenv_root.join("versions").join(version))?;
+        }
+        fs::write(dir.path().join(".python-version"), "3.11\n")?;
+
+        let actual = python_renderer()
+            .path(dir.path())
+            .env("PYENV_ROOT", pyenv_root.to_str().unwrap())
+            .collect();
+        let expected = Some(format!(
+            "via {}",
+            Color::Yellow.bold().paint("🐍 v3.11.10 ")
+        ));
+        assert_eq!(expected, actual);
+        dir.close()
+    }
+
+    #[test]
+    fn version_from_venv_config() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        create_dir_all(dir.path().join("my_venv"))?;
+        fs::write(
+            dir.path().join("my_venv").join("pyvenv.cfg"),
+            "home = /usr/bin\nversion_info = 3.10.2.final.0\n",
+        )?;
+        fs::write(dir.path().join(".python-version"), "3.11.4\n")?;
+        let venv = dir.path().join("my_venv");
+
+        let actual = python_renderer()
+            .path(dir.path())
+            .env("VIRTUAL_ENV", venv.to_str().unwrap())
+            .collect();
+        let expected = Some(format!(
+            "via {}",
+            Color::Yellow.bold().paint("🐍 v3.10.2 (my_venv) ")
+        ));
+        assert_eq!(expected, actual);
+
+        let actual = python_renderer()
+            .path(dir.path())
+            .env("VIRTUAL_ENV", venv.to_str().unwrap())
+            .config(toml::toml! {
+                [python]
+                prefer = ["pyenv", "venv", "system"]
+            })
+            .collect();
+        let expected = Some(format!(
+            "via {}",
+            Color::Yellow.bold().paint("🐍 v3.11.4 (my_venv) ")
+        ));
+        assert_eq!(expected, actual);
+        dir.close()
+    }
+
+    #[test]
+    fn required_version_from_pyproject() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(
+            dir.path().join("pyproject.toml"),
+            "[project]\nname = \"rocket\"\nrequires-python = \">=3.9\"\n",
+        )?;
+
+        let actual = python_renderer()
+            .path(dir.path())
+            .config(toml::toml! {
+                [python]
+                format = "$version( needs $requires)"
+            })
+            .collect();
+        assert_eq!(Some("v3.8.0 needs >=3.9".to_string()), actual);
+        dir.close()
+    }
+
     fn check_python2_renders(dir: &tempfile::TempDir, starship_config: Option<toml::Value>) {
         let config = starship_config.unwrap_or(toml::toml! {
             [python]
             python_binary = "python2"
         });
 
-        let actual = ModuleRenderer::new("python")
-            .path(dir.path())
-            .config(config)
-            .collect();
+        let actual = python_renderer().path(dir.path()).config(config).collect();
 
         let expected = Some(format!("via {}", Color::Yellow.bold().paint("🐍 v2.7.17 ")));
         assert_eq!(expected, actual);
@@ -448,10 +787,7 @@ prompt = '(foo)'
              python_binary = "python3"
         });
 
-        let actual = ModuleRenderer::new("python")
-            .path(dir.path())
-            .config(config)
-            .collect();
+        let actual = python_renderer().path(dir.path()).config(config).collect();
 
         let expected = Some(format!("via {}", Color::Yellow.bold().paint("🐍 v3.8.0 ")));
         assert_eq!(expected, actual);
@@ -466,10 +802,7 @@ prompt = '(foo)'
              python_binary = ["python", "python3"]
         });
 
-        let actual = ModuleRenderer::new("python")
-            .path(dir.path())
-            .config(config)
-            .collect();
+        let actual = python_renderer().path(dir.path()).config(config).collect();
 
         let expected = Some(format!("via {}", Color::Yellow.bold().paint("🐍 v3.8.0 ")));
         assert_eq!(expected, actual);
@@ -482,10 +815,7 @@ prompt = '(foo)'
              pyenv_prefix = "test_pyenv "
         });
 
-        let actual = ModuleRenderer::new("python")
-            .path(dir.path())
-            .config(config)
-            .collect();
+        let actual = python_renderer().path(dir.path()).config(config).collect();
 
         let expected = Some(format!(
             "via {}",
@@ -1,7 +1,10 @@
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
-use std::process::{Command, Output};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process::Output;
 
+use once_cell::sync::Lazy;
 use serde::Deserialize;
 
 use super::{Context, Module, RootModuleConfig};
@@ -25,6 +28,8 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
         return None;
     }
 
+    let rust = Lazy::new(|| get_rust_toolchain(context));
+
     let parsed = StringFormatter::new(config.format).and_then(|formatter| {
         formatter
             .map_meta(|var, _| match var {
@@ -36,9 +41,24 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
                 _ => None,
             })
             .map(|variable| match variable {
-                // This may result in multiple calls to `get_module_version` when a user have
-                // multiple `$version` variables defined in `format`.
-                "version" => get_module_version(context, &config).map(Ok),
+                "version" => {
+                    let rust = rust.deref();
+                    match &rust.version {
+                        Some(version) => format_version(version, config.version_format),
+                        // The toolchain isn't installed, so rustup would have to install it
+                        None => rust.toolchain.clone(),
+                    }
+                    .map(Ok)
+                }
+                "numver" => rust
+                    .version
+                    .as_deref()
+                    .and_then(|version| version.split('-').next())
+                    .map(|numver| Ok(numver.to_owned())),
+                "toolchain" => rust
+                    .toolchain
+                    .as_deref()
+                    .map(|toolchain| Ok(toolchain_channel(toolchain).to_owned())),
                 _ => None,
             })
             .parse(None)
@@ -55,41 +75,57 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     Some(module)
 }
 
-fn get_module_version(context: &Context, config: &RustConfig) -> Option<String> {
+/// The toolchain used in the current directory and the version of its `rustc`, e.g.
+/// `1.34.0-nightly`
+#[derive(Debug)]
+struct RustToolchain {
+    toolchain: Option<String>,
+    version: Option<String>,
+}
+
+fn get_rust_toolchain(context: &Context) -> RustToolchain {
     // `$CARGO_HOME/bin/rustc(.exe) --version` may attempt installing a rustup toolchain.
     // https://github.com/starship/starship/issues/417
     //
     // To display appropriate versions preventing `rustc` from downloading toolchains, we have to
     // check
     // 1. `$RUSTUP_TOOLCHAIN`
-    // 2. `rustup override list`
-    // 3. `rust-toolchain` in `.` or parent directories
+    // 2. directory overrides in the rustup settings
+    // 3. `rust-toolchain` or `rust-toolchain.toml` in `.` or parent directories
+    // 4. the default toolchain in the rustup settings
     // as `rustup` does.
     // https://github.com/rust-lang/rustup.rs/tree/eb694fcada7becc5d9d160bf7c623abe84f8971d#override-precedence
     //
-    // Probably we have no other way to know whether any toolchain override is specified for the
-    // current directory. The following commands also cause toolchain installations.
-    // - `rustup show`
-    // - `rustup show active-toolchain`
-    // - `rustup which`
-    if let Some(toolchain) = env_rustup_toolchain(context)
-        .or_else(|| execute_rustup_override_list(&context.current_dir))
-        .or_else(|| find_rust_toolchain_file(&context))
-    {
-        match execute_rustup_run_rustc_version(&toolchain) {
-            RustupRunRustcVersionOutcome::RustcVersion(rustc_version) => {
-                format_rustc_version(&rustc_version, config.version_format)
-            }
-            RustupRunRustcVersionOutcome::ToolchainName(toolchain) => Some(toolchain),
-            RustupRunRustcVersionOutcome::RustupNotWorking => {
-                // If `rustup` is not in `$PATH` or cannot be executed for other reasons, we can
-                // safely execute `rustc --version`.
-                format_rustc_version(&execute_rustc_version()?, config.version_format)
+    // The version is then read from the manifest of the installed toolchain, as running `rustc`
+    // takes a while. Commands are only run if that fails.
+    let rustup_home = get_rustup_home(context);
+    let settings = rustup_home.as_deref().and_then(read_rustup_settings);
+
+    let toolchain = env_rustup_toolchain(context)
+        .or_else(|| find_toolchain_override(context, settings.as_ref()))
+        .or_else(|| settings.as_ref()?.default_toolchain.clone());
+    let toolchain = match toolchain {
+        Some(toolchain) => toolchain,
+        None => {
+            return RustToolchain {
+                toolchain: None,
+                version: execute_rustc_version(context)
+                    .as_deref()
+                    .and_then(parse_rustc_version),
             }
-            RustupRunRustcVersionOutcome::Err => None,
         }
-    } else {
-        format_rustc_version(&execute_rustc_version()?, config.version_format)
+    };
+
+    let host_triple = settings
+        .as_ref()
+        .and_then(|settings| settings.default_host_triple.as_deref());
+    let version = rustup_home
+        .and_then(|home| read_toolchain_manifest_version(&home, &toolchain, host_triple))
+        .or_else(|| get_rustc_version(context, &toolchain));
+
+    RustToolchain {
+        toolchain: Some(toolchain),
+        version,
     }
 }
 
@@ -98,32 +134,36 @@ fn env_rustup_toolchain(context: &Context) -> Option<String> {
     Some(val.trim().to_owned())
 }
 
-fn execute_rustup_override_list(cwd: &Path) -> Option<String> {
-    let Output { stdout, .. } = Command::new("rustup")
-        .args(&["override", "list"])
-        .output()
-        .ok()?;
-    let stdout = String::from_utf8(stdout).ok()?;
-    extract_toolchain_from_rustup_override_list(&stdout, cwd)
+fn get_rustup_home(context: &Context) -> Option<PathBuf> {
+    context
+        .get_env("RUSTUP_HOME")
+        .map(PathBuf::from)
+        .or_else(|| Some(context.get_home()?.join(".rustup")))
 }
 
-fn extract_toolchain_from_rustup_override_list(stdout: &str, cwd: &Path) -> Option<String> {
-    if stdout == "no overrides\n" {
-        return None;
+/// The parts of rustup's `settings.toml` which select toolchains
+#[derive(Debug, Default, Deserialize)]
+struct RustupSettings {
+    default_host_triple: Option<String>,
+    default_toolchain: Option<String>,
+    #[serde(default)]
+    overrides: HashMap<PathBuf, String>,
+}
+
+fn read_rustup_settings(rustup_home: &Path) -> Option<RustupSettings> {
+    let contents = fs::read_to_string(rustup_home.join("settings.toml")).ok()?;
+    match toml::from_str(&contents) {
+        Ok(settings) => Some(settings),
+        Err(error) => {
+            log::warn!("Unable to parse the rustup settings:\n{}", error);
+            None
+        }
     }
-    stdout
-        .lines()
-        .flat_map(|line| {
-            let mut words = line.split_whitespace();
-            let dir = words.next()?;
-            let toolchain = words.next()?;
-            Some((dir, toolchain))
-        })
-        .find(|(dir, _)| cwd.starts_with(dir))
-        .map(|(_, toolchain)| toolchain.to_owned())
 }
 
-fn find_rust_toolchain_file(context: &Context) -> Option<String> {
+/// Finds the toolchain set for the current directory or its closest parent, by `rustup override`
+/// or a toolchain file
+fn find_toolchain_override(context: &Context, settings: Option<&RustupSettings>) -> Option<String> {
     // Look for 'rust-toolchain' as rustup does.
     // https://github.com/rust-lang/rustup/blob/89912c4cf51645b9c152ab7380fd07574fec43a3/src/config.rs#L546-L616
 
@@ -137,12 +177,12 @@ fn find_rust_toolchain_file(context: &Context) -> Option<String> {
         channel: Option<String>,
     }
 
-    fn read_channel(path: &Path) -> Option<String> {
+    fn read_channel(path: &Path, is_toml: bool) -> Option<String> {
         let contents = fs::read_to_string(path).ok()?;
 
         match contents.lines().count() {
             0 => None,
-            1 => Some(contents),
+            1 if !is_toml => Some(contents),
             _ => {
                 toml::from_str::<OverrideFile>(&contents)
                     .ok()?
@@ -154,26 +194,98 @@ fn find_rust_toolchain_file(context: &Context) -> Option<String> {
         .map(|c| c.trim().to_owned())
     }
 
-    if let Ok(true) = context
-        .dir_contents()
-        .map(|dir| dir.has_file("rust-toolchain"))
-    {
-        if let Some(toolchain) = read_channel(Path::new("rust-toolchain")) {
-            return Some(toolchain);
-        }
+    context.current_dir.ancestors().find_map(|dir| {
+        settings
+            .and_then(|settings| settings.overrides.get(dir).cloned())
+            .or_else(|| read_channel(&dir.join("rust-toolchain"), false))
+            .or_else(|| read_channel(&dir.join("rust-toolchain.toml"), true))
+    })
+}
+
+/// Reads the version of `rustc` from the manifest rustup installed with the toolchain
+fn read_toolchain_manifest_version(
+    rustup_home: &Path,
+    toolchain: &str,
+    host_triple: Option<&str>,
+) -> Option<String> {
+    #[derive(Deserialize)]
+    struct Manifest {
+        pkg: HashMap<String, Package>,
+    }
+
+    #[derive(Deserialize)]
+    struct Package {
+        version: String,
+    }
+
+    // Toolchains without a host triple, like `stable`, are installed for the default host
+    let toolchains = rustup_home.join("toolchains");
+    let toolchain_dir = Some(toolchains.join(toolchain))
+        .filter(|dir| dir.is_dir())
+        .or_else(|| Some(toolchains.join(format!("{}-{}", toolchain, host_triple?))))?;
+    let contents =
+        fs::read_to_string(toolchain_dir.join("lib/rustlib/multirust-channel-manifest.toml"))
+            .ok()?;
+    let manifest: Manifest = toml::from_str(&contents).ok()?;
+    // The version looks like `1.34.0-nightly (b139669f3 2019-04-10)`
+    let version = manifest
+        .pkg
+        .get("rustc")?
+        .version
+        .split_whitespace()
+        .next()?;
+    Some(version.to_owned())
+}
+
+/// Strips the host triple from the name of a toolchain, e.g.
+/// `nightly-2024-01-01-x86_64-unknown-linux-gnu` becomes `nightly-2024-01-01`
+fn toolchain_channel(toolchain: &str) -> &str {
+    let parts: Vec<&str> = toolchain.split('-').collect();
+    let channel = parts[0];
+    let is_channel = ["stable", "beta", "nightly"].contains(&channel)
+        || channel.starts_with(|c: char| c.is_ascii_digit());
+    if !is_channel {
+        // Custom toolchains, e.g. from `rustup toolchain link`, are kept as they are
+        return toolchain;
     }
 
-    let mut dir = &*context.current_dir;
-    loop {
-        if let Some(toolchain) = read_channel(&dir.join("rust-toolchain")) {
-            return Some(toolchain);
+    let is_date = parts.len() >= 4
+        && [4, 2, 2]
+            .iter()
+            .zip(&parts[1..4])
+            .all(|(len, part)| part.len() == *len && part.chars().all(|c| c.is_ascii_digit()));
+    let len = if is_date {
+        parts[..4].join("-").len()
+    } else {
+        channel.len()
+    };
+    &toolchain[..len]
+}
+
+/// The version of the toolchain from `rustup run`, or `rustc --version` without rustup
+fn get_rustc_version(context: &Context, toolchain: &str) -> Option<String> {
+    match execute_rustup_run_rustc_version(context, toolchain) {
+        RustupRunRustcVersionOutcome::RustcVersion(rustc_version) => {
+            parse_rustc_version(&rustc_version)
         }
-        dir = dir.parent()?;
+        RustupRunRustcVersionOutcome::ToolchainName(_) => None,
+        RustupRunRustcVersionOutcome::RustupNotWorking => {
+            // If `rustup` is not in `$PATH` or cannot be executed for other reasons, we can
+            // safely execute `rustc --version`.
+            execute_rustc_version(context)
+                .as_deref()
+                .and_then(parse_rustc_version)
+        }
+        RustupRunRustcVersionOutcome::Err => None,
     }
 }
 
-fn execute_rustup_run_rustc_version(toolchain: &str) -> RustupRunRustcVersionOutcome {
-    Command::new("rustup")
+fn execute_rustup_run_rustc_version(
+    context: &Context,
+    toolchain: &str,
+) -> RustupRunRustcVersionOutcome {
+    context
+        .command("rustup")
         .args(&["run", toolchain, "rustc", "--version"])
         .output()
         .map(extract_toolchain_from_rustup_run_rustc_version)
@@ -196,20 +308,23 @@ fn extract_toolchain_from_rustup_run_rustc_version(output: Output) -> RustupRunR
     RustupRunRustcVersionOutcome::Err
 }
 
-fn execute_rustc_version() -> Option<String> {
-    match Command::new("rustc").arg("--version").output() {
+fn execute_rustc_version(context: &Context) -> Option<String> {
+    match context.command("rustc").arg("--version").output() {
         Ok(output) => Some(String::from_utf8(output.stdout).unwrap()),
         Err(_) => None,
     }
 }
 
-fn format_rustc_version(rustc_version: &str, version_format: &str) -> Option<String> {
+fn parse_rustc_version(rustc_version: &str) -> Option<String> {
     let version = rustc_version
         // split into ["rustc", "1.34.0", ...]
         .split_whitespace()
         // get down to "1.34.0"
         .nth(1)?;
+    Some(version.to_owned())
+}
 
+fn format_version(version: &str, version_format: &str) -> Option<String> {
     match VersionFormatter::format_version(version, version_format) {
         Ok(formatted) => Some(formatted),
         Err(error) => {
@@ -230,6 +345,7 @@ enum RustupRunRustcVersionOutcome {
 #[cfg(test)]
 mod tests {
     use crate::context::Shell;
+    use crate::test::ModuleRenderer;
     use once_cell::sync::Lazy;
     use std::io;
     use std::process::{ExitStatus, Output};
@@ -237,35 +353,93 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_extract_toolchain_from_rustup_override_list() {
-        static NO_OVERRIDES_INPUT: &str = "no overrides\n";
-        static NO_OVERRIDES_CWD: &str = "";
+    fn test_find_toolchain_override_in_rustup_settings() {
+        let settings: RustupSettings = toml::from_str(
+            r#"
+            default_toolchain = "stable-x86_64-unknown-linux-gnu"
+
+            [overrides]
+            "/home/user/src/a" = "beta-x86_64-unknown-linux-gnu"
+            "/home/user/src/a/b" = "nightly-x86_64-unknown-linux-gnu"
+            "#,
+        )
+        .unwrap();
+
+        let find = |cwd: &str| {
+            let context = Context::new_with_shell_and_path(
+                Default::default(),
+                Shell::Unknown,
+                cwd.into(),
+                cwd.into(),
+            );
+            find_toolchain_override(&context, Some(&settings))
+        };
+        assert_eq!(
+            find("/home/user/src/a/src"),
+            Some("beta-x86_64-unknown-linux-gnu".to_owned())
+        );
         assert_eq!(
-            extract_toolchain_from_rustup_override_list(
-                NO_OVERRIDES_INPUT,
-                NO_OVERRIDES_CWD.as_ref(),
-            ),
-            None,
+            find("/home/user/src/a/b/tests"),
+            Some("nightly-x86_64-unknown-linux-gnu".to_owned())
         );
+        assert_eq!(find("/home/user/src/c/examples"), None);
+    }
 
-        static OVERRIDES_INPUT: &str =
-            "/home/user/src/a                                beta-x86_64-unknown-linux-gnu\n\
-             /home/user/src/b                                nightly-x86_64-unknown-linux-gnu\n";
-        static OVERRIDES_CWD_A: &str = "/home/user/src/a/src";
-        static OVERRIDES_CWD_B: &str = "/home/user/src/b/tests";
-        static OVERRIDES_CWD_C: &str = "/home/user/src/c/examples";
+    #[test]
+    fn test_toolchain_channel() {
         assert_eq!(
-            extract_toolchain_from_rustup_override_list(OVERRIDES_INPUT, OVERRIDES_CWD_A.as_ref()),
-            Some("beta-x86_64-unknown-linux-gnu".to_owned()),
+            toolchain_channel("stable-x86_64-unknown-linux-gnu"),
+            "stable"
         );
         assert_eq!(
-            extract_toolchain_from_rustup_override_list(OVERRIDES_INPUT, OVERRIDES_CWD_B.as_ref()),
-            Some("nightly-x86_64-unknown-linux-gnu".to_owned()),
+            toolchain_channel("nightly-2024-01-01-x86_64-pc-windows-msvc"),
+            "nightly-2024-01-01"
         );
         assert_eq!(
-            extract_toolchain_from_rustup_override_list(OVERRIDES_INPUT, OVERRIDES_CWD_C.as_ref()),
-            None,
+            toolchain_channel("nightly-2024-01-01"),
+            "nightly-2024-01-01"
         );
+        assert_eq!(toolchain_channel("1.34.0-aarch64-apple-darwin"), "1.34.0");
+        assert_eq!(toolchain_channel("beta"), "beta");
+        assert_eq!(toolchain_channel("my-toolchain"), "my-toolchain");
+    }
+
+    #[test]
+    fn toolchain_from_rustup_settings_without_rustc() -> io::Result<()> {
+        let rustup_home = tempfile::tempdir()?;
+        fs::write(
+            rustup_home.path().join("settings.toml"),
+            "default_host_triple = \"x86_64-unknown-linux-gnu\"\n\
+             default_toolchain = \"stable-x86_64-unknown-linux-gnu\"\n",
+        )?;
+        let rustlib = rustup_home
+            .path()
+            .join("toolchains/nightly-2024-01-01-x86_64-unknown-linux-gnu/lib/rustlib");
+        fs::create_dir_all(&rustlib)?;
+        fs::write(
+            rustlib.join("multirust-channel-manifest.toml"),
+            "[pkg.rustc]\nversion = \"1.77.0-nightly (e51e98dde 2023-12-31)\"\n",
+        )?;
+
+        let dir = tempfile::tempdir()?;
+        fs::File::create(dir.path().join("main.rs"))?.sync_all()?;
+        fs::write(
+            dir.path().join("rust-toolchain.toml"),
+            "[toolchain]\nchannel = \"nightly-2024-01-01\"\n",
+        )?;
+
+        let actual = ModuleRenderer::new("rust")
+            .path(dir.path())
+            .env("RUSTUP_HOME", rustup_home.path().to_str().unwrap())
+            .config(toml::toml! {
+                [rust]
+                format = "$toolchain $numver $version"
+            })
+            .collect();
+        let expected = Some("nightly-2024-01-01 1.77.0 v1.77.0-nightly".to_owned());
+        assert_eq!(expected, actual);
+        dir.close()?;
+        rustup_home.close()
     }
 
     #[cfg(any(unix, windows))]
@@ -330,6 +504,9 @@ mod tests {
     #[test]
     fn test_format_rustc_version() {
         let config = RustConfig::default();
+        let format_rustc_version = |rustc_version: &str, version_format: &str| {
+            format_version(&parse_rustc_version(rustc_version)?, version_format)
+        };
         let rustc_stable = "rustc 1.34.0 (91856ed52 2019-04-10)";
         let rustc_beta = "rustc 1.34.0-beta.1 (2bc1d406d 2019-04-10)";
         let rustc_nightly = "rustc 1.34.0-nightly (b139669f3 2019-04-10)";
@@ -364,7 +541,7 @@ mod tests {
         );
 
         assert_eq!(
-            find_rust_toolchain_file(&context),
+            find_toolchain_override(&context, None),
             Some("1.34.0".to_owned())
         );
         dir.close()?;
@@ -383,7 +560,7 @@ mod tests {
         );
 
         assert_eq!(
-            find_rust_toolchain_file(&context),
+            find_toolchain_override(&context, None),
             Some("1.34.0".to_owned())
         );
         dir.close()?;
@@ -402,7 +579,7 @@ mod tests {
         );
 
         assert_eq!(
-            find_rust_toolchain_file(&context),
+            find_toolchain_override(&context, None),
             Some("1.34.0".to_owned())
         );
         dir.close()
@@ -23,7 +23,11 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     let shlvl_str = &shlvl.to_string();
 
     let repeat_count = if config.repeat {
-        shlvl.try_into().unwrap_or(1)
+        let count = shlvl.try_into().unwrap_or(1);
+        match config.repeat_max.try_into() {
+            Ok(max) if max > 0 => count.min(max),
+            _ => count,
+        }
     } else {
         1
     };
@@ -32,6 +36,12 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     } else {
         Cow::Borrowed(config.symbol)
     };
+    // Deeply nested shells are easy to forget, so they can stand out more
+    let style = if config.threshold_style_level > 0 && shlvl > config.threshold_style_level {
+        config.threshold_style
+    } else {
+        config.style
+    };
 
     let parsed = StringFormatter::new(config.format).and_then(|formatter| {
         formatter
@@ -40,7 +50,7 @@ pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
                 _ => None,
             })
             .map_style(|variable| match variable {
-                "style" => Some(Ok(config.style)),
+                "style" => Some(Ok(style)),
                 _ => None,
             })
             .map(|variable| match variable {
@@ -219,4 +229,44 @@ mod tests {
 
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn repeat_max() {
+        let actual = ModuleRenderer::new("shlvl")
+            .config(toml::toml! {
+                [shlvl]
+                format = "[$symbol>]($style) "
+                symbol = "~"
+                repeat = true
+                repeat_max = 2
+                disabled = false
+            })
+            .env(SHLVL_ENV_VAR, "5")
+            .collect();
+        let expected = Some(format!("{} ", style().paint("~~>")));
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn threshold_style() {
+        let config = toml::toml! {
+            [shlvl]
+            format = "[$shlvl]($style)"
+            threshold_style = "red"
+            threshold_style_level = 3
+            disabled = false
+        };
+        let actual = ModuleRenderer::new("shlvl")
+            .config(config.clone())
+            .env(SHLVL_ENV_VAR, "3")
+            .collect();
+        assert_eq!(Some(style().paint("3").to_string()), actual);
+
+        let actual = ModuleRenderer::new("shlvl")
+            .config(config)
+            .env(SHLVL_ENV_VAR, "4")
+            .collect();
+        assert_eq!(Some(Color::Red.paint("4").to_string()), actual);
+    }
 }
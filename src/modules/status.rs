@@ -1,97 +1,154 @@
 use super::{Context, Module, RootModuleConfig};
 
 use crate::configs::status::StatusConfig;
+use crate::formatter::string_formatter::StringFormatterError;
 use crate::formatter::StringFormatter;
+use crate::segment::Segment;
 
 type ExitCode = i64;
 type SignalNumber = u32;
 
 /// Creates a module with the status of the last command
 ///
-/// Will display the status only if it is not 0
+/// Will display the status only if it is not 0. With `pipestatus` enabled, the status
+/// of every command of a pipeline is shown if any of them failed.
 pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
     let exit_code = context
         .properties
         .get("status_code")
         .map_or("0", String::as_str);
+    // A single command has no other status than its exit code
+    let pipestatus: Option<Vec<&str>> = context
+        .properties
+        .get("pipestatus")
+        .map(|pipestatus| pipestatus.split_whitespace().collect())
+        .filter(|pipestatus: &Vec<&str>| pipestatus.len() > 1);
+    let pipe_failed = pipestatus.iter().flatten().any(|code| *code != "0");
 
-    if exit_code == "0" {
-        None
-    } else {
-        let mut module = context.new_module("status");
-        let config = StatusConfig::try_load(module.config);
+    if exit_code == "0" && !pipe_failed {
+        return None;
+    }
 
-        // As we default to disabled=true, we have to check here after loading our config module,
-        // before it was only checking against whatever is in the config starship.toml
-        if config.disabled {
-            return None;
-        };
+    let mut module = context.new_module("status");
+    let config = StatusConfig::try_load(module.config);
 
-        let exit_code_int: ExitCode = match exit_code.parse() {
-            Ok(i) => i,
-            Err(_) => return None,
-        };
-
-        let common_meaning = status_common_meaning(exit_code_int);
+    // As we default to disabled=true, we have to check here after loading our config module,
+    // before it was only checking against whatever is in the config starship.toml
+    if config.disabled {
+        return None;
+    };
+
+    let parsed = match pipestatus.filter(|_| config.pipestatus) {
+        Some(pipestatus) => format_pipestatus(&pipestatus, exit_code, &config),
+        None if exit_code == "0" => return None,
+        None => format_exit_code(exit_code, config.format, None, &config),
+    };
+
+    module.set_segments(match parsed {
+        Ok(segments) => segments,
+        Err(error) => {
+            log::warn!("Error in module `status`:\n{}", error);
+            return None;
+        }
+    });
+    Some(module)
+}
 
-        let raw_signal_number = match config.recognize_signal_code {
-            true => status_to_signal(exit_code_int),
-            false => None,
-        };
-        let signal_number = raw_signal_number.map(|sn| sn.to_string());
-        let signal_name = raw_signal_number
-            .and_then(|sn| status_signal_name(sn).or_else(|| signal_number.as_deref()));
-
-        // If not a signal and not a common meaning, it should at least print the raw exit code number
-        let maybe_exit_code_number = match common_meaning.is_none() && signal_name.is_none() {
-            true => Some(exit_code),
-            false => None,
-        };
+/// Formats every status of the pipeline with `pipestatus_segment_format`, joined by
+/// `pipestatus_separator`, as the `$pipestatus` of `pipestatus_format`
+fn format_pipestatus(
+    pipestatus: &[&str],
+    exit_code: &str,
+    config: &StatusConfig,
+) -> Result<Vec<Segment>, StringFormatterError> {
+    let segment_format = config.pipestatus_segment_format.unwrap_or(config.format);
+    let separator = StringFormatter::new(config.pipestatus_separator).and_then(|formatter| {
+        formatter
+            .map_style(|variable| match variable {
+                "style" => Some(Ok(config.style)),
+                _ => None,
+            })
+            .parse(None)
+    })?;
 
-        let parsed = StringFormatter::new(config.format).and_then(|formatter| {
-            formatter
-                .map_meta(|var, _| match var {
-                    "symbol" => match exit_code_int {
-                        126 if config.map_symbol => Some(config.not_executable_symbol),
-                        127 if config.map_symbol => Some(config.not_found_symbol),
-                        130 if config.recognize_signal_code && config.map_symbol => {
-                            Some(config.sigint_symbol)
-                        }
-                        x if (129..256).contains(&x)
-                            && config.recognize_signal_code
-                            && config.map_symbol =>
-                        {
-                            Some(config.signal_symbol)
-                        }
-                        _ => Some(config.symbol),
-                    },
-                    _ => None,
-                })
-                .map_style(|variable| match variable {
-                    "style" => Some(Ok(config.style)),
-                    _ => None,
-                })
-                .map(|variable| match variable {
-                    "status" => Some(Ok(exit_code)),
-                    "int" => Some(Ok(exit_code)),
-                    "maybe_int" => Ok(maybe_exit_code_number.as_deref()).transpose(),
-                    "common_meaning" => Ok(common_meaning.as_deref()).transpose(),
-                    "signal_number" => Ok(signal_number.as_deref()).transpose(),
-                    "signal_name" => Ok(signal_name.as_deref()).transpose(),
-                    _ => None,
-                })
-                .parse(None)
-        });
-
-        module.set_segments(match parsed {
-            Ok(segments) => segments,
-            Err(_error) => {
-                log::warn!("Error parsing format string in `status.format`");
-                return None;
-            }
-        });
-        Some(module)
+    let mut segments = Vec::new();
+    for (index, code) in pipestatus.iter().enumerate() {
+        if index > 0 {
+            segments.extend(separator.iter().cloned());
+        }
+        segments.extend(format_exit_code(code, segment_format, None, config)?);
     }
+    format_exit_code(exit_code, config.pipestatus_format, Some(&segments), config)
+}
+
+fn format_exit_code(
+    exit_code: &str,
+    format: &str,
+    pipestatus: Option<&[Segment]>,
+    config: &StatusConfig,
+) -> Result<Vec<Segment>, StringFormatterError> {
+    let exit_code_int: ExitCode = exit_code
+        .parse()
+        .map_err(|_| StringFormatterError::Custom(format!("Invalid exit code {:?}", exit_code)))?;
+
+    let common_meaning = status_common_meaning(exit_code_int);
+
+    let raw_signal_number = match config.recognize_signal_code {
+        true => status_to_signal(exit_code_int),
+        false => None,
+    };
+    let signal_number = raw_signal_number.map(|sn| sn.to_string());
+    let signal_name = raw_signal_number
+        .and_then(|sn| status_signal_name(sn).or_else(|| signal_number.as_deref()));
+
+    // If not a signal and not a common meaning, it should at least print the raw exit code number
+    let maybe_exit_code_number = match common_meaning.is_none() && signal_name.is_none() {
+        true => Some(exit_code),
+        false => None,
+    };
+
+    StringFormatter::new(format).and_then(|formatter| {
+        formatter
+            .map_meta(|var, _| match var {
+                "symbol" => match exit_code_int {
+                    0 => Some(config.success_symbol),
+                    126 if config.map_symbol => Some(config.not_executable_symbol),
+                    127 if config.map_symbol => Some(config.not_found_symbol),
+                    130 if config.recognize_signal_code && config.map_symbol => {
+                        Some(config.sigint_symbol)
+                    }
+                    x if (129..256).contains(&x)
+                        && config.recognize_signal_code
+                        && config.map_symbol =>
+                    {
+                        Some(config.signal_symbol)
+                    }
+                    _ => Some(config.symbol),
+                },
+                _ => None,
+            })
+            .map_style(|variable| match variable {
+                "style" => Some(Ok(config.style)),
+                _ => None,
+            })
+            .map(|variable| match variable {
+                "status" => Some(Ok(exit_code)),
+                "int" => Some(Ok(exit_code)),
+                "maybe_int" => Ok(maybe_exit_code_number).transpose(),
+                "common_meaning" => Ok(common_meaning).transpose(),
+                "signal_number" => Ok(signal_number.as_deref()).transpose(),
+                "signal_name" => Ok(signal_name).transpose(),
+                // Flags, which are empty but set for groups like `($not_found not found)`
+                "not_executable" if exit_code_int == 126 => Some(Ok("")),
+                "not_found" if exit_code_int == 127 => Some(Ok("")),
+                _ => None,
+            })
+            .map_variables_to_segments(|variable| match variable {
+                "pipestatus" => pipestatus.map(|segments| Ok(segments.to_vec())),
+                _ => None,
+            })
+            .parse(None)
+    })
 }
 
 fn status_common_meaning(ex: ExitCode) -> Option<&'static str> {
@@ -140,6 +197,15 @@ fn status_signal_name(signal: SignalNumber) -> Option<&'static str> {
         20 => Some("TSTP"),   // 128 + 20
         21 => Some("TTIN"),   // 128 + 21
         22 => Some("TTOU"),   // 128 + 22
+        23 => Some("URG"),    // 128 + 23
+        24 => Some("XCPU"),   // 128 + 24
+        25 => Some("XFSZ"),   // 128 + 25
+        26 => Some("VTALRM"), // 128 + 26
+        27 => Some("PROF"),   // 128 + 27
+        28 => Some("WINCH"),  // 128 + 28
+        29 => Some("IO"),     // 128 + 29
+        30 => Some("PWR"),    // 128 + 30
+        31 => Some("SYS"),    // 128 + 31
         _ => None,
     }
 }
@@ -336,4 +402,121 @@ mod tests {
             assert_eq!(expected, actual);
         }
     }
+
+    #[test]
+    fn pipestatus() {
+        let config = toml::toml! {
+            [status]
+            format = "$status"
+            pipestatus = true
+            pipestatus_format = "$pipestatus => $signal_name$maybe_int"
+            disabled = false
+        };
+        let actual = ModuleRenderer::new("status")
+            .config(config.clone())
+            .status(130)
+            .pipestatus(&[0, 1, 130])
+            .collect();
+        assert_eq!(Some("0|1|130 => INT".to_string()), actual);
+
+        // A failure early in the pipeline shows even if the last command succeeded
+        let actual = ModuleRenderer::new("status")
+            .config(config.clone())
+            .status(0)
+            .pipestatus(&[2, 0])
+            .collect();
+        assert_eq!(Some("2|0 => 0".to_string()), actual);
+
+        let actual = ModuleRenderer::new("status")
+            .config(config)
+            .status(0)
+            .pipestatus(&[0, 0])
+            .collect();
+        assert_eq!(None, actual);
+    }
+
+    #[test]
+    fn pipestatus_segment_format() {
+        let actual = ModuleRenderer::new("status")
+            .config(toml::toml! {
+                [status]
+                pipestatus = true
+                pipestatus_separator = " "
+                pipestatus_segment_format = "[$symbol$common_meaning$maybe_int]($style)"
+                pipestatus_format = "$pipestatus"
+                success_symbol = "✔"
+                symbol = "✖"
+                disabled = false
+            })
+            .status(1)
+            .pipestatus(&[0, 1])
+            .collect();
+        let expected = Some(format!(
+            "{} {}",
+            Color::Red.bold().paint("✔0"),
+            Color::Red.bold().paint("✖ERROR")
+        ));
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn pipestatus_disabled() {
+        let actual = ModuleRenderer::new("status")
+            .config(toml::toml! {
+                [status]
+                format = "$status"
+                disabled = false
+            })
+            .status(1)
+            .pipestatus(&[0, 1])
+            .collect();
+        assert_eq!(Some("1".to_string()), actual);
+
+        // A single command isn't a pipeline
+        let actual = ModuleRenderer::new("status")
+            .config(toml::toml! {
+                [status]
+                format = "$status"
+                pipestatus = true
+                disabled = false
+            })
+            .status(1)
+            .pipestatus(&[1])
+            .collect();
+        assert_eq!(Some("1".to_string()), actual);
+    }
+
+    #[test]
+    fn flags() {
+        let config = toml::toml! {
+            [status]
+            format = "$status( not executable$not_executable)( not found$not_found)"
+            disabled = false
+        };
+        let expected = [
+            (1, "1"),
+            (126, "126 not executable"),
+            (127, "127 not found"),
+        ];
+        for (status, expected) in expected.iter() {
+            let actual = ModuleRenderer::new("status")
+                .config(config.clone())
+                .status(*status)
+                .collect();
+            assert_eq!(Some(expected.to_string()), actual);
+        }
+    }
+
+    #[test]
+    fn higher_signal_names() {
+        let actual = ModuleRenderer::new("status")
+            .config(toml::toml! {
+                [status]
+                format = "$signal_name"
+                disabled = false
+            })
+            .status(128 + 28)
+            .collect();
+        assert_eq!(Some("WINCH".to_string()), actual);
+    }
 }
@@ -0,0 +1,100 @@
+use super::{Context, Module, RootModuleConfig};
+
+use crate::configs::sudo::SudoConfig;
+use crate::formatter::StringFormatter;
+
+/// Creates a module which shows that sudo would run commands without asking for a password
+///
+/// This is the case when the credentials are cached since sudo was last used, or when the
+/// user may run commands without a password at all. `sudo -n true` fails instead of asking
+/// for a password otherwise.
+pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
+    let mut module = context.new_module("sudo");
+    let config: SudoConfig = SudoConfig::try_load(module.config);
+
+    // As we default to disabled=true, we have to check here after loading our config module,
+    // before it was only checking against whatever is in the config starship.toml
+    if config.disabled {
+        return None;
+    }
+
+    if cfg!(windows) {
+        log::debug!("The sudo module is not supported on Windows");
+        return None;
+    }
+    context.exec_cmd("sudo", &["-n", "true"])?;
+
+    let parsed = StringFormatter::new(config.format).and_then(|formatter| {
+        formatter
+            .map_meta(|variable, _| match variable {
+                "symbol" => Some(config.symbol),
+                _ => None,
+            })
+            .map_style(|variable| match variable {
+                "style" => Some(Ok(config.style)),
+                _ => None,
+            })
+            .parse(None)
+    });
+
+    module.set_segments(match parsed {
+        Ok(segments) => segments,
+        Err(error) => {
+            log::warn!("Error in module `sudo`:\n{}", error);
+            return None;
+        }
+    });
+
+    Some(module)
+}
+
+#[cfg(test)]
+#[cfg(not(windows))]
+mod tests {
+    use crate::test::ModuleRenderer;
+    use crate::utils::CommandOutput;
+    use ansi_term::Color;
+
+    fn render(cached: bool) -> Option<String> {
+        let output = if cached {
+            Some(CommandOutput {
+                stdout: String::new(),
+                stderr: String::new(),
+            })
+        } else {
+            None
+        };
+        ModuleRenderer::new("sudo")
+            .config(toml::toml! {
+                [sudo]
+                disabled = false
+            })
+            .cmd("sudo -n true", output)
+            .collect()
+    }
+
+    #[test]
+    fn shows_cached_credentials() {
+        let expected = Some(Color::Blue.bold().paint("as 🧙 ").to_string());
+        assert_eq!(expected, render(true));
+    }
+
+    #[test]
+    fn hides_without_credentials() {
+        assert_eq!(None, render(false));
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let actual = ModuleRenderer::new("sudo")
+            .cmd(
+                "sudo -n true",
+                Some(CommandOutput {
+                    stdout: String::new(),
+                    stderr: String::new(),
+                }),
+            )
+            .collect();
+        assert_eq!(None, actual);
+    }
+}
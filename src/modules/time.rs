@@ -94,7 +94,7 @@ fn create_offset_time_string(
 
 /// Format a given time into the given string. This function should be referentially
 /// transparent, which makes it easy to test (unlike anything involving the actual time)
-fn format_time(time_format: &str, local_time: DateTime<Local>) -> String {
+pub fn format_time(time_format: &str, local_time: DateTime<Local>) -> String {
     local_time.format(time_format).to_string()
 }
 
@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+use super::{Context, Module, RootModuleConfig};
+
+use crate::configs::uptime::UptimeConfig;
+use crate::formatter::StringFormatter;
+
+/// Creates a module with how long the system has been running
+///
+/// Will display the uptime if all of the following criteria are met:
+///     - uptime.disabled is false
+///     - uptime.ssh_only is false OR the user is currently connected as an SSH session (`$SSH_CONNECTION`)
+pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
+    let mut module = context.new_module("uptime");
+    let config: UptimeConfig = UptimeConfig::try_load(module.config);
+
+    // As we default to disabled=true, we have to check here after loading our config module,
+    // before it was only checking against whatever is in the config starship.toml
+    if config.disabled {
+        return None;
+    }
+
+    if config.ssh_only && context.get_env("SSH_CONNECTION").is_none() {
+        return None;
+    }
+
+    let uptime = get_uptime()?;
+    // Systems which rebooted recently may have crashed, or have been updated
+    let style = if uptime.as_secs() < config.recent_threshold {
+        config.recent_style
+    } else {
+        config.style
+    };
+
+    let parsed = StringFormatter::new(config.format).and_then(|formatter| {
+        formatter
+            .map_meta(|variable, _| match variable {
+                "symbol" => Some(config.symbol),
+                _ => None,
+            })
+            .map_style(|variable| match variable {
+                "style" => Some(Ok(style)),
+                _ => None,
+            })
+            .map(|variable| match variable {
+                "uptime" => Some(Ok(render_uptime(uptime.as_secs()))),
+                _ => None,
+            })
+            .parse(None)
+    });
+
+    module.set_segments(match parsed {
+        Ok(segments) => segments,
+        Err(error) => {
+            log::warn!("Error in module `uptime`:\n{}", error);
+            return None;
+        }
+    });
+
+    Some(module)
+}
+
+/// Renders the two largest units of the uptime like `cmd_duration`, e.g. `12d3h` or `4h5m`
+fn render_uptime(seconds: u64) -> String {
+    let (minutes, hours, days) = (seconds / 60 % 60, seconds / 3600 % 24, seconds / 86400);
+    let components = [(days, "d"), (hours, "h"), (minutes, "m")];
+    let first = match components.iter().position(|(value, _)| *value > 0) {
+        Some(first) => first,
+        None => return "0m".to_string(),
+    };
+    components[first..]
+        .iter()
+        .take(2)
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, suffix)| format!("{}{}", value, suffix))
+        .collect()
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn get_uptime() -> Option<Duration> {
+    let uptime = std::fs::read_to_string("/proc/uptime").ok()?;
+    let seconds: f64 = uptime.split_whitespace().next()?.parse().ok()?;
+    Some(Duration::from_secs_f64(seconds))
+}
+
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+fn get_uptime() -> Option<Duration> {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let boot = match sys_info::boottime() {
+        Ok(boot) => boot,
+        Err(error) => {
+            log::warn!("Unable to get the boot time: {}", error);
+            return None;
+        }
+    };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+    now.checked_sub(Duration::from_secs(boot.tv_sec as u64))
+}
+
+#[cfg(windows)]
+fn get_uptime() -> Option<Duration> {
+    // SAFETY: GetTickCount64 has no preconditions
+    let millis = unsafe { winapi::um::sysinfoapi::GetTickCount64() };
+    Some(Duration::from_millis(millis))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::ModuleRenderer;
+
+    #[test]
+    fn renders_uptime() {
+        assert_eq!("0m", render_uptime(59));
+        assert_eq!("5m", render_uptime(5 * 60 + 7));
+        assert_eq!("4h5m", render_uptime(4 * 3600 + 5 * 60));
+        assert_eq!("4h", render_uptime(4 * 3600 + 59));
+        assert_eq!("12d3h", render_uptime(12 * 86400 + 3 * 3600 + 40 * 60));
+        assert_eq!("2d", render_uptime(2 * 86400 + 30 * 60));
+    }
+
+    #[test]
+    fn ssh_only() {
+        let config = toml::toml! {
+            [uptime]
+            disabled = false
+            ssh_only = true
+            format = "[up]()"
+        };
+        let actual = ModuleRenderer::new("uptime")
+            .config(config.clone())
+            .collect();
+        assert_eq!(None, actual);
+
+        let actual = ModuleRenderer::new("uptime")
+            .config(config)
+            .env("SSH_CONNECTION", "something")
+            .collect();
+        assert_eq!(Some("up".to_string()), actual);
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let actual = ModuleRenderer::new("uptime").collect();
+        assert_eq!(None, actual);
+    }
+}
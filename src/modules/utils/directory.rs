@@ -1,3 +1,5 @@
+use crate::print::UnicodeWidthGraphemes;
+
 /// Truncate a path to only have a set number of path components
 ///
 /// Will truncate a path to only show the last `length` components in a path.
@@ -22,6 +24,26 @@ pub fn truncate(dir_string: String, length: usize) -> String {
     truncated_components.join("/")
 }
 
+/// Truncate a path to fit in a number of columns
+///
+/// Will remove the leading components of a path until it is at most `width` columns
+/// wide, counting `symbol_width` columns for the symbol prefixed to truncated paths.
+/// The last component is always kept.
+pub fn truncate_to_width(dir_string: String, width: usize, symbol_width: usize) -> String {
+    if dir_string.width_graphemes() <= width {
+        return dir_string;
+    }
+
+    let components = dir_string.split('/').collect::<Vec<&str>>();
+    for start in 1..components.len() {
+        let truncated = components[start..].join("/");
+        if truncated.width_graphemes() + symbol_width <= width {
+            return truncated;
+        }
+    }
+    components[components.len() - 1].to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -67,4 +89,16 @@ mod tests {
         let output = truncate(path.to_string(), 3);
         assert_eq!(output, "engines/booster/rocket");
     }
+
+    #[test]
+    fn truncate_to_width_removes_leading_components() {
+        let path = "~/src/com/example/project/app";
+        assert_eq!(truncate_to_width(path.to_string(), 30, 0), path);
+        assert_eq!(
+            truncate_to_width(path.to_string(), 20, 0),
+            "example/project/app"
+        );
+        assert_eq!(truncate_to_width(path.to_string(), 20, 2), "project/app");
+        assert_eq!(truncate_to_width(path.to_string(), 2, 0), "app");
+    }
 }
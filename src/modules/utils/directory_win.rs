@@ -1,26 +1,31 @@
 extern crate winapi;
 
+use std::io;
 use std::iter;
 use std::mem;
 use std::os::windows::ffi::OsStrExt;
 use std::path::Path;
+use std::ptr;
 use winapi::ctypes::c_void;
 use winapi::shared::minwindef::{BOOL, DWORD};
+use winapi::um::fileapi;
 use winapi::um::handleapi;
 use winapi::um::processthreadsapi;
 use winapi::um::securitybaseapi;
+use winapi::um::winbase::FILE_FLAG_BACKUP_SEMANTICS;
 use winapi::um::winnt::{
-    SecurityImpersonation, BOOLEAN, DACL_SECURITY_INFORMATION, FILE_ALL_ACCESS,
-    FILE_GENERIC_EXECUTE, FILE_GENERIC_READ, FILE_GENERIC_WRITE, GENERIC_MAPPING,
-    GROUP_SECURITY_INFORMATION, HANDLE, LPCWSTR, OWNER_SECURITY_INFORMATION, PRIVILEGE_SET,
-    PSECURITY_DESCRIPTOR, STANDARD_RIGHTS_READ, TOKEN_DUPLICATE, TOKEN_IMPERSONATE, TOKEN_QUERY,
+    SecurityImpersonation, BOOLEAN, DACL_SECURITY_INFORMATION, FILE_ADD_FILE, FILE_ALL_ACCESS,
+    FILE_GENERIC_EXECUTE, FILE_GENERIC_READ, FILE_GENERIC_WRITE, FILE_SHARE_DELETE,
+    FILE_SHARE_READ, FILE_SHARE_WRITE, GENERIC_MAPPING, GROUP_SECURITY_INFORMATION, HANDLE,
+    LPCWSTR, OWNER_SECURITY_INFORMATION, PRIVILEGE_SET, PSECURITY_DESCRIPTOR, STANDARD_RIGHTS_READ,
+    TOKEN_DUPLICATE, TOKEN_IMPERSONATE, TOKEN_QUERY,
 };
 
 /// Checks if the current user has write access right to the `folder_path`
 ///
 /// First, the function extracts DACL from the given directory and then calls `AccessCheck` against
 /// the current process access token and directory's security descriptor.
-/// Does not work for network drives and always returns true
+/// Network drives are checked by the server instead, see `is_network_write_allowed`
 pub fn is_write_allowed(folder_path: &Path) -> std::result::Result<bool, &'static str> {
     let folder_name: Vec<u16> = folder_path
         .as_os_str()
@@ -29,11 +34,7 @@ pub fn is_write_allowed(folder_path: &Path) -> std::result::Result<bool, &'stati
         .collect();
 
     if is_network_path(&folder_name) {
-        log::info!(
-            "Directory '{:?}' is a network drive, unable to check write permissions. See #1506 for details",
-            folder_path
-        );
-        return Ok(true);
+        return is_network_write_allowed(&folder_name);
     }
 
     let mut length: DWORD = 0;
@@ -127,6 +128,34 @@ pub fn is_write_allowed(folder_path: &Path) -> std::result::Result<bool, &'stati
     Ok(result != 0)
 }
 
+/// Checks if the current user can create files in the network folder `folder_name`
+///
+/// The security descriptors of shares can't be checked locally, as their users and groups
+/// belong to the server. Instead, the folder is opened asking for the right to add files,
+/// which the server grants or denies based on the effective access of the user.
+fn is_network_write_allowed(folder_name: &[u16]) -> std::result::Result<bool, &'static str> {
+    let handle = unsafe {
+        fileapi::CreateFileW(
+            folder_name.as_ptr(),
+            FILE_ADD_FILE,
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+            ptr::null_mut(),
+            fileapi::OPEN_EXISTING,
+            // Needed to open a directory
+            FILE_FLAG_BACKUP_SEMANTICS,
+            ptr::null_mut(),
+        )
+    };
+    if handle == handleapi::INVALID_HANDLE_VALUE {
+        return match io::Error::last_os_error().kind() {
+            io::ErrorKind::PermissionDenied => Ok(false),
+            _ => Err("CreateFileW failed to open the network directory"),
+        };
+    }
+    unsafe { handleapi::CloseHandle(handle) };
+    Ok(true)
+}
+
 #[link(name = "Shlwapi")]
 extern "system" {
     fn PathIsNetworkPathW(pszPath: LPCWSTR) -> BOOLEAN;
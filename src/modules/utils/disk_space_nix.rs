@@ -0,0 +1,20 @@
+use nix::sys::statvfs::statvfs;
+use std::path::Path;
+
+/// The total and available bytes of the filesystem containing `path`
+// The integer types of `statvfs` differ between platforms
+#[allow(clippy::useless_conversion)]
+pub fn disk_space(path: &Path) -> Option<(u64, u64)> {
+    let stats = match statvfs(path) {
+        Ok(stats) => stats,
+        Err(error) => {
+            log::debug!("Unable to get the disk space of {:?}: {}", path, error);
+            return None;
+        }
+    };
+    let fragment_size = u64::from(stats.fragment_size());
+    Some((
+        u64::from(stats.blocks()) * fragment_size,
+        u64::from(stats.blocks_available()) * fragment_size,
+    ))
+}
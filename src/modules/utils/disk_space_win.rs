@@ -0,0 +1,33 @@
+extern crate winapi;
+
+use std::iter;
+use std::os::windows::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr;
+use winapi::um::fileapi;
+use winapi::um::winnt::ULARGE_INTEGER;
+
+/// The total and available bytes of the filesystem containing `path`
+pub fn disk_space(path: &Path) -> Option<(u64, u64)> {
+    let path_name: Vec<u16> = path
+        .as_os_str()
+        .encode_wide()
+        .chain(iter::once(0))
+        .collect();
+    let mut available = ULARGE_INTEGER::default();
+    let mut total = ULARGE_INTEGER::default();
+
+    let rc = unsafe {
+        fileapi::GetDiskFreeSpaceExW(
+            path_name.as_ptr(),
+            &mut available,
+            &mut total,
+            ptr::null_mut(),
+        )
+    };
+    if rc == 0 {
+        log::debug!("GetDiskFreeSpaceExW failed for {:?}", path);
+        return None;
+    }
+    Some(unsafe { (*total.QuadPart(), *available.QuadPart()) })
+}
@@ -0,0 +1,93 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::context::Context;
+
+/// How long a background fetch may take before another one is started in its place.
+/// Failed fetches keep their lock, so they are retried after this long.
+const FETCH_LOCK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A value which is fetched over the network by a background process and kept in the
+/// cache directory between prompts, so the prompt never waits for the network
+pub struct FetchCache {
+    path: PathBuf,
+    lock_path: PathBuf,
+}
+
+impl FetchCache {
+    pub fn new(context: &Context, name: &str) -> Option<Self> {
+        let cache_dir = context.get_cache_dir()?;
+        Some(Self {
+            path: cache_dir.join(name),
+            lock_path: cache_dir.join(format!("{}.lock", name)),
+        })
+    }
+
+    /// Returns the cached value and how long ago it was fetched
+    pub fn read(&self) -> Option<(String, Duration)> {
+        let content = fs::read_to_string(&self.path).ok()?;
+        let age = fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .unwrap_or_default();
+        Some((content, age))
+    }
+
+    pub fn write(&self, content: &str) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Write to a temporary file first so readers never see a partial cache
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, content)?;
+        fs::rename(tmp_path, &self.path)
+    }
+
+    /// Starts `starship <subcommand>` in the background to fetch the value again, unless
+    /// another fetch is running or failed recently
+    pub fn spawn_fetch(&self, context: &Context, subcommand: &str) {
+        if self.is_fetching() {
+            return;
+        }
+        let locked = self
+            .lock_path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&self.lock_path, ""));
+
+        if let Err(error) = locked.and_then(|_| spawn_process(context, subcommand)) {
+            log::warn!("Unable to start `starship {}`: {}", subcommand, error);
+            self.release();
+        }
+    }
+
+    /// Allows the next fetch to start, after a successful one
+    pub fn release(&self) {
+        if let Err(error) = fs::remove_file(&self.lock_path) {
+            log::debug!("Unable to remove lock {:?}: {}", self.lock_path, error);
+        }
+    }
+
+    fn is_fetching(&self) -> bool {
+        let age = fs::metadata(&self.lock_path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok());
+
+        matches!(age, Some(age) if age < FETCH_LOCK_TIMEOUT)
+    }
+}
+
+/// Starts the fetch with the environment variables and working directory of the shell
+#[cfg(not(test))]
+fn spawn_process(context: &Context, subcommand: &str) -> io::Result<()> {
+    crate::utils::spawn_detached(context.command(std::env::current_exe()?).arg(subcommand))
+}
+
+#[cfg(test)]
+fn spawn_process(_context: &Context, _subcommand: &str) -> io::Result<()> {
+    Ok(())
+}
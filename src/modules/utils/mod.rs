@@ -6,4 +6,12 @@ pub mod directory_win;
 #[cfg(not(target_os = "windows"))]
 pub mod directory_nix;
 
+#[cfg(target_os = "windows")]
+pub mod disk_space_win;
+
+#[cfg(not(target_os = "windows"))]
+pub mod disk_space_nix;
+
+pub mod fetch_cache;
+
 pub mod path;
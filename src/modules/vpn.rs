@@ -0,0 +1,209 @@
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+
+use wildmatch::WildMatch;
+
+use super::{Context, Module, RootModuleConfig};
+
+use crate::configs::vpn::VpnConfig;
+use crate::formatter::StringFormatter;
+
+/// Where Linux describes the network interfaces
+const SYSFS_NET: &str = "/sys/class/net";
+
+/// A network interface which is up, with its addresses
+struct Interface {
+    name: String,
+    addresses: Vec<IpAddr>,
+}
+
+/// Creates a module with the names of the active VPN interfaces
+///
+/// Interfaces are VPNs if their name matches one of the `interfaces` patterns, or on Linux
+/// if they are tunnel or WireGuard devices. Only interfaces which are up and have an address
+/// beyond the link-local ones are shown, as macOS keeps idle `utun` interfaces around.
+pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
+    let mut module = context.new_module("vpn");
+    let config: VpnConfig = VpnConfig::try_load(module.config);
+
+    // As we default to disabled=true, we have to check here after loading our config module,
+    // before it was only checking against whatever is in the config starship.toml
+    if config.disabled {
+        return None;
+    }
+
+    let patterns: Vec<WildMatch> = config
+        .interfaces
+        .iter()
+        .map(|pattern| WildMatch::new(pattern))
+        .collect();
+    let names: Vec<String> = active_interfaces()
+        .into_iter()
+        .filter(|interface| is_vpn(interface, &patterns, Path::new(SYSFS_NET)))
+        .map(|interface| interface.name)
+        .collect();
+    if names.is_empty() {
+        return None;
+    }
+
+    let parsed = StringFormatter::new(config.format).and_then(|formatter| {
+        formatter
+            .map_meta(|variable, _| match variable {
+                "symbol" => Some(config.symbol),
+                _ => None,
+            })
+            .map_style(|variable| match variable {
+                "style" => Some(Ok(config.style)),
+                _ => None,
+            })
+            .map(|variable| match variable {
+                "name" => Some(Ok(names.join(", "))),
+                _ => None,
+            })
+            .parse(None)
+    });
+
+    module.set_segments(match parsed {
+        Ok(segments) => segments,
+        Err(error) => {
+            log::warn!("Error in module `vpn`:\n{}", error);
+            return None;
+        }
+    });
+
+    Some(module)
+}
+
+#[cfg(not(windows))]
+fn active_interfaces() -> Vec<Interface> {
+    use nix::ifaddrs::getifaddrs;
+    use nix::net::if_::InterfaceFlags;
+    use nix::sys::socket::SockAddr;
+
+    let addresses = match getifaddrs() {
+        Ok(addresses) => addresses,
+        Err(error) => {
+            log::warn!("Unable to list the network interfaces: {}", error);
+            return Vec::new();
+        }
+    };
+    let mut interfaces: Vec<Interface> = Vec::new();
+    for address in addresses {
+        if !address
+            .flags
+            .contains(InterfaceFlags::IFF_UP | InterfaceFlags::IFF_RUNNING)
+        {
+            continue;
+        }
+        let ip = match address.address {
+            Some(SockAddr::Inet(inet)) => inet.ip().to_std(),
+            _ => continue,
+        };
+        match interfaces
+            .iter_mut()
+            .find(|interface| interface.name == address.interface_name)
+        {
+            Some(interface) => interface.addresses.push(ip),
+            None => interfaces.push(Interface {
+                name: address.interface_name,
+                addresses: vec![ip],
+            }),
+        }
+    }
+    interfaces
+}
+
+#[cfg(windows)]
+fn active_interfaces() -> Vec<Interface> {
+    log::debug!("Listing the network interfaces is not supported on Windows");
+    Vec::new()
+}
+
+fn is_vpn(interface: &Interface, patterns: &[WildMatch], sysfs: &Path) -> bool {
+    let routable = interface.addresses.iter().any(|address| match address {
+        IpAddr::V4(address) => !address.is_loopback() && !address.is_link_local(),
+        // fe80::/10 is link-local
+        IpAddr::V6(address) => !address.is_loopback() && address.segments()[0] & 0xffc0 != 0xfe80,
+    });
+    routable
+        && (patterns
+            .iter()
+            .any(|pattern| pattern.is_match(&interface.name))
+            || is_tunnel_device(&sysfs.join(&interface.name)))
+}
+
+/// Tells whether a Linux network device is a TUN/TAP or WireGuard device, from its sysfs directory
+fn is_tunnel_device(device: &Path) -> bool {
+    if device.join("tun_flags").exists() {
+        return true;
+    }
+    matches!(
+        fs::read_to_string(device.join("uevent")),
+        Ok(uevent) if uevent.lines().any(|line| line == "DEVTYPE=wireguard")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::ModuleRenderer;
+    use std::io;
+
+    fn interface(name: &str, address: &str) -> Interface {
+        Interface {
+            name: name.to_string(),
+            addresses: vec![address.parse().unwrap()],
+        }
+    }
+
+    #[test]
+    fn matches_interface_patterns() {
+        let patterns = vec![WildMatch::new("wg*"), WildMatch::new("tun*")];
+        let sysfs = Path::new("/nonexistent");
+        assert!(is_vpn(&interface("wg0", "10.8.0.2"), &patterns, sysfs));
+        assert!(is_vpn(&interface("tun1", "fd00::2"), &patterns, sysfs));
+        assert!(!is_vpn(&interface("eth0", "192.168.1.2"), &patterns, sysfs));
+    }
+
+    #[test]
+    fn skips_link_local_interfaces() {
+        let patterns = vec![WildMatch::new("utun*")];
+        let sysfs = Path::new("/nonexistent");
+        assert!(!is_vpn(&interface("utun0", "fe80::1"), &patterns, sysfs));
+        assert!(!is_vpn(
+            &interface("utun1", "169.254.3.4"),
+            &patterns,
+            sysfs
+        ));
+    }
+
+    #[test]
+    fn detects_tunnel_devices() -> io::Result<()> {
+        let sysfs = tempfile::tempdir()?;
+        let wireguard = sysfs.path().join("mullvad-se1");
+        fs::create_dir(&wireguard)?;
+        fs::write(
+            wireguard.join("uevent"),
+            "DEVTYPE=wireguard\nINTERFACE=mullvad-se1\n",
+        )?;
+        let tun = sysfs.path().join("office");
+        fs::create_dir(&tun)?;
+        fs::write(tun.join("tun_flags"), "0x1001\n")?;
+        let ethernet = sysfs.path().join("enp3s0");
+        fs::create_dir(&ethernet)?;
+        fs::write(ethernet.join("uevent"), "INTERFACE=enp3s0\n")?;
+
+        let vpn = |name| is_vpn(&interface(name, "10.0.0.2"), &[], sysfs.path());
+        assert!(vpn("mullvad-se1"));
+        assert!(vpn("office"));
+        assert!(!vpn("enp3s0"));
+        sysfs.close()
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let actual = ModuleRenderer::new("vpn").collect();
+        assert_eq!(None, actual);
+    }
+}
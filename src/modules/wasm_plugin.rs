@@ -0,0 +1,292 @@
+//! Runs plugins compiled to WebAssembly, when starship is built with the `wasm` feature
+//!
+//! A plugin is a module which imports nothing, so it can only see the context of the prompt
+//! it is given, and exports:
+//!
+//! - `memory`, its memory
+//! - `alloc(len: i32) -> i32`, which reserves `len` bytes for the request and returns
+//!   where they start
+//! - `render(ptr: i32, len: i32) -> i64`, which reads the request as JSON from the given
+//!   bytes and returns where the response starts in the upper 32 bits and its length in
+//!   the lower 32 bits
+//!
+//! The request and response are the same JSON as for plugins run as a process.
+//!
+//! Compiling a plugin takes much longer than running it, so compiled plugins are kept in
+//! memory for the daemon and in the cache directory for the next prompts, until the plugin
+//! changes.
+use std::path::Path;
+use std::time::Duration;
+
+#[cfg(feature = "wasm")]
+use std::collections::HashMap;
+#[cfg(feature = "wasm")]
+use std::convert::TryFrom;
+#[cfg(feature = "wasm")]
+use std::fs;
+#[cfg(feature = "wasm")]
+use std::path::PathBuf;
+#[cfg(feature = "wasm")]
+use std::sync::{mpsc, Mutex};
+#[cfg(feature = "wasm")]
+use std::thread;
+#[cfg(feature = "wasm")]
+use std::time::{Instant, SystemTime};
+
+#[cfg(feature = "wasm")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "wasm")]
+use wasmtime::{Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, Trap};
+
+#[cfg(feature = "wasm")]
+use crate::utils::fnv1a;
+
+/// The most memory a plugin may use, in bytes
+#[cfg(feature = "wasm")]
+const MAX_MEMORY: usize = 64 * 1024 * 1024;
+
+/// The engine all plugins are compiled with, shared so compiled plugins can be reused
+#[cfg(feature = "wasm")]
+static ENGINE: Lazy<Result<Engine, String>> = Lazy::new(|| {
+    let mut config = Config::new();
+    config.epoch_interruption(true);
+    Engine::new(&config).map_err(|error| error.to_string())
+});
+
+/// The compiled plugins by path, with when the plugin was modified before it was compiled
+#[cfg(feature = "wasm")]
+static MODULES: Lazy<Mutex<HashMap<PathBuf, (SystemTime, Module)>>> = Lazy::new(Default::default);
+
+/// Runs the plugin at `path` with the request and returns its response, or why it failed.
+/// The compiled plugin is saved in `cache_dir`, if given.
+#[cfg(feature = "wasm")]
+pub fn run(
+    path: &Path,
+    request: &[u8],
+    timeout: Duration,
+    cache_dir: Option<&Path>,
+) -> Result<Vec<u8>, String> {
+    let engine = ENGINE.as_ref()?;
+    let module = load_module(engine, path, cache_dir).map_err(|error| format!("{:#}", error))?;
+
+    // The engine is shared, so the epoch is increased once the timeout of any plugin is
+    // over, and each plugin checks whether its own timeout is over then
+    let start = Instant::now();
+    let (done, timer) = mpsc::channel::<()>();
+    let timer_engine = engine.clone();
+    thread::spawn(move || {
+        if let Err(mpsc::RecvTimeoutError::Timeout) = timer.recv_timeout(timeout) {
+            timer_engine.increment_epoch();
+        }
+    });
+
+    let output = render(engine, &module, request, move || start.elapsed() >= timeout);
+    drop(done);
+    output.map_err(|error| match error.downcast_ref::<Trap>() {
+        Some(Trap::Interrupt) => format!("timed out after {:?}", timeout),
+        _ => format!("{:#}", error),
+    })
+}
+
+#[cfg(not(feature = "wasm"))]
+pub fn run(
+    path: &Path,
+    _request: &[u8],
+    _timeout: Duration,
+    _cache_dir: Option<&Path>,
+) -> Result<Vec<u8>, String> {
+    Err(format!(
+        "unable to run {:?}, starship was built without the `wasm` feature",
+        path
+    ))
+}
+
+/// The compiled plugin at `path`, compiled again only if it was modified since
+#[cfg(feature = "wasm")]
+fn load_module(engine: &Engine, path: &Path, cache_dir: Option<&Path>) -> wasmtime::Result<Module> {
+    let modified = fs::metadata(path)?.modified()?;
+    if let Ok(modules) = MODULES.lock() {
+        if let Some((compiled, module)) = modules.get(path) {
+            if *compiled == modified {
+                return Ok(module.clone());
+            }
+        }
+    }
+
+    let cache_path = cache_dir.map(|dir| {
+        let key = fnv1a(path.to_string_lossy().as_bytes());
+        dir.join(format!("wasm_{:016x}.cwasm", key))
+    });
+    let cached = cache_path.as_ref().and_then(|cache_path| {
+        let saved = fs::metadata(cache_path).ok()?.modified().ok()?;
+        if saved < modified {
+            return None;
+        }
+        // SAFETY: the file was written by `Module::serialize` below, in the cache directory
+        // of the user. Files of other versions of wasmtime are rejected, not run.
+        unsafe { Module::deserialize_file(engine, cache_path) }.ok()
+    });
+    let module = match cached {
+        Some(module) => module,
+        None => {
+            let module = Module::from_file(engine, path)?;
+            if let Some(cache_path) = &cache_path {
+                let saved = module.serialize().and_then(|compiled| {
+                    if let Some(parent) = cache_path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    Ok(fs::write(cache_path, compiled)?)
+                });
+                if let Err(error) = saved {
+                    log::debug!("Unable to save the compiled plugin {:?}: {}", path, error);
+                }
+            }
+            module
+        }
+    };
+
+    if let Ok(mut modules) = MODULES.lock() {
+        modules.insert(path.to_path_buf(), (modified, module.clone()));
+    }
+    Ok(module)
+}
+
+#[cfg(feature = "wasm")]
+fn render(
+    engine: &Engine,
+    module: &Module,
+    request: &[u8],
+    is_timed_out: impl Fn() -> bool + Send + Sync + 'static,
+) -> wasmtime::Result<Vec<u8>> {
+    let limits = StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build();
+    let mut store = Store::new(engine, limits);
+    store.limiter(|limits: &mut StoreLimits| limits);
+    store.set_epoch_deadline(1);
+    store.epoch_deadline_callback(move |_| match is_timed_out() {
+        true => Err(Trap::Interrupt.into()),
+        false => Ok(1),
+    });
+
+    // Nothing is linked, so plugins which import anything fail to instantiate
+    let instance = Linker::new(engine).instantiate(&mut store, module)?;
+    let memory = instance
+        .get_memory(&mut store, "memory")
+        .ok_or_else(|| wasmtime::Error::msg("the plugin exports no memory"))?;
+    let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+    let render = instance.get_typed_func::<(i32, i32), i64>(&mut store, "render")?;
+
+    let len = i32::try_from(request.len())?;
+    let ptr = alloc.call(&mut store, len)?;
+    memory.write(&mut store, ptr as u32 as usize, request)?;
+    let response = render.call(&mut store, (ptr, len))?;
+
+    let mut output = vec![0; response as u32 as usize];
+    memory.read(&store, (response >> 32) as u32 as usize, &mut output)?;
+    Ok(output)
+}
+
+#[cfg(test)]
+#[cfg(feature = "wasm")]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io;
+
+    fn run_wat(wat: &str, timeout: Duration) -> io::Result<Result<Vec<u8>, String>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("plugin.wat");
+        fs::write(&path, wat)?;
+        let output = run(&path, br#"{"version": 1}"#, timeout, None);
+        dir.close()?;
+        Ok(output)
+    }
+
+    #[test]
+    fn returns_response() -> io::Result<()> {
+        let wat = r#"(module
+            (memory (export "memory") 1)
+            (data (i32.const 0) "{\"segments\": [{\"text\": \"wasm\"}]}")
+            (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+            (func (export "render") (param i32 i32) (result i64) (i64.const 32)))"#;
+        let actual = run_wat(wat, Duration::from_secs(5))?;
+        let expected = Ok(br#"{"segments": [{"text": "wasm"}]}"#.to_vec());
+        assert_eq!(expected, actual);
+        Ok(())
+    }
+
+    #[test]
+    fn reads_request() -> io::Result<()> {
+        // Returns the request as it is
+        let wat = r#"(module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+            (func (export "render") (param i32 i32) (result i64)
+                (i64.or
+                    (i64.shl (i64.extend_i32_u (local.get 0)) (i64.const 32))
+                    (i64.extend_i32_u (local.get 1)))))"#;
+        let actual = run_wat(wat, Duration::from_secs(5))?;
+        assert_eq!(Ok(br#"{"version": 1}"#.to_vec()), actual);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_imports() -> io::Result<()> {
+        let wat = r#"(module
+            (import "wasi_snapshot_preview1" "proc_exit" (func (param i32)))
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) (i32.const 0))
+            (func (export "render") (param i32 i32) (result i64) (i64.const 0)))"#;
+        assert!(run_wat(wat, Duration::from_secs(5))?.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn interrupts_after_timeout() -> io::Result<()> {
+        let wat = r#"(module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) (i32.const 0))
+            (func (export "render") (param i32 i32) (result i64)
+                (loop (br 0))
+                (i64.const 0)))"#;
+        let actual = run_wat(wat, Duration::from_millis(100))?;
+        assert_eq!(Err("timed out after 100ms".to_string()), actual);
+        Ok(())
+    }
+
+    #[test]
+    fn loads_saved_plugins() -> io::Result<()> {
+        let wat = |text: &str| {
+            format!(
+                r#"(module
+                    (memory (export "memory") 1)
+                    (data (i32.const 0) "{}")
+                    (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+                    (func (export "render") (param i32 i32) (result i64) (i64.const {})))"#,
+                text,
+                text.len()
+            )
+        };
+        let dir = tempfile::tempdir()?;
+        let cache_dir = dir.path().join("cache");
+        let path = dir.path().join("plugin.wat");
+        fs::write(&path, wat("compiled"))?;
+        let request = br#"{"version": 1}"#;
+        let timeout = Duration::from_secs(5);
+
+        let output = run(&path, request, timeout, Some(&cache_dir));
+        assert_eq!(Ok(b"compiled".to_vec()), output);
+        let saved = fs::read_dir(&cache_dir)?.next().unwrap()?.path();
+
+        // The daemon keeps the compiled plugin, the next prompts run the saved one instead of
+        // compiling it again
+        let engine = ENGINE.as_ref().unwrap();
+        let other = Module::new(engine, wat("saved")).unwrap();
+        fs::write(&saved, other.serialize().unwrap())?;
+        let output = run(&path, request, timeout, Some(&cache_dir));
+        assert_eq!(Ok(b"compiled".to_vec()), output);
+        MODULES.lock().unwrap().clear();
+        let output = run(&path, request, timeout, Some(&cache_dir));
+        assert_eq!(Ok(b"saved".to_vec()), output);
+        dir.close()
+    }
+}
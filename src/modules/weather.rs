@@ -0,0 +1,333 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::utils::fetch_cache::FetchCache;
+use super::{Context, Module, RootModuleConfig};
+
+use crate::configs::weather::WeatherConfig;
+use crate::formatter::StringFormatter;
+
+const CACHE_NAME: &str = "weather.json";
+
+/// The temperature, description and kind of the current conditions
+type Conditions = (f64, String, &'static str);
+type Parser = fn(&Value, bool) -> Option<Conditions>;
+
+/// The current conditions, as persisted between prompts
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Weather {
+    /// The provider, location and units the conditions were fetched for
+    key: String,
+    temperature: f64,
+    /// The description of the provider, e.g. `Light rain`
+    condition: String,
+    /// One of `clear`, `clouds`, `fog`, `rain`, `snow` or `thunderstorm`
+    kind: String,
+}
+
+/// Creates a module with the current weather at the configured location
+///
+/// The weather is never fetched while rendering the prompt: it is read from a cache which a
+/// background process refreshes once it is older than `ttl` seconds.
+pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
+    let mut module = context.new_module("weather");
+    let config: WeatherConfig = WeatherConfig::try_load(module.config);
+
+    // As we default to disabled=true, we have to check here after loading our config module,
+    // before it was only checking against whatever is in the config starship.toml
+    if config.disabled {
+        return None;
+    }
+
+    let cache = FetchCache::new(context, CACHE_NAME)?;
+    let weather = cache.read().and_then(|(content, age)| {
+        let weather: Weather = serde_json::from_str(&content).ok()?;
+        // Conditions fetched with another configuration are outdated too
+        Some((weather, age)).filter(|(weather, _)| weather.key == cache_key(&config))
+    });
+    if !matches!(weather, Some((_, age)) if age < Duration::from_secs(config.ttl)) {
+        cache.spawn_fetch(context, "refresh-weather");
+    }
+    let (weather, _) = weather?;
+
+    let unit = if config.units == "imperial" {
+        "°F"
+    } else {
+        "°C"
+    };
+    let symbol = config
+        .symbols
+        .get(&weather.kind)
+        .copied()
+        .or_else(|| default_symbol(&weather.kind))
+        .unwrap_or(config.symbol);
+
+    let parsed = StringFormatter::new(config.format).and_then(|formatter| {
+        formatter
+            .map_meta(|variable, _| match variable {
+                "symbol" => Some(symbol),
+                _ => None,
+            })
+            .map_style(|variable| match variable {
+                "style" => Some(Ok(config.style)),
+                _ => None,
+            })
+            .map(|variable| match variable {
+                "temperature" => Some(Ok(format!("{:.0}{}", weather.temperature, unit))),
+                "condition" => Some(Ok(weather.condition.clone())),
+                _ => None,
+            })
+            .parse(None)
+    });
+
+    module.set_segments(match parsed {
+        Ok(segments) => segments,
+        Err(error) => {
+            log::warn!("Error in module `weather`:\n{}", error);
+            return None;
+        }
+    });
+
+    Some(module)
+}
+
+/// Fetches the current weather and saves it for the module to show.
+///
+/// This is run by the background process spawned by the module when the cached weather
+/// has expired.
+pub fn refresh_cache(context: &Context) {
+    let config = WeatherConfig::try_load(context.config.get_module_config("weather"));
+    let cache = match FetchCache::new(context, CACHE_NAME) {
+        Some(cache) => cache,
+        None => return,
+    };
+
+    let imperial = config.units == "imperial";
+    let location = urlencoding::encode(config.location);
+    let (url, parse): (String, Parser) = match config.provider {
+        "wttr.in" => (
+            format!("https://wttr.in/{}?format=j1", location),
+            parse_wttr,
+        ),
+        "openweather" => {
+            if config.location.is_empty() || config.api_key.is_empty() {
+                log::warn!("The openweather provider needs a `location` and an `api_key`");
+                return;
+            }
+            let url = format!(
+                "https://api.openweathermap.org/data/2.5/weather?q={}&units={}&appid={}",
+                location,
+                if imperial { "imperial" } else { "metric" },
+                config.api_key
+            );
+            (url, parse_openweather)
+        }
+        provider => {
+            log::warn!("Unknown weather provider {:?}", provider);
+            return;
+        }
+    };
+
+    let json = match fetch_json(&url, Duration::from_millis(config.timeout)) {
+        Some(json) => json,
+        None => return,
+    };
+    let (temperature, condition, kind) = match parse(&json, imperial) {
+        Some(weather) => weather,
+        None => {
+            log::warn!("Unexpected answer from {}: {}", config.provider, json);
+            return;
+        }
+    };
+    let weather = Weather {
+        key: cache_key(&config),
+        temperature,
+        condition,
+        kind: kind.to_string(),
+    };
+    let written = serde_json::to_string(&weather)
+        .map_err(Into::into)
+        .and_then(|content| cache.write(&content));
+    if let Err(error) = written {
+        log::warn!("Unable to write weather cache: {}", error);
+    }
+    cache.release();
+}
+
+fn cache_key(config: &WeatherConfig) -> String {
+    format!("{}|{}|{}", config.provider, config.location, config.units)
+}
+
+#[cfg(feature = "http")]
+fn fetch_json(url: &str, timeout: Duration) -> Option<Value> {
+    let response = attohttpc::get(url)
+        .timeout(timeout)
+        .send()
+        .and_then(|response| response.error_for_status()?.text());
+    let text = match response {
+        Ok(text) => text,
+        Err(error) => {
+            log::warn!("Unable to fetch the weather: {}", error);
+            return None;
+        }
+    };
+    match serde_json::from_str(&text) {
+        Ok(json) => Some(json),
+        Err(error) => {
+            log::warn!("The weather provider answered invalid JSON: {}", error);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch_json(_url: &str, _timeout: Duration) -> Option<Value> {
+    log::warn!("Fetching the weather needs starship to be built with the `http` feature");
+    None
+}
+
+/// Reads the current conditions from the `j1` format of wttr.in
+fn parse_wttr(json: &Value, imperial: bool) -> Option<Conditions> {
+    let current = &json["current_condition"][0];
+    let temperature = current[if imperial { "temp_F" } else { "temp_C" }]
+        .as_str()?
+        .parse()
+        .ok()?;
+    let condition = current["weatherDesc"][0]["value"].as_str()?.trim();
+    // The codes of World Weather Online, which wttr.in uses
+    let kind = match current["weatherCode"].as_str()?.parse::<u32>().ok()? {
+        113 => "clear",
+        116 | 119 | 122 => "clouds",
+        143 | 248 | 260 => "fog",
+        200 | 386..=395 => "thunderstorm",
+        179 | 182 | 185 | 227 | 230 | 317..=338 | 350 | 362..=377 => "snow",
+        _ => "rain",
+    };
+    Some((temperature, condition.to_string(), kind))
+}
+
+/// Reads the current conditions from OpenWeather, which are already in the requested units
+fn parse_openweather(json: &Value, _imperial: bool) -> Option<Conditions> {
+    let temperature = json["main"]["temp"].as_f64()?;
+    let weather = &json["weather"][0];
+    let condition = capitalize(weather["description"].as_str()?);
+    let kind = match weather["id"].as_u64()? {
+        200..=299 => "thunderstorm",
+        600..=699 => "snow",
+        700..=799 => "fog",
+        800 => "clear",
+        801..=899 => "clouds",
+        _ => "rain",
+    };
+    Some((temperature, condition, kind))
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn default_symbol(kind: &str) -> Option<&'static str> {
+    match kind {
+        "clear" => Some("☀️ "),
+        "clouds" => Some("☁️ "),
+        "fog" => Some("🌫️ "),
+        "rain" => Some("🌧️ "),
+        "snow" => Some("❄️ "),
+        "thunderstorm" => Some("⛈️ "),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::ModuleRenderer;
+    use std::fs;
+    use std::io;
+
+    #[test]
+    fn parses_wttr() {
+        let json = serde_json::json!({
+            "current_condition": [{
+                "temp_C": "12",
+                "temp_F": "54",
+                "weatherCode": "296",
+                "weatherDesc": [{"value": "Light rain "}]
+            }]
+        });
+        assert_eq!(
+            Some((12.0, "Light rain".to_string(), "rain")),
+            parse_wttr(&json, false)
+        );
+        assert_eq!(Some(54.0), parse_wttr(&json, true).map(|weather| weather.0));
+        assert_eq!(None, parse_wttr(&serde_json::json!({}), false));
+    }
+
+    #[test]
+    fn parses_openweather() {
+        let json = serde_json::json!({
+            "weather": [{"id": 802, "main": "Clouds", "description": "scattered clouds"}],
+            "main": {"temp": 17.4}
+        });
+        assert_eq!(
+            Some((17.4, "Scattered clouds".to_string(), "clouds")),
+            parse_openweather(&json, false)
+        );
+    }
+
+    fn render(cache_dir: &std::path::Path, config: toml::Value) -> Option<String> {
+        ModuleRenderer::new("weather")
+            .config(config)
+            .env("STARSHIP_CACHE", cache_dir.to_string_lossy())
+            .collect()
+    }
+
+    #[test]
+    fn shows_cached_weather() -> io::Result<()> {
+        let cache_dir = tempfile::tempdir()?;
+        let weather = Weather {
+            key: "wttr.in|Oslo|metric".to_string(),
+            temperature: -3.4,
+            condition: "Light snow".to_string(),
+            kind: "snow".to_string(),
+        };
+        fs::write(
+            cache_dir.path().join(CACHE_NAME),
+            serde_json::to_string(&weather)?,
+        )?;
+
+        let config = toml::toml! {
+            [weather]
+            disabled = false
+            location = "Oslo"
+            format = "$symbol$temperature $condition"
+        };
+        assert_eq!(
+            Some("❄️ -3°C Light snow".to_string()),
+            render(cache_dir.path(), config)
+        );
+        assert!(!cache_dir.path().join("weather.json.lock").exists());
+
+        // Another location needs fetching
+        let config = toml::toml! {
+            [weather]
+            disabled = false
+            location = "Lima"
+        };
+        assert_eq!(None, render(cache_dir.path(), config));
+        assert!(cache_dir.path().join("weather.json.lock").exists());
+        cache_dir.close()
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let actual = ModuleRenderer::new("weather").collect();
+        assert_eq!(None, actual);
+    }
+}
@@ -0,0 +1,297 @@
+use wildmatch::WildMatch;
+
+use super::{Context, Module, RootModuleConfig, Shell};
+
+use crate::configs::wifi::WifiConfig;
+use crate::formatter::StringFormatter;
+
+#[cfg(target_os = "macos")]
+const AIRPORT: &str =
+    "/System/Library/PrivateFrameworks/Apple80211.framework/Versions/Current/Resources/airport";
+
+/// The network the computer is connected to
+#[derive(Debug, PartialEq)]
+struct Wifi {
+    ssid: String,
+    /// The signal strength, in percent
+    signal: Option<u8>,
+}
+
+/// Creates a module with the SSID and signal strength of the connected Wi-Fi network
+///
+/// Will display the network if all of the following criteria are met:
+///     - wifi.disabled is false
+///     - the SSID doesn't match any of the `trusted` patterns
+///     - `untrusted` is empty OR the SSID matches one of its patterns
+pub fn module<'a>(context: &'a Context) -> Option<Module<'a>> {
+    let mut module = context.new_module("wifi");
+    let config: WifiConfig = WifiConfig::try_load(module.config);
+
+    // As we default to disabled=true, we have to check here after loading our config module,
+    // before it was only checking against whatever is in the config starship.toml
+    if config.disabled {
+        return None;
+    }
+
+    let wifi = get_wifi(context)?;
+    if !is_shown(&wifi.ssid, &config) {
+        log::debug!("Not showing the trusted network {:?}", wifi.ssid);
+        return None;
+    }
+
+    // TODO: Update when v1.0 printing refactor is implemented to only
+    // print escapes in a prompt context.
+    let pct_sign = match context.shell {
+        Shell::Zsh => "%%", // % is an escape in zsh, see PROMPT in `man zshmisc`
+        _ => "%",
+    };
+
+    let parsed = StringFormatter::new(config.format).and_then(|formatter| {
+        formatter
+            .map_meta(|variable, _| match variable {
+                "symbol" => Some(config.symbol),
+                _ => None,
+            })
+            .map_style(|variable| match variable {
+                "style" => Some(Ok(config.style)),
+                _ => None,
+            })
+            .map(|variable| match variable {
+                "ssid" => Some(Ok(wifi.ssid.clone())),
+                "signal" => wifi
+                    .signal
+                    .map(|signal| Ok(format!("{}{}", signal, pct_sign))),
+                _ => None,
+            })
+            .parse(None)
+    });
+
+    module.set_segments(match parsed {
+        Ok(segments) => segments,
+        Err(error) => {
+            log::warn!("Error in module `wifi`:\n{}", error);
+            return None;
+        }
+    });
+
+    Some(module)
+}
+
+fn is_shown(ssid: &str, config: &WifiConfig) -> bool {
+    let matches = |patterns: &[&str]| {
+        patterns
+            .iter()
+            .any(|pattern| WildMatch::new(pattern).is_match(ssid))
+    };
+    !matches(&config.trusted) && (config.untrusted.is_empty() || matches(&config.untrusted))
+}
+
+#[cfg(target_os = "linux")]
+fn get_wifi(context: &Context) -> Option<Wifi> {
+    let nmcli = context.exec_cmd(
+        "nmcli",
+        &["-t", "-f", "in-use,ssid,signal", "device", "wifi"],
+    );
+    if let Some(wifi) = nmcli.and_then(|output| parse_nmcli(&output.stdout)) {
+        return Some(wifi);
+    }
+    // Without NetworkManager, wireless tools still know the SSID
+    let ssid = context
+        .exec_cmd("iwgetid", &["-r"])?
+        .stdout
+        .trim()
+        .to_string();
+    if ssid.is_empty() {
+        return None;
+    }
+    Some(Wifi { ssid, signal: None })
+}
+
+#[cfg(target_os = "macos")]
+fn get_wifi(context: &Context) -> Option<Wifi> {
+    parse_airport(&context.exec_cmd(AIRPORT, &["-I"])?.stdout)
+}
+
+#[cfg(windows)]
+fn get_wifi(context: &Context) -> Option<Wifi> {
+    parse_netsh(
+        &context
+            .exec_cmd("netsh", &["wlan", "show", "interfaces"])?
+            .stdout,
+    )
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn get_wifi(_context: &Context) -> Option<Wifi> {
+    log::debug!("The wifi module is not supported on this platform");
+    None
+}
+
+/// Parses the output of `nmcli -t -f in-use,ssid,signal device wifi`, where the network in
+/// use is marked with `*`, e.g. `*:Home\:5G:72`
+#[cfg(any(target_os = "linux", test))]
+fn parse_nmcli(output: &str) -> Option<Wifi> {
+    output.lines().find_map(|line| {
+        let fields = split_terse(line);
+        match fields.as_slice() {
+            [in_use, ssid, signal] if in_use == "*" && !ssid.is_empty() => Some(Wifi {
+                ssid: ssid.to_string(),
+                signal: signal.parse().ok(),
+            }),
+            _ => None,
+        }
+    })
+}
+
+/// Splits a line of the terse output of `nmcli` at the colons which aren't escaped
+#[cfg(any(target_os = "linux", test))]
+fn split_terse(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => field.extend(chars.next()),
+            ':' => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Parses the output of `airport -I`, which has lines like `SSID: Home` and `agrCtlRSSI: -55`
+#[cfg(any(target_os = "macos", test))]
+fn parse_airport(output: &str) -> Option<Wifi> {
+    let value = |key: &str| {
+        output.lines().find_map(|line| {
+            let (name, value) = line.trim().split_once(':')?;
+            if name == key {
+                Some(value.trim())
+            } else {
+                None
+            }
+        })
+    };
+    let ssid = value("SSID").filter(|ssid| !ssid.is_empty())?;
+    // The signal is in dBm, from -100 (unusable) to -50 (excellent)
+    let signal = value("agrCtlRSSI")
+        .and_then(|rssi| rssi.parse::<i32>().ok())
+        .map(|rssi| (2 * (rssi + 100)).clamp(0, 100) as u8);
+    Some(Wifi {
+        ssid: ssid.to_string(),
+        signal,
+    })
+}
+
+/// Parses the output of `netsh wlan show interfaces`, which has lines like
+/// `SSID                   : Home` and `Signal                 : 90%`
+#[cfg(any(windows, test))]
+fn parse_netsh(output: &str) -> Option<Wifi> {
+    let value = |key: &str| {
+        output.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            if name.trim() == key {
+                Some(value.trim())
+            } else {
+                None
+            }
+        })
+    };
+    let ssid = value("SSID").filter(|ssid| !ssid.is_empty())?;
+    let signal = value("Signal").and_then(|signal| signal.trim_end_matches('%').parse().ok());
+    Some(Wifi {
+        ssid: ssid.to_string(),
+        signal,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::ModuleRenderer;
+
+    fn wifi(ssid: &str, signal: Option<u8>) -> Option<Wifi> {
+        Some(Wifi {
+            ssid: ssid.to_string(),
+            signal,
+        })
+    }
+
+    #[test]
+    fn parses_nmcli() {
+        let output = " :Neighbours:40\n*:Home\\:5G:72\n*:Home\\:5G:70\n";
+        assert_eq!(wifi("Home:5G", Some(72)), parse_nmcli(output));
+        assert_eq!(None, parse_nmcli(" :Neighbours:40\n"));
+    }
+
+    #[test]
+    fn parses_airport() {
+        let output = "     agrCtlRSSI: -55
+     agrExtRSSI: 0
+          BSSID: 4:5b:2:10:a3:1
+           SSID: Café: Free
+        channel: 36,80
+";
+        assert_eq!(wifi("Café: Free", Some(90)), parse_airport(output));
+        assert_eq!(None, parse_airport("AirPort: Off\n"));
+    }
+
+    #[test]
+    fn parses_netsh() {
+        let output = "
+There is 1 interface on the system:
+
+    Name                   : Wi-Fi
+    State                  : connected
+    SSID                   : Home
+    BSSID                  : 04:5b:02:10:a3:01
+    Signal                 : 88%
+";
+        assert_eq!(wifi("Home", Some(88)), parse_netsh(output));
+        assert_eq!(
+            None,
+            parse_netsh("    State                  : disconnected\n")
+        );
+    }
+
+    #[test]
+    fn hides_trusted_networks() {
+        let config = |trusted: Vec<&'static str>, untrusted: Vec<&'static str>| WifiConfig {
+            trusted,
+            untrusted,
+            ..WifiConfig::default()
+        };
+        assert!(is_shown("Airport", &config(vec![], vec![])));
+        assert!(!is_shown("Home", &config(vec!["Home*"], vec![])));
+        assert!(is_shown("Cafe", &config(vec!["Home*"], vec![])));
+        assert!(!is_shown("Cafe", &config(vec![], vec!["Airport*"])));
+        assert!(is_shown("Airport Free", &config(vec![], vec!["Airport*"])));
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let actual = ModuleRenderer::new("wifi").collect();
+        assert_eq!(None, actual);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn shows_connected_network() {
+        let actual = ModuleRenderer::new("wifi")
+            .config(toml::toml! {
+                [wifi]
+                disabled = false
+                format = "$ssid $signal"
+            })
+            .cmd(
+                "nmcli -t -f in-use,ssid,signal device wifi",
+                Some(crate::utils::CommandOutput {
+                    stdout: "*:Cafe:64\n".to_string(),
+                    stderr: String::new(),
+                }),
+            )
+            .collect();
+        assert_eq!(Some("Cafe 64%".to_string()), actual);
+    }
+}
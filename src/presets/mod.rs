@@ -0,0 +1,142 @@
+use std::ffi::OsStr;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+
+use crate::configure::get_config_path;
+
+/// A config bundled with starship, so it can be used offline
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub config: &'static str,
+}
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "bracketed-segments",
+        description: "Wraps each module in brackets instead of words like \"via\" and \"on\"",
+        config: include_str!("bracketed-segments.toml"),
+    },
+    Preset {
+        name: "nerd-font-symbols",
+        description: "Uses icons from a Nerd Font as the symbols of modules",
+        config: include_str!("nerd-font-symbols.toml"),
+    },
+    Preset {
+        name: "plain-text",
+        description: "Uses plain text as the symbols of modules, for terminals without emoji",
+        config: include_str!("plain-text.toml"),
+    },
+    Preset {
+        name: "pure-style",
+        description: "Looks like the Pure prompt for zsh",
+        config: include_str!("pure-style.toml"),
+    },
+];
+
+fn find(name: &str) -> &'static Preset {
+    match PRESETS.iter().find(|preset| preset.name == name) {
+        Some(preset) => preset,
+        None => {
+            eprintln!(
+                "Unknown preset {:?}. Use `starship preset` to list the presets.",
+                name
+            );
+            process::exit(1);
+        }
+    }
+}
+
+/// Lists the bundled presets with their descriptions
+pub fn list() {
+    let width = PRESETS
+        .iter()
+        .map(|preset| preset.name.len())
+        .max()
+        .unwrap_or(0);
+    for preset in PRESETS {
+        println!(
+            "{:<width$}  {}",
+            preset.name,
+            preset.description,
+            width = width
+        );
+    }
+}
+
+/// Prints the config of a preset
+pub fn print(name: &str) {
+    print!("{}", find(name).config);
+}
+
+/// Writes the config of a preset to the config file. An existing config file is only
+/// replaced with `force`.
+pub fn install(name: &str, force: bool) {
+    let preset = find(name);
+    let config_path = get_config_path();
+    let display_path = Path::new(&config_path).display();
+    match write_preset(preset, &config_path, force) {
+        Ok(()) => println!("Installed the {} preset to {}", preset.name, display_path),
+        Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+            eprintln!(
+                "{} already exists. Use --force to replace it with the preset.",
+                display_path
+            );
+            process::exit(1);
+        }
+        Err(error) => {
+            eprintln!("Unable to write {}: {}", display_path, error);
+            process::exit(1);
+        }
+    }
+}
+
+fn write_preset(preset: &Preset, path: &OsStr, force: bool) -> io::Result<()> {
+    if let Some(parent) = Path::new(path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(force)
+        .create_new(!force)
+        .truncate(true)
+        .open(path)?;
+    file.write_all(preset.config.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::validate;
+
+    #[test]
+    fn presets_are_valid_configs() {
+        for preset in PRESETS {
+            let config = toml::from_str(preset.config)
+                .unwrap_or_else(|error| panic!("{} is invalid TOML: {}", preset.name, error));
+            let problems: Vec<_> = validate(&config).iter().map(ToString::to_string).collect();
+            assert!(problems.is_empty(), "{}: {:?}", preset.name, problems);
+        }
+    }
+
+    #[test]
+    fn installing_keeps_existing_config() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("config").join("starship.toml");
+        let preset = find("plain-text");
+
+        write_preset(preset, path.as_os_str(), false)?;
+        assert_eq!(preset.config, fs::read_to_string(&path)?);
+
+        let pure = find("pure-style");
+        let error = write_preset(pure, path.as_os_str(), false).unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, error.kind());
+        assert_eq!(preset.config, fs::read_to_string(&path)?);
+
+        write_preset(pure, path.as_os_str(), true)?;
+        assert_eq!(pure.config, fs::read_to_string(&path)?);
+        dir.close()
+    }
+}
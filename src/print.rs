@@ -1,46 +1,85 @@
-use ansi_term::ANSIStrings;
+use ansi_term::{ANSIStrings, Color, Style};
 use clap::ArgMatches;
 use rayon::prelude::*;
-use std::collections::BTreeSet;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::convert::TryFrom;
 use std::fmt::{self, Debug, Write as FmtWrite};
 use std::io::{self, Write};
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthChar;
 
+use crate::bug_report;
+use crate::config::{parse_color_string, StarshipConfig};
 use crate::configs::PROMPT_ORDER;
-use crate::context::{Context, Shell};
+use crate::context::{is_hidden, ColorDepth, Context, ModuleDetails, Shell, Target};
+use crate::daemon::{self, ClientEnv, DaemonCache};
 use crate::formatter::{StringFormatter, VariableHolder};
+use crate::history;
 use crate::module::Module;
 use crate::module::ALL_MODULES;
+use crate::module_cache::ModuleCache;
 use crate::modules;
 use crate::segment::Segment;
+use crate::utils::wrap_colorseq_for_shell;
 
 pub struct Grapheme<'a>(&'a str);
 
 impl<'a> Grapheme<'a> {
     pub fn width(&self) -> usize {
+        self.width_with_ambiguous(1)
+    }
+
+    /// The width of the grapheme on a terminal that renders East Asian
+    /// ambiguous-width characters `ambiguous_width` (1 or 2) columns wide
+    ///
+    /// Terminals show characters followed by an emoji presentation selector as wide emoji,
+    /// and those followed by a text presentation selector as narrow text. Flags are pairs of
+    /// regional indicators, which are only one column wide on their own.
+    pub fn width_with_ambiguous(&self, ambiguous_width: usize) -> usize {
+        let is_regional_indicator = |c: char| ('\u{1f1e6}'..='\u{1f1ff}').contains(&c);
+        if self.0.contains('\u{fe0f}') {
+            return 2;
+        }
+        if self.0.contains('\u{fe0e}') {
+            return 1;
+        }
+        if self.0.chars().filter(|&c| is_regional_indicator(c)).count() == 2 {
+            return 2;
+        }
         self.0
             .chars()
-            .filter_map(UnicodeWidthChar::width)
+            .filter_map(|c| {
+                if ambiguous_width == 2 {
+                    c.width_cjk()
+                } else {
+                    c.width()
+                }
+            })
             .max()
             .unwrap_or(0)
     }
 }
 
 pub trait UnicodeWidthGraphemes {
-    fn width_graphemes(&self) -> usize;
+    fn width_graphemes(&self) -> usize {
+        self.width_graphemes_with_ambiguous(1)
+    }
+
+    fn width_graphemes_with_ambiguous(&self, ambiguous_width: usize) -> usize;
 }
 
 impl<T> UnicodeWidthGraphemes for T
 where
     T: AsRef<str>,
 {
-    fn width_graphemes(&self) -> usize {
+    fn width_graphemes_with_ambiguous(&self, ambiguous_width: usize) -> usize {
         self.as_ref()
             .graphemes(true)
             .map(Grapheme)
-            .map(|g| g.width())
+            .map(|g| g.width_with_ambiguous(ambiguous_width))
             .sum()
     }
 }
@@ -51,20 +90,92 @@ fn test_grapheme_aware_width() {
     assert_eq!(2, "👩‍👩‍👦‍👦".width_graphemes());
     assert_eq!(1, "Ü".width_graphemes());
     assert_eq!(11, "normal text".width_graphemes());
+    assert_eq!(4, "日本".width_graphemes());
+}
+
+#[test]
+fn test_emoji_presentation_width() {
+    // ☁ is narrow text unless followed by the emoji presentation selector
+    assert_eq!(1, "☁".width_graphemes());
+    assert_eq!(2, "☁️".width_graphemes());
+    assert_eq!(1, "⌚︎".width_graphemes());
+    assert_eq!(2, "1️⃣".width_graphemes());
+    assert_eq!(2, "🇩🇪".width_graphemes());
+    assert_eq!(4, "🇩🇪🇫🇷".width_graphemes());
+}
+
+#[test]
+fn test_ambiguous_width() {
+    assert_eq!(1, "…".width_graphemes());
+    assert_eq!(2, "…".width_graphemes_with_ambiguous(2));
+    assert_eq!(4, "→ab".width_graphemes_with_ambiguous(2));
+    assert_eq!(2, "日".width_graphemes_with_ambiguous(1));
+}
+
+/// Set while starship renders a prompt, so that commands it runs (e.g. custom modules)
+/// can't render prompts themselves and recurse endlessly
+pub(crate) const RECURSION_GUARD: &str = "STARSHIP_RECURSION_GUARD";
+
+/// Returns true if starship was invoked by a command run while rendering a prompt,
+/// otherwise marks the current process and its children as rendering one
+fn is_recursive_invocation() -> bool {
+    if std::env::var_os(RECURSION_GUARD).is_some() {
+        log::warn!(
+            "Starship was invoked while rendering a prompt, check your custom modules and shell hooks for calls to starship"
+        );
+        return true;
+    }
+    std::env::set_var(RECURSION_GUARD, "1");
+    false
 }
 
 pub fn prompt(args: ArgMatches) {
-    let context = Context::new(args);
+    if is_recursive_invocation() {
+        if !args.is_present("right") {
+            print!("> ");
+        }
+        return;
+    }
+
+    // The daemon only renders prompts as text
+    let output = if args.value_of("format") == Some("json") {
+        get_prompt_json(Context::new(args))
+    } else {
+        daemon::request(&args).unwrap_or_else(|| render_prompt(Context::new(args), None))
+    };
     let stdout = io::stdout();
     let mut handle = stdout.lock();
-    write!(handle, "{}", get_prompt(context)).unwrap();
+    write!(handle, "{}", output).unwrap();
+}
+
+/// Renders a prompt sent to `starship daemon` for the shell of `client`, which keeps
+/// `cache` between prompts
+pub fn daemon_prompt(args: ArgMatches, mut client: ClientEnv, cache: &Arc<DaemonCache>) -> String {
+    // Commands run for the prompt get the environment variables of the client
+    client
+        .env
+        .insert(RECURSION_GUARD.to_string(), "1".to_string());
+    render_prompt(Context::new_for_client(args, client), Some(cache))
+}
+
+fn render_prompt(mut context: Context, daemon: Option<&Arc<DaemonCache>>) -> String {
+    if let Some(cache) = daemon {
+        context.daemon = Some(Arc::clone(cache));
+        // Keep the module cache in the memory of the daemon
+        context.module_cache = ModuleCache::new(&context);
+    }
+    if context.target == Target::Main {
+        history::record(&context);
+    }
+    get_prompt(context)
 }
 
 pub fn get_prompt(context: Context) -> String {
+    let start = Instant::now();
     let config = context.config.get_root_config();
     let mut buf = String::new();
 
-    match std::env::var_os("TERM") {
+    match context.get_env("TERM") {
         Some(term) if term == "dumb" => {
             log::error!("Under a 'dumb' terminal (TERM=dumb).");
             buf.push_str("Starship disabled due to TERM=dumb > ");
@@ -75,77 +186,729 @@ pub fn get_prompt(context: Context) -> String {
 
     // A workaround for a fish bug (see #739,#279). Applying it to all shells
     // breaks things (see #808,#824,#834). Should only be printed in fish.
-    if let Shell::Fish = context.shell {
+    if let (Shell::Fish, Target::Main) = (context.shell, context.target) {
         buf.push_str("\x1b[J"); // An ASCII control code to clear screen
     }
 
-    let formatter = if let Ok(formatter) = StringFormatter::new(config.format) {
+    let root_module = match render_prompt_module(&context) {
+        Some(root_module) => root_module,
+        None => {
+            buf.push('>');
+            return buf;
+        }
+    };
+
+    let module_strings = root_module.ansi_strings_for_shell(context.shell);
+
+    // Semantic prompt markers (OSC 133) let terminals find where prompts and commands start
+    let semantic_prompt = config.semantic_prompt && context.target == Target::Main;
+    if semantic_prompt {
+        let mut markers = String::new();
+        if let Some(status) = context.properties.get("status_code") {
+            write!(markers, "\x1b]133;D;{}\x07", status).unwrap();
+        }
+        markers.push_str("\x1b]133;A\x07");
+        buf.push_str(&wrap_colorseq_for_shell(markers, context.shell));
+    }
+
+    if !config.window_title.is_empty() && context.target == Target::Main {
+        // Control characters (e.g. from directory names) would end the title sequence early
+        let title: String = render_statusline(&context, config.window_title, &root_module.segments)
+            .chars()
+            .filter(|c| !c.is_control())
+            .collect();
+        buf.push_str(&wrap_colorseq_for_shell(
+            format!("\x1b]0;{}\x07", title),
+            context.shell,
+        ));
+    }
+
+    if config.add_newline && context.target == Target::Main {
+        writeln!(buf).unwrap();
+    }
+
+    // PowerShell has no native right prompt, so it is drawn at the end of the current line:
+    // save the cursor, move to the column where the right prompt starts, restore the cursor
+    let right_prompt_column = match (context.shell, context.target) {
+        (Shell::PowerShell, Target::Right) if !root_module.is_empty() => {
+            let width = root_module
+                .get_segments()
+                .concat()
+                .width_graphemes_with_ambiguous(config.ambiguous_width);
+            context.width.checked_sub(width).map(|column| column + 1)
+        }
+        _ => None,
+    };
+    if let Some(column) = right_prompt_column {
+        write!(buf, "\x1b[s\x1b[{}G", column).unwrap();
+    }
+    write!(buf, "{}", ANSIStrings(&module_strings)).unwrap();
+    if right_prompt_column.is_some() {
+        buf.push_str("\x1b[u");
+    }
+    if semantic_prompt {
+        buf.push_str(&wrap_colorseq_for_shell(
+            "\x1b]133;B\x07".to_string(),
+            context.shell,
+        ));
+    }
+
+    // escape \n and ! characters for tcsh
+    if let Shell::Tcsh = context.shell {
+        buf = buf.replace('!', "\\!");
+        // space is required before newline
+        buf = buf.replace('\n', " \\n");
+    }
+
+    if let Some(metrics) = &context.metrics {
+        metrics.timing("prompt", start.elapsed());
+    }
+    if let (Some(guard), Target::Main | Target::Right) = (&context.slow_modules, context.target) {
+        guard.save();
+    }
+    if let (Some(cache), Target::Main | Target::Right) = (&context.module_cache, context.target) {
+        cache.save();
+    }
+
+    buf
+}
+
+/// Renders the format of the prompt `context` is for, with fills expanded and colors
+/// downgraded to the ones the terminal supports. Returns `None` if the format is invalid.
+fn render_prompt_module<'a>(context: &'a Context) -> Option<Module<'a>> {
+    let config = context.config.get_root_config();
+    let (format, other_format) = match context.target {
+        Target::Main => (config.format, config.right_format),
+        Target::Right => (config.right_format, config.format),
+        Target::Transient => (config.transient_format, ""),
+        Target::Continuation => (config.continuation_format, ""),
+    };
+    // Modules explicitly shown in the other prompt are left out of `$all`
+    let excluded_modules: BTreeSet<String> = StringFormatter::new(other_format)
+        .map(|formatter| formatter.get_variables())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|module| module != "all")
+        .collect();
+
+    let formatter = if let Ok(formatter) = StringFormatter::new(format) {
         formatter
     } else {
         log::error!("Error parsing `format`");
-        buf.push('>');
-        return buf;
+        return None;
     };
+    let mut root_module = render_root_module(formatter, context, &excluded_modules);
+    root_module.segments = add_backgrounds(
+        std::mem::take(&mut root_module.segments),
+        context,
+        config.segment_separator,
+        config.segment_subseparator,
+    );
+    // A right prompt shares its line with the left prompt, so it can't be filled
+    if context.target != Target::Right {
+        fill_lines(
+            &mut root_module.segments,
+            context.width,
+            config.ambiguous_width,
+        );
+    }
+    downgrade_colors(&mut root_module, context);
+    Some(root_module)
+}
+
+/// Replaces the colors of `module` with the closest ones the terminal can show
+fn downgrade_colors(module: &mut Module, context: &Context) {
+    let color_depth = context.color_depth();
+    if color_depth == ColorDepth::TrueColor {
+        return;
+    }
+    for segment in &mut module.segments {
+        segment.style = segment.style.map(|style| color_depth.downgrade(style));
+        segment.underline = segment
+            .underline
+            .map(|underline| color_depth.downgrade_underline(underline));
+    }
+}
+
+/// The prompt printed by `starship prompt --format json`
+#[derive(Serialize)]
+struct JsonPrompt<'a> {
+    segments: Vec<JsonSegment<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonSegment<'a> {
+    module: Option<&'a str>,
+    text: &'a str,
+    foreground: Option<String>,
+    background: Option<String>,
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    dimmed: bool,
+    strikethrough: bool,
+    blink: bool,
+    double_underline: bool,
+    underline_color: Option<String>,
+    link: Option<&'a str>,
+}
+
+/// Renders the prompt as JSON segments with their module and style, for status bars and
+/// terminals which style them natively instead of parsing escape sequences
+pub fn get_prompt_json(context: Context) -> String {
+    let root_module = render_prompt_module(&context);
+    let segments: Vec<JsonSegment> = root_module
+        .iter()
+        .flat_map(|root_module| &root_module.segments)
+        .filter(|segment| !segment.value.is_empty())
+        .map(|segment| {
+            let style = segment.style.unwrap_or_default();
+            let underline = segment.underline.unwrap_or_default();
+            JsonSegment {
+                module: segment.module.as_deref(),
+                text: &segment.value,
+                foreground: style.foreground.map(color_name),
+                background: style.background.map(color_name),
+                bold: style.is_bold,
+                italic: style.is_italic,
+                underline: style.is_underline,
+                dimmed: style.is_dimmed,
+                strikethrough: style.is_strikethrough,
+                blink: style.is_blink,
+                double_underline: underline.double,
+                underline_color: underline.color.map(color_name),
+                link: segment.link.as_deref(),
+            }
+        })
+        .collect();
+    serde_json::to_string(&JsonPrompt { segments }).unwrap()
+}
+
+/// The name of a color as written in style strings, e.g. `red`, `208` or `#ff8700`
+fn color_name(color: Color) -> String {
+    match color {
+        Color::Black => "black".to_string(),
+        Color::Red => "red".to_string(),
+        Color::Green => "green".to_string(),
+        Color::Yellow => "yellow".to_string(),
+        Color::Blue => "blue".to_string(),
+        Color::Purple => "purple".to_string(),
+        Color::Cyan => "cyan".to_string(),
+        Color::White => "white".to_string(),
+        Color::Fixed(n) => n.to_string(),
+        Color::RGB(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+    }
+}
+
+/// Renders a root format string, expanding `$all` to every module except `excluded_modules`
+fn render_root_module<'a>(
+    formatter: StringFormatter,
+    context: &'a Context,
+    excluded_modules: &BTreeSet<String>,
+) -> Module<'a> {
     let modules = formatter.get_variables();
+    let module_list = modules.union(excluded_modules).cloned().collect();
+    let ambiguous_width = context.config.get_root_config().ambiguous_width;
+    let module_segments = |module| module_segments(module, ambiguous_width);
     let formatter = formatter.map_variables_to_segments(|module| {
         // Make $all display all modules
         if module == "all" {
             Some(Ok(PROMPT_ORDER
                 .par_iter()
+                .filter(|module| !excluded_modules.contains(**module))
                 .flat_map(|module| {
-                    handle_module(module, &context, &modules)
+                    handle_module(module, context, &module_list)
                         .into_iter()
-                        .flat_map(|module| module.segments)
+                        .flat_map(module_segments)
                         .collect::<Vec<Segment>>()
                 })
                 .collect::<Vec<_>>()))
-        } else if context.is_module_disabled_in_config(&module) {
+        } else if module == "width" {
+            Some(Ok(vec![Segment::new(None, context.width.to_string())]))
+        } else if module == "align" {
+            // Aligns the lines of formats written as arrays, see `config::join_format_lines`
+            Some(Ok(vec![Segment::fill(None, " ")]))
+        } else if context.is_module_disabled_in_config(module) {
             None
         } else {
             // Get segments from module
-            Some(Ok(handle_module(module, &context, &modules)
+            Some(Ok(handle_module(module, context, &module_list)
                 .into_iter()
-                .flat_map(|module| module.segments)
+                .flat_map(module_segments)
                 .collect::<Vec<Segment>>()))
         }
     });
 
-    // Creates a root module and prints it.
+    // Creates a root module
     let mut root_module = Module::new("Starship Root", "The root module", None);
     root_module.set_segments(
         formatter
             .parse(None)
             .expect("Unexpected error returned in root format variables"),
     );
+    root_module
+}
 
-    let module_strings = root_module.ansi_strings_for_shell(context.shell);
-    if config.add_newline {
-        writeln!(buf).unwrap();
+/// The segments of a module, marked as coming from it
+fn module_segments(mut module: Module, ambiguous_width: usize) -> Vec<Segment> {
+    constrain_width(&mut module, ambiguous_width);
+    let name = module.get_name().clone();
+    module
+        .segments
+        .into_iter()
+        .map(|mut segment| {
+            segment.module = Some(name.clone());
+            segment
+        })
+        .collect()
+}
+
+/// Pads the segments of a module with spaces to the `min_width` option of the module, or
+/// truncates them with `…` to its `max_width` option. Fills and empty modules are left out.
+fn constrain_width(module: &mut Module, ambiguous_width: usize) {
+    let get = |key: &str| {
+        module
+            .config?
+            .get(key)?
+            .as_integer()
+            .and_then(|width| usize::try_from(width).ok())
+    };
+    let (min_width, max_width) = (get("min_width"), get("max_width"));
+    let segments = &mut module.segments;
+    let width: usize = segments
+        .iter()
+        .filter(|segment| !segment.fill)
+        .map(|segment| {
+            segment
+                .value
+                .width_graphemes_with_ambiguous(ambiguous_width)
+        })
+        .sum();
+    if width == 0 {
+        return;
     }
-    write!(buf, "{}", ANSIStrings(&module_strings)).unwrap();
 
-    // escape \n and ! characters for tcsh
-    if let Shell::Tcsh = context.shell {
-        buf = buf.replace('!', "\\!");
-        // space is required before newline
-        buf = buf.replace('\n', " \\n");
+    if let Some(max_width) = max_width.filter(|&max_width| width > max_width) {
+        // Leave a column for the ellipsis, which goes after the last grapheme that fits
+        let mut remaining = max_width.saturating_sub(1);
+        let mut cut = None;
+        for (index, segment) in segments.iter_mut().enumerate() {
+            if segment.fill {
+                continue;
+            }
+            if cut.is_some() {
+                segment.value.clear();
+                continue;
+            }
+            let mut kept = String::new();
+            for grapheme in segment.value.graphemes(true) {
+                let grapheme_width = Grapheme(grapheme).width_with_ambiguous(ambiguous_width);
+                if grapheme_width > remaining {
+                    cut = Some(index);
+                    break;
+                }
+                remaining -= grapheme_width;
+                kept.push_str(grapheme);
+            }
+            segment.value = kept;
+        }
+        if let (Some(index), true) = (cut, max_width > 0) {
+            segments[index].value.push('…');
+        }
+        segments.retain(|segment| segment.fill || !segment.value.is_empty());
+    } else if let Some(min_width) = min_width.filter(|&min_width| width < min_width) {
+        if let Some(last) = segments.iter_mut().rev().find(|segment| !segment.fill) {
+            last.value.push_str(&" ".repeat(min_width - width));
+        }
     }
+}
 
-    buf
+/// Colors the segments of modules with a `background` option, and separates them with
+/// `separator` styled to blend their backgrounds into each other. Modules with the same
+/// background are separated with `subseparator` instead.
+fn add_backgrounds(
+    segments: Vec<Segment>,
+    context: &Context,
+    separator: &str,
+    subseparator: &str,
+) -> Vec<Segment> {
+    let background_of = |module: &str| {
+        let config = context
+            .config
+            .get_module_config(module)
+            .or_else(|| context.config.get_custom_module_config(module))?;
+        parse_color_string(config.get("background")?.as_str()?)
+    };
+    let mut backgrounds: HashMap<String, Option<Color>> = HashMap::new();
+
+    let mut output = Vec::with_capacity(segments.len());
+    // The module and background of the last segment with a background
+    let mut previous: Option<(String, Color)> = None;
+    for mut segment in segments {
+        if segment.value.is_empty() {
+            output.push(segment);
+            continue;
+        }
+        let background = segment.module.as_ref().and_then(|module| {
+            *backgrounds
+                .entry(module.clone())
+                .or_insert_with(|| background_of(module))
+        });
+        let same_module = matches!(
+            (&previous, &segment.module),
+            (Some((previous_module, _)), Some(module)) if previous_module == module
+        );
+        if let Some((_, previous_background)) = previous.filter(|_| !same_module) {
+            if background == Some(previous_background) {
+                if !subseparator.is_empty() {
+                    let style = Style::new().on(previous_background);
+                    output.push(Segment::new(Some(style), subseparator));
+                }
+            } else if !separator.is_empty() {
+                let mut style = Style::new().fg(previous_background);
+                style.background = background;
+                output.push(Segment::new(Some(style), separator));
+            }
+        }
+        if let Some(background) = background {
+            let mut style = segment.style.unwrap_or_default();
+            style.background = style.background.or(Some(background));
+            segment.style = Some(style);
+        }
+        previous = background.map(|background| (segment.module.clone().unwrap(), background));
+        output.push(segment);
+    }
+    if let Some((_, previous_background)) = previous {
+        if !separator.is_empty() {
+            let style = Style::new().fg(previous_background);
+            output.push(Segment::new(Some(style), separator));
+        }
+    }
+    output
+}
+
+/// Expands the fill segments of every line so that the line takes up `width` columns.
+/// The remaining space of a line is split evenly between its fill segments.
+fn fill_lines(segments: &mut [Segment], width: usize, ambiguous_width: usize) {
+    fn fill_line(
+        segments: &mut [Segment],
+        fills: &[usize],
+        used: usize,
+        width: usize,
+        ambiguous_width: usize,
+    ) {
+        if fills.is_empty() {
+            return;
+        }
+        let remaining = width.saturating_sub(used);
+        for (n, &index) in fills.iter().enumerate() {
+            let fill_width = remaining / fills.len() + usize::from(n < remaining % fills.len());
+            let segment = &mut segments[index];
+            segment.value = repeat_to_width(&segment.value, fill_width, ambiguous_width);
+        }
+    }
+
+    let mut fills = Vec::new();
+    let mut used = 0;
+    for index in 0..segments.len() {
+        if segments[index].fill {
+            fills.push(index);
+            continue;
+        }
+
+        let mut lines = segments[index]
+            .value
+            .split('\n')
+            .map(|line| line.width_graphemes_with_ambiguous(ambiguous_width));
+        used += lines.next().unwrap_or_default();
+        if let Some(last_width) = lines.next_back() {
+            fill_line(segments, &fills, used, width, ambiguous_width);
+            fills.clear();
+            used = last_width;
+        }
+    }
+    fill_line(segments, &fills, used, width, ambiguous_width);
+}
+
+/// Repeats the graphemes of `symbol` until `width` columns are used,
+/// padding with spaces where the next grapheme doesn't fit
+fn repeat_to_width(symbol: &str, width: usize, ambiguous_width: usize) -> String {
+    let mut out = String::new();
+    let mut used = 0;
+    for grapheme in symbol.graphemes(true).cycle() {
+        let grapheme_width = Grapheme(grapheme).width_with_ambiguous(ambiguous_width);
+        if grapheme_width == 0 || used + grapheme_width > width {
+            break;
+        }
+        out.push_str(grapheme);
+        used += grapheme_width;
+    }
+    out.push_str(&" ".repeat(width - used));
+    out
+}
+
+pub fn statusline(args: ArgMatches) {
+    if is_recursive_invocation() {
+        return;
+    }
+
+    let context = Context::new(args.clone());
+    let config = context.config.get_root_config();
+    let format = args.value_of("format").unwrap_or(config.format);
+    println!("{}", get_statusline(&context, format));
+}
+
+/// Renders `format` as a single line of plain text, without any styling or escape sequences,
+/// for embedding into editor status lines or terminal multiplexer layouts
+pub fn get_statusline(context: &Context, format: &str) -> String {
+    render_statusline(context, format, &[])
+}
+
+/// Renders `format` as a single line of plain text. Modules with segments in `rendered`,
+/// e.g. the prompt the window title is set with, are not rendered again.
+fn render_statusline(context: &Context, format: &str, rendered: &[Segment]) -> String {
+    let formatter = match StringFormatter::new(format) {
+        Ok(formatter) => formatter,
+        Err(error) => {
+            log::error!("Error parsing `format`: {}", error);
+            return String::new();
+        }
+    };
+    let mut rendered_modules: HashMap<&str, Vec<Segment>> = HashMap::new();
+    for segment in rendered {
+        if let Some(module) = &segment.module {
+            rendered_modules
+                .entry(module)
+                .or_default()
+                .push(segment.clone());
+        }
+    }
+    let formatter =
+        formatter.map_variables_to_segments(|module| rendered_modules.get(module).cloned().map(Ok));
+    let root_module = render_root_module(formatter, context, &BTreeSet::new());
+
+    root_module
+        .get_segments()
+        .concat()
+        .split(&['\n', '\r'][..])
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 pub fn module(module_name: &str, args: ArgMatches) {
+    if is_recursive_invocation() {
+        return;
+    }
+
     let context = Context::new(args);
     let module = get_module(module_name, context).unwrap_or_default();
     print!("{}", module);
 }
 
+/// A module listed by `starship module --list`
+struct ModuleListing {
+    name: &'static str,
+    description: &'static str,
+    /// Whether it is shown in the current directory, hidden there or disabled, if listed
+    /// with `--with-status`
+    status: Option<&'static str>,
+    /// The files, folders and extensions it is shown for by default
+    detected_by: Vec<String>,
+}
+
+/// Lists all modules, with their description and default detection rules. With
+/// `--with-status`, also whether they are shown in the current directory.
+pub fn list_modules(args: ArgMatches) {
+    let with_status = args.is_present("status_column");
+    let context = Context::new(args);
+    let modules = module_listings(&context, with_status);
+    let name_width = modules
+        .iter()
+        .map(|module| module.name.len())
+        .max()
+        .unwrap_or(0);
+    // The status column and the two spaces after it
+    let status_width = if with_status { "disabled".len() + 2 } else { 0 };
+
+    println!(
+        " {:<name_width$}  {:<status_width$}Description",
+        "Module",
+        if with_status { "Status" } else { "" },
+        name_width = name_width,
+        status_width = status_width
+    );
+    for module in modules {
+        println!(
+            " {:<name_width$}  {:<status_width$}{}",
+            module.name,
+            module.status.unwrap_or_default(),
+            module.description,
+            name_width = name_width,
+            status_width = status_width
+        );
+        if !module.detected_by.is_empty() {
+            println!(
+                " {}Detects {}",
+                " ".repeat(name_width + status_width + 2),
+                module.detected_by.join(", ")
+            );
+        }
+    }
+}
+
+/// The modules to list, only rendering them to find whether they are shown if `with_status`
+fn module_listings(context: &Context, with_status: bool) -> Vec<ModuleListing> {
+    let defaults = toml::Value::try_from(crate::configs::FullConfig::default()).ok();
+    ALL_MODULES
+        .par_iter()
+        .map(|&name| {
+            let default = defaults.as_ref().and_then(|defaults| defaults.get(name));
+            let status = || {
+                let disabled = context
+                    .config
+                    .get_module_config(name)
+                    .and_then(|config| config.get("disabled"))
+                    .or_else(|| default?.get("disabled"))
+                    .and_then(toml::Value::as_bool)
+                    .unwrap_or(false);
+                if disabled || context.is_module_disabled_in_config(name) {
+                    "disabled"
+                } else if matches!(modules::handle(name, context), Some(module) if !module.is_empty())
+                {
+                    "shown"
+                } else {
+                    "hidden"
+                }
+            };
+            ModuleListing {
+                name,
+                description: modules::description(name),
+                status: if with_status { Some(status()) } else { None },
+                detected_by: default.map(detection_rules).unwrap_or_default(),
+            }
+        })
+        .collect()
+}
+
+/// The `detect_extensions`, `detect_files` and `detect_folders` of a module's config
+fn detection_rules(config: &toml::Value) -> Vec<String> {
+    let rules = |key: &str| {
+        config
+            .get(key)
+            .and_then(toml::Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(toml::Value::as_str)
+            .map(str::to_owned)
+            .collect::<Vec<_>>()
+    };
+    let extensions = rules("detect_extensions")
+        .into_iter()
+        .map(|extension| format!("*.{}", extension));
+    let folders = rules("detect_folders")
+        .into_iter()
+        .map(|folder| format!("{}/", folder));
+    extensions
+        .chain(rules("detect_files"))
+        .chain(folders)
+        .collect()
+}
+
 pub fn get_module(module_name: &str, context: Context) -> Option<String> {
-    modules::handle(module_name, &context).map(|m| m.to_string())
+    modules::handle(module_name, &context).map(|mut module| {
+        downgrade_colors(&mut module, &context);
+        module.to_string()
+    })
+}
+
+/// Renders a single module for `context`, see [`crate::render_module`]
+pub fn render_module(module_name: &str, context: Context) -> Option<String> {
+    let ambiguous_width = context.config.get_root_config().ambiguous_width;
+    render_named_module(module_name, &context)
+        .map(|mut module| {
+            constrain_width(&mut module, ambiguous_width);
+            downgrade_colors(&mut module, &context);
+            module.to_string()
+        })
+        .filter(|module| !module.is_empty())
+}
+
+pub fn refresh_git_status(args: ArgMatches) {
+    let mut context = Context::new(args);
+    // The refresh runs in the background, so slow repositories are given more time
+    context.cmd_timeout = Duration::from_secs(60);
+    modules::git_status::refresh_cache(&context);
+}
+
+pub fn refresh_public_ip(args: ArgMatches) {
+    let mut context = Context::new(args);
+    // The fetch runs in the background, so slow networks are given more time
+    context.cmd_timeout = Duration::from_secs(30);
+    modules::public_ip::refresh_cache(&context);
+}
+
+pub fn refresh_weather(args: ArgMatches) {
+    let context = Context::new(args);
+    modules::weather::refresh_cache(&context);
+}
+
+/// Renders the `async_modules` for the prompt with the given `--async-id` and saves
+/// their output, for the shell to redraw the prompt with
+pub fn render_async(args: ArgMatches) {
+    let mut context = Context::new(args);
+    let results = match context.async_results.take() {
+        Some(results) => results,
+        None => return,
+    };
+    // This runs in the background, so slow modules are given more time
+    context.cmd_timeout = Duration::from_secs(60);
+
+    let modules = results
+        .modules()
+        .par_iter()
+        .map(|module| {
+            let segments = render_named_module(module, &context).map(|module| module.segments);
+            (module.clone(), segments)
+        })
+        .collect();
+    results.save(modules);
+
+    if let Some(cache) = &context.module_cache {
+        cache.save();
+    }
+}
+
+/// Renders a module, custom module (`custom.<name>`) or plugin (`plugins.<name>`) by its
+/// name, unless it is disabled
+fn render_named_module<'a>(module: &str, context: &'a Context) -> Option<Module<'a>> {
+    if let Some(name) = module.strip_prefix("plugins.") {
+        return match context.is_plugin_disabled_in_config(name) {
+            Some(false) => modules::plugin::module(name, context),
+            _ => None,
+        };
+    }
+    match module.strip_prefix("custom.") {
+        Some(name) => match context.is_custom_module_disabled_in_config(name) {
+            Some(false) => modules::custom::module(name, context),
+            _ => None,
+        },
+        None if ALL_MODULES.contains(&module) && !context.is_module_disabled_in_config(module) => {
+            modules::handle(module, context)
+        }
+        None => None,
+    }
 }
 
 pub fn timings(args: ArgMatches) {
+    let json = args.value_of("format") == Some("json");
+    let threshold = args
+        .value_of("threshold")
+        .and_then(|threshold| threshold.parse().ok())
+        .map(Duration::from_millis);
     let context = Context::new(args);
+    let ambiguous_width = context.config.get_root_config().ambiguous_width;
 
     struct ModuleTiming {
         name: String,
@@ -155,17 +918,29 @@ pub fn timings(args: ArgMatches) {
         duration_len: usize,
     }
 
-    let mut modules = compute_modules(&context)
-        .iter()
-        .filter(|module| !module.is_empty() || module.duration.as_millis() > 0)
+    let modules = compute_modules(&context);
+    bug_report::save_timings(&modules);
+    let modules = modules.iter().filter(|module| match threshold {
+        Some(threshold) => module.duration >= threshold,
+        None => !module.is_empty() || module.duration.as_millis() > 0,
+    });
+    if json {
+        print_timings_json(modules);
+        return;
+    }
+
+    let mut modules = modules
         .map(|module| ModuleTiming {
             name: String::from(module.get_name().as_str()),
-            name_len: module.get_name().width_graphemes(),
+            name_len: module
+                .get_name()
+                .width_graphemes_with_ambiguous(ambiguous_width),
             value: ansi_term::ANSIStrings(&module.ansi_strings())
                 .to_string()
                 .replace('\n', "\\n"),
             duration: module.duration,
-            duration_len: format_duration(&module.duration).width_graphemes(),
+            duration_len: format_duration(&module.duration)
+                .width_graphemes_with_ambiguous(ambiguous_width),
         })
         .collect::<Vec<ModuleTiming>>();
 
@@ -190,14 +965,189 @@ pub fn timings(args: ArgMatches) {
     }
 }
 
+/// Prints the timings of modules as JSON, with the time spent running commands and the
+/// rest of the time, mostly spent detecting whether to show the module
+fn print_timings_json<'a, 'b: 'a>(modules: impl Iterator<Item = &'a Module<'b>>) {
+    #[derive(Serialize)]
+    struct ModuleTiming<'a> {
+        name: &'a str,
+        duration_ms: f64,
+        detection_ms: f64,
+        command_ms: f64,
+        output: String,
+    }
+
+    let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let mut modules: Vec<ModuleTiming> = modules
+        .map(|module| {
+            let command_duration = module.command_duration.min(module.duration);
+            ModuleTiming {
+                name: module.get_name(),
+                duration_ms: millis(module.duration),
+                detection_ms: millis(module.duration - command_duration),
+                command_ms: millis(command_duration),
+                output: module.get_segments().concat(),
+            }
+        })
+        .collect();
+    modules.sort_by(|a, b| b.duration_ms.total_cmp(&a.duration_ms));
+
+    let timings = serde_json::json!({ "modules": modules });
+    println!("{}", serde_json::to_string_pretty(&timings).unwrap());
+}
+
+/// The output of a prompt rendered with one of the configs being compared, with the time
+/// each module took to render it
+struct Rendering {
+    output: String,
+    duration: Duration,
+    modules: BTreeMap<String, Duration>,
+}
+
+fn render_for_comparison<'a>(new_context: impl Fn() -> Context<'a>) -> Rendering {
+    let new_context = || {
+        let mut context = new_context();
+        // Render without shell-specific escapes, so the output can be printed as is
+        context.shell = Shell::Unknown;
+        // Comparing has no side effects: nothing is sent as metrics or kept for later
+        // prompts, and modules aren't skipped or reused because of earlier prompts
+        context.metrics = None;
+        context.slow_modules = None;
+        context.module_cache = None;
+        context.async_results = None;
+        context
+    };
+
+    // Render once without measuring, so that both configs run with warm file system caches
+    get_prompt(new_context());
+
+    let timings = Arc::new(Mutex::new(BTreeMap::new()));
+    let mut context = new_context();
+    context.module_timings = Some(Arc::clone(&timings));
+    let start = Instant::now();
+    let output = get_prompt(context);
+    let duration = start.elapsed();
+    let modules = timings
+        .lock()
+        .map(|mut timings| std::mem::take(&mut *timings))
+        .unwrap_or_default();
+
+    Rendering {
+        output,
+        duration,
+        modules,
+    }
+}
+
+/// The lines which differ between two prompts, as `- <old line>` and `+ <new line>`
+fn diff_lines(old: &str, new: &str) -> Vec<String> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let mut diff = Vec::new();
+    for index in 0..old_lines.len().max(new_lines.len()) {
+        let (old_line, new_line) = (old_lines.get(index), new_lines.get(index));
+        if old_line == new_line {
+            continue;
+        }
+        diff.extend(old_line.map(|line| format!("- {}", line)));
+        diff.extend(new_line.map(|line| format!("+ {}", line)));
+    }
+    diff
+}
+
+pub fn compare(args: ArgMatches) {
+    let (old_path, new_path) = match (args.value_of("old"), args.value_of("new")) {
+        (Some(old_path), Some(new_path)) => (old_path, new_path),
+        _ => return,
+    };
+    let (old_config, new_config) = match (
+        StarshipConfig::from_file(old_path),
+        StarshipConfig::from_file(new_path),
+    ) {
+        (Some(old_config), Some(new_config)) => (old_config, new_config),
+        (None, _) => return eprintln!("Unable to load the config {}", old_path),
+        (_, None) => return eprintln!("Unable to load the config {}", new_path),
+    };
+
+    let with_config = |config: &StarshipConfig| {
+        let args = args.clone();
+        let config = config.config.clone();
+        move || {
+            let mut context = Context::new(args.clone());
+            context.config = StarshipConfig {
+                config: config.clone(),
+            };
+            context
+        }
+    };
+    let old = render_for_comparison(with_config(&old_config));
+    let new = render_for_comparison(with_config(&new_config));
+
+    println!("\n Prompt with {}:\n{}", old_path, old.output);
+    println!("\n Prompt with {}:\n{}", new_path, new.output);
+    if old.output == new.output {
+        println!("\n Both configs render the same prompt.");
+    } else {
+        println!("\n The lines which differ between the prompts:");
+        for line in diff_lines(&old.output, &new.output) {
+            println!(" {}", line);
+        }
+    }
+
+    let names: BTreeSet<&String> = old.modules.keys().chain(new.modules.keys()).collect();
+    let rows = names
+        .into_iter()
+        .map(|name| {
+            (
+                name.as_str(),
+                old.modules.get(name).copied(),
+                new.modules.get(name).copied(),
+            )
+        })
+        .chain(std::iter::once((
+            "total",
+            Some(old.duration),
+            Some(new.duration),
+        )))
+        .collect::<Vec<_>>();
+
+    let format_cell = |duration: Option<Duration>| match duration {
+        Some(duration) => format_duration(&duration),
+        None => "-".to_string(),
+    };
+    let name_width = rows.iter().map(|(name, ..)| name.len()).max().unwrap_or(0);
+
+    println!("\n Timings of the modules in both prompts:");
+    for (name, old_duration, new_duration) in rows {
+        let change = match (old_duration, new_duration) {
+            (Some(old), Some(new)) => {
+                format!("{:+}ms", new.as_millis() as i128 - old.as_millis() as i128)
+            }
+            _ => String::new(),
+        };
+        println!(
+            " {:name_width$}  {:>8}  {:>8}  {:>8}",
+            name,
+            format_cell(old_duration),
+            format_cell(new_duration),
+            change,
+            name_width = name_width
+        );
+    }
+}
+
 pub fn explain(args: ArgMatches) {
-    let context = Context::new(args);
+    crate::config::warn_invalid_values();
+    let mut context = Context::new(args);
+    context.explain = true;
+    let ambiguous_width = context.config.get_root_config().ambiguous_width;
 
     struct ModuleInfo {
         value: String,
         value_len: usize,
         desc: String,
         duration: String,
+        details: Vec<String>,
     }
 
     static DONT_PRINT: &[&str] = &["line_break"];
@@ -207,14 +1157,21 @@ pub fn explain(args: ArgMatches) {
         .filter(|module| !DONT_PRINT.contains(&module.get_name().as_str()))
         // this contains empty modules which should not print
         .filter(|module| !module.is_empty())
-        .map(|module| {
+        .map(|mut module| {
+            downgrade_colors(&mut module, &context);
             let value = module.get_segments().join("");
             ModuleInfo {
                 value: ansi_term::ANSIStrings(&module.ansi_strings()).to_string(),
-                value_len: value.width_graphemes()
-                    + format_duration(&module.duration).width_graphemes(),
+                value_len: value.width_graphemes_with_ambiguous(ambiguous_width)
+                    + format_duration(&module.duration)
+                        .width_graphemes_with_ambiguous(ambiguous_width),
                 desc: module.get_description().to_owned(),
                 duration: format_duration(&module.duration),
+                details: module
+                    .details
+                    .as_ref()
+                    .map(explain_details)
+                    .unwrap_or_default(),
             }
         })
         .collect::<Vec<ModuleInfo>>();
@@ -255,7 +1212,7 @@ pub fn explain(args: ArgMatches) {
                 }
 
                 // Handle normal wrapping
-                current_pos += Grapheme(g).width();
+                current_pos += Grapheme(g).width_with_ambiguous(ambiguous_width);
                 // Wrap when hitting max width or newline
                 if g == "\n" || current_pos > desc_width {
                     // trim spaces on linebreak
@@ -282,9 +1239,29 @@ pub fn explain(args: ArgMatches) {
                 info.desc,
             );
         };
+        for detail in info.details {
+            println!("{}{}", " ".repeat(max_module_width + PADDING_WIDTH), detail);
+        }
     }
 }
 
+/// Lines explaining why a module is shown and where its output comes from
+fn explain_details(details: &ModuleDetails) -> Vec<String> {
+    let detected_by = details
+        .detected_by
+        .iter()
+        .map(|detected_by| format!("Detected by {}", detected_by));
+    let variables = details
+        .variables
+        .iter()
+        .map(|(name, value)| format!("${} = {:?}", name, value));
+    let commands = details
+        .commands
+        .iter()
+        .map(|(command, duration)| format!("Ran `{}` ({})", command, format_duration(duration)));
+    detected_by.chain(variables).chain(commands).collect()
+}
+
 fn compute_modules<'a>(context: &'a Context) -> Vec<Module<'a>> {
     let mut prompt_order: Vec<Module<'a>> = Vec::new();
 
@@ -337,7 +1314,13 @@ fn handle_module<'a>(
         // Write out all custom modules, except for those that are explicitly set
         if let Some(custom_modules) = context.config.get_custom_modules() {
             let custom_modules = custom_modules.iter().filter_map(|(custom_module, config)| {
-                if should_add_implicit_custom_module(custom_module, config, &module_list) {
+                if should_add_implicit_module(
+                    "custom",
+                    custom_module,
+                    config,
+                    &module_list,
+                    context.width,
+                ) {
                     modules::custom::module(custom_module, &context)
                 } else {
                     None
@@ -362,6 +1345,28 @@ fn handle_module<'a>(
                     ),
             },
         }
+    } else if module == "plugins" {
+        // Write out all plugins, except for those that are explicitly set
+        if let Some(plugins) = context.config.get_plugins() {
+            let plugins = plugins.iter().filter_map(|(plugin, config)| {
+                if should_add_implicit_module("plugins", plugin, config, module_list, context.width)
+                {
+                    modules::plugin::module(plugin, context)
+                } else {
+                    None
+                }
+            });
+            modules.extend(plugins);
+        }
+    } else if let Some(plugin) = module.strip_prefix("plugins.") {
+        match context.is_plugin_disabled_in_config(plugin) {
+            Some(true) => (),
+            Some(false) => modules.extend(modules::plugin::module(plugin, context)),
+            None => log::debug!(
+                "top level format contains plugin \"{}\", but no configuration was provided.",
+                plugin,
+            ),
+        }
     } else {
         log::debug!(
             "Expected top level format to contain value from {:?}. Instead received {}",
@@ -373,12 +1378,16 @@ fn handle_module<'a>(
     modules
 }
 
-fn should_add_implicit_custom_module(
-    custom_module: &str,
+/// Whether a custom module or plugin is shown by `$custom` or `$plugins`, which are
+/// given by `table`
+fn should_add_implicit_module(
+    table: &str,
+    name: &str,
     config: &toml::Value,
     module_list: &BTreeSet<String>,
+    width: usize,
 ) -> bool {
-    let explicit_module_name = format!("custom.{}", custom_module);
+    let explicit_module_name = format!("{}.{}", table, name);
     let is_explicitly_specified = module_list.contains(&explicit_module_name);
 
     if is_explicitly_specified {
@@ -393,6 +1402,7 @@ fn should_add_implicit_custom_module(
         .unwrap_or(&false_value)
         .as_bool()
         .unwrap_or(false)
+        && !is_hidden(Some(config), width)
 }
 
 pub fn format_duration(duration: &Duration) -> String {
@@ -403,3 +1413,478 @@ pub fn format_duration(duration: &Duration) -> String {
         format!("{:?}ms", &milis)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ansi_term::Style;
+    use std::path::PathBuf;
+
+    fn new_context<'a>(config: toml::Value) -> Context<'a> {
+        let mut context = Context::new_with_shell_and_path(
+            ArgMatches::default(),
+            Shell::Unknown,
+            PathBuf::new(),
+            PathBuf::new(),
+        );
+        context.config = StarshipConfig {
+            config: Some(config),
+        };
+        context
+    }
+
+    fn render(target: Target, config: toml::Value) -> String {
+        let mut context = new_context(config);
+        context.target = target;
+        get_prompt(context)
+    }
+
+    #[test]
+    fn window_title_is_set_by_main_prompt() {
+        let config = toml::toml! {
+            format = "left"
+            transient_format = "left"
+            window_title = "[title](bold red)"
+            add_newline = false
+        };
+
+        assert_eq!("\x1b]0;title\x07left", render(Target::Main, config.clone()));
+        assert_eq!("left", render(Target::Transient, config));
+    }
+
+    #[test]
+    fn window_title_reuses_rendered_modules() {
+        let context = new_context(toml::Value::Table(toml::value::Table::new()));
+        let mut segment = Segment::new(None, "rendered");
+        segment.module = Some("directory".to_string());
+
+        assert_eq!(
+            "in rendered",
+            render_statusline(&context, "in $directory", &[segment])
+        );
+    }
+
+    #[test]
+    fn renders_configs_for_comparison() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = toml::toml! {
+            format = "$character$directory"
+            add_newline = false
+            [character]
+            success_symbol = ">"
+            [directory]
+            format = "$path"
+            truncation_length = 1
+        };
+        let new_context = || {
+            let mut context = new_context(config.clone());
+            context.current_dir = dir.path().to_path_buf();
+            context.logical_dir = dir.path().to_path_buf();
+            context
+        };
+
+        let rendering = render_for_comparison(new_context);
+        let name = dir.path().file_name().unwrap().to_string_lossy();
+        assert_eq!(format!("> {}", name), rendering.output);
+        assert_eq!(
+            vec!["character", "directory"],
+            rendering.modules.keys().collect::<Vec<_>>()
+        );
+        dir.close()
+    }
+
+    #[test]
+    fn diffs_the_lines_of_compared_prompts() {
+        assert_eq!(vec!["- b", "+ c", "+ d"], diff_lines("a\nb", "a\nc\nd"));
+        assert!(diff_lines("a\nb", "a\nb").is_empty());
+    }
+
+    #[test]
+    fn colors_are_downgraded_for_the_terminal() {
+        let mut context = new_context(toml::toml! {
+            format = "[left](#ff0000)"
+            add_newline = false
+        });
+        context.env.insert("TERM", "xterm-256color".to_string());
+        assert_eq!("\x1b[38;2;255;0;0mleft\x1b[0m", get_prompt(context));
+
+        let context = new_context(toml::toml! {
+            format = "[left](#ff0000)"
+            add_newline = false
+            color_depth = "256"
+        });
+        assert_eq!("\x1b[38;5;196mleft\x1b[0m", get_prompt(context));
+
+        let mut context = new_context(toml::toml! {
+            color_depth = "256"
+            [username]
+            show_always = true
+            format = "[$user]($style)"
+            style_user = "#ff0000"
+            style_root = "#ff0000"
+        });
+        context.env.insert("USER", "astronaut".to_string());
+        assert_eq!(
+            Some("\x1b[38;5;196mastronaut\x1b[0m".to_string()),
+            get_module("username", context)
+        );
+    }
+
+    #[test]
+    fn referenced_modules_are_rendered_once() {
+        let context = new_context(toml::toml! {
+            [character]
+            success_symbol = "${directory.path}"
+            format = "$symbol"
+        });
+        let variables = [("directory.path".to_string(), "rendered".to_string())];
+        context
+            .referenced_variables
+            .lock()
+            .unwrap()
+            .insert("directory".to_string(), Arc::new(variables.into()));
+        assert_eq!(
+            Some("rendered".to_string()),
+            get_module("character", context)
+        );
+    }
+
+    #[test]
+    fn backgrounds_are_joined_by_separators() {
+        let context = new_context(toml::toml! {
+            [directory]
+            background = "blue"
+            [git_branch]
+            background = "blue"
+            [nodejs]
+            background = "green"
+        });
+        let segment = |module: &str, style: Option<Style>, value: &str| {
+            let mut segment = Segment::new(style, value);
+            segment.module = Some(module.to_string());
+            segment
+        };
+        let segments = vec![
+            segment("directory", Some(Color::White.bold()), "~"),
+            segment("directory", None, " "),
+            segment("git_branch", None, "main"),
+            segment("nodejs", Some(Color::Black.on(Color::Red)), "v16"),
+            segment("character", None, "❯"),
+        ];
+
+        let actual: Vec<(String, Option<Style>)> = add_backgrounds(segments, &context, ">", "|")
+            .into_iter()
+            .map(|segment| (segment.value, segment.style))
+            .collect();
+        let expected = vec![
+            ("~", Some(Color::White.bold().on(Color::Blue))),
+            (" ", Some(Style::new().on(Color::Blue))),
+            ("|", Some(Style::new().on(Color::Blue))),
+            ("main", Some(Style::new().on(Color::Blue))),
+            (">", Some(Color::Blue.on(Color::Green))),
+            ("v16", Some(Color::Black.on(Color::Red))),
+            (">", Some(Color::Green.normal())),
+            ("❯", None),
+        ];
+        let expected: Vec<(String, Option<Style>)> = expected
+            .into_iter()
+            .map(|(value, style)| (value.to_string(), style))
+            .collect();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn renders_prompts_from_built_contexts() {
+        let config = toml::toml! {
+            format = "$status"
+            right_format = "right"
+            add_newline = false
+            [status]
+            disabled = false
+            format = "$status"
+        };
+        let context = |target| {
+            Context::builder()
+                .shell(Shell::Unknown)
+                .config(StarshipConfig {
+                    config: Some(config.clone()),
+                })
+                .status_code(3)
+                .target(target)
+                .build()
+        };
+
+        assert_eq!("3", crate::render_prompt(context(Target::Main)));
+        assert_eq!("right", crate::render_prompt(context(Target::Right)));
+        assert_eq!(
+            Some("3".to_string()),
+            crate::render_module("status", context(Target::Main))
+        );
+        assert_eq!(None, crate::render_module("unknown", context(Target::Main)));
+    }
+
+    #[test]
+    fn renders_prompts_as_json() {
+        let context = Context::builder()
+            .shell(Shell::Unknown)
+            .config(StarshipConfig {
+                config: Some(toml::toml! {
+                    format = "[>](bold #ff8700) $status"
+                    add_newline = false
+                    color_depth = "truecolor"
+                    [status]
+                    disabled = false
+                    format = "[$status](red bg:238 strikethrough double-underline underline:blue)"
+                }),
+            })
+            .status_code(1)
+            .build();
+
+        let prompt: serde_json::Value = serde_json::from_str(&get_prompt_json(context)).unwrap();
+        let expected = serde_json::json!({"segments": [
+            {
+                "module": null, "text": ">", "foreground": "#ff8700", "background": null,
+                "bold": true, "italic": false, "underline": false, "dimmed": false,
+                "strikethrough": false, "blink": false, "double_underline": false,
+                "underline_color": null, "link": null,
+            },
+            {
+                "module": null, "text": " ", "foreground": null, "background": null,
+                "bold": false, "italic": false, "underline": false, "dimmed": false,
+                "strikethrough": false, "blink": false, "double_underline": false,
+                "underline_color": null, "link": null,
+            },
+            {
+                "module": "status", "text": "1", "foreground": "red", "background": "238",
+                "bold": false, "italic": false, "underline": true, "dimmed": false,
+                "strikethrough": true, "blink": false, "double_underline": true,
+                "underline_color": "blue", "link": null,
+            },
+        ]});
+        assert_eq!(expected, prompt);
+    }
+
+    #[test]
+    fn lists_modules_with_their_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let context = Context::builder()
+            .path(dir.path())
+            .shell(Shell::Unknown)
+            .config(StarshipConfig {
+                config: Some(toml::toml! {
+                    [aws]
+                    disabled = true
+                }),
+            })
+            .build();
+        let listings = module_listings(&context, true);
+        let listing = |name| {
+            listings
+                .iter()
+                .find(|listing| listing.name == name)
+                .unwrap()
+        };
+
+        assert_eq!(Some("shown"), listing("character").status);
+        assert_eq!(Some("disabled"), listing("aws").status);
+        // Disabled by default
+        assert_eq!(Some("disabled"), listing("status").status);
+        let rust = listing("rust");
+        assert_eq!(Some("hidden"), rust.status);
+        assert_eq!(vec!["*.rs", "Cargo.toml"], rust.detected_by);
+
+        let listings = module_listings(&context, false);
+        assert!(listings.iter().all(|listing| listing.status.is_none()));
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn hidden_modules_are_not_rendered() {
+        let config = toml::toml! {
+            format = "[$character](bold)"
+            add_newline = false
+            [character]
+            display = "hidden"
+        };
+
+        assert_eq!("", render(Target::Main, config));
+    }
+
+    #[test]
+    fn modules_hide_in_narrow_terminals() {
+        let config = toml::toml! {
+            format = "$status(${width} < 60)[ narrow]()"
+            add_newline = false
+            [status]
+            disabled = false
+            min_terminal_width = 80
+            format = "wide at ${width}"
+        };
+
+        let mut context = new_context(config.clone());
+        context.properties.insert("status_code", "1".to_string());
+        context.width = 100;
+        assert_eq!("wide at 100", get_prompt(context));
+        let mut context = new_context(config);
+        context.properties.insert("status_code", "1".to_string());
+        context.width = 50;
+        assert_eq!(" narrow", get_prompt(context));
+    }
+
+    #[test]
+    fn modules_are_padded_and_truncated_to_their_width() {
+        let module = |value: &str, config: &toml::Value| {
+            let mut module = Module::new("test", "", Some(config));
+            module.set_segments(vec![
+                Segment::new(None, value),
+                Segment::new(Some(Color::Red.normal()), "!"),
+            ]);
+            constrain_width(&mut module, 1);
+            module.get_segments().join("|")
+        };
+        let config = toml::toml! {
+            min_width = 6
+            max_width = 8
+        };
+
+        assert_eq!("ab|!   ", module("ab", &config));
+        assert_eq!("abcdefg|!", module("abcdefg", &config));
+        assert_eq!("abcdefg…", module("abcdefgh", &config));
+        assert_eq!("日本語…", module("日本語日", &config));
+        assert_eq!("", module("", &toml::toml! { max_width = 0 }));
+        assert_eq!("a…", module("abc", &toml::toml! { max_width = 2 }));
+    }
+
+    #[test]
+    fn semantic_prompt_markers() {
+        let config = toml::toml! {
+            format = "left"
+            right_format = "right"
+            semantic_prompt = true
+        };
+
+        let mut context = new_context(config.clone());
+        context.properties.insert("status_code", "1".to_string());
+        assert_eq!(
+            "\x1b]133;D;1\x07\x1b]133;A\x07\nleft\x1b]133;B\x07",
+            get_prompt(context)
+        );
+        assert_eq!("right", render(Target::Right, config));
+    }
+
+    #[test]
+    fn right_prompt_uses_right_format() {
+        let config = toml::toml! {
+            format = "left"
+            right_format = "right"
+        };
+
+        assert_eq!("\nleft", render(Target::Main, config.clone()));
+        assert_eq!("right", render(Target::Right, config));
+    }
+
+    #[test]
+    fn continuation_prompt_uses_continuation_format() {
+        let config = toml::toml! {
+            format = "left"
+            continuation_format = "[>](bold) "
+        };
+
+        let expected = format!("{} ", Style::new().bold().paint(">"));
+        assert_eq!(expected, render(Target::Continuation, config));
+    }
+
+    #[test]
+    fn powershell_right_prompt_is_right_aligned() {
+        let mut context = new_context(toml::toml! {
+            ambiguous_width = 2
+            right_format = "→ab"
+        });
+        context.shell = Shell::PowerShell;
+        context.target = Target::Right;
+        context.width = 20;
+
+        assert_eq!("\x1b[s\x1b[17G→ab\x1b[u", get_prompt(context));
+    }
+
+    #[test]
+    fn fill_takes_remaining_width() {
+        let mut segments = vec![
+            Segment::new(None, "ab"),
+            Segment::fill(None, "-="),
+            Segment::new(None, "cd\ne"),
+            Segment::fill(None, "."),
+            Segment::fill(None, "."),
+            Segment::new(None, "f"),
+        ];
+        fill_lines(&mut segments, 9, 1);
+
+        let actual: Vec<&str> = segments.iter().map(|s| s.value.as_str()).collect();
+        assert_eq!(vec!["ab", "-=-=-", "cd\ne", "....", "...", "f"], actual);
+    }
+
+    #[test]
+    fn align_fills_lines_with_spaces() {
+        let mut context = new_context(toml::toml! {
+            format = "ab\n${align}cd\n${align}e${align}"
+            add_newline = false
+        });
+        context.width = 7;
+
+        assert_eq!("ab\n     cd\n   e   ", get_prompt(context));
+    }
+
+    #[test]
+    fn align_fills_lines_of_module_formats() {
+        let mut context = new_context(toml::toml! {
+            format = "$character"
+            add_newline = false
+            [character]
+            format = "ab\n${align}cd"
+        });
+        context.width = 7;
+
+        assert_eq!("ab\n     cd", get_prompt(context));
+    }
+
+    #[test]
+    fn fill_pads_wide_symbols() {
+        assert_eq!("日日 ", repeat_to_width("日", 5, 1));
+        assert_eq!("…… ", repeat_to_width("…", 5, 2));
+        assert_eq!("☁️☁️", repeat_to_width("☁️", 4, 1));
+        assert_eq!("", repeat_to_width("-", 0, 1));
+    }
+
+    #[test]
+    fn transient_prompt_uses_transient_format() {
+        let config = toml::toml! {
+            format = "left"
+            transient_format = "transient"
+        };
+
+        assert_eq!("transient", render(Target::Transient, config));
+    }
+
+    #[test]
+    fn all_skips_right_prompt_modules() {
+        let config = toml::toml! {
+            add_newline = false
+            format = "$all"
+            right_format = "$character"
+        };
+
+        assert!(!render(Target::Main, config.clone()).contains('❯'));
+        assert!(render(Target::Right, config).contains('❯'));
+    }
+
+    #[test]
+    fn statusline_is_plain_text() {
+        let context = new_context(toml::toml! {
+            [character]
+            success_symbol = "[>](bold green)"
+        });
+
+        let actual = get_statusline(&context, "[in](red) \n[dir](blue)\n$character");
+        assert_eq!("in dir >", actual);
+    }
+}
@@ -1,16 +1,34 @@
-use ansi_term::{ANSIString, Style};
+use ansi_term::{ANSIString, Color, Style};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::fmt;
 
+use crate::config::Underline;
+
 /// A segment is a single configurable element in a module. This will usually
 /// contain a data point to provide context for the prompt's user
 /// (e.g. The version that software is running).
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Segment {
     /// The segment's style. If None, will inherit the style of the module containing it.
     pub style: Option<Style>,
 
     /// The string value of the current segment.
     pub value: String,
+
+    /// Whether `value` should be repeated to fill the rest of the line.
+    pub fill: bool,
+
+    /// A URL the segment links to, using an OSC 8 terminal hyperlink.
+    pub link: Option<String>,
+
+    /// A double or colored underline, on top of the underline of `style`.
+    #[serde(default)]
+    pub underline: Option<Underline>,
+
+    /// The name of the module the segment is from, once added to the prompt.
+    #[serde(skip)]
+    pub module: Option<String>,
 }
 
 impl Segment {
@@ -22,18 +40,91 @@ impl Segment {
         Self {
             style,
             value: value.into(),
+            fill: false,
+            link: None,
+            underline: None,
+            module: None,
+        }
+    }
+
+    /// Creates a segment which repeats `symbol` to fill the rest of the line.
+    pub fn fill<T>(style: Option<Style>, symbol: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            style,
+            value: symbol.into(),
+            fill: true,
+            link: None,
+            underline: None,
+            module: None,
         }
     }
 
+    /// Makes the segment a hyperlink to `url`.
+    pub fn with_link<T>(mut self, url: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.link = Some(url.into());
+        self
+    }
+
     // Returns the ANSIString of the segment value, not including its prefix and suffix
     pub fn ansi_string(&self) -> ANSIString {
+        let mut value = Cow::Borrowed(self.value.as_str());
+        if let Some(link) = &self.link {
+            value = format!("\x1b]8;;{}\x07{}\x1b]8;;\x07", link, value).into();
+        }
+        if let Some(underline) = &self.underline {
+            let is_underline = matches!(self.style, Some(style) if style.is_underline);
+            value = underline_value(underline, &value, is_underline).into();
+        }
+
         match self.style {
-            Some(style) => style.paint(&self.value),
-            None => ANSIString::from(&self.value),
+            Some(style) => style.paint(value),
+            None => ANSIString::from(value),
         }
     }
 }
 
+/// Wraps the value in the codes of a double or colored underline, and the codes which undo
+/// them afterwards. Following segments with the same style don't repeat the codes of their
+/// style, so the underline of the style is restored rather than reset.
+fn underline_value(underline: &Underline, value: &str, is_underline: bool) -> String {
+    let mut prefix = String::new();
+    let mut suffix = String::new();
+    if underline.double {
+        prefix.push_str("\x1b[4:2m");
+        suffix.push_str(if is_underline { "\x1b[4m" } else { "\x1b[24m" });
+    }
+    if let Some(color) = underline.color {
+        let color = match color {
+            Color::Fixed(n) => format!("5;{}", n),
+            Color::RGB(r, g, b) => format!("2;{};{};{}", r, g, b),
+            basic => format!("5;{}", basic_color_index(basic)),
+        };
+        prefix.push_str(&format!("\x1b[58;{}m", color));
+        suffix.push_str("\x1b[59m");
+    }
+    format!("{}{}{}", prefix, value, suffix)
+}
+
+/// The index of one of the 8 basic colors in the 256 colors of xterm
+fn basic_color_index(color: Color) -> u8 {
+    match color {
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Purple => 5,
+        Color::Cyan => 6,
+        _ => 7,
+    }
+}
+
 impl fmt::Display for Segment {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.ansi_string())
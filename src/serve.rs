@@ -0,0 +1,331 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::config::StarshipConfig;
+use crate::context::{Context, Shell, Target};
+use crate::daemon::{ClientEnv, DaemonCache};
+use crate::module_cache::ModuleCache;
+use crate::print;
+
+/// A JSON-RPC 2.0 request, one per line of the input
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    jsonrpc: Option<String>,
+    /// Requests without an id are notifications, which aren't answered
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<Error>,
+}
+
+#[derive(Serialize)]
+struct Error {
+    code: i64,
+    message: String,
+}
+
+impl Error {
+    const PARSE_ERROR: i64 = -32700;
+    const INVALID_REQUEST: i64 = -32600;
+    const METHOD_NOT_FOUND: i64 = -32601;
+    const INVALID_PARAMS: i64 = -32602;
+
+    fn new<T: ToString>(code: i64, message: T) -> Self {
+        Error {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// What the prompt is rendered for, like the arguments of `starship prompt`
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct PromptParams {
+    cwd: Option<PathBuf>,
+    logical_cwd: Option<PathBuf>,
+    /// The exit code of the previous command
+    status: Option<i32>,
+    /// The exit codes of the commands of the previous pipeline
+    pipestatus: Option<Vec<i32>>,
+    /// The history event number of the next command
+    history_number: Option<u64>,
+    /// How long the previous command took, in milliseconds
+    duration: Option<u64>,
+    jobs: Option<u64>,
+    running_jobs: Option<u64>,
+    stopped_jobs: Option<u64>,
+    keymap: Option<String>,
+    width: Option<usize>,
+    shell: Option<String>,
+    /// `main`, `right`, `transient` or `continuation`
+    target: Option<String>,
+    /// Only render this module, e.g. `git_branch`
+    module: Option<String>,
+}
+
+/// Reads newline-delimited JSON-RPC requests from `input` and writes a response line for
+/// each to `output`, until the input ends.
+///
+/// The `prompt` method renders a prompt, or a single module if `module` is set. Caches
+/// are kept between requests like with `starship daemon`.
+pub fn run<R: BufRead, W: Write>(input: R, output: W) -> io::Result<()> {
+    serve(input, output, StarshipConfig::initialize)
+}
+
+fn serve<R, W, C>(input: R, mut output: W, load_config: C) -> io::Result<()>
+where
+    R: BufRead,
+    W: Write,
+    C: Fn() -> StarshipConfig,
+{
+    let cache = Arc::new(DaemonCache::default());
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) if request.jsonrpc.as_deref() != Some("2.0") => Response {
+                jsonrpc: "2.0",
+                id: request.id.unwrap_or(Value::Null),
+                result: None,
+                error: Some(Error::new(
+                    Error::INVALID_REQUEST,
+                    "Only JSON-RPC 2.0 requests are supported",
+                )),
+            },
+            Ok(request) => {
+                let id = match request.id {
+                    Some(id) => id,
+                    None => continue,
+                };
+                let (result, error) =
+                    match handle(&request.method, request.params, &cache, &load_config) {
+                        Ok(result) => (Some(result), None),
+                        Err(error) => (None, Some(error)),
+                    };
+                Response {
+                    jsonrpc: "2.0",
+                    id,
+                    result,
+                    error,
+                }
+            }
+            Err(error) => Response {
+                jsonrpc: "2.0",
+                id: Value::Null,
+                result: None,
+                error: Some(Error::new(Error::PARSE_ERROR, error)),
+            },
+        };
+        serde_json::to_writer(&mut output, &response)?;
+        output.write_all(b"\n")?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+fn handle<C>(
+    method: &str,
+    params: Value,
+    cache: &Arc<DaemonCache>,
+    load_config: C,
+) -> Result<Value, Error>
+where
+    C: Fn() -> StarshipConfig,
+{
+    if method != "prompt" {
+        return Err(Error::new(
+            Error::METHOD_NOT_FOUND,
+            format!("Unknown method {:?}", method),
+        ));
+    }
+    let params: PromptParams = match params {
+        Value::Null => PromptParams::default(),
+        params => serde_json::from_value(params)
+            .map_err(|error| Error::new(Error::INVALID_PARAMS, error))?,
+    };
+
+    let target = match params.target.as_deref() {
+        None | Some("main") => Target::Main,
+        Some("right") => Target::Right,
+        Some("transient") => Target::Transient,
+        Some("continuation") => Target::Continuation,
+        Some(target) => {
+            return Err(Error::new(
+                Error::INVALID_PARAMS,
+                format!("Unknown target {:?}", target),
+            ))
+        }
+    };
+    // Commands run for the prompt get the cwd of the request, and must not render
+    // prompts themselves. The process itself is left as it is for the next requests.
+    let mut client = ClientEnv::current().ok_or_else(|| {
+        Error::new(
+            Error::INVALID_PARAMS,
+            "Unable to read the current directory",
+        )
+    })?;
+    if let Some(cwd) = params.cwd {
+        if !cwd.is_dir() {
+            return Err(Error::new(
+                Error::INVALID_PARAMS,
+                format!("Invalid cwd {:?}: not a directory", cwd),
+            ));
+        }
+        client.cwd = cwd;
+    }
+    client
+        .env
+        .insert(print::RECURSION_GUARD.to_string(), "1".to_string());
+
+    let mut builder = Context::builder()
+        .config(load_config())
+        .target(target)
+        .client_env(client);
+    if let Some(logical_cwd) = params.logical_cwd {
+        builder = builder.logical_path(logical_cwd);
+    }
+    if let Some(status) = params.status {
+        builder = builder.status_code(status);
+    }
+    if let Some(pipestatus) = params.pipestatus {
+        builder = builder.pipestatus(&pipestatus);
+    }
+    if let Some(number) = params.history_number {
+        builder = builder.history_number(number);
+    }
+    if let Some(duration) = params.duration {
+        builder = builder.cmd_duration(duration.into());
+    }
+    if let Some(jobs) = params.jobs {
+        builder = builder.jobs(jobs);
+    }
+    if let Some(jobs) = params.running_jobs {
+        builder = builder.running_jobs(jobs);
+    }
+    if let Some(jobs) = params.stopped_jobs {
+        builder = builder.stopped_jobs(jobs);
+    }
+    if let Some(keymap) = params.keymap {
+        builder = builder.keymap(keymap);
+    }
+    if let Some(width) = params.width {
+        builder = builder.terminal_width(width);
+    }
+    if let Some(shell) = params.shell {
+        builder = builder.shell(Shell::from_name(&shell));
+    }
+    let mut context = builder.build();
+    context.daemon = Some(Arc::clone(cache));
+    context.module_cache = ModuleCache::new(&context);
+
+    Ok(match params.module {
+        Some(module) => print::render_module(&module, context).map_or(Value::Null, Value::String),
+        None => Value::String(print::get_prompt(context)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn serve_lines(input: &str) -> Vec<Value> {
+        let config = || StarshipConfig {
+            config: Some(toml::toml! {
+                format = "$status"
+                right_format = "right"
+                add_newline = false
+                [status]
+                disabled = false
+                format = "$status"
+            }),
+        };
+        let mut output = Vec::new();
+        serve(input.as_bytes(), &mut output, config).unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn renders_prompts() {
+        let responses = serve_lines(concat!(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "prompt", "params": {"status": 2, "shell": "bash"}}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": "r", "method": "prompt", "params": {"target": "right"}}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "method": "prompt"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "prompt", "params": {"status": 4, "module": "status"}}"#,
+            "\n",
+        ));
+
+        assert_eq!(3, responses.len());
+        assert_eq!(
+            serde_json::json!({"jsonrpc": "2.0", "id": 1, "result": "2"}),
+            responses[0]
+        );
+        assert_eq!("right", responses[1]["result"]);
+        assert_eq!("4", responses[2]["result"]);
+    }
+
+    #[test]
+    fn keeps_the_working_directory() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let before = std::env::current_dir()?;
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "prompt",
+            "params": {"cwd": dir.path(), "status": 1, "shell": "bash"},
+        });
+        let responses = serve_lines(&format!("{}\n", request));
+
+        assert_eq!("1", responses[0]["result"]);
+        assert_eq!(before, std::env::current_dir()?);
+        dir.close()
+    }
+
+    #[test]
+    fn reports_errors() {
+        let responses = serve_lines(concat!(
+            "not json\n",
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "render"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "prompt", "params": {"target": "left"}}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "prompt", "params": {"stauts": 1}}"#,
+            "\n",
+            r#"{"jsonrpc": "1.0", "id": 4, "method": "prompt"}"#,
+            "\n",
+            r#"{"id": 5, "method": "prompt"}"#,
+            "\n",
+        ));
+
+        let codes: Vec<_> = responses
+            .iter()
+            .map(|response| response["error"]["code"].as_i64().unwrap())
+            .collect();
+        assert_eq!(vec![-32700, -32601, -32602, -32602, -32600, -32600], codes);
+        assert_eq!(Value::Null, responses[0]["id"]);
+    }
+}
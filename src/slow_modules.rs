@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::configs::slow_modules::SlowModulesConfig;
+use crate::context::Context;
+use crate::utils;
+
+/// How many prompts in a row each module was slow, by directory
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct SlowModulesState(BTreeMap<String, BTreeMap<String, usize>>);
+
+/// Skips modules which were too slow for `threshold` prompts in a row in the current directory.
+///
+/// The counts are kept in a file per shell session, so a module that is slow on e.g. a
+/// network mount is only skipped there, and only until the shell exits.
+pub struct SlowModuleGuard {
+    path: PathBuf,
+    dir: String,
+    timeout: Duration,
+    threshold: usize,
+    state: SlowModulesState,
+    rendered: Mutex<BTreeMap<String, bool>>,
+}
+
+impl SlowModuleGuard {
+    /// Creates a guard for the current directory. Returns `None` if the guard is disabled
+    /// or there is no session to scope it to.
+    pub fn new(context: &Context) -> Option<Self> {
+        let config = context.config.get_root_config().slow_modules;
+        if config.disabled {
+            return None;
+        }
+
+        let session_key = context.get_env("STARSHIP_SESSION_KEY")?;
+        let path = context
+            .get_cache_dir()?
+            .join(format!("slow_modules_{}.json", session_key));
+        Some(Self::load(path, &context.current_dir, &config))
+    }
+
+    fn load(path: PathBuf, dir: &Path, config: &SlowModulesConfig) -> Self {
+        let state = read_state(&path);
+        Self {
+            path,
+            dir: dir.to_string_lossy().to_string(),
+            timeout: Duration::from_millis(config.timeout),
+            threshold: config.threshold,
+            state,
+            rendered: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Whether `module` was slow too often in the current directory to be rendered
+    pub fn is_skipped(&self, module: &str) -> bool {
+        self.count(module) >= self.threshold
+    }
+
+    /// Records how long `module` took to render
+    pub fn record(&self, module: &str, elapsed: Duration) {
+        if let Ok(mut rendered) = self.rendered.lock() {
+            rendered.insert(module.to_string(), elapsed > self.timeout);
+        }
+    }
+
+    /// Updates the counts with the modules rendered for this prompt and writes them back
+    pub fn save(&self) {
+        let rendered = match self.rendered.lock() {
+            Ok(rendered) if !rendered.is_empty() => rendered,
+            _ => return,
+        };
+
+        // The first prompt of a session clears the counts of sessions which ended long ago
+        if !self.path.exists() {
+            if let Some(dir) = self.path.parent() {
+                utils::remove_stale_files(dir, "slow_modules_", utils::SESSION_FILE_TTL);
+            }
+        }
+
+        // Re-read the state, since the right prompt may have been rendered in the meantime
+        let mut state = read_state(&self.path);
+        let counts = state.0.entry(self.dir.clone()).or_default();
+        for (module, &slow) in rendered.iter() {
+            let count = counts.entry(module.to_string()).or_default();
+            *count = if slow { *count + 1 } else { 0 };
+            if *count == self.threshold {
+                log::warn!(
+                    "Module `{}` took longer than {:?} for {} prompts in a row in {}, \
+                     it will be skipped there for the rest of this session",
+                    module,
+                    self.timeout,
+                    self.threshold,
+                    self.dir
+                );
+            }
+        }
+        counts.retain(|_, count| *count > 0);
+        if counts.is_empty() {
+            state.0.remove(&self.dir);
+        }
+
+        let result = serde_json::to_string(&state)
+            .map_err(io::Error::from)
+            .and_then(|content| {
+                if let Some(parent) = self.path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&self.path, content)
+            });
+        if let Err(error) = result {
+            log::debug!("Unable to save slow modules to {:?}: {}", self.path, error);
+        }
+    }
+
+    fn count(&self, module: &str) -> usize {
+        self.state
+            .0
+            .get(&self.dir)
+            .and_then(|counts| counts.get(module))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+fn read_state(path: &Path) -> SlowModulesState {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(path: &Path, dir: &str, durations: &[(&str, u64)]) -> SlowModuleGuard {
+        let config = SlowModulesConfig {
+            timeout: 100,
+            threshold: 2,
+            disabled: false,
+        };
+        let guard = SlowModuleGuard::load(path.to_path_buf(), Path::new(dir), &config);
+        for (module, millis) in durations {
+            guard.record(module, Duration::from_millis(*millis));
+        }
+        guard
+    }
+
+    #[test]
+    fn skips_modules_slow_in_a_row() -> io::Result<()> {
+        let cache = tempfile::tempdir()?;
+        let path = cache.path().join("slow_modules_test.json");
+
+        render(&path, "/mnt", &[("git_status", 200), ("rust", 200)]).save();
+        render(&path, "/mnt", &[("git_status", 200), ("rust", 10)]).save();
+
+        let guard = render(&path, "/mnt", &[]);
+        assert!(guard.is_skipped("git_status"));
+        assert!(!guard.is_skipped("rust"));
+        assert!(!render(&path, "/home", &[]).is_skipped("git_status"));
+        cache.close()
+    }
+
+    #[test]
+    fn fast_render_resets_count() -> io::Result<()> {
+        let cache = tempfile::tempdir()?;
+        let path = cache.path().join("slow_modules_test.json");
+
+        render(&path, "/mnt", &[("git_status", 200)]).save();
+        render(&path, "/mnt", &[("git_status", 10)]).save();
+        render(&path, "/mnt", &[("git_status", 200)]).save();
+
+        assert!(!render(&path, "/mnt", &[]).is_skipped("git_status"));
+        cache.close()
+    }
+}
@@ -0,0 +1,274 @@
+use toml::Value;
+
+use crate::config::{describe_value, ConfigProblem, RootModuleConfig};
+use crate::configs::StarshipRootConfig;
+
+/// The names of all symbol sets
+const SETS: &[&str] = &["emoji", "nerd", "ascii"];
+
+/// Symbols of the `nerd` and `ascii` symbol sets, as `(module, option, nerd, ascii)`.
+/// An empty symbol keeps the default one, which is also the symbol of the `emoji` set.
+const SYMBOLS: &[(&str, &str, &str, &str)] = &[
+    ("aws", "symbol", "  ", "aws "),
+    ("battery", "full_symbol", "", "full "),
+    ("battery", "charging_symbol", "", "charging "),
+    ("battery", "discharging_symbol", "", "discharging "),
+    ("battery", "unknown_symbol", "", "unknown "),
+    ("battery", "empty_symbol", "", "empty "),
+    ("character", "success_symbol", "", "[>](bold green)"),
+    ("character", "error_symbol", "", "[>](bold red)"),
+    ("character", "vicmd_symbol", "", "[<](bold green)"),
+    ("cmake", "symbol", "", "cmake "),
+    ("conda", "symbol", " ", "conda "),
+    ("cpu_temp", "symbol", " ", "temp "),
+    ("crystal", "symbol", "", "cr "),
+    ("dart", "symbol", " ", "dart "),
+    ("deno", "symbol", "", "deno "),
+    ("directory", "read_only", " ", " ro"),
+    ("disk_usage", "symbol", " ", "disk "),
+    ("docker_context", "symbol", " ", "docker "),
+    ("elixir", "symbol", " ", "exs "),
+    ("elm", "symbol", " ", "elm "),
+    ("erlang", "symbol", "", "erl "),
+    ("gcloud", "symbol", "", "gcp "),
+    ("git_branch", "symbol", " ", "git "),
+    ("git_commit", "tag_symbol", "", " tag "),
+    ("golang", "symbol", " ", "go "),
+    ("helm", "symbol", "", "helm "),
+    ("hg_branch", "symbol", " ", "hg "),
+    ("java", "symbol", " ", "java "),
+    ("jobs", "symbol", "", "*"),
+    ("jobs", "running_symbol", "", "&"),
+    ("jobs", "stopped_symbol", "", "^Z"),
+    ("julia", "symbol", " ", "jl "),
+    ("kotlin", "symbol", "", "kt "),
+    ("kubernetes", "symbol", "", "kubernetes "),
+    ("locale", "symbol", " ", "locale "),
+    ("lua", "symbol", "", "lua "),
+    ("memory_usage", "symbol", " ", "memory "),
+    ("nim", "symbol", " ", "nim "),
+    ("nix_shell", "symbol", " ", "nix "),
+    ("nodejs", "symbol", "", "nodejs "),
+    ("ocaml", "symbol", "", "ml "),
+    ("openstack", "symbol", "", "openstack "),
+    ("package", "symbol", " ", "pkg "),
+    ("perl", "symbol", " ", "pl "),
+    ("php", "symbol", " ", "php "),
+    ("proxy", "symbol", " ", "proxy "),
+    ("public_ip", "symbol", " ", "ip "),
+    ("purescript", "symbol", "", "purs "),
+    ("python", "symbol", " ", "py "),
+    ("red", "symbol", "", "red "),
+    ("ruby", "symbol", " ", "rb "),
+    ("rust", "symbol", " ", "rs "),
+    ("scala", "symbol", " ", "scala "),
+    ("shlvl", "symbol", "", "shlvl "),
+    ("status", "symbol", "", "x"),
+    ("status", "not_executable_symbol", "", "noexec"),
+    ("status", "not_found_symbol", "", "notfound"),
+    ("status", "sigint_symbol", "", "intr"),
+    ("status", "signal_symbol", "", "sig"),
+    ("sudo", "symbol", " ", "sudo "),
+    ("swift", "symbol", "ﯣ ", "swift "),
+    ("terraform", "symbol", "", "terraform "),
+    ("vagrant", "symbol", "", "vagrant "),
+    ("vpn", "symbol", " ", "vpn "),
+    ("wifi", "symbol", "直 ", "wifi "),
+    ("zig", "symbol", "", "zig "),
+];
+
+/// Replaces the symbols of all modules with the ones of the configured `symbol_set`.
+///
+/// Symbols set in the config are kept, unless a module has its own symbol for the set in its
+/// `symbols` table, e.g. `symbols = { ascii = "rs " }`.
+pub fn apply(config: &mut Value, get_env: &dyn Fn(&str) -> Option<String>) {
+    let root = StarshipRootConfig::load(config);
+    let set = active_set(root.symbol_set, root.symbol_fallback, get_env);
+    log::debug!("Using the \"{}\" symbol set", set);
+    let set = set.to_owned();
+
+    let table = match config.as_table_mut() {
+        Some(table) => table,
+        None => return,
+    };
+
+    for (module, option, nerd, ascii) in SYMBOLS {
+        let symbol = match set.as_str() {
+            "nerd" => nerd,
+            "ascii" => ascii,
+            _ => continue,
+        };
+        if symbol.is_empty() {
+            continue;
+        }
+        let module = table
+            .entry(module.to_string())
+            .or_insert_with(|| Value::Table(Default::default()));
+        if let Some(module) = module.as_table_mut() {
+            module
+                .entry(option.to_string())
+                .or_insert_with(|| Value::String(symbol.to_string()));
+        }
+    }
+
+    for (name, module) in table.iter_mut() {
+        match (name.as_str(), module.as_table_mut()) {
+            ("custom", Some(custom)) => custom
+                .iter_mut()
+                .for_each(|(_, module)| use_own_symbol(module, &set)),
+            _ => use_own_symbol(module, &set),
+        }
+    }
+}
+
+/// Replaces the symbol of a module with the one from its `symbols` table for `set`, if any
+fn use_own_symbol(module: &mut Value, set: &str) {
+    let module = match module.as_table_mut() {
+        Some(module) => module,
+        None => return,
+    };
+    if let Some(symbol) = module
+        .remove("symbols")
+        .and_then(|symbols| symbols.get(set).cloned())
+    {
+        module.insert("symbol".to_string(), symbol);
+    }
+}
+
+/// Removes the `symbols` tables of all modules, e.g. to validate the rest of their config,
+/// and adds their problems to `problems`
+pub fn remove_symbols(config: &mut Value, path: &str, problems: &mut Vec<ConfigProblem>) {
+    let table = match config.as_table_mut() {
+        Some(table) => table,
+        None => return,
+    };
+
+    let mut remove = |name: &str, module: &mut Value| {
+        let symbols = match module
+            .as_table_mut()
+            .and_then(|module| module.remove("symbols"))
+        {
+            Some(symbols) => symbols,
+            None => return,
+        };
+        let path = [path, name, "symbols"]
+            .iter()
+            .filter(|part| !part.is_empty())
+            .copied()
+            .collect::<Vec<_>>()
+            .join(".");
+        let symbols = match symbols.as_table() {
+            Some(symbols) => symbols,
+            None => {
+                problems.push(ConfigProblem::invalid_value(
+                    &path,
+                    format!("expected a table, found {}", describe_value(&symbols)),
+                ));
+                return;
+            }
+        };
+        for (set, symbol) in symbols {
+            let path = format!("{}.{}", path, set);
+            if !SETS.contains(&set.as_str()) {
+                problems.push(ConfigProblem::unknown_key(
+                    &path,
+                    "unknown symbol set, expected \"emoji\", \"nerd\" or \"ascii\"",
+                ));
+            } else if !symbol.is_str() {
+                problems.push(ConfigProblem::invalid_value(
+                    &path,
+                    format!("expected a string, found {}", describe_value(symbol)),
+                ));
+            }
+        }
+    };
+
+    for (name, module) in table.iter_mut() {
+        match (name.as_str(), module.as_table_mut()) {
+            ("custom", Some(custom)) => custom.iter_mut().for_each(|(custom_name, module)| {
+                remove(&format!("custom.{}", custom_name), module)
+            }),
+            _ => remove(name, module),
+        }
+    }
+}
+
+/// The symbol set to use, falling back to `fallback` if the terminal likely can't show `set`
+fn active_set<'a, F>(set: &'a str, fallback: &'a str, get_env: F) -> &'a str
+where
+    F: Fn(&str) -> Option<String>,
+{
+    if set == "ascii" || fallback.is_empty() {
+        return set;
+    }
+
+    // The Linux console can't show symbols outside of its font, and a dumb terminal can't
+    // show anything else either
+    let console = matches!(get_env("TERM").as_deref(), Some("linux" | "dumb"));
+    // The font of the terminal on the other end of a SSH session is unknown
+    let ssh = ["SSH_CONNECTION", "SSH_CLIENT", "SSH_TTY"]
+        .iter()
+        .any(|key| get_env(key).is_some());
+
+    if console || (set == "nerd" && ssh) {
+        fallback
+    } else {
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_back_in_the_console_and_over_ssh() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |key: &str| {
+                vars.iter()
+                    .find(|(name, _)| *name == key)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+
+        assert_eq!(
+            "nerd",
+            active_set("nerd", "ascii", env(&[("TERM", "xterm")]))
+        );
+        assert_eq!(
+            "ascii",
+            active_set("nerd", "ascii", env(&[("TERM", "linux")]))
+        );
+        assert_eq!(
+            "emoji",
+            active_set("nerd", "emoji", env(&[("SSH_TTY", "/dev/pts/1")]))
+        );
+        assert_eq!(
+            "emoji",
+            active_set("emoji", "ascii", env(&[("SSH_TTY", "/dev/pts/1")]))
+        );
+        assert_eq!("nerd", active_set("nerd", "", env(&[("TERM", "linux")])));
+    }
+
+    #[test]
+    fn replaces_symbols() {
+        let mut config = toml::toml! {
+            symbol_set = "ascii"
+            symbol_fallback = ""
+            [nodejs]
+            symbol = "node "
+            [python]
+            symbols = { ascii = "python ", nerd = "N" }
+            [custom.foo]
+            symbols = { ascii = "foo " }
+        };
+        apply(&mut config, &|_| None);
+
+        assert_eq!(Some("node "), config["nodejs"]["symbol"].as_str());
+        assert_eq!(Some("python "), config["python"]["symbol"].as_str());
+        assert_eq!(Some("rs "), config["rust"]["symbol"].as_str());
+        assert_eq!(Some(" ro"), config["directory"]["read_only"].as_str());
+        assert_eq!(Some("foo "), config["custom"]["foo"]["symbol"].as_str());
+        assert!(config["python"].get("symbols").is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::context::{Context, Shell};
+use crate::context::{Context, Shell, Wsl};
 use crate::logger::StarshipLogger;
 use crate::{config::StarshipConfig, utils::CommandOutput};
 use log::{Level, LevelFilter};
@@ -94,11 +94,23 @@ impl<'a> ModuleRenderer<'a> {
         self
     }
 
+    pub fn terminal_width(mut self, width: usize) -> Self {
+        self.context.width = width;
+        self
+    }
+
     pub fn jobs(mut self, jobs: u64) -> Self {
         self.context.properties.insert("jobs", jobs.to_string());
         self
     }
 
+    pub fn job_states(mut self, running: u64, stopped: u64) -> Self {
+        let properties = &mut self.context.properties;
+        properties.insert("running_jobs", running.to_string());
+        properties.insert("stopped_jobs", stopped.to_string());
+        self
+    }
+
     pub fn cmd_duration(mut self, duration: u64) -> Self {
         self.context
             .properties
@@ -114,6 +126,12 @@ impl<'a> ModuleRenderer<'a> {
         self
     }
 
+    /// Makes the module run as if in the Windows Subsystem for Linux
+    pub fn wsl(mut self, wsl: Wsl) -> Self {
+        self.context.wsl = Some(wsl).into();
+        self
+    }
+
     pub fn status(mut self, status: i32) -> Self {
         self.context
             .properties
@@ -121,6 +139,21 @@ impl<'a> ModuleRenderer<'a> {
         self
     }
 
+    pub fn history_number(mut self, number: u64) -> Self {
+        self.context
+            .properties
+            .insert("history_number", number.to_string());
+        self
+    }
+
+    pub fn pipestatus(mut self, pipestatus: &[i32]) -> Self {
+        let pipestatus: Vec<String> = pipestatus.iter().map(i32::to_string).collect();
+        self.context
+            .properties
+            .insert("pipestatus", pipestatus.join(" "));
+        self
+    }
+
     /// Renders the module returning its output
     pub fn collect(self) -> Option<String> {
         let ret = crate::print::get_module(self.name, self.context);
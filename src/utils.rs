@@ -1,11 +1,12 @@
 use process_control::{ChildExt, Timeout};
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{Read, Result};
 use std::path::Path;
 use std::process::{Command, Stdio};
 use std::time::{Duration, Instant};
 
 use crate::context::Shell;
+use crate::daemon::ClientEnv;
 
 /// Return the string contents of a file
 pub fn read_file<P: AsRef<Path>>(file_name: P) -> Result<String> {
@@ -16,6 +17,51 @@ pub fn read_file<P: AsRef<Path>>(file_name: P) -> Result<String> {
     Ok(data)
 }
 
+/// How long the files of a shell session are kept after they were last written
+pub const SESSION_FILE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Removes the files in `dir` whose name starts with `prefix` and which weren't written for
+/// `max_age`, like the files of shell sessions which have ended
+pub fn remove_stale_files(dir: &Path, prefix: &str, max_age: Duration) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let age = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok());
+        let is_stale = matches!(age, Some(age) if age >= max_age);
+        if is_stale && entry.file_name().to_string_lossy().starts_with(prefix) {
+            if let Err(error) = fs::remove_file(entry.path()) {
+                log::debug!("Unable to remove {:?}: {}", entry.path(), error);
+            }
+        }
+    }
+}
+
+/// Starts `command` in the background, with its input and output discarded. A thread waits
+/// for it to exit, so it doesn't linger as a zombie process in the daemon.
+pub fn spawn_detached(command: &mut Command) -> Result<()> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// The 64-bit FNV-1a hash of `bytes`. Unlike `DefaultHasher`, it stays the same across
+/// versions of Rust, so keys saved in files still match after starship is rebuilt.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
 #[derive(Debug, Clone)]
 pub struct CommandOutput {
     pub stdout: String,
@@ -31,7 +77,18 @@ impl PartialEq for CommandOutput {
 /// Execute a command and return the output on stdout and stderr if successful
 #[cfg(not(test))]
 pub fn exec_cmd(cmd: &str, args: &[&str], time_limit: Duration) -> Option<CommandOutput> {
-    internal_exec_cmd(&cmd, &args, time_limit)
+    internal_exec_cmd(&cmd, &args, time_limit, None)
+}
+
+/// Execute a command in the working directory and with the environment variables of
+/// `client`, and return the output on stdout and stderr if successful
+pub fn exec_cmd_for(
+    cmd: &str,
+    args: &[&str],
+    time_limit: Duration,
+    client: &ClientEnv,
+) -> Option<CommandOutput> {
+    internal_exec_cmd(cmd, args, time_limit, Some(client))
 }
 
 #[cfg(test)]
@@ -218,7 +275,7 @@ CMake suite maintained and supported by Kitware (kitware.com/cmake).\n",
             stderr: String::default(),
         }),
         // If we don't have a mocked command fall back to executing the command
-        _ => internal_exec_cmd(&cmd, &args, time_limit),
+        _ => internal_exec_cmd(&cmd, &args, time_limit, None),
     }
 }
 
@@ -234,7 +291,37 @@ pub fn wrap_colorseq_for_shell(mut ansi: String, shell: Shell) -> String {
 
     const ESCAPE_BEGIN: char = '\u{1b}';
     const ESCAPE_END: char = 'm';
-    wrap_seq_for_shell(ansi, shell, ESCAPE_BEGIN, ESCAPE_END)
+    // OSC sequences (e.g. hyperlinks) are terminated by BEL rather than `m`
+    const OSC_BEGIN: &str = "\u{1b}]";
+    const OSC_END: char = '\u{7}';
+
+    let mut wrapped = String::new();
+    let mut rest = ansi.as_str();
+    while let Some(start) = rest.find(OSC_BEGIN) {
+        let end = rest[start..]
+            .find(OSC_END)
+            .map_or(rest.len(), |end| start + end + 1);
+        wrapped.push_str(&wrap_seq_for_shell(
+            rest[..start].to_string(),
+            shell,
+            ESCAPE_BEGIN,
+            ESCAPE_END,
+        ));
+        wrapped.push_str(&wrap_seq_for_shell(
+            rest[start..end].to_string(),
+            shell,
+            ESCAPE_BEGIN,
+            OSC_END,
+        ));
+        rest = &rest[end..];
+    }
+    wrapped.push_str(&wrap_seq_for_shell(
+        rest.to_string(),
+        shell,
+        ESCAPE_BEGIN,
+        ESCAPE_END,
+    ));
+    wrapped
 }
 
 /// Many shells cannot deal with raw unprintable characters and miscompute the cursor position,
@@ -283,10 +370,15 @@ pub fn wrap_seq_for_shell(
     final_string
 }
 
-fn internal_exec_cmd(cmd: &str, args: &[&str], time_limit: Duration) -> Option<CommandOutput> {
+fn internal_exec_cmd(
+    cmd: &str,
+    args: &[&str],
+    time_limit: Duration,
+    client: Option<&ClientEnv>,
+) -> Option<CommandOutput> {
     log::trace!("Executing command {:?} with args {:?}", cmd, args);
 
-    let full_path = match which::which(cmd) {
+    let full_path = match client.map_or_else(|| which::which(cmd), |client| client.which(cmd)) {
         Ok(full_path) => {
             log::trace!("Using {:?} as {:?}", full_path, cmd);
             full_path
@@ -299,7 +391,11 @@ fn internal_exec_cmd(cmd: &str, args: &[&str], time_limit: Duration) -> Option<C
 
     let start = Instant::now();
 
-    let process = match Command::new(full_path)
+    let mut command = Command::new(full_path);
+    if let Some(client) = client {
+        client.apply(&mut command);
+    }
+    let process = match command
         .args(args)
         .stderr(Stdio::piped())
         .stdout(Stdio::piped())
@@ -363,6 +459,40 @@ fn internal_exec_cmd(cmd: &str, args: &[&str], time_limit: Duration) -> Option<C
 mod tests {
     use super::*;
 
+    #[test]
+    fn stale_files_are_removed() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("toggles_1.toml"), "")?;
+        fs::write(dir.path().join("session_1.log"), "")?;
+
+        remove_stale_files(dir.path(), "toggles_", SESSION_FILE_TTL);
+        assert!(dir.path().join("toggles_1.toml").exists());
+
+        remove_stale_files(dir.path(), "toggles_", Duration::ZERO);
+        assert!(!dir.path().join("toggles_1.toml").exists());
+        assert!(dir.path().join("session_1.log").exists());
+        dir.close()
+    }
+
+    #[test]
+    fn spawns_detached_commands() -> Result<()> {
+        assert!(spawn_detached(&mut Command::new("nonexistent_starship_command")).is_err());
+
+        #[cfg(not(windows))]
+        {
+            let dir = tempfile::tempdir()?;
+            let marker = dir.path().join("spawned");
+            spawn_detached(Command::new("touch").arg(&marker))?;
+            let start = Instant::now();
+            while !marker.exists() && start.elapsed() < Duration::from_secs(5) {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            assert!(marker.exists());
+            dir.close()?;
+        }
+        Ok(())
+    }
+
     #[test]
     fn exec_mocked_command() {
         let result = exec_cmd("dummy_command", &[], Duration::from_millis(500));
@@ -380,7 +510,7 @@ mod tests {
     #[test]
     #[cfg(not(windows))]
     fn exec_no_output() {
-        let result = internal_exec_cmd("true", &[], Duration::from_millis(500));
+        let result = internal_exec_cmd("true", &[], Duration::from_millis(500), None);
         let expected = Some(CommandOutput {
             stdout: String::from(""),
             stderr: String::from(""),
@@ -392,8 +522,12 @@ mod tests {
     #[test]
     #[cfg(not(windows))]
     fn exec_with_output_stdout() {
-        let result =
-            internal_exec_cmd("/bin/sh", &["-c", "echo hello"], Duration::from_millis(500));
+        let result = internal_exec_cmd(
+            "/bin/sh",
+            &["-c", "echo hello"],
+            Duration::from_millis(500),
+            None,
+        );
         let expected = Some(CommandOutput {
             stdout: String::from("hello\n"),
             stderr: String::from(""),
@@ -409,6 +543,7 @@ mod tests {
             "/bin/sh",
             &["-c", "echo hello >&2"],
             Duration::from_millis(500),
+            None,
         );
         let expected = Some(CommandOutput {
             stdout: String::from(""),
@@ -418,6 +553,32 @@ mod tests {
         assert_eq!(result, expected)
     }
 
+    #[test]
+    #[cfg(not(windows))]
+    fn exec_for_client() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cwd = dir.path().canonicalize()?;
+        let client = ClientEnv {
+            cwd: cwd.clone(),
+            env: vec![("NAME".to_string(), "client".to_string())]
+                .into_iter()
+                .collect(),
+        };
+        let result = exec_cmd_for(
+            "/bin/sh",
+            &["-c", "pwd; echo $NAME"],
+            Duration::from_millis(500),
+            &client,
+        );
+        let expected = Some(CommandOutput {
+            stdout: format!("{}\nclient\n", cwd.display()),
+            stderr: String::from(""),
+        });
+
+        assert_eq!(result, expected);
+        dir.close()
+    }
+
     #[test]
     #[cfg(not(windows))]
     fn exec_with_output_both() {
@@ -425,6 +586,7 @@ mod tests {
             "/bin/sh",
             &["-c", "echo hello; echo world >&2"],
             Duration::from_millis(500),
+            None,
         );
         let expected = Some(CommandOutput {
             stdout: String::from("hello\n"),
@@ -437,7 +599,7 @@ mod tests {
     #[test]
     #[cfg(not(windows))]
     fn exec_with_non_zero_exit_code() {
-        let result = internal_exec_cmd("false", &[], Duration::from_millis(500));
+        let result = internal_exec_cmd("false", &[], Duration::from_millis(500), None);
         let expected = None;
 
         assert_eq!(result, expected)
@@ -446,7 +608,7 @@ mod tests {
     #[test]
     #[cfg(not(windows))]
     fn exec_slow_command() {
-        let result = internal_exec_cmd("sleep", &["500"], Duration::from_millis(500));
+        let result = internal_exec_cmd("sleep", &["500"], Duration::from_millis(500), None);
         let expected = None;
 
         assert_eq!(result, expected)
@@ -490,6 +652,19 @@ mod tests {
         assert_eq!(&bresult5, "");
     }
 
+    #[test]
+    fn test_hyperlink_wrappers() {
+        let test = "\x1b[31m\x1b]8;;https://a.b/c\x07link\x1b]8;;\x07\x1b[0m";
+        assert_eq!(
+            wrap_colorseq_for_shell(test.to_owned(), Shell::Zsh),
+            "%{\x1b[31m%}%{\x1b]8;;https://a.b/c\x07%}link%{\x1b]8;;\x07%}%{\x1b[0m%}"
+        );
+        assert_eq!(
+            wrap_colorseq_for_shell(test.to_owned(), Shell::PowerShell),
+            test
+        );
+    }
+
     #[test]
     fn test_bash_escape() {
         let test = "$(echo a)";
@@ -14,19 +14,32 @@ fn impl_module_config(dinput: DeriveInput) -> proc_macro::TokenStream {
 
     let mut from_config = quote! {};
     let mut load_config = quote! {};
+    let mut init_display = quote! {};
+    let mut apply_display = quote! {};
+    let mut validate_config = quote! {};
 
     if let syn::Data::Struct(data) = dinput.data {
         if let syn::Fields::Named(fields_named) = data.fields {
             let mut load_tokens = quote! {};
+            let mut validate_tokens = quote! {};
             let mut fields = quote! {};
 
             for field in fields_named.named.iter() {
                 let ident = field.ident.as_ref().unwrap();
+                let ty = &field.ty;
 
                 let new_load_tokens = quote! {
                     stringify!(#ident) => self.#ident.load_config(v),
                 };
 
+                validate_tokens = quote! {
+                    #validate_tokens
+                    stringify!(#ident) => {
+                        <#ty as ModuleConfig<'a>>::validate_config(v, &path, problems);
+                        crate::config::validate_style_option(stringify!(#ident), v, &path, problems);
+                    }
+                };
+
                 let new_field = quote! {
                     stringify!(#ident),
                 };
@@ -42,9 +55,70 @@ fn impl_module_config(dinput: DeriveInput) -> proc_macro::TokenStream {
                 };
             }
 
+            let has_field = |name: &str| {
+                fields_named
+                    .named
+                    .iter()
+                    .any(|field| matches!(&field.ident, Some(ident) if ident == name))
+            };
+            // Configs with a format are the ones of modules, which also accept the options
+            // in `COMMON_MODULE_OPTIONS` unless they have their own
+            let is_module = has_field("format");
+
+            // Modules can be shortened to their symbol with `display = "symbol"`.
+            // `display = "hidden"` is handled together with `disabled` when rendering.
+            if is_module && !has_field("display") {
+                let symbol_format = if has_field("style") {
+                    "[$symbol]($style)"
+                } else {
+                    "$symbol"
+                };
+                let apply_symbol = if has_field("symbol") {
+                    quote! { self.format = #symbol_format; }
+                } else {
+                    quote! {
+                        ::log::warn!("`display = \"symbol\"` is not supported by this module");
+                    }
+                };
+
+                load_tokens = quote! {
+                    #load_tokens
+                    "display" => match v.as_str() {
+                        Some("symbol") => display_symbol = true,
+                        Some("full") | Some("hidden") => (),
+                        _ => ::log::warn!(
+                            "\"display\" should be \"full\", \"symbol\" or \"hidden\", found {}",
+                            v
+                        ),
+                    },
+                };
+                init_display = quote! {
+                    let mut display_symbol = false;
+                };
+                apply_display = quote! {
+                    if display_symbol {
+                        #apply_symbol
+                    }
+                };
+            }
+
+            if is_module {
+                load_tokens = quote! {
+                    #load_tokens
+                    key if crate::config::COMMON_MODULE_OPTIONS.contains(&key) => (),
+                };
+                validate_tokens = quote! {
+                    #validate_tokens
+                    key if crate::config::COMMON_MODULE_OPTIONS.contains(&key) => {
+                        crate::config::validate_common_option(key, v, &path, problems)
+                    }
+                };
+            }
+
             load_config = quote! {
                 fn load_config(&mut self, config: &'a toml::Value) {
                     if let toml::Value::Table(config) = config {
+                        #init_display
                         config.iter().for_each(|(k, v)| {
                             match k.as_str() {
                                 #load_tokens
@@ -68,6 +142,7 @@ fn impl_module_config(dinput: DeriveInput) -> proc_macro::TokenStream {
                                 },
                             }
                         });
+                        #apply_display
                     }
                 }
             };
@@ -78,6 +153,54 @@ fn impl_module_config(dinput: DeriveInput) -> proc_macro::TokenStream {
                     Some(out)
                 }
             };
+            validate_config = quote! {
+                fn expected() -> &'static str {
+                    "a table"
+                }
+
+                fn validate_config(
+                    config: &'a toml::Value,
+                    path: &str,
+                    problems: &mut Vec<crate::config::ConfigProblem>,
+                ) {
+                    let config = match config {
+                        toml::Value::Table(config) => config,
+                        _ => {
+                            problems.push(crate::config::ConfigProblem::invalid_value(
+                                path,
+                                format!(
+                                    "expected a table, found {}",
+                                    crate::config::describe_value(config),
+                                ),
+                            ));
+                            return;
+                        }
+                    };
+                    for (k, v) in config {
+                        let path = if path.is_empty() {
+                            k.clone()
+                        } else {
+                            format!("{}.{}", path, k)
+                        };
+                        match k.as_str() {
+                            #validate_tokens
+                            unknown => {
+                                let did_you_mean = ::std::array::IntoIter::new([#fields])
+                                    .map(|field| (::strsim::jaro_winkler(unknown, field), field))
+                                    .filter(|(score, _field)| *score > 0.8)
+                                    .max_by(|(score_a, _), (score_b, _)| {
+                                        score_a.partial_cmp(score_b).unwrap_or(::std::cmp::Ordering::Equal)
+                                    });
+                                let message = match did_you_mean {
+                                    Some((_score, field)) => format!("unknown key, did you mean `{}`?", field),
+                                    None => "unknown key".to_string(),
+                                };
+                                problems.push(crate::config::ConfigProblem::unknown_key(&path, message));
+                            }
+                        }
+                    }
+                }
+            };
         }
     }
 
@@ -85,6 +208,7 @@ fn impl_module_config(dinput: DeriveInput) -> proc_macro::TokenStream {
         impl<'a> ModuleConfig<'a> for #struct_ident #ty_generics #where_clause {
             #from_config
             #load_config
+            #validate_config
         }
     })
 }
@@ -0,0 +1,51 @@
+//! Checks that the completions generated by clap are extended with the dynamic ones.
+use std::process::Command;
+
+fn completions(shell: &str) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_starship"))
+        .args(&["completions", shell])
+        .output()
+        .expect("failed to run starship");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn bash() {
+    let script = completions("bash");
+    assert!(script.contains("            module) kind=modules ;;\n"));
+    assert!(script.contains("            config|configure) kind=config-keys ;;\n"));
+    assert!(script.ends_with("complete -F _starship_dynamic -o bashdefault -o default starship\n"));
+    assert!(!script.contains("complete -F _starship -o"));
+}
+
+#[test]
+fn zsh() {
+    let script = completions("zsh");
+    for line in &[
+        "':name -- The name of the module to be printed:{compadd -- $(starship complete modules)}' \\",
+        "':name -- The name of the module to be toggled:{compadd -- $(starship complete modules)}' \\",
+        "'::name -- Configuration key to edit:{compadd -- $(starship complete config-keys)}' \\",
+        "'::name -- The preset to print or install:{compadd -- $(starship complete presets)}' \\",
+    ] {
+        assert!(script.contains(line), "missing {}", line);
+    }
+}
+
+#[test]
+fn fish() {
+    let script = completions("fish");
+    for line in &[
+        "complete -c starship -n \"__fish_seen_subcommand_from module\" -f -a \"(starship complete modules)\"\n",
+        "complete -c starship -n \"__fish_seen_subcommand_from toggle\" -f -a \"(starship complete modules)\"\n",
+        "complete -c starship -n \"__fish_seen_subcommand_from config configure\" -f -a \"(starship complete config-keys)\"\n",
+        "complete -c starship -n \"__fish_seen_subcommand_from preset\" -f -a \"(starship complete presets)\"\n",
+    ] {
+        assert!(script.contains(line), "missing {}", line);
+    }
+}
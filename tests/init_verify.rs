@@ -0,0 +1,47 @@
+//! Runs `starship init --verify` for every supported shell. Tests of shells which aren't
+//! installed everywhere are ignored and run on CI, which installs them.
+#![cfg(not(windows))]
+
+use std::process::Command;
+
+fn verify(shell: &str) {
+    assert!(
+        Command::new(shell).args(&["-c", "exit"]).output().is_ok(),
+        "{} is not installed",
+        shell
+    );
+    // zsh and fish are run in a terminal made by `script`
+    assert!(
+        shell == "bash" || Command::new("script").arg("--version").output().is_ok(),
+        "script is not installed, which is needed to verify {}",
+        shell
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_starship"))
+        .args(&["init", "--verify", shell])
+        .output()
+        .expect("failed to run starship");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn bash() {
+    verify("bash");
+}
+
+#[test]
+#[ignore = "needs zsh and script"]
+fn zsh() {
+    verify("zsh");
+}
+
+#[test]
+#[ignore = "needs fish and script"]
+fn fish() {
+    verify("fish");
+}
@@ -0,0 +1,27 @@
+//! Checks that starship doesn't render prompts when run while rendering a prompt.
+
+use std::process::Command;
+
+#[test]
+fn prints_minimal_prompt_when_recursive() {
+    let output = Command::new(env!("CARGO_BIN_EXE_starship"))
+        .args(&["prompt"])
+        .env("STARSHIP_RECURSION_GUARD", "1")
+        .output()
+        .expect("failed to run starship");
+
+    assert!(output.status.success());
+    assert_eq!("> ", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn prints_nothing_for_module_when_recursive() {
+    let output = Command::new(env!("CARGO_BIN_EXE_starship"))
+        .args(&["module", "character"])
+        .env("STARSHIP_RECURSION_GUARD", "1")
+        .output()
+        .expect("failed to run starship");
+
+    assert!(output.status.success());
+    assert_eq!("", String::from_utf8_lossy(&output.stdout));
+}